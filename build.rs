@@ -23,7 +23,7 @@ fn main() {
         for entry in fs::read_dir(locales_src_dir).unwrap() {
             let entry = entry.unwrap();
             let path = entry.path();
-            if path.is_file() && path.extension().map_or(false, |ext| ext == "ftl") {
+            if path.is_file() && path.extension().is_some_and(|ext| ext == "ftl") {
                 if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
                     // stem is the language code, e.g., "en", "de"
                     
@@ -1,6 +1,6 @@
 use anyhow::Result;
 use crossterm::event::{self, Event, KeyCode};
-use std::{time::{Duration, Instant}};
+use std::{path::{Path, PathBuf}, time::{Duration, Instant}};
 use ratatui::{
     backend::Backend,
     Terminal,
@@ -8,12 +8,16 @@ use ratatui::{
 
 use crate::{
     config::Config,
-    core::{system_info::collect_system_info, check_root, disk_info::create_dummy_system_disk_info},
+    core::{system_info::{collect_system_info, detect_efi, detect_package_manager, detect_timezone, read_os_release}, check_root, command::{CommandRunner, DryRunCommandRunner, SystemCommandRunner}, disk_info::create_dummy_system_disk_info, secret::SecretString},
     ui::{
-        tui::{draw_ui, UiState, build_disk_display_list},
+        install_forms::{header_backup_form, network_form, tpm2_form, ANY_INTERFACE, HEADER_BACKUP_DIRECTORY, HEADER_BACKUP_USB, METHOD_STATIC},
+        key_management::{KeyAction, KeyFocus, KeyForm, KeyManagementState, Revealed},
+        tui::{draw_ui, UiState, InstallProgress, build_disk_display_list},
         widgets::{MenuItem, MenuType, Screen, DialogType},
     },
     lang::get_text,
+    tools::{backup::BackupCopy, diceware, gpg::{self, GpgHome, KeySpec}, header_backup::{self, HeaderStore}, in_place_encryption::{self, ExistingRoot, InPlacePlan}, keyslots, recovery_key::{self, read_shares, RecoveryKey, RecoverySheet}, ssh_keys, vault::{self, EntryKind, Vault, VaultEntry}},
+    tools::installation::{network::{self, AddressMethod, NetworkSettings}, plan::{locale_for_language, Bootloader, UkiBuilder, DEFAULT_BOOTLOADER_ID, DEFAULT_LUKS_MAPPER}, secure_boot, select_backend, start_installation, InstallEvent, InstallHandle, InstallPlan, InstallSource, LuksProfile, DEFAULT_TARGET_ROOT, SecureBootMode, Tpm2Settings, tpm2::{self, Tpm2Plan}},
 };
 
 // Installation wizard related enums and structs
@@ -26,6 +30,7 @@ pub enum InstallationStep {
     DesktopChoice,
     KernelChoice,
     SecureBootChoice,
+    Tpm2Setup,
     UpdateSettings,
     AdditionalPackages,
     Summary,
@@ -55,11 +60,53 @@ pub struct InstallationConfig {
     pub target_disk: Option<String>,
     pub hostname: Option<String>,
     pub username: Option<String>,
-    // Passwords should not be stored in plain text here long-term,
-    // but might be needed temporarily during collection before passing to scripts.
-    // Consider secure handling. For now, as Option<String>.
-    pub user_password: Option<String>,
-    pub luks_password: Option<String>,
+    pub user_password: Option<SecretString>,
+    /// `None` leaves root as the distribution sets it up
+    pub root_password: Option<SecretString>,
+    pub luks_password: Option<SecretString>,
+    /// Locale of the installed system, e.g. `de_DE.UTF-8`
+    pub locale: Option<String>,
+    /// Timezone of the installed system, e.g. `Europe/Berlin`
+    pub timezone: Option<String>,
+    /// Keyboard layout of the installed system, e.g. `de`
+    pub keyboard_layout: Option<String>,
+    /// Where packages or the root filesystem come from
+    pub source: InstallSource,
+    pub bootloader: Bootloader,
+    /// EFI directory and boot entry name, e.g. `debian`
+    pub bootloader_id: Option<String>,
+    /// Build unified kernel images with this tool (systemd-boot only)
+    pub uki: Option<UkiBuilder>,
+    /// Keys the boot chain is signed with for Secure Boot
+    pub secure_boot: SecureBootMode,
+    /// Generated password confirming the MOK enrollment, shown to the user
+    pub mok_password: Option<SecretString>,
+    /// TPM2 auto-unlock of the root container, `None` for passphrase only
+    pub tpm2: Option<Tpm2Settings>,
+    /// PIN required together with the TPM, if the settings ask for one; set by the TPM2 step
+    pub tpm2_pin: Option<SecretString>,
+    /// Install GRUB for both UEFI and legacy BIOS (cryptodisk mode only)
+    pub hybrid_boot: bool,
+    /// Embed a keyfile in the initramfs so the passphrase is only asked once by GRUB
+    pub embed_keyfile: bool,
+    /// Root container parameters, `None` for the bootloader's default
+    pub luks_profile: Option<LuksProfile>,
+    /// /boot container parameters in cryptodisk mode, `None` for GRUB compatible defaults
+    pub boot_luks_profile: Option<LuksProfile>,
+    /// USB device holding ESP, /boot and the detached root header, `None` to boot from the target
+    pub boot_usb: Option<String>,
+    /// Device the boot USB key is cloned to after installation
+    pub boot_usb_backup: Option<String>,
+    /// Where the LUKS headers are backed up to after installation
+    pub header_backup: Option<HeaderStore>,
+    /// Add a generated recovery key next to the passphrase
+    pub recovery_key: bool,
+    /// Directory on removable media the recovery sheet is written to
+    pub recovery_sheet_dir: Option<String>,
+    /// File or directory with the public keys for the new user's `authorized_keys`
+    pub ssh_authorized_keys: Option<String>,
+    /// Wired network of the installed system, `None` for NetworkManager's defaults
+    pub network: Option<NetworkSettings>,
 }
 
 /// Application state
@@ -76,6 +123,10 @@ pub struct App<B: Backend + std::io::Write> {
     last_tick: Instant,
     /// Tick rate for UI updates
     tick_rate: Duration,
+    /// Handle of the installation worker while it is running
+    install_handle: Option<InstallHandle>,
+    /// In-place encryption running from key management, with the message shown once it finished
+    key_worker: Option<(InstallHandle, String)>,
 }
 
 impl<B: Backend + std::io::Write> App<B> {
@@ -142,6 +193,8 @@ impl<B: Backend + std::io::Write> App<B> {
             should_quit: false,
             last_tick: Instant::now(),
             tick_rate: Duration::from_millis(100),
+            install_handle: None,
+            key_worker: None,
         }
     }
 
@@ -168,6 +221,9 @@ impl<B: Backend + std::io::Write> App<B> {
                 }
             }
 
+            self.poll_installation();
+            self.poll_key_worker();
+
             // Handle tick for animations etc.
             if self.last_tick.elapsed() >= self.tick_rate {
                 self.last_tick = Instant::now();
@@ -175,6 +231,21 @@ impl<B: Backend + std::io::Write> App<B> {
             }
         }
 
+        if let Some(mut handle) = self.install_handle.take() {
+            handle.cancel();
+            log::info!("Waiting for the installation worker to clean up...");
+            self.ui_state.push_log_line("Waiting for the installation worker to clean up...".to_string());
+            self.terminal.draw(|f| draw_ui(f, &mut self.ui_state))?;
+            handle.join();
+        }
+        // An interrupted encryption resumes on the next start
+        if let Some((mut handle, _)) = self.key_worker.take() {
+            handle.cancel();
+            self.ui_state.push_log_line("Waiting for the encryption worker to stop...".to_string());
+            self.terminal.draw(|f| draw_ui(f, &mut self.ui_state))?;
+            handle.join();
+        }
+
         Ok(())
     }
 
@@ -183,23 +254,35 @@ impl<B: Backend + std::io::Write> App<B> {
         let key_code = key.code;
         let modifiers = key.modifiers;
 
+        // A generated passphrase is shown once; any key dismisses it
+        if self.ui_state.generated_passphrase.take().is_some() {
+            return;
+        }
+        if key_code == KeyCode::F(2) && self.ui_state.active_dialog.is_none() {
+            self.generate_passphrase();
+            return;
+        }
+        if key_code == KeyCode::F(3) && self.ui_state.active_dialog.is_none() {
+            self.toggle_reveal();
+            return;
+        }
+
         // Alt + L for Log Panel & Alt + T for Theme Selection Dialog
         if modifiers == crossterm::event::KeyModifiers::ALT {
             match key_code {
-                KeyCode::Char('l') | KeyCode::Char('L') => {
-                    if self.ui_state.current_screen == Screen::SystemInstallation {
-                        self.ui_state.show_log_panel = !self.ui_state.show_log_panel;
-                        log::info!("Log panel toggled with Alt+L: {}", self.ui_state.show_log_panel);
-                        return;
-                    }
+                KeyCode::Char('l') | KeyCode::Char('L')
+                    if self.ui_state.current_screen == Screen::SystemInstallation => {
+                    self.ui_state.show_log_panel = !self.ui_state.show_log_panel;
+                    log::info!("Log panel toggled with Alt+L: {}", self.ui_state.show_log_panel);
+                    return;
                 }
-                KeyCode::Char('t') | KeyCode::Char('T') => { // Alt+T for Theme Selection
-                    if self.ui_state.active_dialog.is_none() { // Only open if no other dialog is active
-                        log::info!("Opening Theme Selector dialog.");
-                        self.ui_state.active_dialog = Some(DialogType::ThemeSelector);
-                        self.ui_state.dialog_selected_option = self.ui_state.active_theme_index; // Pre-select current theme
-                        return;
-                    }
+                // Alt+T for Theme Selection, only if no other dialog is active
+                KeyCode::Char('t') | KeyCode::Char('T')
+                    if self.ui_state.active_dialog.is_none() => {
+                    log::info!("Opening Theme Selector dialog.");
+                    self.ui_state.active_dialog = Some(DialogType::ThemeSelector);
+                    self.ui_state.dialog_selected_option = self.ui_state.active_theme_index; // Pre-select current theme
+                    return;
                 }
                 _ => {}
             }
@@ -213,15 +296,13 @@ impl<B: Backend + std::io::Write> App<B> {
             match dialog_type_clone {
                 Some(DialogType::YesNo {..}) => {
                     match key_code {
-                        KeyCode::Left | KeyCode::Char('h') => {
-                            if self.ui_state.dialog_selected_option == 1 {
-                                self.ui_state.dialog_selected_option = 0;
-                            }
+                        KeyCode::Left | KeyCode::Char('h')
+                            if self.ui_state.dialog_selected_option == 1 => {
+                            self.ui_state.dialog_selected_option = 0;
                         }
-                        KeyCode::Right | KeyCode::Char('l') => {
-                            if self.ui_state.dialog_selected_option == 0 {
-                                self.ui_state.dialog_selected_option = 1;
-                            }
+                        KeyCode::Right | KeyCode::Char('l')
+                            if self.ui_state.dialog_selected_option == 0 => {
+                            self.ui_state.dialog_selected_option = 1;
                         }
                         KeyCode::Char('y') | KeyCode::Char('j') => {
                             self.ui_state.dialog_selected_option = 0;
@@ -240,19 +321,17 @@ impl<B: Backend + std::io::Write> App<B> {
                 }
                 Some(DialogType::ThemeSelector) => {
                     match key_code {
-                        KeyCode::Up | KeyCode::Char('k') => {
-                            if !self.ui_state.themes.is_empty() {
-                                if self.ui_state.dialog_selected_option > 0 {
-                                    self.ui_state.dialog_selected_option -= 1;
-                                } else {
-                                    self.ui_state.dialog_selected_option = self.ui_state.themes.len() - 1; // Wrap around
-                                }
+                        KeyCode::Up | KeyCode::Char('k')
+                            if !self.ui_state.themes.is_empty() => {
+                            if self.ui_state.dialog_selected_option > 0 {
+                                self.ui_state.dialog_selected_option -= 1;
+                            } else {
+                                self.ui_state.dialog_selected_option = self.ui_state.themes.len() - 1; // Wrap around
                             }
                         }
-                        KeyCode::Down | KeyCode::Char('j') => {
-                            if !self.ui_state.themes.is_empty() {
-                                self.ui_state.dialog_selected_option = (self.ui_state.dialog_selected_option + 1) % self.ui_state.themes.len(); // Wrap around
-                            }
+                        KeyCode::Down | KeyCode::Char('j')
+                            if !self.ui_state.themes.is_empty() => {
+                            self.ui_state.dialog_selected_option = (self.ui_state.dialog_selected_option + 1) % self.ui_state.themes.len(); // Wrap around
                         }
                         KeyCode::Enter => {
                             self.handle_theme_dialog_confirm();
@@ -268,6 +347,36 @@ impl<B: Backend + std::io::Write> App<B> {
             return; // Input handled by dialog, no further processing for this key event
         }
 
+        // ESC while the installation worker runs asks to cancel it instead of exiting.
+        if key_code == KeyCode::Esc && self.install_handle.is_some() {
+            self.confirm_cancel_installation();
+            return;
+        }
+
+        // Forms of the wizard steps edit text themselves; Enter, Esc and Backspace in an empty field fall through
+        if self.ui_state.current_screen == Screen::SystemInstallation && self.ui_state.active_dialog.is_none() {
+            if let Some(form) = self.ui_state.step_form_mut() {
+                if form.handle_key(key) {
+                    return;
+                }
+            }
+        }
+
+        // An open key management form edits text itself; Esc, Enter and Backspace stay with the screen
+        let keys = &mut self.ui_state.key_management;
+        if self.ui_state.current_screen == Screen::KeyManagement && keys.revealed.is_none() {
+            if let Some(form) = keys.form.as_mut() {
+                if form.form.handle_key(key) {
+                    return;
+                }
+            }
+        }
+        let keys = &self.ui_state.key_management;
+        if self.ui_state.current_screen == Screen::KeyManagement && (keys.form.is_some() || keys.revealed.is_some()) {
+            self.handle_key_management_input(key_code);
+            return;
+        }
+
         // Global ESC to confirm exit, if no dialog is active and not already in confirm exit/message screen.
         if key_code == KeyCode::Esc && 
            self.ui_state.active_dialog.is_none() && // Ensure no other dialog is already active
@@ -285,9 +394,9 @@ impl<B: Backend + std::io::Write> App<B> {
             // Specific back navigation for certain screens BEFORE global previous_screen logic
             match self.ui_state.current_screen {
                 Screen::SystemInstallation => {
-                self.handle_installation_previous_step(); 
-                return;
-            }
+                    self.handle_installation_previous_step();
+                    return;
+                }
                 Screen::KeyboardSelect => {
                     self.ui_state.set_current_screen(Screen::LanguageSelect);
                     return;
@@ -296,6 +405,10 @@ impl<B: Backend + std::io::Write> App<B> {
                     self.ui_state.set_current_screen(Screen::KeyboardSelect);
                     return;
                 }
+                Screen::KeyManagement => {
+                    self.key_management_back();
+                    return;
+                }
                 _ => {}
             }
             
@@ -334,8 +447,7 @@ impl<B: Backend + std::io::Write> App<B> {
                                 },
                                 "keys" => {
                                     log::info!("Starting key management module...");
-                                    // TODO: Implement key management module
-                                    self.ui_state.show_message(&get_text("LANG_KEYS"), &get_text("LANG_NOT_IMPLEMENTED"));
+                                    self.start_key_management();
                                 },
                                 _ => {
                                     log::warn!("Unknown menu item selected: {}", menu_item.id);
@@ -429,6 +541,7 @@ impl<B: Backend + std::io::Write> App<B> {
                 // but standard dialog navigation (left/right/enter/esc) is handled by the active_dialog block.
                 // For now, this block will do nothing as the active_dialog logic takes precedence.
             },
+            Screen::KeyManagement => self.handle_key_management_input(key_code),
             Screen::SystemInstallation => {
                 // Specific key handling for SystemInstallation directly here
                 match key_code {
@@ -436,10 +549,14 @@ impl<B: Backend + std::io::Write> App<B> {
                         // Only if no dialog is active, handle Enter for the installation step
                         if self.ui_state.active_dialog.is_none() {
                             if self.ui_state.installation_step_requires_text_input() {
-                                // In text input: Enter confirms the input and proceeds to the next step.
-                                // The current input is processed in handle_installation_next_step.
-                                log::debug!("Enter pressed in text input step ({:?}), proceeding to next step.", self.ui_state.installation_step);
-                                self.handle_installation_next_step(); 
+                                // In text input: Enter moves to the next field and leaves the step after the last one.
+                                // The input is processed in handle_installation_next_step.
+                                if let Some(form) = self.ui_state.step_form_mut().filter(|form| !form.is_last_focused()) {
+                                    form.focus_next();
+                                } else {
+                                    log::debug!("Enter pressed in text input step ({:?}), proceeding to next step.", self.ui_state.installation_step);
+                                    self.handle_installation_next_step();
+                                }
                             } else {
                                 // Not a text input step: Enter proceeds to the next step normally.
                                 self.handle_installation_next_step(); 
@@ -449,7 +566,7 @@ impl<B: Backend + std::io::Write> App<B> {
                         }
                     }
                     // Beispiel: 'd' für Test-Dialog (kann später entfernt werden)
-                    KeyCode::Char('d') | KeyCode::Char('D') => {
+                    KeyCode::Char('d') | KeyCode::Char('D') if !self.ui_state.installation_step_requires_text_input() => {
                         if self.ui_state.active_dialog.is_none() { // Nur wenn kein anderer Dialog aktiv ist
                              self.ui_state.active_dialog = Some(DialogType::YesNo {
                                 title_key: "DIALOG_YESNO_EXAMPLE_TITLE".to_string(),
@@ -482,6 +599,353 @@ impl<B: Backend + std::io::Write> App<B> {
         // self.ui_state.current_screen = Screen::ConfirmExit; // NO LONGER CHANGING current_screen
     }
 
+    /// Show the dialog asking whether the running installation should be cancelled
+    fn confirm_cancel_installation(&mut self) {
+        if self.ui_state.active_dialog.is_some() {
+            return;
+        }
+        if self.install_handle.as_ref().is_some_and(|h| h.is_cancel_requested()) {
+            log::debug!("Installation cancellation already requested, waiting for the worker.");
+            return;
+        }
+        self.ui_state.active_dialog = Some(DialogType::YesNo {
+            title_key: "INSTALL_CANCEL_TITLE".to_string(),
+            message_key: "INSTALL_CANCEL_MESSAGE".to_string(),
+        });
+        self.ui_state.dialog_selected_option = 1; // Default to "No"
+    }
+
+    /// Start the installation worker for the collected configuration
+    fn start_installation_run(&mut self) {
+        if self.install_handle.is_some() {
+            log::warn!("Installation worker already running, not starting another one.");
+            return;
+        }
+        self.ui_state.log_buffer.clear();
+        self.ui_state.install_progress = InstallProgress {
+            running: true,
+            ..Default::default()
+        };
+        self.prepare_installation_config();
+
+        let config = self.ui_state.installation_config.clone();
+        self.install_handle = Some(start_installation(config, self.config.dry_run));
+    }
+
+    /// Fill the installation config with the defaults and the settings of config.yaml
+    ///
+    /// Runs when the summary is entered, so it shows what will be installed,
+    /// and again right before the installation starts.
+    fn prepare_installation_config(&mut self) {
+        let installation_config = &mut self.ui_state.installation_config;
+        installation_config
+            .locale
+            .get_or_insert_with(|| locale_for_language(&self.config.current_lang));
+        installation_config
+            .keyboard_layout
+            .get_or_insert_with(|| self.config.keyboard.clone());
+        installation_config.timezone.get_or_insert_with(detect_timezone);
+        installation_config.source = InstallSource::from_config(
+            self.config.offline_repository.as_deref(),
+            self.config.offline_image.as_deref(),
+        );
+        installation_config.bootloader = self.config.bootloader;
+        installation_config.uki = self.config.uki;
+        installation_config
+            .bootloader_id
+            .get_or_insert_with(|| read_os_release().get("ID").cloned().unwrap_or_else(|| DEFAULT_BOOTLOADER_ID.to_string()));
+        installation_config.hybrid_boot = self.config.hybrid_boot;
+        installation_config.embed_keyfile = self.config.embed_keyfile;
+        installation_config.luks_profile = self.config.luks_profile.clone();
+        installation_config.boot_luks_profile = self.config.boot_luks_profile.clone();
+        installation_config.boot_usb = self.config.boot_usb.clone();
+        installation_config.boot_usb_backup = self.config.boot_usb_backup.clone();
+        installation_config.recovery_key = self.config.recovery_key;
+        installation_config.recovery_sheet_dir = self.config.recovery_sheet_dir.clone();
+        installation_config.ssh_authorized_keys = self.config.ssh_authorized_keys.clone();
+    }
+
+    /// Expected unlock time of the planned containers, shown on the summary
+    fn estimate_unlock_time(&mut self) {
+        let config = &self.ui_state.installation_config;
+        let plan = select_backend(&detect_package_manager())
+            .and_then(|backend| InstallPlan::from_config(config, Path::new(DEFAULT_TARGET_ROOT), detect_efi(), backend.family()));
+        self.ui_state.unlock_estimates = match plan {
+            Ok(plan) => plan
+                .luks_containers()
+                .into_iter()
+                .map(|(name, profile, by_grub)| (name, profile.unlock_estimate(by_grub)))
+                .collect(),
+            Err(e) => {
+                log::warn!("No unlock time estimate: {:#}", e);
+                Vec::new()
+            }
+        };
+    }
+
+    /// Apply events from the installation worker to the UI state
+    fn poll_installation(&mut self) {
+        let Some(handle) = &self.install_handle else {
+            return;
+        };
+
+        let mut outcome = None;
+        for event in handle.drain_events() {
+            match event {
+                InstallEvent::Phase { phase, percent } => {
+                    self.ui_state.install_progress.phase = Some(phase);
+                    self.ui_state.install_progress.percent = percent;
+                    self.ui_state.push_log_line(format!("==> {}", get_text(phase.title_key())));
+                }
+                InstallEvent::Command(command) => {
+                    self.ui_state.push_log_line(format!("$ {}", command));
+                    self.ui_state.install_progress.current_command = Some(command);
+                }
+                InstallEvent::Output(line) => self.ui_state.push_log_line(line),
+                InstallEvent::RecoveryKey(sheet) => self.ui_state.install_progress.recovery = Some(sheet),
+                InstallEvent::Finished | InstallEvent::Failed(_) | InstallEvent::Cancelled => {
+                    outcome = Some(event);
+                }
+            }
+        }
+
+        let Some(outcome) = outcome else {
+            return;
+        };
+        self.install_handle = None;
+        let progress = &mut self.ui_state.install_progress;
+        progress.running = false;
+        progress.current_command = None;
+
+        match outcome {
+            InstallEvent::Finished => {
+                log::info!("Installation worker finished successfully.");
+                progress.percent = 100;
+                progress.finished = true;
+                self.handle_installation_next_step();
+            }
+            InstallEvent::Failed(error) => {
+                log::error!("Installation worker failed: {}", error);
+                self.ui_state.push_log_line(format!("ERROR: {}", error));
+                self.ui_state.install_progress.error = Some(error);
+                self.mark_current_task(InstallationTaskStatus::Failed);
+            }
+            InstallEvent::Cancelled => {
+                log::warn!("Installation worker cancelled.");
+                self.ui_state.install_progress.cancelled = true;
+                self.mark_current_task(InstallationTaskStatus::Failed);
+            }
+            _ => {}
+        }
+    }
+
+    /// Forward the protocol of a running in-place encryption and report its outcome
+    fn poll_key_worker(&mut self) {
+        let Some((handle, _)) = &self.key_worker else {
+            return;
+        };
+
+        let mut outcome = None;
+        for event in handle.drain_events() {
+            match event {
+                InstallEvent::Command(command) => self.ui_state.push_log_line(format!("$ {}", command)),
+                InstallEvent::Output(line) => self.ui_state.push_log_line(line),
+                InstallEvent::Finished | InstallEvent::Failed(_) | InstallEvent::Cancelled => outcome = Some(event),
+                InstallEvent::Phase { .. } | InstallEvent::RecoveryKey(_) => {}
+            }
+        }
+
+        let Some(outcome) = outcome else {
+            return;
+        };
+        let Some((_, done)) = self.key_worker.take() else {
+            return;
+        };
+        let status = match outcome {
+            InstallEvent::Finished => {
+                log::info!("In-place encryption finished");
+                Ok(done)
+            }
+            InstallEvent::Failed(error) => {
+                log::error!("In-place encryption failed: {}", error);
+                self.ui_state.push_log_line(format!("ERROR: {}", error));
+                Err(error)
+            }
+            _ => {
+                log::warn!("In-place encryption cancelled");
+                Err(get_text("KEYS_INPLACE_CANCELLED"))
+            }
+        };
+        self.ui_state.key_management.status = Some(status);
+        self.refresh_key_dump();
+    }
+
+    fn mark_current_task(&mut self, status: InstallationTaskStatus) {
+        if let Some(task) = self.ui_state.installation_tasks.get_mut(self.ui_state.current_installation_task_index) {
+            task.status = status;
+        }
+    }
+
+    /// Resolve the item selected in the disk setup list to the disk it belongs to
+    fn selected_target_disk(&self) -> Option<String> {
+        let selected = self.ui_state.disk_setup_selected_item_path.as_ref()?;
+        let info = self.ui_state.system_disk_info.as_ref()?;
+        info.disks
+            .iter()
+            .find(|disk| disk.path == *selected || disk.partitions.iter().any(|p| selected.starts_with(&p.path)))
+            .map(|disk| disk.path.clone())
+    }
+
+    /// Store the input of the step that is being left in the installation config.
+    /// Returns false if the input is invalid and the wizard must not advance.
+    fn commit_installation_step_input(&mut self) -> bool {
+        match self.ui_state.installation_step {
+            Some(InstallationStep::DiskSetup) => match self.selected_target_disk() {
+                Some(disk) => {
+                    log::info!("Installation target disk: {}", disk);
+                    self.ui_state.installation_config.target_disk = Some(disk);
+                }
+                None => {
+                    let key = if self.ui_state.disk_setup_selected_item_path.is_some() {
+                        "LANG_DISK_SETUP_INVALID_TARGET"
+                    } else {
+                        "LANG_DISK_SETUP_NO_TARGET"
+                    };
+                    self.ui_state.show_error(&get_text("TASK_DISK_SETUP"), &get_text(key));
+                    return false;
+                }
+            },
+            Some(InstallationStep::SecureBootChoice) => {
+                let mode = SecureBootMode::all()[self.ui_state.secure_boot_selected];
+                log::info!("Secure Boot mode: {}", mode);
+                let config = &mut self.ui_state.installation_config;
+                config.secure_boot = mode;
+                if mode != SecureBootMode::Mok {
+                    config.mok_password = None;
+                } else if config.mok_password.is_none() {
+                    match secure_boot::generate_mok_password() {
+                        Ok(password) => config.mok_password = Some(password),
+                        Err(e) => {
+                            self.ui_state.show_error(&get_text("TASK_SECURE_BOOT"), &e.to_string());
+                            return false;
+                        }
+                    }
+                }
+            }
+            Some(InstallationStep::UserSetup) => {
+                let form = &mut self.ui_state.user_setup;
+                if !form.validate() {
+                    return false;
+                }
+                if form.filled("user_password").is_some() && form.filled("username").is_none() {
+                    form.set_error("username", get_text("USER_SETUP_PASSWORD_WITHOUT_USER"));
+                    return false;
+                }
+                for id in ["user_password", "root_password", "luks_password"] {
+                    let confirm = format!("{}_confirm", id);
+                    if form.value(id) != form.value(&confirm) {
+                        form.set_error(&confirm, get_text("USER_SETUP_MISMATCH"));
+                        return false;
+                    }
+                }
+                let config = &mut self.ui_state.installation_config;
+                config.hostname = form.filled("hostname");
+                config.username = form.filled("username");
+                config.user_password = form.filled("user_password").map(SecretString::from);
+                config.root_password = form.filled("root_password").map(SecretString::from);
+                config.luks_password = form.filled("luks_password").map(SecretString::from);
+                log::info!("User setup: host {:?}, user {:?}", config.hostname, config.username);
+            }
+            Some(InstallationStep::Tpm2Setup) => {
+                let encrypted = self.ui_state.installation_config.luks_password.is_some();
+                let form = &mut self.ui_state.tpm2;
+                if encrypted && !form.validate() {
+                    return false;
+                }
+                let with_pin = form.checked("tpm2_with_pin");
+                if with_pin && form.value("tpm2_pin") != form.value("tpm2_pin_confirm") {
+                    form.set_error("tpm2_pin_confirm", get_text("USER_SETUP_MISMATCH"));
+                    return false;
+                }
+                // TPM2 unlocks the root container, so it needs an encrypted root
+                let settings = (encrypted && form.checked("tpm2")).then(|| Tpm2Settings {
+                    // Checked by the form already
+                    pcrs: tpm2::parse_pcrs(form.value("tpm2_pcrs")).unwrap_or_default(),
+                    with_pin,
+                    device: self.config.tpm2.as_ref().map_or_else(|| tpm2::DEFAULT_TPM2_DEVICE.to_string(), |tpm2| tpm2.device.clone()),
+                });
+                let config = &mut self.ui_state.installation_config;
+                config.tpm2_pin = settings.as_ref().filter(|settings| settings.with_pin).and_then(|_| form.filled("tpm2_pin")).map(SecretString::from);
+                log::info!("TPM2 unlock: {:?}", settings);
+                config.tpm2 = settings;
+            }
+            Some(InstallationStep::Summary) => {
+                // The worker would only fail after the disk was wiped
+                let config = &self.ui_state.installation_config;
+                if let Some(Err(e)) = config.tpm2.as_ref().map(|settings| Tpm2Plan::from_settings(settings, config.tpm2_pin.as_ref())) {
+                    self.ui_state.show_error(&get_text("TASK_TPM2"), &e.to_string());
+                    return false;
+                }
+                let encrypted = self.ui_state.installation_config.luks_password.is_some();
+                let form = &mut self.ui_state.header_backup;
+                if encrypted && !form.validate() {
+                    return false;
+                }
+                if form.value("header_backup") == HEADER_BACKUP_USB
+                    && form.value("header_usb_passphrase") != form.value("header_usb_passphrase_confirm")
+                {
+                    form.set_error("header_usb_passphrase_confirm", get_text("USER_SETUP_MISMATCH"));
+                    return false;
+                }
+                let store = match form.value("header_backup") {
+                    _ if !encrypted => None,
+                    HEADER_BACKUP_DIRECTORY => form.filled("header_dir").map(|dir| HeaderStore::Directory(PathBuf::from(dir))),
+                    HEADER_BACKUP_USB => form.filled("header_usb").map(|device| HeaderStore::EncryptedUsb {
+                        device,
+                        passphrase: SecretString::from(form.value("header_usb_passphrase")),
+                        format: form.checked("header_usb_format"),
+                    }),
+                    _ => None,
+                };
+                log::info!("LUKS header backup: {}", store.as_ref().map_or_else(|| "none".to_string(), HeaderStore::to_string));
+                self.ui_state.installation_config.header_backup = store;
+            }
+            Some(InstallationStep::NetworkConfig) => {
+                let form = &mut self.ui_state.network;
+                if !form.validate() {
+                    return false;
+                }
+                let method = if form.value("method") == METHOD_STATIC { AddressMethod::Static } else { AddressMethod::Dhcp };
+                let settings = NetworkSettings {
+                    method,
+                    interface: form.filled("interface").filter(|interface| interface != ANY_INTERFACE),
+                    address: form.filled("address"),
+                    gateway: form.filled("gateway"),
+                    // Checked by the form already
+                    dns: form.filled("dns").and_then(|dns| network::parse_dns(&dns).ok()).unwrap_or_default(),
+                    mtu: form.filled("mtu").and_then(|mtu| mtu.parse().ok()),
+                    ipv6: form.checked("ipv6"),
+                };
+                // The form checked every value on its own, left are the checks across fields
+                if let (AddressMethod::Static, Some(address), Some(gateway)) = (method, &settings.address, &settings.gateway) {
+                    if network::is_ipv6(address) != network::is_ipv6(gateway) {
+                        form.set_error("gateway", get_text("FORM_ERROR_GATEWAY_FAMILY"));
+                        return false;
+                    }
+                }
+                if let Err(error) = settings.validate() {
+                    log::warn!("Network settings rejected: {:#}", error);
+                    form.set_error("dns", get_text("FORM_ERROR_DNS_IPV6"));
+                    return false;
+                }
+                log::info!("Network: {:?}", settings);
+                self.ui_state.installation_config.network = Some(settings);
+            }
+            _ => {}
+        }
+        true
+    }
+
     /// Restore terminal to original state
     pub fn restore_terminal(&mut self) -> Result<()> {
         self.terminal.clear()?;
@@ -497,6 +961,15 @@ impl<B: Backend + std::io::Write> App<B> {
     fn handle_installation_next_step(&mut self) {
         log::info!("Attempting to navigate to next installation step.");
 
+        if self.ui_state.installation_step == Some(InstallationStep::Installing) && !self.ui_state.install_progress.finished {
+            log::debug!("Installation has not finished yet, staying on the Installing step.");
+            return;
+        }
+
+        if !self.commit_installation_step_input() {
+            return;
+        }
+
         if self.ui_state.current_installation_task_index < self.ui_state.installation_tasks.len() - 1 {
             // Mark current task as completed
             if let Some(task) = self.ui_state.installation_tasks.get_mut(self.ui_state.current_installation_task_index) {
@@ -540,6 +1013,15 @@ impl<B: Backend + std::io::Write> App<B> {
                             self.ui_state.disk_setup_selected_item_path = Some(self.ui_state.current_disk_display_items[0].id_path.clone());
                         }
                     }
+                } else if step_to_set == InstallationStep::SecureBootChoice {
+                    let current = self.ui_state.installation_config.secure_boot;
+                    self.ui_state.secure_boot_selected =
+                        SecureBootMode::all().iter().position(|mode| *mode == current).unwrap_or(0);
+                } else if step_to_set == InstallationStep::Summary {
+                    self.prepare_installation_config();
+                    self.estimate_unlock_time();
+                } else if step_to_set == InstallationStep::Installing {
+                    self.start_installation_run();
                 }
                 // Add 'else if' here for other steps that need initialization upon entry
             } else {
@@ -562,6 +1044,15 @@ impl<B: Backend + std::io::Write> App<B> {
     }
 
     fn handle_installation_previous_step(&mut self) {
+        if self.install_handle.is_some() {
+            log::debug!("Ignoring back navigation while the installation is running.");
+            return;
+        }
+        if self.ui_state.installation_step == Some(InstallationStep::Completed) {
+            log::debug!("Installation completed, back navigation is disabled.");
+            return;
+        }
+
         if self.ui_state.installation_tasks.is_empty() {
             log::warn!("handle_installation_previous_step called with empty installation_tasks. Returning to MainMenu.");
             self.ui_state.set_current_screen(Screen::MainMenu);
@@ -580,7 +1071,6 @@ impl<B: Backend + std::io::Write> App<B> {
             self.ui_state.installation_tasks[self.ui_state.current_installation_task_index].status = InstallationTaskStatus::Active;
             self.ui_state.installation_step = Some(self.ui_state.installation_tasks[self.ui_state.current_installation_task_index].step);
             self.ui_state.installation_task_list_state.select(Some(self.ui_state.current_installation_task_index));
-            self.ui_state.input_buffer.clear(); // Clear input buffer when changing steps
         } else {
             // At the first step (e.g., Welcome), current_installation_task_index is 0.
             // Going "back" should return to the main menu.
@@ -600,11 +1090,13 @@ impl<B: Backend + std::io::Write> App<B> {
     fn handle_installation_input(&mut self, key_code: KeyCode) {
         if let Some(step) = self.ui_state.installation_step {
             match step {
-                InstallationStep::UserSetup => { 
+                InstallationStep::SecureBootChoice => {
+                    let count = SecureBootMode::all().len();
+                    let selected = &mut self.ui_state.secure_boot_selected;
                     match key_code {
-                        KeyCode::Char(c) => self.ui_state.input_buffer.push(c),
-                        KeyCode::Backspace => { self.ui_state.input_buffer.pop(); },
-                        _ => { }
+                        KeyCode::Up => *selected = (*selected + count - 1) % count,
+                        KeyCode::Down => *selected = (*selected + 1) % count,
+                        _ => {}
                     }
                 }
                 InstallationStep::DiskSetup => {
@@ -727,6 +1219,19 @@ impl<B: Backend + std::io::Write> App<B> {
                         } else {
                             self.ui_state.active_dialog = None; 
                         }
+                    } else if title_key.starts_with("KEYS_") {
+                        self.ui_state.active_dialog = None;
+                        if choice_is_yes {
+                            self.run_key_action();
+                        }
+                    } else if title_key == "INSTALL_CANCEL_TITLE" {
+                        if choice_is_yes {
+                            if let Some(handle) = &self.install_handle {
+                                handle.cancel();
+                                self.ui_state.push_log_line(get_text("INSTALL_CANCEL_REQUESTED"));
+                            }
+                        }
+                        self.ui_state.active_dialog = None;
                     } else {
                         log::warn!("Unhandled YesNo dialog confirmation for title_key: {}", title_key);
                         self.ui_state.active_dialog = None; 
@@ -744,6 +1249,428 @@ impl<B: Backend + std::io::Write> App<B> {
         }
     }
 
+    /// Open the key management screen with the LUKS containers of the disk probe
+    fn start_key_management(&mut self) {
+        let containers = self.ui_state.system_disk_info.as_ref().map(keyslots::containers).unwrap_or_default();
+        log::info!("Key management: {} LUKS container(s) found", containers.len());
+        self.ui_state.key_management = KeyManagementState {
+            containers,
+            keyboard_layout: self.config.keyboard.clone(),
+            ..Default::default()
+        };
+        self.refresh_key_dump();
+        self.ui_state.set_current_screen(Screen::KeyManagement);
+    }
+
+    /// Runner for key management commands; in dry-run mode every container shows a sample header
+    fn key_runner(&self) -> Box<dyn CommandRunner> {
+        if self.config.dry_run {
+            let mut runner = DryRunCommandRunner::new();
+            runner.respond_to("cryptsetup luksDump", keyslots::DRY_RUN_DUMP);
+            runner.respond_to("cryptsetup luksUUID", "00000000-0000-4000-8000-000000000000");
+            Box::new(runner)
+        } else {
+            Box::new(SystemCommandRunner::new())
+        }
+    }
+
+    /// Re-read keyslots and tokens of the selected container
+    fn refresh_key_dump(&mut self) {
+        let device = self.ui_state.key_management.selected().map(|container| container.device.clone());
+        let dump = device.map(|device| keyslots::dump(self.key_runner().as_mut(), &device).map_err(|e| e.to_string()));
+        self.ui_state.key_management.dump = dump;
+    }
+
+    /// Backspace on the key management screen: close the form, leave the actions, then the screen
+    fn key_management_back(&mut self) {
+        let keys = &mut self.ui_state.key_management;
+        if keys.form.is_some() {
+            keys.form = None;
+        } else if keys.focus == KeyFocus::Actions {
+            keys.focus = KeyFocus::Containers;
+        } else {
+            self.ui_state.set_current_screen(Screen::MainMenu);
+        }
+    }
+
+    fn handle_key_management_input(&mut self, key_code: KeyCode) {
+        let keys = &mut self.ui_state.key_management;
+        // Key material is shown once; any key dismisses it
+        if keys.revealed.take().is_some() {
+            return;
+        }
+        if let Some(form) = keys.form.as_mut() {
+            // Editing and focus keys were taken by the form already
+            match key_code {
+                KeyCode::Esc => keys.form = None,
+                KeyCode::Enter if !form.form.is_last_focused() => form.form.focus_next(),
+                KeyCode::Enter => self.submit_key_form(),
+                _ => {}
+            }
+            return;
+        }
+
+        let container_count = keys.containers.len();
+        let action_count = KeyAction::all().len();
+        match (keys.focus, key_code) {
+            (KeyFocus::Containers, KeyCode::Up | KeyCode::Down) if container_count > 0 => {
+                keys.selected_container = if key_code == KeyCode::Up {
+                    (keys.selected_container + container_count - 1) % container_count
+                } else {
+                    (keys.selected_container + 1) % container_count
+                };
+                keys.status = None;
+                self.refresh_key_dump();
+            }
+            (KeyFocus::Containers, KeyCode::Enter | KeyCode::Right) => {
+                keys.focus = KeyFocus::Actions;
+            }
+            (KeyFocus::Actions, KeyCode::Up) => keys.selected_action = (keys.selected_action + action_count - 1) % action_count,
+            (KeyFocus::Actions, KeyCode::Down) => keys.selected_action = (keys.selected_action + 1) % action_count,
+            (KeyFocus::Actions, KeyCode::Left) => keys.focus = KeyFocus::Containers,
+            (KeyFocus::Actions, KeyCode::Enter) => {
+                keys.form = Some(KeyForm::new(keys.selected_action()));
+                keys.status = None;
+            }
+            _ => {}
+        }
+    }
+
+    /// Show or hide the focused password of the key form or the wizard step
+    fn toggle_reveal(&mut self) {
+        match self.ui_state.current_screen {
+            Screen::KeyManagement => {
+                if let Some(form) = self.ui_state.key_management.form.as_mut() {
+                    form.form.focused_input_mut().toggle_reveal();
+                }
+            }
+            Screen::SystemInstallation => {
+                if let Some(form) = self.ui_state.step_form_mut() {
+                    form.focused_input_mut().toggle_reveal();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Fill the focused password field with a generated passphrase and show it once
+    fn generate_passphrase(&mut self) {
+        let passphrase = match diceware::generate(&self.config.current_lang, &self.config.diceware) {
+            Ok(passphrase) => passphrase,
+            Err(e) => {
+                log::error!("Generating a passphrase failed: {}", e);
+                return;
+            }
+        };
+        let filled = match self.ui_state.current_screen {
+            Screen::KeyManagement => {
+                self.ui_state.key_management.form.as_mut().is_some_and(|form| form.form.fill_secret(&passphrase.value))
+            }
+            Screen::SystemInstallation => self.ui_state.step_form_mut().is_some_and(|form| form.fill_secret(&passphrase.value)),
+            _ => false,
+        };
+        if filled {
+            log::info!("Generated a {} word passphrase ({:.0} bit)", passphrase.words, passphrase.entropy_bits);
+            self.ui_state.generated_passphrase = Some(passphrase);
+        }
+    }
+
+    /// Validate the open form; destructive actions ask for confirmation first
+    fn submit_key_form(&mut self) {
+        let keys = &mut self.ui_state.key_management;
+        let device = keys.selected().map_or(String::new(), |container| container.device.clone());
+        let dump = keys.selected_dump().cloned();
+        let Some(form) = keys.form.as_mut() else {
+            return;
+        };
+        if form.action.needs_container() && device.is_empty() {
+            keys.status = Some(Err(get_text("KEYS_ERROR_NO_CONTAINER")));
+            return;
+        }
+        if let Err(error) = form.validate(&device, dump.as_ref()) {
+            keys.status = Some(Err(error));
+            return;
+        }
+        if let Some((title_key, message_key)) = form.confirmation() {
+            self.ui_state.active_dialog = Some(DialogType::YesNo {
+                title_key: title_key.to_string(),
+                message_key: message_key.to_string(),
+            });
+            self.ui_state.dialog_selected_option = 1; // Default to "No"
+        } else {
+            self.run_key_action();
+        }
+    }
+
+    /// Run the action of the validated form on the selected container
+    fn run_key_action(&mut self) {
+        let keys = &mut self.ui_state.key_management;
+        let Some(form) = keys.form.take() else {
+            return;
+        };
+        // Empty for actions that do not work on a container
+        let container = keys.selected().cloned().unwrap_or_default();
+        let dump = keys.selected_dump().cloned().unwrap_or_default();
+        let mut runner = self.key_runner();
+        let runner = runner.as_mut();
+        let device = container.device.as_str();
+        let slot = form.slot().unwrap_or_default();
+        let existing = form.value("existing");
+        let done = get_text("KEYS_ACTION_DONE");
+        let mut revealed = None;
+        let gpg_home = GpgHome::default();
+        let vault_path = match (form.value("vault_path"), &self.config.vault_path) {
+            ("", Some(path)) => PathBuf::from(path),
+            ("", None) => vault::default_path(),
+            (path, _) => PathBuf::from(path),
+        };
+        // Key produced by the action, stored if the form has a vault passphrase
+        let mut produced: Option<VaultEntry> = None;
+        let hostname = std::fs::read_to_string("/etc/hostname").unwrap_or_default().trim().to_string();
+        let uuid = dump.uuid.clone().or_else(|| container.uuid.clone()).unwrap_or_default();
+        let new_sheet = |key: RecoveryKey| RecoverySheet::new(&hostname, device, &uuid, key);
+        // Vaults may live on a USB key shared by several machines
+        let owner = if hostname.is_empty() { device.to_string() } else { format!("{} on {}", device, hostname) };
+        // Header files live in a directory or, for verify and restore, on an encrypted USB stick
+        let usb_store = |device: &str| HeaderStore::EncryptedUsb {
+            device: device.to_string(),
+            passphrase: SecretString::from(form.value("usb_passphrase")),
+            format: form.checked("format"),
+        };
+        let header_location = || {
+            let header = Path::new(form.value("header"));
+            match form.value("usb") {
+                "" => (
+                    HeaderStore::Directory(header.parent().map(Path::to_path_buf).unwrap_or_default()),
+                    PathBuf::from(header.file_name().unwrap_or_default()),
+                ),
+                usb => (usb_store(usb), header.strip_prefix("/").unwrap_or(header).to_path_buf()),
+            }
+        };
+        let result = match form.action {
+            KeyAction::AddPassphrase => keyslots::add_passphrase(runner, device, existing, form.value("new")).map(|()| {
+                produced = Some(VaultEntry::new(EntryKind::Passphrase, &format!("LUKS {}", uuid), &owner, form.value("new")));
+                done
+            }),
+            KeyAction::AddKeyfile => {
+                let keyfile = Path::new(form.value("keyfile"));
+                keyslots::add_keyfile(runner, device, existing, keyfile).and_then(|()| {
+                    if !form.value("vault").is_empty() {
+                        let label = format!("{} (LUKS {})", keyfile.display(), uuid);
+                        produced = Some(VaultEntry::from_file(EntryKind::Keyfile, &label, &owner, keyfile)?);
+                    }
+                    Ok(done)
+                })
+            }
+            KeyAction::ChangePassphrase => {
+                keyslots::change_passphrase(runner, device, slot, existing, form.value("new")).map(|()| done)
+            }
+            KeyAction::KillSlot => keyslots::kill_slot(runner, device, &dump, slot, existing).map(|()| done),
+            KeyAction::ConvertPbkdf => {
+                let profile = LuksProfile::presets()
+                    .into_iter()
+                    .find(|(name, _)| *name == form.value("profile"))
+                    .map(|(_, profile)| profile)
+                    .unwrap_or_default();
+                keyslots::convert_pbkdf(runner, device, &dump, slot, existing, &profile).map(|()| done)
+            }
+            KeyAction::AddRecoveryKey => RecoveryKey::generate().and_then(|key| {
+                recovery_key::enroll(runner, device, existing, &key, &[])?;
+                produced = Some(VaultEntry::new(EntryKind::RecoveryKey, &format!("LUKS {}", uuid), &owner, key.as_str()));
+                let sheet = new_sheet(key);
+                let message = match form.value("sheet_dir") {
+                    "" => get_text("KEYS_RECOVERY_ADDED"),
+                    dir => {
+                        sheet.write(runner, Path::new(dir))?;
+                        format!("{} {}", get_text("KEYS_RECOVERY_SHEET_WRITTEN"), dir)
+                    }
+                };
+                revealed = Some(Revealed::RecoveryKey(sheet));
+                Ok(message)
+            }),
+            KeyAction::SplitRecoveryKey => RecoveryKey::generate().and_then(|key| {
+                let threshold = form.value("threshold").parse().unwrap_or_default();
+                let shares = key.split(threshold, form.value("count").parse().unwrap_or_default())?;
+                recovery_key::enroll(runner, device, existing, &key, &[])?;
+                // Only the shares leave this function, the key itself is never shown
+                let sheet = new_sheet(key);
+                let message = match form.value("sheet_dir") {
+                    "" => get_text("KEYS_SHARES_CREATED"),
+                    dir => {
+                        sheet.write_shares(runner, Path::new(dir), &shares)?;
+                        format!("{} {}", get_text("KEYS_SHARES_WRITTEN"), dir)
+                    }
+                };
+                revealed = Some(Revealed::Shares(sheet, shares));
+                Ok(message)
+            }),
+            KeyAction::CombineShares => read_shares(form.value("shares")).and_then(|shares| {
+                let key = RecoveryKey::combine(&shares)?;
+                if form.value("mode") == "unlock" {
+                    let name = container.uuid.as_ref().map_or_else(|| "luks-recovered".to_string(), |uuid| format!("luks-{}", uuid));
+                    keyslots::open(runner, device, &name, key.as_str())?;
+                    Ok(format!("{} /dev/mapper/{}", get_text("KEYS_OPENED_AS"), name))
+                } else {
+                    keyslots::add_passphrase(runner, device, key.as_str(), form.value("new")).map(|()| done)
+                }
+            }),
+            KeyAction::BackupHeader => {
+                let target = form.value("target");
+                let store = if target.starts_with("/dev/") {
+                    usb_store(target)
+                } else {
+                    HeaderStore::Directory(PathBuf::from(target))
+                };
+                header_backup::backup_header(runner, device, &store)
+                    .map(|name| format!("{} {} ({})", get_text("KEYS_HEADER_SAVED"), name.display(), store))
+            }
+            KeyAction::VerifyHeader => {
+                let (store, header) = header_location();
+                let passphrase = Some(existing).filter(|passphrase| !passphrase.is_empty());
+                header_backup::verify_header(runner, &store, &header, device, passphrase)
+                    .map(|uuid| format!("{} {}", get_text("KEYS_HEADER_VERIFIED"), uuid))
+            }
+            KeyAction::RestoreHeader => {
+                let (store, header) = header_location();
+                header_backup::restore_header(runner, &store, &header, device, existing).map(|()| get_text("KEYS_HEADER_RESTORED"))
+            }
+            KeyAction::EncryptInPlace => {
+                let partition = form.value("partition");
+                let content = match form.value("content") {
+                    "lvm" => ExistingRoot::LvmPv {
+                        volume_group: form.value("volume_group").to_string(),
+                        root_lv: form.value("root_lv").to_string(),
+                    },
+                    _ => ExistingRoot::Ext4,
+                };
+                let backup = match form.value("backup_kind") {
+                    "image" => BackupCopy::Image(PathBuf::from(form.value("backup"))),
+                    _ => BackupCopy::Directory(PathBuf::from(form.value("backup"))),
+                };
+                let plan = InPlacePlan {
+                    device: partition.to_string(),
+                    content,
+                    mapper_name: DEFAULT_LUKS_MAPPER.to_string(),
+                    passphrase: SecretString::from(form.value("new")),
+                    profile: LuksProfile::presets()
+                        .into_iter()
+                        .find(|(name, _)| *name == form.value("profile"))
+                        .map(|(_, profile)| profile)
+                        .unwrap_or_default(),
+                    backup,
+                    mount_point: PathBuf::from(in_place_encryption::DEFAULT_MOUNT_POINT),
+                };
+                if self.key_worker.is_some() {
+                    Err(anyhow::anyhow!(get_text("KEYS_INPLACE_BUSY")))
+                } else {
+                    let done = format!("{} {}", get_text("KEYS_INPLACE_DONE"), partition);
+                    self.key_worker = Some((in_place_encryption::start(plan, self.config.dry_run), done));
+                    self.ui_state.show_log_panel = true;
+                    Ok(format!("{} {}", get_text("KEYS_INPLACE_STARTED"), partition))
+                }
+            }
+            KeyAction::GenerateSshKey => ssh_keys::generate(form.value("comment"), Some(form.value("new"))).and_then(|key| {
+                let public_path = key.write(runner, Path::new(form.value("path")))?;
+                let label = format!("{} {}", form.value("path"), key.fingerprint);
+                produced = Some(VaultEntry::new(EntryKind::SshKey, &label, &hostname, &key.private_key).with_notes(&key.public_key));
+                Ok(format!("{} {} ({})", get_text("KEYS_SSH_GENERATED"), key.fingerprint, public_path.display()))
+            }),
+            KeyAction::ImportSshKeys => ssh_keys::read_public_keys(Path::new(form.value("source")))
+                .and_then(|keys| ssh_keys::import_authorized_keys(runner, form.value("user"), &keys))
+                .map(|added| format!("{} {}", get_text("KEYS_SSH_IMPORTED"), added)),
+            KeyAction::GenerateGpgKey => {
+                let spec = KeySpec {
+                    name: form.value("name").to_string(),
+                    email: form.value("email").to_string(),
+                    comment: Some(form.value("comment").to_string()).filter(|comment| !comment.is_empty()),
+                    expire: Some(form.value("expire")).filter(|expire| !expire.is_empty()).unwrap_or(gpg::DEFAULT_EXPIRE).to_string(),
+                    passphrase: Some(form.value("new")).filter(|passphrase| !passphrase.is_empty()).map(SecretString::from),
+                };
+                gpg::generate_key(runner, &gpg_home, &spec).map(|fingerprint| format!("{} {}", get_text("KEYS_GPG_GENERATED"), fingerprint))
+            }
+            KeyAction::ListGpgKeys => gpg::list_keys(runner, &gpg_home).map(|mut keys| {
+                let filter = form.value("filter").to_lowercase();
+                keys.retain(|key| {
+                    key.primary.fingerprint.to_lowercase().contains(&filter)
+                        || key.user_ids.iter().any(|user_id| user_id.to_lowercase().contains(&filter))
+                });
+                let message = format!("{} {}", get_text("KEYS_GPG_LISTED"), keys.len());
+                revealed = Some(Revealed::GpgKeys(keys));
+                message
+            }),
+            KeyAction::ExportGpgKey => {
+                let (key, target) = (form.value("key"), Path::new(form.value("target")));
+                gpg::export_public(runner, &gpg_home, key, target).and_then(|public| {
+                    let mut files = vec![public.display().to_string()];
+                    if form.value("what") != "public" {
+                        files.push(gpg::export_secret(runner, &gpg_home, key, existing, target)?.display().to_string());
+                    }
+                    Ok(format!("{} {}", get_text("KEYS_GPG_EXPORTED"), files.join(", ")))
+                })
+            }
+            KeyAction::RevokeGpgKey => {
+                gpg::revocation_certificate(runner, &gpg_home, form.value("key"), existing, Path::new(form.value("target")))
+                    .map(|path| format!("{} {}", get_text("KEYS_GPG_REVOCATION_WRITTEN"), path.display()))
+            }
+            KeyAction::ImportGpgKeys => gpg::import_keys(runner, &gpg_home, Path::new(form.value("source")), existing)
+                .map(|count| format!("{} {}", get_text("KEYS_GPG_IMPORTED"), count)),
+            KeyAction::CreateVault => {
+                if vault_path.exists() {
+                    Err(anyhow::anyhow!("{} already exists", vault_path.display()))
+                } else {
+                    Vault::new(&vault_path)
+                        .save(runner, form.value("new"))
+                        .map(|()| format!("{} {}", get_text("KEYS_VAULT_CREATED"), vault_path.display()))
+                }
+            }
+            KeyAction::BrowseVault => Vault::open(&vault_path, form.value("master")).map(|vault| {
+                let message = format!("{} {}", get_text("KEYS_VAULT_LISTED"), vault.entries.len());
+                revealed = Some(Revealed::VaultEntries(vault.entries.iter().map(VaultEntry::without_secret).collect()));
+                message
+            }),
+            KeyAction::ShowVaultEntry => Vault::open(&vault_path, form.value("master")).and_then(|vault| {
+                let number = form.value("entry").parse().unwrap_or_default();
+                revealed = Some(Revealed::VaultSecret(number, vault.entry(number)?.clone()));
+                Ok(get_text("KEYS_VAULT_SHOWN"))
+            }),
+            KeyAction::AddVaultEntry => {
+                let kind = EntryKind::from_name(form.value("kind")).unwrap_or(EntryKind::Other);
+                let (label, device) = (form.value("label"), form.value("device"));
+                match form.value("file") {
+                    "" => Ok(VaultEntry::new(kind, label, device, form.value("secret"))),
+                    file => VaultEntry::from_file(kind, label, device, Path::new(file)),
+                }
+                .and_then(|entry| vault::store(runner, &vault_path, form.value("master"), entry.with_notes(form.value("notes"))))
+                .map(|()| get_text("KEYS_VAULT_ADDED"))
+            }
+            KeyAction::RemoveVaultEntry => Vault::open(&vault_path, form.value("master")).and_then(|mut vault| {
+                let removed = vault.remove(form.value("entry").parse().unwrap_or_default())?;
+                vault.save(runner, form.value("master"))?;
+                Ok(format!("{} {}", get_text("KEYS_VAULT_REMOVED"), removed.label))
+            }),
+        };
+        // Keep the key in the vault as well; the action itself already succeeded
+        let result = match (result, produced, form.value("vault")) {
+            (Ok(message), Some(entry), master) if !master.is_empty() => match vault::store(runner, &vault_path, master, entry) {
+                Ok(()) => Ok(format!("{} {}", message, get_text("KEYS_VAULT_STORED"))),
+                Err(error) => Err(anyhow::anyhow!("{} {} {}", message, get_text("KEYS_VAULT_STORE_FAILED"), error)),
+            },
+            (result, _, _) => result,
+        };
+        self.ui_state.key_management.revealed = revealed;
+        self.ui_state.key_management.status = Some(match result {
+            Ok(message) => {
+                log::info!("Key action {:?} on {} finished", form.action, device);
+                Ok(message)
+            }
+            Err(error) => {
+                log::error!("Key action {:?} on {} failed: {}", form.action, device, error);
+                Err(error.to_string())
+            }
+        });
+        self.refresh_key_dump();
+    }
+
     fn handle_dialog_cancel(&mut self) {
         if let Some(dialog_type) = &self.ui_state.active_dialog {
             log::info!("Dialog cancelled. Dialog type: {:?}", dialog_type);
@@ -768,6 +1695,9 @@ impl<B: Backend + std::io::Write> App<B> {
         self.ui_state.installation_step = Some(InstallationStep::Welcome); // Start with Welcome
         self.ui_state.current_installation_task_index = 0; // Ensure task index is reset
         self.ui_state.installation_tasks = self.initialize_installation_tasks();
+        self.ui_state.network = network_form(&network::detect_interfaces());
+        self.ui_state.header_backup = header_backup_form(self.config.header_backup.as_deref());
+        self.ui_state.tpm2 = tpm2_form(self.config.tpm2.as_ref());
         self.ui_state.installation_task_list_state.select(Some(0)); // Select first task in list
         self.update_active_task_status(); // Set Welcome task to Active
         self.ui_state.set_current_screen(Screen::SystemInstallation);
@@ -778,7 +1708,12 @@ impl<B: Backend + std::io::Write> App<B> {
             InstallationTaskItem { id: "welcome".to_string(), title: get_text("TASK_WELCOME"), step: InstallationStep::Welcome, status: InstallationTaskStatus::Active },
             InstallationTaskItem { id: "disk_setup".to_string(), title: get_text("TASK_DISK_SETUP"), step: InstallationStep::DiskSetup, status: InstallationTaskStatus::Pending },
             InstallationTaskItem { id: "user_setup".to_string(), title: get_text("TASK_USER_SETUP"), step: InstallationStep::UserSetup, status: InstallationTaskStatus::Pending },
+            InstallationTaskItem { id: "network".to_string(), title: get_text("TASK_NETWORK_CONFIG"), step: InstallationStep::NetworkConfig, status: InstallationTaskStatus::Pending },
+            InstallationTaskItem { id: "secure_boot".to_string(), title: get_text("TASK_SECURE_BOOT"), step: InstallationStep::SecureBootChoice, status: InstallationTaskStatus::Pending },
+            InstallationTaskItem { id: "tpm2".to_string(), title: get_text("TASK_TPM2"), step: InstallationStep::Tpm2Setup, status: InstallationTaskStatus::Pending },
             InstallationTaskItem { id: "summary".to_string(), title: get_text("TASK_SUMMARY"), step: InstallationStep::Summary, status: InstallationTaskStatus::Pending },
+            InstallationTaskItem { id: "installing".to_string(), title: get_text("TASK_INSTALLING"), step: InstallationStep::Installing, status: InstallationTaskStatus::Pending },
+            InstallationTaskItem { id: "completed".to_string(), title: get_text("TASK_COMPLETED"), step: InstallationStep::Completed, status: InstallationTaskStatus::Pending },
         ]
     }
 
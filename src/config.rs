@@ -2,7 +2,7 @@ use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::{fs, path::PathBuf};
 
-use crate::get_config_dir;
+use crate::{get_config_dir, tools::{diceware::DicewareSettings, installation::{plan::{Bootloader, UkiBuilder}, LuksProfile, Tpm2Settings}}};
 
 /// Application configuration
 #[derive(Debug, Serialize, Deserialize)]
@@ -11,9 +11,67 @@ pub struct Config {
     pub keyboard: String,
     pub debug_mode: bool,
     pub log_file: String,
+    /// Record installer commands instead of executing them
+    #[serde(default = "default_dry_run")]
+    pub dry_run: bool,
+    /// Local apt/pacman/dnf repository directory for offline installations
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offline_repository: Option<PathBuf>,
+    /// Distribution ISO (file or mount point) whose live root is installed offline
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offline_image: Option<PathBuf>,
+    /// Bootloader of installed systems: grub, grub-cryptodisk or systemd-boot
+    #[serde(default)]
+    pub bootloader: Bootloader,
+    /// Build unified kernel images with ukify or objcopy (systemd-boot only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uki: Option<UkiBuilder>,
+    /// Preset of the TPM2 step; `device` is only set here
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tpm2: Option<Tpm2Settings>,
+    /// Install GRUB for both UEFI and legacy BIOS (grub-cryptodisk only)
+    #[serde(default)]
+    pub hybrid_boot: bool,
+    /// Embed a keyfile in the initramfs so the passphrase is only typed at the GRUB prompt
+    #[serde(default)]
+    pub embed_keyfile: bool,
+    /// Format parameters of the root container; chosen by bootloader when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub luks_profile: Option<LuksProfile>,
+    /// Format parameters of the /boot container (grub-cryptodisk only), GRUB compatible when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub boot_luks_profile: Option<LuksProfile>,
+    /// USB device receiving ESP, /boot and the detached root header, e.g. /dev/sdb
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub boot_usb: Option<String>,
+    /// Second USB device the boot key is cloned to after installation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub boot_usb_backup: Option<String>,
+    /// Directory preset on the summary for backups of the new LUKS headers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub header_backup: Option<String>,
+    /// Add a generated recovery key to the new LUKS containers
+    #[serde(default)]
+    pub recovery_key: bool,
+    /// Mounted removable medium receiving the recovery sheet
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recovery_sheet_dir: Option<String>,
+    /// Public key file or directory of `*.pub` files for the new user; disables SSH password logins
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssh_authorized_keys: Option<String>,
+    /// Key vault file, e.g. on a mounted USB key; the config directory when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vault_path: Option<String>,
+    /// Word count and separator of generated passphrases
+    #[serde(default)]
+    pub diceware: DicewareSettings,
     pub ui: UiConfig,
 }
 
+fn default_dry_run() -> bool {
+    true
+}
+
 /// UI-specific configuration
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UiConfig {
@@ -34,6 +92,24 @@ impl Default for Config {
             keyboard: "de".to_string(),
             debug_mode: true,
             log_file: current_working_dir.join("lunitool.log").to_string_lossy().into_owned(),
+            dry_run: default_dry_run(),
+            offline_repository: None,
+            offline_image: None,
+            bootloader: Bootloader::default(),
+            uki: None,
+            tpm2: None,
+            hybrid_boot: false,
+            embed_keyfile: false,
+            luks_profile: None,
+            boot_luks_profile: None,
+            boot_usb: None,
+            boot_usb_backup: None,
+            header_backup: None,
+            recovery_key: false,
+            recovery_sheet_dir: None,
+            ssh_authorized_keys: None,
+            vault_path: None,
+            diceware: DicewareSettings::default(),
             ui: UiConfig {
                 theme: "default".to_string(),
                 auto_size: true,
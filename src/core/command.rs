@@ -0,0 +1,314 @@
+use anyhow::{anyhow, Context, Result};
+use std::{
+    fmt,
+    io::{BufRead, BufReader, Read, Write},
+    path::PathBuf,
+    process::{Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+use zeroize::Zeroizing;
+
+/// Callback receiving every line a child process writes to stdout or stderr
+pub type OutputSink = Arc<dyn Fn(&str) + Send + Sync>;
+
+/// Description of a single external command
+#[derive(Clone, Default)]
+pub struct CommandSpec {
+    /// Program to execute (looked up in PATH)
+    pub program: String,
+    /// Arguments passed to the program
+    pub args: Vec<String>,
+    /// Data written to the child's stdin, if any; the only way secrets reach a command
+    pub stdin: Option<Zeroizing<Vec<u8>>>,
+    /// Additional environment variables
+    pub env: Vec<(String, String)>,
+    /// Working directory of the child
+    pub current_dir: Option<PathBuf>,
+}
+
+impl CommandSpec {
+    /// Create a new command for the given program
+    pub fn new(program: &str) -> Self {
+        Self {
+            program: program.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Append a single argument
+    pub fn arg<S: AsRef<str>>(mut self, arg: S) -> Self {
+        self.args.push(arg.as_ref().to_string());
+        self
+    }
+
+    /// Append several arguments
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.args.extend(args.into_iter().map(|a| a.as_ref().to_string()));
+        self
+    }
+
+    /// Feed the given data to the child's stdin
+    pub fn stdin<D: Into<Vec<u8>>>(mut self, data: D) -> Self {
+        self.stdin = Some(Zeroizing::new(data.into()));
+        self
+    }
+
+    /// Set an environment variable for the child
+    pub fn env(mut self, key: &str, value: &str) -> Self {
+        self.env.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Run the child in the given directory
+    pub fn current_dir<P: Into<PathBuf>>(mut self, dir: P) -> Self {
+        self.current_dir = Some(dir.into());
+        self
+    }
+}
+
+// stdin usually carries a passphrase; only its size is shown
+impl fmt::Debug for CommandSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CommandSpec")
+            .field("program", &self.program)
+            .field("args", &self.args)
+            .field("stdin_bytes", &self.stdin.as_ref().map(|data| data.len()))
+            .field("env", &self.env)
+            .field("current_dir", &self.current_dir)
+            .finish()
+    }
+}
+
+impl fmt::Display for CommandSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.program)?;
+        for arg in &self.args {
+            if arg.is_empty() || arg.contains(char::is_whitespace) {
+                write!(f, " '{}'", arg)?;
+            } else {
+                write!(f, " {}", arg)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Captured result of a finished command
+#[derive(Debug, Clone, Default)]
+pub struct CommandOutput {
+    /// Exit code, `None` if the child was terminated by a signal
+    pub status: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl CommandOutput {
+    /// Whether the command exited with status 0
+    pub fn success(&self) -> bool {
+        self.status == Some(0)
+    }
+}
+
+/// Executes external commands on behalf of lunitool
+pub trait CommandRunner: Send {
+    /// Run a command to completion and capture its output
+    fn run(&mut self, spec: &CommandSpec) -> Result<CommandOutput>;
+
+    /// Whether commands are only recorded instead of executed
+    fn is_dry_run(&self) -> bool {
+        false
+    }
+
+    /// Run a command and turn a non-zero exit code into an error
+    fn run_checked(&mut self, spec: &CommandSpec) -> Result<CommandOutput> {
+        let output = self.run(spec)?;
+        if !output.success() {
+            let code = output.status.map_or_else(|| "signal".to_string(), |c| c.to_string());
+            let detail = output.stderr.lines().last().unwrap_or("").trim().to_string();
+            if detail.is_empty() {
+                return Err(anyhow!("'{}' failed with exit code: {}", spec.program, code));
+            }
+            return Err(anyhow!("'{}' failed with exit code: {} ({})", spec.program, code, detail));
+        }
+        Ok(output)
+    }
+}
+
+/// Runner that spawns real processes and streams their output line by line
+#[derive(Default)]
+pub struct SystemCommandRunner {
+    sink: Option<OutputSink>,
+    cancel: Option<Arc<AtomicBool>>,
+}
+
+impl SystemCommandRunner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forward every output line of child processes to the given sink
+    pub fn with_output_sink(mut self, sink: OutputSink) -> Self {
+        self.sink = Some(sink);
+        self
+    }
+
+    /// Kill running children as soon as the flag is set
+    pub fn with_cancel_flag(mut self, cancel: Arc<AtomicBool>) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancel.as_ref().is_some_and(|c| c.load(Ordering::SeqCst))
+    }
+}
+
+/// Read a child stream line by line, forwarding each line to the sink
+fn spawn_line_reader<R: Read + Send + 'static>(stream: R, sink: Option<OutputSink>) -> thread::JoinHandle<String> {
+    thread::spawn(move || {
+        let mut collected = String::new();
+        for line in BufReader::new(stream).lines().map_while(|l| l.ok()) {
+            if let Some(sink) = &sink {
+                sink(&line);
+            }
+            collected.push_str(&line);
+            collected.push('\n');
+        }
+        collected
+    })
+}
+
+impl CommandRunner for SystemCommandRunner {
+    fn run(&mut self, spec: &CommandSpec) -> Result<CommandOutput> {
+        if self.is_cancelled() {
+            return Err(anyhow!("Cancelled before running '{}'", spec.program));
+        }
+        log::debug!("Executing: {}", spec);
+
+        let mut cmd = Command::new(&spec.program);
+        cmd.args(&spec.args)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .stdin(if spec.stdin.is_some() { Stdio::piped() } else { Stdio::null() });
+        for (key, value) in &spec.env {
+            cmd.env(key, value);
+        }
+        if let Some(dir) = &spec.current_dir {
+            cmd.current_dir(dir);
+        }
+
+        let mut child = cmd.spawn().with_context(|| format!("Failed to start '{}'", spec.program))?;
+
+        if let (Some(data), Some(mut stdin)) = (spec.stdin.clone(), child.stdin.take()) {
+            thread::spawn(move || {
+                // A child that exits early closes the pipe; nothing to report then.
+                let _ = stdin.write_all(&data);
+            });
+        }
+
+        let stdout_reader = child.stdout.take().map(|s| spawn_line_reader(s, self.sink.clone()));
+        let stderr_reader = child.stderr.take().map(|s| spawn_line_reader(s, self.sink.clone()));
+
+        let status = loop {
+            if let Some(status) = child.try_wait()? {
+                break status;
+            }
+            if self.is_cancelled() {
+                log::warn!("Cancellation requested, killing '{}'", spec.program);
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(anyhow!("'{}' was cancelled", spec.program));
+            }
+            thread::sleep(Duration::from_millis(50));
+        };
+
+        let stdout = stdout_reader.and_then(|h| h.join().ok()).unwrap_or_default();
+        let stderr = stderr_reader.and_then(|h| h.join().ok()).unwrap_or_default();
+
+        Ok(CommandOutput {
+            status: status.code(),
+            stdout,
+            stderr,
+        })
+    }
+}
+
+/// Runner that records commands without executing them
+///
+/// Canned stdout can be registered per command prefix, so callers that parse
+/// output (e.g. `blkid`) can be exercised without touching real devices.
+#[derive(Default)]
+pub struct DryRunCommandRunner {
+    history: Vec<CommandSpec>,
+    responses: Vec<(String, CommandOutput)>,
+    sink: Option<OutputSink>,
+}
+
+impl DryRunCommandRunner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forward a line per recorded command to the given sink
+    pub fn with_output_sink(mut self, sink: OutputSink) -> Self {
+        self.sink = Some(sink);
+        self
+    }
+
+    /// Return `stdout` for every command whose rendered form starts with `prefix`
+    pub fn respond_to(&mut self, prefix: &str, stdout: &str) {
+        self.responses.push((
+            prefix.to_string(),
+            CommandOutput {
+                status: Some(0),
+                stdout: stdout.to_string(),
+                stderr: String::new(),
+            },
+        ));
+    }
+
+    /// Commands recorded so far, in execution order
+    pub fn history(&self) -> &[CommandSpec] {
+        &self.history
+    }
+
+    /// Recorded commands rendered as strings
+    pub fn rendered_history(&self) -> Vec<String> {
+        self.history.iter().map(|c| c.to_string()).collect()
+    }
+}
+
+impl CommandRunner for DryRunCommandRunner {
+    fn run(&mut self, spec: &CommandSpec) -> Result<CommandOutput> {
+        let rendered = spec.to_string();
+        log::debug!("[dry-run] {}", rendered);
+        if let Some(sink) = &self.sink {
+            sink(&format!("[dry-run] {}", rendered));
+        }
+        self.history.push(spec.clone());
+
+        let output = self
+            .responses
+            .iter()
+            .find(|(prefix, _)| rendered.starts_with(prefix.as_str()))
+            .map(|(_, output)| output.clone())
+            .unwrap_or(CommandOutput {
+                status: Some(0),
+                ..Default::default()
+            });
+        Ok(output)
+    }
+
+    fn is_dry_run(&self) -> bool {
+        true
+    }
+}
@@ -153,25 +153,26 @@ pub fn create_dummy_system_disk_info() -> SystemDiskInfo {
     });
 
     // LVM VG "vg_system" on sda2 (inside LUKS)
-    let mut vg_system_lvs = Vec::new();
-    vg_system_lvs.push(LvmLogicalVolume {
-        name: "lv_root".to_string(),
-        path: "/dev/vg_system/lv_root".to_string(),
-        uuid: "lv-uuid-root".to_string(),
-        size_bytes: 100 * 1024 * 1024 * 1024, // 100 GB
-        fs_type: Some("ext4".to_string()),
-        mount_point: Some("/".to_string()),
-        ..Default::default()
-    });
-    vg_system_lvs.push(LvmLogicalVolume {
-        name: "lv_home".to_string(),
-        path: "/dev/vg_system/lv_home".to_string(),
-        uuid: "lv-uuid-home".to_string(),
-        size_bytes: 130 * 1024 * 1024 * 1024, // 130 GB
-        fs_type: Some("ext4".to_string()),
-        mount_point: Some("/home".to_string()),
-        ..Default::default()
-    });
+    let vg_system_lvs = vec![
+        LvmLogicalVolume {
+            name: "lv_root".to_string(),
+            path: "/dev/vg_system/lv_root".to_string(),
+            uuid: "lv-uuid-root".to_string(),
+            size_bytes: 100 * 1024 * 1024 * 1024, // 100 GB
+            fs_type: Some("ext4".to_string()),
+            mount_point: Some("/".to_string()),
+            ..Default::default()
+        },
+        LvmLogicalVolume {
+            name: "lv_home".to_string(),
+            path: "/dev/vg_system/lv_home".to_string(),
+            uuid: "lv-uuid-home".to_string(),
+            size_bytes: 130 * 1024 * 1024 * 1024, // 130 GB
+            fs_type: Some("ext4".to_string()),
+            mount_point: Some("/home".to_string()),
+            ..Default::default()
+        },
+    ];
     lvm_vgs.push(LvmVolumeGroup {
         name: "vg_system".to_string(),
         uuid: "vg-uuid-system".to_string(),
@@ -182,24 +183,25 @@ pub fn create_dummy_system_disk_info() -> SystemDiskInfo {
     });
 
     // --- Disk 2: /dev/sdb (HDD with NTFS and some free space) ---
-    let mut sdb_partitions = Vec::new();
-    // sdb1: NTFS (Windows Data?)
-    sdb_partitions.push(Partition {
-        path: "/dev/sdb1".to_string(),
-        fs_type: Some("ntfs".to_string()),
-        fs_label: Some("WindowsData".to_string()),
-        size_bytes: 500 * 1024 * 1024 * 1024, // 500 GB
-        mount_point: None, // Not mounted in this example
-        content: Some(PartitionContent::FileSystem),
-        ..Default::default()
-    });
-    // sdb2: Unallocated/Unknown (for new Lunitool install?)
-    sdb_partitions.push(Partition {
-        path: "/dev/sdb2".to_string(), // This might not exist as a path yet if unallocated
-        size_bytes: 500 * 1024 * 1024 * 1024, // 500 GB Free Space (example)
-        content: Some(PartitionContent::Unknown), // Represents free, unformatted space
-        ..Default::default()
-    });
+    let sdb_partitions = vec![
+        // sdb1: NTFS (Windows Data?)
+        Partition {
+            path: "/dev/sdb1".to_string(),
+            fs_type: Some("ntfs".to_string()),
+            fs_label: Some("WindowsData".to_string()),
+            size_bytes: 500 * 1024 * 1024 * 1024, // 500 GB
+            mount_point: None, // Not mounted in this example
+            content: Some(PartitionContent::FileSystem),
+            ..Default::default()
+        },
+        // sdb2: Unallocated/Unknown (for new Lunitool install?)
+        Partition {
+            path: "/dev/sdb2".to_string(), // This might not exist as a path yet if unallocated
+            size_bytes: 500 * 1024 * 1024 * 1024, // 500 GB Free Space (example)
+            content: Some(PartitionContent::Unknown), // Represents free, unformatted space
+            ..Default::default()
+        },
+    ];
 
     disks.push(PhysicalDisk {
         path: "/dev/sdb".to_string(),
@@ -240,8 +242,7 @@ pub fn create_dummy_system_disk_info() -> SystemDiskInfo {
     });
 
     // LVM VG "vg_data" on nvme0n1p2
-    let mut vg_data_lvs = Vec::new();
-    vg_data_lvs.push(LvmLogicalVolume {
+    let vg_data_lvs = vec![LvmLogicalVolume {
         name: "lv_games".to_string(),
         path: "/dev/vg_data/lv_games".to_string(),
         uuid: "lv-uuid-games".to_string(),
@@ -249,7 +250,7 @@ pub fn create_dummy_system_disk_info() -> SystemDiskInfo {
         fs_type: Some("btrfs".to_string()),
         mount_point: Some("/mnt/games".to_string()),
         ..Default::default()
-    });
+    }];
     lvm_vgs.push(LvmVolumeGroup {
         name: "vg_data".to_string(),
         uuid: "vg-uuid-data".to_string(),
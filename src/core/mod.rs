@@ -1,5 +1,7 @@
 pub mod system_info;
 pub mod disk_info;
+pub mod command;
+pub mod secret;
 
 pub use system_info::collect_system_info;
 
@@ -47,7 +49,7 @@ pub fn set_keyboard(layout: &str) -> Result<()> {
         
         // If both loadkeys and setxkbmap failed on Unix
         log::warn!("Failed to set keyboard layout '{}' on Unix using system tools (loadkeys, setxkbmap).", layout);
-        return Err(anyhow::anyhow!("Failed to set keyboard layout '{}' on Unix using available system tools.", layout));
+        Err(anyhow::anyhow!("Failed to set keyboard layout '{}' on Unix using available system tools.", layout))
     }
 
     #[cfg(not(unix))]
@@ -0,0 +1,204 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{borrow::Cow, fmt};
+
+use zeroize::Zeroize;
+
+/// Shown instead of a secret in Debug, Display and the log
+pub const REDACTED: &str = "<redacted>";
+
+/// Key words whose value is cut from log lines, compared in lower case
+const SECRET_KEYS: &[&str] = &["password", "passwort", "passphrase", "passwd", "newpin", "pin", "secret", "token"];
+/// ModHex alphabet of recovery keys, see `tools::recovery_key`
+const MODHEX: &str = "cbdefghijklnrtuv";
+
+/// Password, passphrase or PIN held in memory
+///
+/// The buffer is overwritten when the value is dropped, and formatting only
+/// ever prints `<redacted>`. Commands get the value through stdin via
+/// `expose`, never as an argument, where any user could read it from `ps`.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct SecretString(String);
+
+impl SecretString {
+    pub fn new(value: impl Into<String>) -> Self {
+        Self(value.into())
+    }
+
+    /// The plain value, for stdin of a command or a comparison
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl From<String> for SecretString {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+impl From<&str> for SecretString {
+    fn from(value: &str) -> Self {
+        Self(value.to_string())
+    }
+}
+
+// Only the encrypted vault serializes secrets
+impl Serialize for SecretString {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for SecretString {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Self)
+    }
+}
+
+impl Drop for SecretString {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl fmt::Display for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+/// Cut secrets that slipped into a log line
+///
+/// Values after a secret key word and `=` or `:` (`password=…`,
+/// `NEWPIN: …`, `"passphrase": "…"`, `Passwort = …`) are replaced, as are recovery keys in
+/// their dashed ModHex form.
+pub fn redact(line: &str) -> Cow<'_, str> {
+    let lower = line.to_ascii_lowercase();
+    let mut cuts: Vec<(usize, usize)> = Vec::new();
+
+    for key in SECRET_KEYS {
+        for (at, _) in lower.match_indices(key) {
+            // Only whole words: "pin" must not match inside "spinner" or "pinentry"
+            let before = lower[..at].chars().next_back();
+            if before.is_some_and(|c| c.is_ascii_alphanumeric()) {
+                continue;
+            }
+            if let Some(value) = value_after(line, at + key.len()) {
+                cuts.push(value);
+            }
+        }
+    }
+    cuts.extend(recovery_keys(&lower));
+
+    if cuts.is_empty() {
+        return Cow::Borrowed(line);
+    }
+    cuts.sort();
+    let mut redacted = String::with_capacity(line.len());
+    let mut copied = 0;
+    for (start, end) in cuts {
+        if start < copied {
+            continue;
+        }
+        redacted.push_str(&line[copied..start]);
+        redacted.push_str(REDACTED);
+        copied = end;
+    }
+    redacted.push_str(&line[copied..]);
+    Cow::Owned(redacted)
+}
+
+/// Byte range of the value following a key word that ends at `at`, if one follows
+fn value_after(line: &str, at: usize) -> Option<(usize, usize)> {
+    let rest = &line[at..];
+    // Quoted keys as in YAML or JSON, `_` joined suffixes such as `password_hash` are other keys
+    let after_key = rest.trim_start_matches(['"', '\'']).trim_start_matches(' ');
+    let separator = after_key.chars().next().filter(|c| matches!(c, '=' | ':'))?;
+    let value = after_key[separator.len_utf8()..].trim_start_matches(' ');
+    // A quoted value ends at its closing quote, anything else at the next space or delimiter
+    let (value, end_of_value): (&str, fn(char) -> bool) = match value.strip_prefix(['"', '\'']) {
+        Some(quoted) => (quoted, |c| matches!(c, '"' | '\'')),
+        None => (value, |c| c.is_whitespace() || matches!(c, ',' | ';' | '}')),
+    };
+    let start = line.len() - value.len();
+    let len = value.find(end_of_value).unwrap_or(value.len());
+    (len > 0 && value[..len] != *REDACTED).then_some((start, start + len))
+}
+
+/// Byte ranges of dashed ModHex groups that look like a recovery key (at least four groups of eight)
+fn recovery_keys(lower: &str) -> Vec<(usize, usize)> {
+    let is_key_char = |c: char| c == '-' || MODHEX.contains(c);
+    let mut found = Vec::new();
+    let mut start = None;
+    for (at, c) in lower.char_indices().chain([(lower.len(), ' ')]) {
+        match (start, is_key_char(c)) {
+            (None, true) => start = Some(at),
+            (Some(from), false) => {
+                let groups: Vec<&str> = lower[from..at].split('-').collect();
+                if groups.len() >= 4 && groups.iter().all(|group| group.len() == 8) {
+                    found.push((from, at));
+                }
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::recovery_key::RecoveryKey;
+
+    #[test]
+    fn cuts_values_after_secret_key_words() {
+        assert_eq!(redact("password=hunter2 user=anna"), "password=<redacted> user=anna");
+        assert_eq!(redact("Passphrase: open sesame"), "Passphrase: <redacted> sesame");
+        assert_eq!(redact(r#"{"passphrase": "open sesame", "slot": 1}"#), r#"{"passphrase": "<redacted>", "slot": 1}"#);
+        assert_eq!(redact("NEWPIN:1234;PIN=4321"), "NEWPIN:<redacted>;PIN=<redacted>");
+        assert_eq!(redact("luks_password: 'a b c'"), "luks_password: '<redacted>'");
+        assert_eq!(redact("Passwort = geheim"), "Passwort = <redacted>");
+        assert_eq!(redact("token=abc,secret=def"), "token=<redacted>,secret=<redacted>");
+    }
+
+    #[test]
+    fn cuts_recovery_keys() {
+        let key = RecoveryKey::generate().unwrap();
+        let line = format!("Recovery key {} enrolled", key.as_str());
+        assert_eq!(redact(&line), "Recovery key <redacted> enrolled");
+        assert_eq!(redact(&key.as_str().to_uppercase()), REDACTED);
+    }
+
+    #[test]
+    fn leaves_lines_without_secrets_alone() {
+        for line in [
+            "pinentry-program /usr/bin/pinentry-curses",
+            "Spinning up the worker, Pinned: yes",
+            "password_hash=$6$salt$hash",
+            "Passwords do not match",
+            "Passphrase strength: weak",
+            "tokens issued: 3",
+            "cccccccc-bbbbbbbb-dddddddd",
+            "cryptsetup luksOpen /dev/sda2 cryptroot",
+            "UUID=0b2e5c6f-1f3e-4f43-8c7a-2d7c3d1c9b4e",
+        ] {
+            assert!(matches!(redact(line), Cow::Borrowed(_)), "{} was changed to {}", line, redact(line));
+        }
+    }
+
+    #[test]
+    fn already_redacted_values_stay_as_they_are() {
+        assert!(matches!(redact("password=<redacted>"), Cow::Borrowed(_)));
+    }
+}
@@ -106,8 +106,13 @@ fn detect_live_environment() -> bool {
     false
 }
 
+/// Detect whether the running system was booted via UEFI
+pub fn detect_efi() -> bool {
+    std::path::Path::new("/sys/firmware/efi").exists()
+}
+
 /// Detect available package manager
-fn detect_package_manager() -> String {
+pub fn detect_package_manager() -> String {
     use std::process::Command;
     
     // List of common package managers and their check commands
@@ -125,4 +130,44 @@ fn detect_package_manager() -> String {
     }
     
     "unknown".to_string()
-}
\ No newline at end of file
+}
+/// Read the key/value pairs of `/etc/os-release` (quotes stripped)
+pub fn read_os_release() -> HashMap<String, String> {
+    read_os_release_from(std::path::Path::new("/etc/os-release"))
+}
+
+/// Read an os-release style file from an arbitrary path
+pub fn read_os_release_from(path: &std::path::Path) -> HashMap<String, String> {
+    let mut values = HashMap::new();
+    let Ok(content) = std::fs::read_to_string(path) else {
+        return values;
+    };
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some((key, value)) = line.split_once('=') {
+            values.insert(key.to_string(), value.trim_matches('"').trim_matches('\'').to_string());
+        }
+    }
+    values
+}
+
+/// Detect the timezone of the running system, falling back to UTC
+pub fn detect_timezone() -> String {
+    if let Ok(tz) = std::fs::read_to_string("/etc/timezone") {
+        let tz = tz.trim();
+        if !tz.is_empty() {
+            return tz.to_string();
+        }
+    }
+    // /etc/localtime usually links into /usr/share/zoneinfo/<Area>/<City>
+    if let Ok(target) = std::fs::read_link("/etc/localtime") {
+        let target = target.to_string_lossy().into_owned();
+        if let Some((_, tz)) = target.split_once("zoneinfo/") {
+            return tz.to_string();
+        }
+    }
+    "UTC".to_string()
+}
@@ -52,7 +52,7 @@ fn load_all_bundles() -> HashMap<LanguageIdentifier, ConcurrentFluentBundle<Flue
     }
 
     // Check if the default locale was actually loaded
-    if bundles.get(&DEFAULT_LOCALE).is_none() && SUPPORTED_LOCALES.contains(&DEFAULT_LOCALE) {
+    if !bundles.contains_key(&DEFAULT_LOCALE) && SUPPORTED_LOCALES.contains(&DEFAULT_LOCALE) {
         log::warn!("Default locale '{}' is supported and should have been embedded, but was not found in loaded BUNDLES. Translations may not work as expected.", DEFAULT_LOCALE);
     } else if !SUPPORTED_LOCALES.contains(&DEFAULT_LOCALE) && !SUPPORTED_LOCALES.is_empty(){
         log::warn!("Default locale '{}' is not listed in SUPPORTED_LOCALES. Fallback translations might not function correctly.", DEFAULT_LOCALE);
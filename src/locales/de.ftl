@@ -28,7 +28,65 @@ INSTALL_LABEL_COMMAND_STATUS = Ausgeführter Befehl:
 INSTALL_WELCOME_DESC = Diese Beschreibung erläutert den Willkommensschritt. Drücken Sie Enter, um fortzufahren.
 INSTALL_WELCOME_MESSAGE = Willkommen zur Lunitool System-Installation. Dieser Assistent führt Sie durch die notwendigen Schritte.
 INSTALL_USER_SETUP_DESC = Konfigurieren Sie hier Benutzerdetails wie Hostname, Benutzername und Passwörter.
-PROMPT_HOSTNAME = Hostname eingeben
+PROMPT_HOSTNAME = Hostname
+PROMPT_USERNAME = Benutzername
+PROMPT_USER_PASSWORD = Benutzerpasswort
+PROMPT_ROOT_PASSWORD = Root-Passwort
+PROMPT_LUKS_PASSPHRASE = Passphrase der Festplattenverschlüsselung (leer: keine Verschlüsselung)
+PROMPT_CONFIRM_PASSWORD = Passwort wiederholen
+PROMPT_CONFIRM_PASSPHRASE = Passphrase wiederholen
+INSTALL_USER_SETUP_HINT = Tab/Hoch/Runter wechselt das Feld, Pos1/Ende und Strg+W bearbeiten den Text, F2 erzeugt eine Passphrase für ein Passwortfeld, F3 zeigt oder verbirgt sie, Enter im letzten Feld geht weiter.
+USER_SETUP_PASSWORD_WITHOUT_USER = Ein Benutzerpasswort braucht einen Benutzernamen.
+USER_SETUP_MISMATCH = Ein Passwort und seine Wiederholung stimmen nicht überein.
+
+# Netzwerkschritt
+INSTALL_NETWORK_DESC = Richten Sie das kabelgebundene Netzwerk des installierten Systems ein. Mit DHCP und ohne weitere Änderungen konfiguriert NetworkManager es automatisch.
+PROMPT_NETWORK_METHOD = IPv4-Adresse
+PROMPT_NETWORK_INTERFACE = Schnittstelle
+PROMPT_NETWORK_ADDRESS = Adresse/Präfix
+PROMPT_NETWORK_GATEWAY = Gateway
+PROMPT_NETWORK_DNS = Nameserver
+PROMPT_NETWORK_MTU = MTU (leer: automatisch)
+PROMPT_NETWORK_IPV6 = IPv6 automatisch
+PROMPT_HEADER_BACKUP = LUKS-Header-Sicherung
+PROMPT_HEADER_BACKUP_DIR = Verzeichnis
+PROMPT_HEADER_BACKUP_USB = USB-Stick
+PROMPT_HEADER_BACKUP_USB_PASSPHRASE = USB-Passphrase
+PROMPT_HEADER_BACKUP_USB_FORMAT = Stick löschen und verschlüsseln
+INSTALL_NETWORK_HINT = Tab/Hoch/Runter wechselt das Feld, Leertaste oder Links/Rechts ändert eine Auswahl, Enter öffnet die Liste der Schnittstellen, Enter im letzten Feld geht weiter.
+
+# Formularfelder
+FORM_ERROR_REQUIRED = Dieses Feld darf nicht leer sein.
+FORM_ERROR_HOSTNAME = Nur Buchstaben, Ziffern und innere Bindestriche, höchstens 63 Zeichen.
+FORM_ERROR_USERNAME = Kleinbuchstaben, Ziffern, _ und -, beginnend mit einem Buchstaben oder _, höchstens 32 Zeichen.
+FORM_ERROR_ADDRESS = Geben Sie eine IP-Adresse mit Präfixlänge ein, z. B. 192.168.1.10/24.
+FORM_ERROR_IP = Geben Sie eine IP-Adresse ein, z. B. 192.168.1.1.
+FORM_ERROR_DNS = Geben Sie IP-Adressen getrennt durch Leerzeichen oder Kommas ein.
+FORM_ERROR_MTU = Die MTU muss zwischen 576 und 9000 liegen.
+FORM_ERROR_GATEWAY_FAMILY = Das Gateway muss zur Adressfamilie der Adresse passen (IPv4 oder IPv6).
+FORM_ERROR_DNS_IPV6 = IPv6-Namensserver benötigen IPv6; schalten Sie es ein oder geben Sie IPv4-Server an.
+FORM_ERROR_DEVICE = Geben Sie einen Geräteknoten ein, z. B. /dev/sdb.
+FORM_ERROR_PCRS = Geben Sie PCR-Nummern von 0 bis 23 getrennt durch + ein, z. B. 0+7.
+
+# Passwortstärke
+STRENGTH_TITLE = Stärke
+STRENGTH_VERY_WEAK = sehr schwach
+STRENGTH_WEAK = schwach
+STRENGTH_FAIR = mittel
+STRENGTH_STRONG = stark
+STRENGTH_VERY_STRONG = sehr stark
+STRENGTH_WARNING_COMMON = Eines der häufigsten Passwörter.
+STRENGTH_WARNING_WORD = Einzelne Wörter sind leicht zu erraten, auch mit Ersetzungen wie 4 für a.
+STRENGTH_WARNING_SEQUENCE = Folgen wie abc oder 6543 sind leicht zu erraten.
+STRENGTH_WARNING_REPEAT = Wiederholte Zeichen wie aaa bringen wenig.
+STRENGTH_WARNING_KEYBOARD = Reihen benachbarter Tasten sind leicht zu erraten.
+STRENGTH_WARNING_YEAR = Jahreszahlen sind leicht zu erraten.
+LAYOUT_WARNING_NOT_US = GRUB liest die Passphrase mit US-Layout und kann nicht eingeben:
+LAYOUT_WARNING_DIFFERS = GRUB nutzt das US-Layout, dort liegen diese auf anderen Tasten:
+PASSPHRASE_GENERATED_TITLE = Erzeugte Passphrase
+PASSPHRASE_WORDS = Wörter
+PASSPHRASE_BITS = Bit Entropie
+PASSPHRASE_GENERATED_HINT = Jetzt notieren, sie wird nicht erneut angezeigt. Eine beliebige Taste schließt das Fenster.
 INSTALL_FOOTER_HINTS = Enter: Weiter | Backspace: Zurück | L: Log | Esc: Abbrechen
 
 # NEU für Festplatteneinrichtung
@@ -43,6 +101,7 @@ TASK_NETWORK_CONFIG = Netzwerkkonfiguration
 TASK_DESKTOP_CHOICE = Desktop-Auswahl
 TASK_KERNEL_CHOICE = Kernel-Auswahl
 TASK_SECURE_BOOT = Secure Boot
+TASK_TPM2 = TPM2-Entsperrung
 TASK_UPDATE_SETTINGS = Update-Einstellungen
 TASK_ADD_PACKAGES = Zusätzliche Pakete
 TASK_SUMMARY = Zusammenfassung
@@ -100,4 +159,209 @@ LANG_DISK_SETUP_NO_TARGET = Kein Installationsziel ausgewählt.
 # NEUE SCHLÜSSEL FÜR THEME AUSWAHL
 DIALOG_THEME_SELECTOR_TITLE = Thema auswählen
 DIALOG_THEME_SELECTOR_NO_THEMES = Keine Themen gefunden.
-LANG_TOGGLE_THEME_SHORT = Thema
\ No newline at end of file
+LANG_TOGGLE_THEME_SHORT = Thema
+
+# Installations-Worker
+INSTALL_INSTALLING_DESC = Das System wird installiert. Befehle und deren Ausgabe werden ins Protokoll geschrieben (Alt+L). Esc bricht die Installation ab.
+INSTALL_COMPLETED_DESC = Die Installation ist abgeschlossen. Sie können den Computer jetzt neu starten.
+INSTALL_LABEL_PHASE = Phase:
+INSTALL_RUNNING_MESSAGE = Installation läuft, bitte warten...
+INSTALL_FAILED_MESSAGE = Die Installation ist fehlgeschlagen. Mit Backspace gelangen Sie zurück zur Zusammenfassung.
+INSTALL_CANCELLED_MESSAGE = Die Installation wurde abgebrochen. Mit Backspace gelangen Sie zurück zur Zusammenfassung.
+INSTALL_COMPLETED_MESSAGE = Die Installation wurde erfolgreich abgeschlossen.
+RECOVERY_KEY_TITLE = Wiederherstellungsschlüssel
+RECOVERY_KEY_DEVICE = Entsperrt
+RECOVERY_KEY_WARNING = Wer diesen Schlüssel besitzt, kann das Gerät entschlüsseln. Offline und sicher verwahren.
+INSTALL_LOG_HINT = Alt+L zeigt das Protokoll mit der Live-Ausgabe der Befehle.
+INSTALL_CANCEL_TITLE = Installation abbrechen
+INSTALL_CANCEL_MESSAGE = Möchten Sie die laufende Installation wirklich abbrechen? Die Zielfestplatte kann unvollständig zurückbleiben.
+INSTALL_CANCEL_REQUESTED = Abbruch angefordert, laufender Befehl wird beendet...
+INSTALL_PHASE_PREPARING = Vorbereitung
+INSTALL_PHASE_PARTITIONING = Partitionierung
+INSTALL_PHASE_FORMATTING = Formatierung
+INSTALL_PHASE_MOUNTING = Dateisysteme einhängen
+INSTALL_PHASE_BASE_SYSTEM = Basissystem installieren
+INSTALL_PHASE_CONFIGURING = System konfigurieren
+INSTALL_PHASE_USERS = Benutzer anlegen
+INSTALL_PHASE_BOOTLOADER = Bootloader installieren
+INSTALL_PHASE_FINALIZING = Abschluss
+
+# Secure Boot
+INSTALL_SECURE_BOOT_DESC = Wählen Sie, wie das installierte System mit Secure Boot startet. shim nutzt die Schlüssel der Distribution; ein Machine Owner Key wird beim nächsten Start in MokManager mit einem Einmalpasswort bestätigt, das die Zusammenfassung anzeigt; eigene Schlüssel ersetzen die Firmware-Schlüssel und erfordern den Setup-Modus. Hoch/Runter wählt, Enter bestätigt.
+PROMPT_SECURE_BOOT_MODE = Secure-Boot-Modus auswählen:
+SECURE_BOOT_MODE_DISABLED = Deaktiviert (keine Signierung)
+SECURE_BOOT_MODE_SHIM = shim-signed (Schlüssel der Distribution)
+SECURE_BOOT_MODE_MOK = Machine Owner Key (erzeugter Schlüssel, Registrierung per mokutil)
+SECURE_BOOT_MODE_CUSTOM = Eigene PK/KEK/db-Schlüssel (ersetzen die Firmware-Schlüssel)
+MOK_PASSWORD_TITLE = MokManager-Passwort
+MOK_PASSWORD_HINT = Beim nächsten Start fragt MokManager nach der Aufnahme des neuen Schlüssels: Wählen Sie "Enroll MOK", "Continue" und "Yes" und geben Sie dann dieses Passwort ein. MokManager verwendet das US-Tastaturlayout; das Passwort wird nur einmal benötigt.
+TPM2_PCR7_WARNING = Der TPM-Schlüssel ist an PCR 7 gebunden, das sich ändert, sobald die neuen Secure-Boot-Schlüssel beim nächsten Start aufgenommen sind. Bis das TPM erneut eingebunden ist, wird die Passphrase der Festplatte abgefragt; führen Sie nach dem ersten Start aus:
+TPM2_ROOT_PARTITION = <Root-Partition>
+INSTALL_TPM2_DESC = Lassen Sie das TPM die verschlüsselte Root-Partition beim Start entsperren. Der Schlüssel ist an die gewählten PCRs gebunden, standardmäßig PCR 7 mit dem Secure-Boot-Zustand; mit PIN gibt das TPM ihn erst nach Eingabe der PIN frei. Die Passphrase der Festplatte funktioniert weiterhin als Rückfallebene.
+INSTALL_TPM2_NO_ENCRYPTION = Die TPM2-Entsperrung erfordert eine verschlüsselte Root-Partition. Geben Sie dafür im Schritt "Benutzer einrichten" eine Passphrase für die Festplattenverschlüsselung ein.
+INSTALL_TPM2_HINT = Tab/Hoch/Runter wechselt das Feld, Leertaste schaltet ein Kontrollkästchen um, F3 zeigt oder verbirgt die PIN, Enter im letzten Feld fährt fort.
+PROMPT_TPM2 = Mit dem TPM entsperren
+PROMPT_TPM2_PCRS = PCRs
+PROMPT_TPM2_WITH_PIN = Zusätzlich eine PIN abfragen
+PROMPT_TPM2_PIN = PIN
+PROMPT_CONFIRM_PIN = PIN wiederholen
+
+# Zusammenfassung
+INSTALL_SUMMARY_DESC = Prüfen Sie die gesammelten Einstellungen. Enter startet die Installation und löscht die Zielfestplatte, Backspace geht zurück, um eine Einstellung zu ändern.
+INSTALL_SUMMARY_MESSAGE = Die Installation kann mit diesen Einstellungen starten:
+SUMMARY_LABEL_DISK = Zielfestplatte:
+SUMMARY_LABEL_SECURE_BOOT = Secure Boot:
+SUMMARY_LABEL_TPM2 = TPM2-Entsperrung:
+SUMMARY_TPM2_OFF = aus
+SUMMARY_TPM2_PIN = , mit PIN
+SUMMARY_LABEL_UNLOCK = Entsperren per Passphrase:
+SUMMARY_UNLOCK_MEMORY = Speicher
+SUMMARY_UNLOCK_GRUB = in GRUB
+SUMMARY_UNLOCK_GRUB_HINT = GRUB fragt die Passphrase vor dem Bootmenü ab und leitet den Schlüssel weit langsamer ab als Linux; der Start hält nach der Eingabe entsprechend lange an.
+INSTALL_HEADER_BACKUP_HINT = Eine Kopie der LUKS-Header ist der einzige Weg zurück zu den Daten, wenn ein Header beschädigt wird. Wählen Sie, wo die Installation sie speichert, z. B. ein Verzeichnis auf einem Wechselmedium oder einen verschlüsselten USB-Stick.
+
+# Schlüsselverwaltung
+KEYS_CONTAINERS_TITLE = LUKS-Container
+KEYS_NO_CONTAINERS = Keine LUKS-Container gefunden.
+KEYS_OPEN_AS = geöffnet als
+KEYS_CLOSED = geschlossen
+KEYS_KEYSLOTS_TITLE = Keyslots und Token
+KEYS_SELECT_CONTAINER = Wählen Sie einen Container, um seine Keyslots anzuzeigen.
+KEYS_SLOT = Slot
+KEYS_TOKEN = Token
+KEYS_BOUND_TO = genutzt von
+KEYS_ACTIONS_TITLE = Aktionen
+KEYS_HINT = Enter öffnet die Aktionen des gewählten Containers. Das Entfernen oder Umwandeln von Keyslots eines Containers, den GRUB entsperrt, kann das System unstartbar machen.
+KEYS_ACTION_ADD_PASSPHRASE = Passphrase hinzufügen
+KEYS_ACTION_ADD_KEYFILE = Schlüsseldatei hinzufügen
+KEYS_ACTION_CHANGE_PASSPHRASE = Passphrase ändern
+KEYS_ACTION_KILL_SLOT = Keyslot entfernen
+KEYS_ACTION_CONVERT_PBKDF = Schlüsselableitung (PBKDF) umwandeln
+KEYS_ACTION_ADD_RECOVERY_KEY = Wiederherstellungsschlüssel hinzufügen
+KEYS_ACTION_SPLIT_RECOVERY_KEY = Wiederherstellungsschlüssel in Teile aufteilen
+KEYS_ACTION_COMBINE_SHARES = Teile zusammenführen
+KEYS_ACTION_DONE = Fertig. Die Keyslots wurden aktualisiert.
+KEYS_FIELD_EXISTING_PASSPHRASE = Vorhandene Passphrase
+KEYS_FIELD_CURRENT_PASSPHRASE = Aktuelle Passphrase des Slots
+KEYS_FIELD_REMAINING_PASSPHRASE = Passphrase eines verbleibenden Slots
+KEYS_FIELD_SLOT_PASSPHRASE = Passphrase des Slots
+KEYS_FIELD_NEW_PASSPHRASE = Neue Passphrase
+KEYS_FIELD_CONFIRM_PASSPHRASE = Neue Passphrase wiederholen
+KEYS_FIELD_KEYFILE = Schlüsseldatei (wird angelegt, falls nicht vorhanden)
+KEYS_FIELD_SLOT = Keyslot-Nummer
+KEYS_FIELD_PROFILE = PBKDF-Profil
+KEYS_FORM_HINT = Tab/Hoch/Runter wechselt das Feld, Links/Rechts ändert eine Auswahl, F2 erzeugt eine Passphrase, F3 zeigt oder verbirgt sie, Enter im letzten Feld führt die Aktion aus.
+KEYS_ERROR_SLOT = Dieser Keyslot ist nicht belegt.
+KEYS_ERROR_MISMATCH = Die neuen Passphrasen stimmen nicht überein.
+KEYS_ERROR_SHARES = Es müssen mindestens 2 Teile benötigt werden, höchstens so viele wie erzeugt werden (bis 255).
+KEYS_ERROR_VAULT_SECRET = Entweder das Geheimnis oder eine Datei angeben, aus der es gelesen wird.
+KEYS_KILL_CONFIRM_TITLE = Keyslot entfernen
+KEYS_KILL_CONFIRM_MESSAGE = Möchten Sie diesen Keyslot wirklich entfernen? Die darin gespeicherte Passphrase bzw. der Schlüssel kann den Container danach nicht mehr entsperren.
+KEYS_ACTION_BACKUP_HEADER = LUKS-Header sichern
+KEYS_ACTION_VERIFY_HEADER = Header-Sicherung prüfen
+KEYS_ACTION_RESTORE_HEADER = Header aus Sicherung wiederherstellen
+KEYS_ACTION_ENCRYPT_IN_PLACE = Bestehende Root-Partition direkt verschlüsseln
+KEYS_ACTION_GENERATE_SSH_KEY = SSH-Schlüssel erzeugen (ed25519)
+KEYS_ACTION_IMPORT_SSH_KEYS = Öffentliche SSH-Schlüssel importieren
+KEYS_ACTION_GENERATE_GPG_KEY = GPG-Schlüssel erzeugen (ed25519/cv25519)
+KEYS_ACTION_LIST_GPG_KEYS = GPG-Schlüssel anzeigen
+KEYS_ACTION_EXPORT_GPG_KEY = GPG-Schlüssel exportieren
+KEYS_ACTION_REVOKE_GPG_KEY = GPG-Widerrufszertifikat erstellen
+KEYS_ACTION_IMPORT_GPG_KEYS = GPG-Schlüssel importieren
+KEYS_ACTION_CREATE_VAULT = Schlüsseltresor anlegen
+KEYS_ACTION_BROWSE_VAULT = Schlüsseltresor durchsuchen
+KEYS_ACTION_SHOW_VAULT_ENTRY = Tresoreintrag anzeigen
+KEYS_ACTION_ADD_VAULT_ENTRY = Schlüssel im Tresor ablegen
+KEYS_ACTION_REMOVE_VAULT_ENTRY = Tresoreintrag entfernen
+KEYS_FIELD_HEADER_TARGET = Zielverzeichnis oder verschlüsseltes USB-Gerät (/dev/...)
+KEYS_FIELD_USB_PASSPHRASE = Passphrase des USB-Sticks (nur USB)
+KEYS_FIELD_FORMAT_USB = USB-Stick zuerst verschlüsseln (löscht ihn)
+KEYS_FIELD_HEADER_FILE = Header-Sicherungsdatei (Pfad auf dem USB-Stick, falls angegeben)
+KEYS_FIELD_TEST_PASSPHRASE = Mit der Sicherung zu prüfende Passphrase (optional)
+KEYS_FIELD_BACKUP_PASSPHRASE = Passphrase eines Keyslots der Sicherung
+KEYS_FIELD_HEADER_USB = Verschlüsseltes USB-Gerät mit der Sicherung (optional)
+KEYS_FIELD_SHEET_DIR = Wechselmedium für das Wiederherstellungsblatt (optional)
+KEYS_FIELD_THRESHOLD = Benötigte Teile zur Wiederherstellung
+KEYS_FIELD_SHARE_COUNT = Anzahl der Teile
+KEYS_FIELD_SHARE_DIR = Verzeichnis für die Teilblätter (optional)
+KEYS_FIELD_SHARES = Teile oder Teilblatt-Dateien, durch Leerzeichen getrennt
+KEYS_FIELD_COMBINE_MODE = Mit dem wiederhergestellten Schlüssel
+KEYS_FIELD_CONFIRM_DEVICE = Container-Gerät zur Bestätigung eingeben
+KEYS_FIELD_INPLACE_PARTITION = Unverschlüsselte Root-Partition, z. B. /dev/sda2
+KEYS_FIELD_INPLACE_CONTENT = Partition enthält
+KEYS_FIELD_INPLACE_VOLUME_GROUP = Volume Group (nur LVM)
+KEYS_FIELD_INPLACE_ROOT_LV = Logisches Root-Volume (nur LVM)
+KEYS_FIELD_INPLACE_BACKUP = Sicherung des Root-Dateisystems
+KEYS_FIELD_INPLACE_BACKUP_KIND = Sicherung ist ein(e)
+KEYS_FIELD_INPLACE_CONFIRM_PARTITION = Partition zur Bestätigung erneut eingeben
+KEYS_FIELD_SSH_KEY_PATH = Datei des privaten Schlüssels, z. B. /root/.ssh/id_ed25519
+KEYS_FIELD_SSH_COMMENT = Kommentar (optional)
+KEYS_FIELD_SSH_PASSPHRASE = Passphrase des Schlüssels (optional)
+KEYS_FIELD_SSH_SOURCE = Schlüsseldatei oder Verzeichnis mit *.pub-Dateien (z. B. USB-Stick)
+KEYS_FIELD_SSH_USER = Lokaler Benutzer, der die Schlüssel erhält
+KEYS_FIELD_GPG_NAME = Name
+KEYS_FIELD_GPG_EMAIL = E-Mail-Adresse
+KEYS_FIELD_GPG_COMMENT = Kommentar (optional)
+KEYS_FIELD_GPG_EXPIRE = Ablauf, z. B. 1y, 18m, 0 für nie (Standard 2y)
+KEYS_FIELD_GPG_PASSPHRASE = Passphrase des Schlüssels (optional, empfohlen)
+KEYS_FIELD_GPG_FILTER = Nach Name, E-Mail oder Fingerabdruck filtern (optional)
+KEYS_FIELD_GPG_KEY = Schlüssel (Fingerabdruck oder E-Mail-Adresse)
+KEYS_FIELD_GPG_TARGET = Zielverzeichnis, z. B. auf einem USB-Stick
+KEYS_FIELD_GPG_EXPORT = Export
+KEYS_FIELD_GPG_KEY_PASSPHRASE = Passphrase des Schlüssels (falls vorhanden)
+KEYS_FIELD_GPG_SOURCE = Zu importierende Schlüsseldatei
+KEYS_FIELD_STORE_IN_VAULT = Master-Passphrase des Tresors, um den Schlüssel dort abzulegen (optional)
+KEYS_FIELD_VAULT_PASSPHRASE = Master-Passphrase des Tresors
+KEYS_FIELD_VAULT_PATH = Tresordatei (leer: konfigurierter Tresor; auch auf USB-Stick möglich)
+KEYS_FIELD_VAULT_ENTRY = Nummer des Eintrags
+KEYS_FIELD_VAULT_KIND = Art
+KEYS_FIELD_VAULT_LABEL = Bezeichnung
+KEYS_FIELD_VAULT_DEVICE = Gerät oder Host (optional)
+KEYS_FIELD_VAULT_NOTES = Notizen (optional)
+KEYS_FIELD_VAULT_SECRET = Geheimnis (oder leer lassen und eine Datei angeben)
+KEYS_FIELD_VAULT_FILE = Geheimnis aus Datei lesen (z. B. Secure-Boot-Schlüssel)
+KEYS_ERROR_NO_HEADER = Der Header dieses Containers ist nicht lesbar. Nur Aktionen für Header-Sicherungen sind verfügbar.
+KEYS_ERROR_NO_CONTAINER = Zuerst einen LUKS-Container auswählen.
+KEYS_ERROR_USB_PASSPHRASE = Die Passphrase des USB-Sticks wird benötigt.
+KEYS_ERROR_CONFIRM_DEVICE = Das eingegebene Gerät entspricht nicht dem gewählten Container.
+KEYS_ERROR_CONFIRM_PARTITION = Die eingegebene Partition stimmt nicht mit der zu verschlüsselnden überein.
+KEYS_HEADER_SAVED = Header gespeichert als
+KEYS_HEADER_VERIFIED = Header-Sicherung geprüft, UUID
+KEYS_HEADER_RESTORED = Der Header wurde aus der Sicherung wiederhergestellt.
+KEYS_INPLACE_DONE = Verschlüsselung abgeschlossen; bewahren Sie die Sicherung auf, bis das System vom verschlüsselten Root gestartet ist:
+KEYS_INPLACE_STARTED = Die Verschlüsselung läuft im Hintergrund, das Protokoll (Alt+L) zeigt den Fortschritt:
+KEYS_INPLACE_CANCELLED = Verschlüsselung unterbrochen; starten Sie sie für dieselbe Partition erneut, um fortzufahren.
+KEYS_INPLACE_BUSY = Eine Verschlüsselung läuft noch; warten Sie, bis sie abgeschlossen ist.
+KEYS_RECOVERY_ADDED = Wiederherstellungsschlüssel hinzugefügt. Jetzt notieren, er wird nur einmal angezeigt.
+KEYS_RECOVERY_SHEET_WRITTEN = Wiederherstellungsschlüssel hinzugefügt, Blatt geschrieben nach
+KEYS_RECOVERY_DISMISS = Beliebige Taste: schließen (der Schlüssel wird nicht erneut angezeigt)
+KEYS_SHARES_CREATED = Wiederherstellungsschlüssel hinzugefügt und aufgeteilt. Teile jetzt verteilen, sie werden nur einmal angezeigt.
+KEYS_SHARES_WRITTEN = Wiederherstellungsschlüssel hinzugefügt, Teilblätter geschrieben nach
+KEYS_SHARE = Teil
+KEYS_SHARE_THRESHOLD = benötigt:
+KEYS_SHARES_WARNING = Jeden Teil einer anderen Person geben. Der Schlüssel selbst wird nirgends gespeichert.
+KEYS_OPENED_AS = Container geöffnet als
+KEYS_SSH_GENERATED = SSH-Schlüssel erzeugt:
+KEYS_SSH_IMPORTED = SSH-Schlüssel zu authorized_keys hinzugefügt:
+KEYS_GPG_GENERATED = GPG-Schlüssel erzeugt:
+KEYS_GPG_LISTED = GPG-Schlüssel im Schlüsselbund:
+KEYS_GPG_EMPTY = Der lunitool-Schlüsselbund enthält keine Schlüssel.
+KEYS_GPG_EXPORTED = Exportiert:
+KEYS_GPG_REVOCATION_WRITTEN = Widerrufszertifikat geschrieben nach
+KEYS_GPG_IMPORTED = Importierte Schlüssel:
+KEYS_VAULT_CREATED = Schlüsseltresor angelegt:
+KEYS_VAULT_LISTED = Tresoreinträge:
+KEYS_VAULT_EMPTY = Der Tresor enthält keine Schlüssel.
+KEYS_VAULT_SHOWN = Tresoreintrag angezeigt; eine beliebige Taste blendet ihn aus.
+KEYS_VAULT_ADDED = Schlüssel im Tresor abgelegt.
+KEYS_VAULT_STORED = Der Schlüssel wurde auch im Tresor abgelegt.
+KEYS_VAULT_STORE_FAILED = Ablegen im Tresor fehlgeschlagen:
+KEYS_VAULT_REMOVED = Aus dem Tresor entfernt:
+KEYS_RESTORE_CONFIRM_TITLE = LUKS-Header wiederherstellen
+KEYS_RESTORE_CONFIRM_MESSAGE = Die Wiederherstellung ersetzt ALLE Keyslots des Containers durch die der Sicherung. Seit der Sicherung hinzugefügte oder geänderte Passphrasen funktionieren danach nicht mehr. Fortfahren?
+KEYS_INPLACE_CONFIRM_TITLE = Partition direkt verschlüsseln
+KEYS_INPLACE_CONFIRM_MESSAGE = Die Partition wird verkleinert und an Ort und Stelle verschlüsselt; eine Unterbrechung kann die Daten darauf kosten. Die Sicherung wird vor jeder Änderung mit der Partition abgeglichen, und ein unterbrochener Lauf wird beim erneuten Start fortgesetzt. Die Sicherung muss auf einem anderen Datenträger liegen. Fortfahren?
+KEYS_VAULT_REMOVE_CONFIRM_TITLE = Tresoreintrag entfernen
+KEYS_VAULT_REMOVE_CONFIRM_MESSAGE = Der Schlüssel wird endgültig aus dem Tresor gelöscht. Sicherstellen, dass er nicht mehr gebraucht wird oder anderswo verwahrt ist. Fortfahren?
+KEYS_FORMAT_USB_CONFIRM_TITLE = USB-Stick löschen
+KEYS_FORMAT_USB_CONFIRM_MESSAGE = Alle Daten auf dem USB-Stick werden gelöscht, bevor er verschlüsselt wird. Fortfahren?
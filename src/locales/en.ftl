@@ -28,7 +28,65 @@ INSTALL_LABEL_COMMAND_STATUS = Executed Command:
 INSTALL_WELCOME_DESC = This description explains the welcome step. Press Enter to continue.
 INSTALL_WELCOME_MESSAGE = Welcome to the Lunitool System Installation. This wizard will guide you through the necessary steps.
 INSTALL_USER_SETUP_DESC = Configure user details here, such as hostname, username, and passwords.
-PROMPT_HOSTNAME = Enter hostname
+PROMPT_HOSTNAME = Hostname
+PROMPT_USERNAME = User name
+PROMPT_USER_PASSWORD = User password
+PROMPT_ROOT_PASSWORD = Root password
+PROMPT_LUKS_PASSPHRASE = Disk encryption passphrase (empty: no encryption)
+PROMPT_CONFIRM_PASSWORD = Repeat password
+PROMPT_CONFIRM_PASSPHRASE = Repeat passphrase
+INSTALL_USER_SETUP_HINT = Tab/Up/Down switches fields, Home/End and Ctrl+W edit the text, F2 generates a passphrase for a password field, F3 shows or hides it, Enter on the last field continues.
+USER_SETUP_PASSWORD_WITHOUT_USER = A user password needs a user name.
+USER_SETUP_MISMATCH = A password and its repetition do not match.
+
+# Network step
+INSTALL_NETWORK_DESC = Set up the wired network of the installed system. With DHCP and no other changes NetworkManager configures it automatically.
+PROMPT_NETWORK_METHOD = IPv4 address
+PROMPT_NETWORK_INTERFACE = Interface
+PROMPT_NETWORK_ADDRESS = Address/prefix
+PROMPT_NETWORK_GATEWAY = Gateway
+PROMPT_NETWORK_DNS = Name servers
+PROMPT_NETWORK_MTU = MTU (empty: automatic)
+PROMPT_NETWORK_IPV6 = Automatic IPv6
+PROMPT_HEADER_BACKUP = LUKS header backup
+PROMPT_HEADER_BACKUP_DIR = Directory
+PROMPT_HEADER_BACKUP_USB = USB stick
+PROMPT_HEADER_BACKUP_USB_PASSPHRASE = USB passphrase
+PROMPT_HEADER_BACKUP_USB_FORMAT = Wipe and encrypt the stick
+INSTALL_NETWORK_HINT = Tab/Up/Down switches fields, Space or Left/Right changes a choice, Enter opens the interface list, Enter on the last field continues.
+
+# Form fields
+FORM_ERROR_REQUIRED = This field must not be empty.
+FORM_ERROR_HOSTNAME = Letters, digits and inner hyphens only, at most 63 characters.
+FORM_ERROR_USERNAME = Lower case letters, digits, _ and -, starting with a letter or _, at most 32 characters.
+FORM_ERROR_ADDRESS = Enter an IP address with prefix length, e.g. 192.168.1.10/24.
+FORM_ERROR_IP = Enter an IP address, e.g. 192.168.1.1.
+FORM_ERROR_DNS = Enter IP addresses separated by spaces or commas.
+FORM_ERROR_MTU = The MTU must be between 576 and 9000.
+FORM_ERROR_GATEWAY_FAMILY = The gateway must be of the same address family as the address (IPv4 or IPv6).
+FORM_ERROR_DNS_IPV6 = IPv6 name servers need IPv6; switch it on or enter IPv4 servers.
+FORM_ERROR_DEVICE = Enter a device node, e.g. /dev/sdb.
+FORM_ERROR_PCRS = Enter PCR numbers from 0 to 23 separated by +, e.g. 0+7.
+
+# Password strength
+STRENGTH_TITLE = Strength
+STRENGTH_VERY_WEAK = very weak
+STRENGTH_WEAK = weak
+STRENGTH_FAIR = fair
+STRENGTH_STRONG = strong
+STRENGTH_VERY_STRONG = very strong
+STRENGTH_WARNING_COMMON = One of the most common passwords.
+STRENGTH_WARNING_WORD = Single words are easy to guess, even with substitutions like 4 for a.
+STRENGTH_WARNING_SEQUENCE = Sequences like abc or 6543 are easy to guess.
+STRENGTH_WARNING_REPEAT = Repeated characters like aaa add little.
+STRENGTH_WARNING_KEYBOARD = Rows of neighbouring keys are easy to guess.
+STRENGTH_WARNING_YEAR = Years are easy to guess.
+LAYOUT_WARNING_NOT_US = GRUB reads the passphrase with the US layout and cannot type:
+LAYOUT_WARNING_DIFFERS = GRUB uses the US layout, these sit on other keys there:
+PASSPHRASE_GENERATED_TITLE = Generated passphrase
+PASSPHRASE_WORDS = words
+PASSPHRASE_BITS = bits of entropy
+PASSPHRASE_GENERATED_HINT = Write it down now, it is not shown again. Any key closes this window.
 INSTALL_FOOTER_HINTS = Enter: Next | Backspace: Back | L: Toggle Log | Esc: Cancel
 
 # NEW for DiskSetup
@@ -43,6 +101,7 @@ TASK_NETWORK_CONFIG = Network Configuration
 TASK_DESKTOP_CHOICE = Desktop Choice
 TASK_KERNEL_CHOICE = Kernel Choice
 TASK_SECURE_BOOT = Secure Boot
+TASK_TPM2 = TPM2 Unlock
 TASK_UPDATE_SETTINGS = Update Settings
 TASK_ADD_PACKAGES = Additional Packages
 TASK_SUMMARY = Summary
@@ -104,4 +163,209 @@ LANG_TOGGLE_THEME_SHORT = Theme
 
 # Existing keys (example, do not change, for orientation only)
 # DIALOG_YES = Yes
-# DIALOG_NO = No 
\ No newline at end of file
+# DIALOG_NO = No 
+
+# Installation worker
+INSTALL_INSTALLING_DESC = The system is being installed. Commands and their output are written to the protocol (Alt+L). Esc cancels the installation.
+INSTALL_COMPLETED_DESC = The installation has finished. You can now restart the computer.
+INSTALL_LABEL_PHASE = Phase:
+INSTALL_RUNNING_MESSAGE = Installation in progress, please wait...
+INSTALL_FAILED_MESSAGE = The installation failed. Press Backspace to return to the summary.
+INSTALL_CANCELLED_MESSAGE = The installation was cancelled. Press Backspace to return to the summary.
+INSTALL_COMPLETED_MESSAGE = The installation completed successfully.
+RECOVERY_KEY_TITLE = Recovery key
+RECOVERY_KEY_DEVICE = Unlocks
+RECOVERY_KEY_WARNING = Anyone holding this key can decrypt the device. Store it offline and out of reach.
+INSTALL_LOG_HINT = Alt+L shows the protocol with the live command output.
+INSTALL_CANCEL_TITLE = Cancel Installation
+INSTALL_CANCEL_MESSAGE = Do you really want to cancel the running installation? The target disk may be left in an incomplete state.
+INSTALL_CANCEL_REQUESTED = Cancellation requested, stopping the running command...
+INSTALL_PHASE_PREPARING = Preparing
+INSTALL_PHASE_PARTITIONING = Partitioning
+INSTALL_PHASE_FORMATTING = Formatting
+INSTALL_PHASE_MOUNTING = Mounting filesystems
+INSTALL_PHASE_BASE_SYSTEM = Installing base system
+INSTALL_PHASE_CONFIGURING = Configuring system
+INSTALL_PHASE_USERS = Creating users
+INSTALL_PHASE_BOOTLOADER = Installing bootloader
+INSTALL_PHASE_FINALIZING = Finalizing
+
+# Secure Boot
+INSTALL_SECURE_BOOT_DESC = Choose how the installed system boots with Secure Boot. shim uses the distribution's keys; a Machine Owner Key is confirmed in MokManager on the next boot with a one-time password shown on the summary; custom keys replace the firmware keys and require Setup Mode. Up/Down selects, Enter confirms.
+PROMPT_SECURE_BOOT_MODE = Select the Secure Boot mode:
+SECURE_BOOT_MODE_DISABLED = Disabled (no signing)
+SECURE_BOOT_MODE_SHIM = shim-signed (distribution keys)
+SECURE_BOOT_MODE_MOK = Machine Owner Key (generated key, enrolled with mokutil)
+SECURE_BOOT_MODE_CUSTOM = Custom PK/KEK/db keys (replaces the firmware keys)
+MOK_PASSWORD_TITLE = MokManager password
+MOK_PASSWORD_HINT = On the next boot MokManager asks to enroll the new key: choose "Enroll MOK", "Continue" and "Yes", then type this password. MokManager uses the US keyboard layout; the password is only needed once.
+TPM2_PCR7_WARNING = The TPM key is sealed against PCR 7, which changes once the new Secure Boot keys are enrolled on the next boot. The disk passphrase is asked until the TPM is enrolled again; after the first boot run:
+TPM2_ROOT_PARTITION = <root partition>
+INSTALL_TPM2_DESC = Let the TPM unlock the encrypted root at boot. The key is sealed against the selected PCRs, by default PCR 7 with the Secure Boot state; with a PIN the TPM only releases it after the PIN is typed. The disk passphrase keeps working as fallback.
+INSTALL_TPM2_NO_ENCRYPTION = TPM2 unlocking needs an encrypted root. Enter a disk encryption passphrase in the User Setup step to use it.
+INSTALL_TPM2_HINT = Tab/Up/Down switches fields, Space toggles a checkbox, F3 shows or hides the PIN, Enter on the last field continues.
+PROMPT_TPM2 = Unlock with the TPM
+PROMPT_TPM2_PCRS = PCRs
+PROMPT_TPM2_WITH_PIN = Also ask for a PIN
+PROMPT_TPM2_PIN = PIN
+PROMPT_CONFIRM_PIN = Repeat PIN
+
+# Summary
+INSTALL_SUMMARY_DESC = Check the collected settings. Enter starts the installation and erases the target disk, Backspace goes back to change a setting.
+INSTALL_SUMMARY_MESSAGE = The installation is ready to start with these settings:
+SUMMARY_LABEL_DISK = Target disk:
+SUMMARY_LABEL_SECURE_BOOT = Secure Boot:
+SUMMARY_LABEL_TPM2 = TPM2 unlock:
+SUMMARY_TPM2_OFF = off
+SUMMARY_TPM2_PIN = , with PIN
+SUMMARY_LABEL_UNLOCK = Passphrase unlock:
+SUMMARY_UNLOCK_MEMORY = memory
+SUMMARY_UNLOCK_GRUB = in GRUB
+SUMMARY_UNLOCK_GRUB_HINT = GRUB asks for the passphrase before the boot menu and derives the key far slower than Linux; the boot pauses that long after you type it.
+INSTALL_HEADER_BACKUP_HINT = A copy of the LUKS headers is the only way back to the data if a header gets damaged. Choose where the installation saves it, e.g. a directory on removable media or an encrypted USB stick.
+
+# Key management
+KEYS_CONTAINERS_TITLE = LUKS Containers
+KEYS_NO_CONTAINERS = No LUKS containers found.
+KEYS_OPEN_AS = open as
+KEYS_CLOSED = closed
+KEYS_KEYSLOTS_TITLE = Keyslots and Tokens
+KEYS_SELECT_CONTAINER = Select a container to show its keyslots.
+KEYS_SLOT = Slot
+KEYS_TOKEN = Token
+KEYS_BOUND_TO = used by
+KEYS_ACTIONS_TITLE = Actions
+KEYS_HINT = Enter opens the actions of the selected container. Removing or converting keyslots of a container GRUB unlocks can make the system unbootable.
+KEYS_ACTION_ADD_PASSPHRASE = Add passphrase
+KEYS_ACTION_ADD_KEYFILE = Add keyfile
+KEYS_ACTION_CHANGE_PASSPHRASE = Change passphrase
+KEYS_ACTION_KILL_SLOT = Remove keyslot
+KEYS_ACTION_CONVERT_PBKDF = Convert key derivation (PBKDF)
+KEYS_ACTION_ADD_RECOVERY_KEY = Add recovery key
+KEYS_ACTION_SPLIT_RECOVERY_KEY = Split recovery key into shares
+KEYS_ACTION_COMBINE_SHARES = Combine shares
+KEYS_ACTION_DONE = Done. The keyslots have been updated.
+KEYS_FIELD_EXISTING_PASSPHRASE = Existing passphrase
+KEYS_FIELD_CURRENT_PASSPHRASE = Current passphrase of the slot
+KEYS_FIELD_REMAINING_PASSPHRASE = Passphrase of a remaining slot
+KEYS_FIELD_SLOT_PASSPHRASE = Passphrase of the slot
+KEYS_FIELD_NEW_PASSPHRASE = New passphrase
+KEYS_FIELD_CONFIRM_PASSPHRASE = Repeat new passphrase
+KEYS_FIELD_KEYFILE = Keyfile (created if it does not exist)
+KEYS_FIELD_SLOT = Keyslot number
+KEYS_FIELD_PROFILE = PBKDF profile
+KEYS_FORM_HINT = Tab/Up/Down switches fields, Left/Right changes a selection, F2 generates a passphrase, F3 shows or hides it, Enter on the last field runs the action.
+KEYS_ERROR_SLOT = This keyslot is not in use.
+KEYS_ERROR_MISMATCH = The new passphrases do not match.
+KEYS_ERROR_SHARES = At least 2 shares must be needed, and no more than are created (up to 255).
+KEYS_ERROR_VAULT_SECRET = Enter either the secret or a file to read it from.
+KEYS_KILL_CONFIRM_TITLE = Remove Keyslot
+KEYS_KILL_CONFIRM_MESSAGE = Do you really want to remove this keyslot? The passphrase or key stored in it can no longer unlock the container.
+KEYS_ACTION_BACKUP_HEADER = Back up LUKS header
+KEYS_ACTION_VERIFY_HEADER = Verify header backup
+KEYS_ACTION_RESTORE_HEADER = Restore header from backup
+KEYS_ACTION_ENCRYPT_IN_PLACE = Encrypt an existing root partition in place
+KEYS_ACTION_GENERATE_SSH_KEY = Generate SSH key (ed25519)
+KEYS_ACTION_IMPORT_SSH_KEYS = Import SSH public keys
+KEYS_ACTION_GENERATE_GPG_KEY = Generate GPG key (ed25519/cv25519)
+KEYS_ACTION_LIST_GPG_KEYS = List GPG keys
+KEYS_ACTION_EXPORT_GPG_KEY = Export GPG key
+KEYS_ACTION_REVOKE_GPG_KEY = Create GPG revocation certificate
+KEYS_ACTION_IMPORT_GPG_KEYS = Import GPG keys
+KEYS_ACTION_CREATE_VAULT = Create key vault
+KEYS_ACTION_BROWSE_VAULT = Browse key vault
+KEYS_ACTION_SHOW_VAULT_ENTRY = Show vault entry
+KEYS_ACTION_ADD_VAULT_ENTRY = Add key to vault
+KEYS_ACTION_REMOVE_VAULT_ENTRY = Remove vault entry
+KEYS_FIELD_HEADER_TARGET = Target directory or encrypted USB device (/dev/...)
+KEYS_FIELD_USB_PASSPHRASE = Passphrase of the USB stick (USB only)
+KEYS_FIELD_FORMAT_USB = Encrypt the USB stick first (erases it)
+KEYS_FIELD_HEADER_FILE = Header backup file (path on the USB stick if one is given)
+KEYS_FIELD_TEST_PASSPHRASE = Passphrase to test with the backup (optional)
+KEYS_FIELD_BACKUP_PASSPHRASE = Passphrase of a keyslot in the backup
+KEYS_FIELD_HEADER_USB = Encrypted USB device holding the backup (optional)
+KEYS_FIELD_SHEET_DIR = Removable medium for the recovery sheet (optional)
+KEYS_FIELD_THRESHOLD = Shares needed to restore the key
+KEYS_FIELD_SHARE_COUNT = Number of shares
+KEYS_FIELD_SHARE_DIR = Directory for the share sheets (optional)
+KEYS_FIELD_SHARES = Shares or share sheet files, separated by spaces
+KEYS_FIELD_COMBINE_MODE = With the restored key
+KEYS_FIELD_CONFIRM_DEVICE = Type the container device to confirm
+KEYS_FIELD_INPLACE_PARTITION = Unencrypted root partition, e.g. /dev/sda2
+KEYS_FIELD_INPLACE_CONTENT = Partition holds
+KEYS_FIELD_INPLACE_VOLUME_GROUP = Volume group (LVM only)
+KEYS_FIELD_INPLACE_ROOT_LV = Root logical volume (LVM only)
+KEYS_FIELD_INPLACE_BACKUP = Backup of the root filesystem
+KEYS_FIELD_INPLACE_BACKUP_KIND = Backup is a
+KEYS_FIELD_INPLACE_CONFIRM_PARTITION = Type the partition again to confirm
+KEYS_FIELD_SSH_KEY_PATH = Private key file, e.g. /root/.ssh/id_ed25519
+KEYS_FIELD_SSH_COMMENT = Comment (optional)
+KEYS_FIELD_SSH_PASSPHRASE = Key passphrase (optional)
+KEYS_FIELD_SSH_SOURCE = Public key file or directory with *.pub files (e.g. USB stick)
+KEYS_FIELD_SSH_USER = Local user receiving the keys
+KEYS_FIELD_GPG_NAME = Name
+KEYS_FIELD_GPG_EMAIL = E-mail address
+KEYS_FIELD_GPG_COMMENT = Comment (optional)
+KEYS_FIELD_GPG_EXPIRE = Expiry, e.g. 1y, 18m, 0 for never (default 2y)
+KEYS_FIELD_GPG_PASSPHRASE = Key passphrase (optional, recommended)
+KEYS_FIELD_GPG_FILTER = Filter by name, e-mail or fingerprint (optional)
+KEYS_FIELD_GPG_KEY = Key (fingerprint or e-mail address)
+KEYS_FIELD_GPG_TARGET = Target directory, e.g. on a USB stick
+KEYS_FIELD_GPG_EXPORT = Export
+KEYS_FIELD_GPG_KEY_PASSPHRASE = Key passphrase (if the key has one)
+KEYS_FIELD_GPG_SOURCE = Key file to import
+KEYS_FIELD_STORE_IN_VAULT = Vault master passphrase to also store the key (optional)
+KEYS_FIELD_VAULT_PASSPHRASE = Vault master passphrase
+KEYS_FIELD_VAULT_PATH = Vault file (empty: configured vault; may be on a USB key)
+KEYS_FIELD_VAULT_ENTRY = Entry number
+KEYS_FIELD_VAULT_KIND = Type
+KEYS_FIELD_VAULT_LABEL = Label
+KEYS_FIELD_VAULT_DEVICE = Device or host (optional)
+KEYS_FIELD_VAULT_NOTES = Notes (optional)
+KEYS_FIELD_VAULT_SECRET = Secret (or leave empty and give a file)
+KEYS_FIELD_VAULT_FILE = Read the secret from file (e.g. a Secure Boot key)
+KEYS_ERROR_NO_HEADER = The header of this container cannot be read. Only header backup actions are available.
+KEYS_ERROR_NO_CONTAINER = Select a LUKS container first.
+KEYS_ERROR_USB_PASSPHRASE = The passphrase of the USB stick is required.
+KEYS_ERROR_CONFIRM_DEVICE = The typed device does not match the selected container.
+KEYS_ERROR_CONFIRM_PARTITION = The typed partition does not match the partition to encrypt.
+KEYS_HEADER_SAVED = Header saved as
+KEYS_HEADER_VERIFIED = Header backup verified, UUID
+KEYS_HEADER_RESTORED = The header has been restored from the backup.
+KEYS_INPLACE_DONE = Encryption finished; keep the backup until the system booted from the encrypted root:
+KEYS_INPLACE_STARTED = Encryption runs in the background, the protocol (Alt+L) shows its progress:
+KEYS_INPLACE_CANCELLED = Encryption interrupted; start it again on the same partition to resume.
+KEYS_INPLACE_BUSY = An encryption is still running; wait until it has finished.
+KEYS_RECOVERY_ADDED = Recovery key added. Write it down now, it is shown only once.
+KEYS_RECOVERY_SHEET_WRITTEN = Recovery key added, sheet written to
+KEYS_RECOVERY_DISMISS = Any key: close (the key is not shown again)
+KEYS_SHARES_CREATED = Recovery key added and split. Hand out the shares now, they are shown only once.
+KEYS_SHARES_WRITTEN = Recovery key added, share sheets written to
+KEYS_SHARE = Share
+KEYS_SHARE_THRESHOLD = needed:
+KEYS_SHARES_WARNING = Give every share to a different person. The key itself is not stored anywhere.
+KEYS_OPENED_AS = Container opened as
+KEYS_SSH_GENERATED = SSH key generated:
+KEYS_SSH_IMPORTED = SSH keys added to authorized_keys:
+KEYS_GPG_GENERATED = GPG key generated:
+KEYS_GPG_LISTED = GPG keys in the keyring:
+KEYS_GPG_EMPTY = The lunitool keyring contains no keys.
+KEYS_GPG_EXPORTED = Exported:
+KEYS_GPG_REVOCATION_WRITTEN = Revocation certificate written to
+KEYS_GPG_IMPORTED = Keys imported:
+KEYS_VAULT_CREATED = Key vault created:
+KEYS_VAULT_LISTED = Vault entries:
+KEYS_VAULT_EMPTY = The vault contains no keys.
+KEYS_VAULT_SHOWN = Vault entry shown; any key hides it.
+KEYS_VAULT_ADDED = Key added to the vault.
+KEYS_VAULT_STORED = The key was also stored in the vault.
+KEYS_VAULT_STORE_FAILED = Storing it in the vault failed:
+KEYS_VAULT_REMOVED = Removed from the vault:
+KEYS_RESTORE_CONFIRM_TITLE = Restore LUKS Header
+KEYS_RESTORE_CONFIRM_MESSAGE = Restoring replaces ALL keyslots of the container with those of the backup. Passphrases added or changed since the backup stop working. Continue?
+KEYS_INPLACE_CONFIRM_TITLE = Encrypt Partition In Place
+KEYS_INPLACE_CONFIRM_MESSAGE = The partition is shrunk and encrypted in place; an interruption can cost the data on it. The backup is verified against the partition before anything changes, and an interrupted run resumes when started again. The backup must be on another disk. Continue?
+KEYS_VAULT_REMOVE_CONFIRM_TITLE = Remove vault entry
+KEYS_VAULT_REMOVE_CONFIRM_MESSAGE = The key is deleted from the vault for good. Make sure it is no longer needed or kept elsewhere. Continue?
+KEYS_FORMAT_USB_CONFIRM_TITLE = Erase USB Stick
+KEYS_FORMAT_USB_CONFIRM_MESSAGE = All data on the USB stick will be erased before it is encrypted. Continue?
@@ -4,6 +4,8 @@ use fern::colors::{Color, ColoredLevelConfig};
 use log::LevelFilter;
 use std::{fs, path::Path};
 
+use crate::core::secret::redact;
+
 /// Setup the logger
 pub fn setup_logger(log_file_path: &str, debug_mode: bool) -> Result<()> {
     let colors = ColoredLevelConfig::new()
@@ -45,7 +47,7 @@ pub fn setup_logger(log_file_path: &str, debug_mode: bool) -> Result<()> {
                 Local::now().format("%Y-%m-%d %H:%M:%S"),
                 colors.color(record.level()),
                 record.target(),
-                message
+                redact(&message.to_string())
             ))
         })
         .level(base_level) // General log level
@@ -1,5 +1,28 @@
-use anyhow::Result;
-use std::process::Command;
+use anyhow::{anyhow, Result};
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::core::command::{CommandRunner, CommandSpec};
+
+/// An existing backup of a filesystem
+#[derive(Debug, Clone)]
+pub enum BackupCopy {
+    /// File tree copied with `backup_directory` (rsync)
+    Directory(PathBuf),
+    /// Disk or filesystem image with a `<image>.sha256` checksum file next to it
+    Image(PathBuf),
+}
+
+impl std::fmt::Display for BackupCopy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BackupCopy::Directory(path) => write!(f, "directory {}", path.display()),
+            BackupCopy::Image(path) => write!(f, "image {}", path.display()),
+        }
+    }
+}
 
 /// Start the backup process
 pub fn start_backup() -> Result<()> {
@@ -22,7 +45,7 @@ pub fn backup_directory(source: &str, destination: &str) -> Result<()> {
     // Example implementation using rsync
     if cfg!(unix) {
         let status = Command::new("rsync")
-            .args(&["-av", "--progress", source, destination])
+            .args(["-av", "--progress", source, destination])
             .status()?;
             
         if !status.success() {
@@ -43,7 +66,7 @@ pub fn restore_backup(source: &str, destination: &str) -> Result<()> {
     // Example implementation using rsync
     if cfg!(unix) {
         let status = Command::new("rsync")
-            .args(&["-av", "--progress", source, destination])
+            .args(["-av", "--progress", source, destination])
             .status()?;
             
         if !status.success() {
@@ -55,4 +78,48 @@ pub fn restore_backup(source: &str, destination: &str) -> Result<()> {
     }
     
     Ok(())
-}
\ No newline at end of file
+}
+/// Check that `backup` is complete and intact
+///
+/// A directory backup is compared file by file, with checksums, against the
+/// filesystem mounted at `source_mount`; any difference fails the check. An
+/// image backup is checked against its sha256 file.
+pub fn verify_backup(runner: &mut dyn CommandRunner, source_mount: &Path, backup: &BackupCopy) -> Result<()> {
+    log::info!("Verifying backup {} of {}", backup, source_mount.display());
+
+    match backup {
+        BackupCopy::Directory(dir) => {
+            let output = runner.run_checked(
+                &CommandSpec::new("rsync")
+                    .args(["-a", "-n", "--checksum", "--itemize-changes", "--delete"])
+                    .arg(format!("{}/", source_mount.display()))
+                    .arg(format!("{}/", dir.display())),
+            )?;
+            let differences: Vec<&str> = output.stdout.lines().filter(|line| !line.trim().is_empty()).collect();
+            if !differences.is_empty() {
+                return Err(anyhow!(
+                    "Backup {} differs from {} in {} entries, first: {}",
+                    dir.display(),
+                    source_mount.display(),
+                    differences.len(),
+                    differences[0]
+                ));
+            }
+        }
+        BackupCopy::Image(image) => {
+            let file_name = image
+                .file_name()
+                .ok_or_else(|| anyhow!("Invalid backup image path {}", image.display()))?
+                .to_string_lossy();
+            let checksum = format!("{}.sha256", file_name);
+            let dir = image.parent().unwrap_or(Path::new("."));
+            if !runner.is_dry_run() && !dir.join(&checksum).exists() {
+                return Err(anyhow!("Backup image {} has no {} to verify it with", image.display(), checksum));
+            }
+            runner
+                .run_checked(&CommandSpec::new("sha256sum").args(["-c", &checksum]).current_dir(dir))
+                .map_err(|e| anyhow!("Backup image {} is damaged: {}", image.display(), e))?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,148 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+/// EFF style short lists: 1296 words, numbered by four dice rolls
+///
+/// The words only use letters that sit on the same keys on US and German
+/// keyboards (no y, z or umlauts), so a passphrase can be typed at prompts
+/// that ignore the configured layout, such as GRUB.
+const WORDLIST_EN: &str = include_str!("en.txt");
+const WORDLIST_DE: &str = include_str!("de.txt");
+
+/// Settings of the passphrase generator in config.yaml
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DicewareSettings {
+    pub words: usize,
+    /// Between the words; a space is typed the same on every layout
+    pub separator: String,
+}
+
+impl Default for DicewareSettings {
+    fn default() -> Self {
+        Self {
+            words: 6,
+            separator: " ".to_string(),
+        }
+    }
+}
+
+/// Words of the list for `language`, English if there is none
+pub fn wordlist(language: &str) -> Vec<&'static str> {
+    let list = match language {
+        "de" => WORDLIST_DE,
+        _ => WORDLIST_EN,
+    };
+    list.lines().filter_map(|line| line.split_whitespace().last()).collect()
+}
+
+/// Generated passphrase with its strength
+#[derive(Clone, PartialEq)]
+pub struct Passphrase {
+    pub value: String,
+    pub words: usize,
+    /// Bits of entropy, assuming the attacker knows list and separator
+    pub entropy_bits: f64,
+}
+
+impl std::fmt::Debug for Passphrase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Passphrase").field("words", &self.words).finish_non_exhaustive()
+    }
+}
+
+/// Entropy of `words` words drawn uniformly from a list of `list_len`
+pub fn entropy_bits(words: usize, list_len: usize) -> f64 {
+    words as f64 * (list_len as f64).log2()
+}
+
+/// Draw `settings.words` words from the list of `language`
+///
+/// Indices come from the operating system's random source; values beyond
+/// the largest multiple of the list length are rejected to avoid modulo bias.
+pub fn generate(language: &str, settings: &DicewareSettings) -> Result<Passphrase> {
+    if settings.words == 0 {
+        return Err(anyhow!("A passphrase needs at least one word"));
+    }
+    let list = wordlist(language);
+    let mut words = Vec::with_capacity(settings.words);
+    while words.len() < settings.words {
+        let mut bytes = [0u8; 2];
+        getrandom::getrandom(&mut bytes).map_err(|e| anyhow!("No randomness for the passphrase: {}", e))?;
+        if let Some(index) = word_index(u16::from_le_bytes(bytes), list.len()) {
+            words.push(list[index]);
+        }
+    }
+    Ok(Passphrase {
+        value: words.join(&settings.separator),
+        words: settings.words,
+        entropy_bits: entropy_bits(settings.words, list.len()),
+    })
+}
+
+/// Index into a list of `list_len` words for a random `value`, `None` if it has to be rejected
+fn word_index(value: u16, list_len: usize) -> Option<usize> {
+    let limit = u16::MAX - u16::MAX % list_len as u16;
+    (value < limit).then_some(value as usize % list_len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn wordlists_are_numbered_by_four_dice() {
+        for list in [WORDLIST_EN, WORDLIST_DE] {
+            let rolls: Vec<String> = (0..1296)
+                .map(|n: u32| (0..4).rev().map(|digit| char::from(b'1' + (n / 6u32.pow(digit) % 6) as u8)).collect())
+                .collect();
+            let numbers: Vec<&str> = list.lines().filter_map(|line| line.split_whitespace().next()).collect();
+            assert_eq!(numbers, rolls);
+        }
+    }
+
+    #[test]
+    fn words_are_unique_and_typed_alike_on_us_and_german_layouts() {
+        for language in ["en", "de"] {
+            let words = wordlist(language);
+            assert_eq!(words.len(), 1296, "{}", language);
+            assert_eq!(words.iter().collect::<HashSet<_>>().len(), words.len(), "{}", language);
+            for word in &words {
+                assert!(word.chars().all(|c| c.is_ascii_lowercase() && c != 'y' && c != 'z'), "{}", word);
+            }
+        }
+        assert_eq!(wordlist("fr"), wordlist("en"));
+        assert_ne!(wordlist("de"), wordlist("en"));
+    }
+
+    #[test]
+    fn every_word_is_equally_likely() {
+        let mut hits = vec![0u32; 1296];
+        for value in 0..=u16::MAX {
+            if let Some(index) = word_index(value, hits.len()) {
+                hits[index] += 1;
+            }
+        }
+        assert!(hits.iter().all(|&count| count == hits[0]));
+        // Only the biased tail of 65536 % 1296 values is thrown away
+        assert_eq!(hits[0] as usize * hits.len(), 65536 - 65536 % 1296);
+    }
+
+    #[test]
+    fn generates_the_configured_number_of_words() {
+        let settings = DicewareSettings {
+            words: 7,
+            separator: "-".to_string(),
+        };
+        let passphrase = generate("de", &settings).unwrap();
+        let list = wordlist("de");
+        let words: Vec<&str> = passphrase.value.split('-').collect();
+        assert_eq!(words.len(), 7);
+        assert!(words.iter().all(|word| list.contains(word)));
+        assert_eq!(passphrase.words, 7);
+        assert!((passphrase.entropy_bits - 7.0 * 1296f64.log2()).abs() < 1e-9);
+
+        assert!(generate("en", &DicewareSettings { words: 0, ..Default::default() }).is_err());
+    }
+}
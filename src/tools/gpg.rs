@@ -0,0 +1,471 @@
+use anyhow::{anyhow, Result};
+use std::path::{Path, PathBuf};
+
+use crate::core::{
+    command::{CommandRunner, CommandSpec},
+    secret::SecretString,
+};
+use crate::get_config_dir;
+
+/// Expiry of generated keys when none is given
+pub const DEFAULT_EXPIRE: &str = "2y";
+
+/// Keyring lunitool works with, separate from the user's ~/.gnupg
+#[derive(Debug, Clone)]
+pub struct GpgHome(PathBuf);
+
+impl Default for GpgHome {
+    fn default() -> Self {
+        Self(get_config_dir().join("gnupg"))
+    }
+}
+
+impl GpgHome {
+    pub fn new(path: &Path) -> Self {
+        Self(path.to_path_buf())
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    /// Create the keyring directory; gpg refuses homes readable by others
+    pub fn ensure(&self, runner: &mut dyn CommandRunner) -> Result<()> {
+        runner.run_checked(&CommandSpec::new("install").args(["-d", "-m", "0700"]).arg(self.0.to_string_lossy()))?;
+        Ok(())
+    }
+
+    /// `gpg` on this keyring, answering questions from stdin only
+    fn gpg_without_batch(&self) -> CommandSpec {
+        CommandSpec::new("gpg").env("GNUPGHOME", &self.0.to_string_lossy()).arg("--no-tty")
+    }
+
+    /// `gpg` in batch mode on this keyring
+    fn gpg(&self) -> CommandSpec {
+        self.gpg_without_batch().arg("--batch")
+    }
+
+    /// `gpg` reading the passphrase from the first line of stdin instead of a pinentry
+    fn gpg_with_passphrase(&self, passphrase: &str) -> Result<CommandSpec> {
+        if passphrase.contains('\n') {
+            return Err(anyhow!("The passphrase must not contain line breaks"));
+        }
+        Ok(self
+            .gpg()
+            .args(["--pinentry-mode", "loopback", "--passphrase-fd", "0"])
+            .stdin(format!("{}\n", passphrase)))
+    }
+}
+
+/// Parameters of a new key: ed25519 certification key with ed25519 signing
+/// and cv25519 encryption subkeys
+#[derive(Clone)]
+pub struct KeySpec {
+    pub name: String,
+    pub email: String,
+    pub comment: Option<String>,
+    /// gpg expiry such as `2y`, `18m` or `0` for none
+    pub expire: String,
+    /// `None` leaves the secret keys unprotected
+    pub passphrase: Option<SecretString>,
+}
+
+impl std::fmt::Debug for KeySpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KeySpec")
+            .field("name", &self.name)
+            .field("email", &self.email)
+            .field("expire", &self.expire)
+            .finish_non_exhaustive()
+    }
+}
+
+impl KeySpec {
+    /// Unattended key generation parameters, handed to gpg on stdin
+    fn batch(&self) -> Result<String> {
+        let values = [
+            Some(self.name.as_str()),
+            Some(self.email.as_str()),
+            self.comment.as_deref(),
+            Some(self.expire.as_str()),
+            self.passphrase.as_ref().map(SecretString::expose),
+        ];
+        if values.iter().flatten().any(|value| value.contains('\n')) {
+            return Err(anyhow!("Key parameters must not contain line breaks"));
+        }
+        let mut batch = String::from(
+            "Key-Type: eddsa\nKey-Curve: ed25519\nKey-Usage: cert\n\
+             Subkey-Type: ecdh\nSubkey-Curve: cv25519\nSubkey-Usage: encrypt\n",
+        );
+        batch.push_str(&format!("Name-Real: {}\nName-Email: {}\n", self.name, self.email));
+        if let Some(comment) = self.comment.as_ref().filter(|comment| !comment.is_empty()) {
+            batch.push_str(&format!("Name-Comment: {}\n", comment));
+        }
+        batch.push_str(&format!("Expire-Date: {}\n", self.expire));
+        match self.passphrase.as_ref().filter(|passphrase| !passphrase.is_empty()) {
+            Some(passphrase) => batch.push_str(&format!("Passphrase: {}\n", passphrase.expose())),
+            None => batch.push_str("%no-protection\n"),
+        }
+        batch.push_str("%commit\n");
+        Ok(batch)
+    }
+}
+
+/// Generate a key pair in `home` and return its fingerprint
+///
+/// The parameters go through stdin, so the passphrase never touches the
+/// disk. The signing subkey is added afterwards, as the batch format
+/// supports only one subkey.
+pub fn generate_key(runner: &mut dyn CommandRunner, home: &GpgHome, spec: &KeySpec) -> Result<String> {
+    home.ensure(runner)?;
+    log::info!("Generating GPG key for {} <{}>", spec.name, spec.email);
+    let output = runner.run_checked(&home.gpg().args(["--status-fd", "1", "--full-generate-key"]).stdin(spec.batch()?))?;
+    let fingerprint = output
+        .stdout
+        .lines()
+        .find_map(|line| line.strip_prefix("[GNUPG:] KEY_CREATED "))
+        .and_then(|created| created.split_whitespace().nth(1))
+        .map(str::to_string)
+        .or_else(|| runner.is_dry_run().then(|| "0".repeat(40)))
+        .ok_or_else(|| anyhow!("gpg did not report the new key"))?;
+
+    let passphrase = spec.passphrase.as_ref().map_or("", SecretString::expose);
+    runner.run_checked(
+        &home
+            .gpg_with_passphrase(passphrase)?
+            .args(["--quick-add-key", &fingerprint, "ed25519", "sign", &spec.expire]),
+    )?;
+    log::info!("GPG key {} created", fingerprint);
+    Ok(fingerprint)
+}
+
+/// Primary key or subkey as listed by `gpg --with-colons`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GpgSubkey {
+    pub fingerprint: String,
+    /// e.g. `ed25519`, `cv25519` or `rsa4096`
+    pub algorithm: String,
+    /// Capabilities such as `sc`, `e`, `a`
+    pub usage: String,
+    /// Creation date, `YYYY-MM-DD`
+    pub created: String,
+    pub expires: Option<String>,
+    /// `r` revoked, `e` expired, others valid
+    pub validity: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GpgKey {
+    pub primary: GpgSubkey,
+    pub user_ids: Vec<String>,
+    pub subkeys: Vec<GpgSubkey>,
+    /// The secret key is in the keyring
+    pub secret: bool,
+}
+
+fn format_timestamp(value: &str) -> Option<String> {
+    let seconds: i64 = value.parse().ok().filter(|seconds| *seconds > 0)?;
+    chrono::DateTime::from_timestamp(seconds, 0).map(|date| date.format("%Y-%m-%d").to_string())
+}
+
+fn algorithm_name(id: &str, bits: &str, curve: &str) -> String {
+    match (id, curve) {
+        (_, curve) if !curve.is_empty() => curve.to_string(),
+        ("1", _) => format!("rsa{}", bits),
+        ("17", _) => format!("dsa{}", bits),
+        (id, _) => format!("algo{}", id),
+    }
+}
+
+/// Parse `gpg --with-colons --with-fingerprint` key listings
+pub fn parse_key_listing(output: &str) -> Vec<GpgKey> {
+    let mut keys: Vec<GpgKey> = Vec::new();
+    for line in output.lines() {
+        let fields: Vec<&str> = line.split(':').collect();
+        let field = |index: usize| fields.get(index).copied().unwrap_or_default();
+        match field(0) {
+            "pub" | "sec" | "sub" | "ssb" => {
+                let subkey = GpgSubkey {
+                    fingerprint: String::new(),
+                    algorithm: algorithm_name(field(3), field(2), field(16)),
+                    usage: field(11).to_string(),
+                    created: format_timestamp(field(5)).unwrap_or_default(),
+                    expires: format_timestamp(field(6)),
+                    validity: field(1).to_string(),
+                };
+                if matches!(field(0), "pub" | "sec") {
+                    keys.push(GpgKey { primary: subkey, secret: field(0) == "sec", ..Default::default() });
+                } else if let Some(key) = keys.last_mut() {
+                    key.subkeys.push(subkey);
+                }
+            }
+            "fpr" => {
+                // The fingerprint record follows the key record it belongs to
+                if let Some(key) = keys.last_mut() {
+                    let target = key.subkeys.last_mut().unwrap_or(&mut key.primary);
+                    if target.fingerprint.is_empty() {
+                        target.fingerprint = field(9).to_string();
+                    }
+                }
+            }
+            "uid" => {
+                if let Some(key) = keys.last_mut() {
+                    key.user_ids.push(field(9).replace("\\x3a", ":"));
+                }
+            }
+            _ => {}
+        }
+    }
+    keys
+}
+
+/// Keys in `home`, with secret keys marked
+pub fn list_keys(runner: &mut dyn CommandRunner, home: &GpgHome) -> Result<Vec<GpgKey>> {
+    home.ensure(runner)?;
+    // Given twice, subkeys get fingerprint records as well
+    let listing = ["--with-colons", "--with-fingerprint", "--with-fingerprint"];
+    let public = runner.run_checked(&home.gpg().args(listing).arg("--list-keys"))?;
+    let secret = runner.run_checked(&home.gpg().args(listing).arg("--list-secret-keys"))?;
+    let secret_keys = parse_key_listing(&secret.stdout);
+    let mut keys = parse_key_listing(&public.stdout);
+    for key in &mut keys {
+        key.secret = secret_keys.iter().any(|secret| secret.primary.fingerprint == key.primary.fingerprint);
+    }
+    Ok(keys)
+}
+
+/// Write `contents` to `path` with `mode`
+fn write_file(runner: &mut dyn CommandRunner, path: &Path, mode: &str, contents: &str) -> Result<()> {
+    runner.run_checked(
+        &CommandSpec::new("install")
+            .args(["-D", "-m", mode, "/dev/stdin"])
+            .arg(path.to_string_lossy())
+            .stdin(contents),
+    )?;
+    Ok(())
+}
+
+/// Export the armored public key of `key` to `dir`
+pub fn export_public(runner: &mut dyn CommandRunner, home: &GpgHome, key: &str, dir: &Path) -> Result<PathBuf> {
+    let output = runner.run_checked(&home.gpg().args(["--armor", "--export", key]))?;
+    if output.stdout.trim().is_empty() && !runner.is_dry_run() {
+        return Err(anyhow!("No public key {} in the keyring", key));
+    }
+    let path = dir.join(format!("{}-public.asc", key));
+    write_file(runner, &path, "0644", &output.stdout)?;
+    log::info!("Public key {} exported to {}", key, path.display());
+    Ok(path)
+}
+
+/// Back up the armored secret keys of `key` to `dir`, readable by root only
+///
+/// The backup stays protected by the key's passphrase, which gpg asks for
+/// before exporting.
+pub fn export_secret(runner: &mut dyn CommandRunner, home: &GpgHome, key: &str, passphrase: &str, dir: &Path) -> Result<PathBuf> {
+    let output = runner.run_checked(&home.gpg_with_passphrase(passphrase)?.args(["--armor", "--export-secret-keys", key]))?;
+    if output.stdout.trim().is_empty() && !runner.is_dry_run() {
+        return Err(anyhow!("No secret key {} in the keyring", key));
+    }
+    let path = dir.join(format!("{}-secret.asc", key));
+    write_file(runner, &path, "0600", &output.stdout)?;
+    log::info!("Secret key {} backed up to {}", key, path.display());
+    Ok(path)
+}
+
+/// Create a revocation certificate for `key` in `dir`
+///
+/// gpg refuses this in batch mode, so its questions are answered through
+/// the command channel: confirm, reason "no reason specified", empty
+/// description, confirm again, passphrase.
+pub fn revocation_certificate(runner: &mut dyn CommandRunner, home: &GpgHome, key: &str, passphrase: &str, dir: &Path) -> Result<PathBuf> {
+    if passphrase.contains('\n') {
+        return Err(anyhow!("The passphrase must not contain line breaks"));
+    }
+    let output = runner.run_checked(
+        &home
+            .gpg_without_batch()
+            .args(["--pinentry-mode", "loopback", "--command-fd", "0", "--armor", "--gen-revoke", key])
+            .stdin(format!("y\n0\n\ny\n{}\n", passphrase)),
+    )?;
+    if !output.stdout.contains("BEGIN PGP PUBLIC KEY BLOCK") && !runner.is_dry_run() {
+        return Err(anyhow!("gpg did not create a revocation certificate for {}", key));
+    }
+    let path = dir.join(format!("{}-revocation.asc", key));
+    write_file(runner, &path, "0600", &output.stdout)?;
+    log::info!("Revocation certificate for {} written to {}", key, path.display());
+    Ok(path)
+}
+
+/// Import public or secret keys from an armored or binary file
+///
+/// Secret keys keep their passphrase; `passphrase` is only needed to import
+/// them and may be empty for public keys.
+pub fn import_keys(runner: &mut dyn CommandRunner, home: &GpgHome, file: &Path, passphrase: &str) -> Result<String> {
+    home.ensure(runner)?;
+    let output = runner.run_checked(
+        &home
+            .gpg_with_passphrase(passphrase)?
+            .args(["--status-fd", "1", "--import"])
+            .arg(file.to_string_lossy()),
+    )?;
+    let summary = output
+        .stdout
+        .lines()
+        .find_map(|line| line.strip_prefix("[GNUPG:] IMPORT_RES "))
+        .and_then(|counts| counts.split_whitespace().next())
+        .unwrap_or("0")
+        .to_string();
+    log::info!("Imported {} key(s) from {}", summary, file.display());
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::command::DryRunCommandRunner;
+
+    const HOME: &str = "/root/.config/lunitool/gnupg";
+    const FINGERPRINT: &str = "3AA5C34371567BD2B1F5E2E3C4D1A0F2B8E6D9C1";
+    const PASSPHRASE: &str = "correct horse battery";
+
+    fn spec(passphrase: Option<&str>) -> KeySpec {
+        KeySpec {
+            name: "Alice Example".to_string(),
+            email: "alice@example.org".to_string(),
+            comment: Some("backup".to_string()),
+            expire: DEFAULT_EXPIRE.to_string(),
+            passphrase: passphrase.map(SecretString::from),
+        }
+    }
+
+    fn stdin(spec: &CommandSpec) -> String {
+        String::from_utf8(spec.stdin.as_deref().cloned().unwrap_or_default()).unwrap()
+    }
+
+    /// Every gpg call works on the lunitool keyring and no secret shows up in argv
+    fn assert_isolated(runner: &DryRunCommandRunner) {
+        for spec in runner.history() {
+            if spec.program == "gpg" {
+                assert_eq!(spec.env, [("GNUPGHOME".to_string(), HOME.to_string())], "{}", spec);
+                assert!(spec.args.iter().any(|arg| arg == "--no-tty"), "{}", spec);
+            }
+            assert!(!spec.args.iter().any(|arg| arg.contains(PASSPHRASE)), "passphrase in argv of {}", spec);
+        }
+    }
+
+    #[test]
+    fn generates_keys_from_batch_parameters_on_stdin() {
+        let mut runner = DryRunCommandRunner::new();
+        runner.respond_to(
+            "gpg --no-tty --batch --status-fd 1 --full-generate-key",
+            &format!("[GNUPG:] KEY_CONSIDERED {} 0\n[GNUPG:] KEY_CREATED P {}\n", FINGERPRINT, FINGERPRINT),
+        );
+        let home = GpgHome::new(Path::new(HOME));
+        assert_eq!(generate_key(&mut runner, &home, &spec(Some(PASSPHRASE))).unwrap(), FINGERPRINT);
+
+        assert_eq!(
+            runner.rendered_history(),
+            [
+                format!("install -d -m 0700 {}", HOME),
+                "gpg --no-tty --batch --status-fd 1 --full-generate-key".to_string(),
+                format!(
+                    "gpg --no-tty --batch --pinentry-mode loopback --passphrase-fd 0 --quick-add-key {} ed25519 sign 2y",
+                    FINGERPRINT
+                ),
+            ]
+        );
+        assert_eq!(
+            stdin(&runner.history()[1]),
+            "Key-Type: eddsa\nKey-Curve: ed25519\nKey-Usage: cert\n\
+             Subkey-Type: ecdh\nSubkey-Curve: cv25519\nSubkey-Usage: encrypt\n\
+             Name-Real: Alice Example\nName-Email: alice@example.org\nName-Comment: backup\n\
+             Expire-Date: 2y\nPassphrase: correct horse battery\n%commit\n"
+        );
+        assert_eq!(stdin(&runner.history()[2]), format!("{}\n", PASSPHRASE));
+        assert_isolated(&runner);
+    }
+
+    #[test]
+    fn keys_without_passphrase_are_unprotected() {
+        let batch = spec(None).batch().unwrap();
+        assert!(batch.ends_with("Expire-Date: 2y\n%no-protection\n%commit\n"));
+        assert!(!batch.contains("Passphrase:"));
+        assert!(spec(Some("")).batch().unwrap().contains("%no-protection\n"));
+    }
+
+    #[test]
+    fn line_breaks_cannot_inject_batch_parameters() {
+        let injected = KeySpec {
+            name: "Alice\n%no-protection".to_string(),
+            ..spec(Some(PASSPHRASE))
+        };
+        let mut runner = DryRunCommandRunner::new();
+        assert!(generate_key(&mut runner, &GpgHome::new(Path::new(HOME)), &injected).is_err());
+        assert!(!runner.history().iter().any(|spec| spec.program == "gpg"));
+        assert!(spec(Some("two\nlines")).batch().is_err());
+    }
+
+    #[test]
+    fn exports_public_0644_and_secret_material_0600() {
+        let home = GpgHome::new(Path::new(HOME));
+        let dir = Path::new("/media/usb");
+        let mut runner = DryRunCommandRunner::new();
+        export_public(&mut runner, &home, FINGERPRINT, dir).unwrap();
+        export_secret(&mut runner, &home, FINGERPRINT, PASSPHRASE, dir).unwrap();
+        revocation_certificate(&mut runner, &home, FINGERPRINT, PASSPHRASE, dir).unwrap();
+
+        assert_eq!(
+            runner.rendered_history(),
+            [
+                format!("gpg --no-tty --batch --armor --export {}", FINGERPRINT),
+                format!("install -D -m 0644 /dev/stdin /media/usb/{}-public.asc", FINGERPRINT),
+                format!(
+                    "gpg --no-tty --batch --pinentry-mode loopback --passphrase-fd 0 --armor --export-secret-keys {}",
+                    FINGERPRINT
+                ),
+                format!("install -D -m 0600 /dev/stdin /media/usb/{}-secret.asc", FINGERPRINT),
+                format!(
+                    "gpg --no-tty --pinentry-mode loopback --command-fd 0 --armor --gen-revoke {}",
+                    FINGERPRINT
+                ),
+                format!("install -D -m 0600 /dev/stdin /media/usb/{}-revocation.asc", FINGERPRINT),
+            ]
+        );
+        assert_eq!(stdin(&runner.history()[2]), format!("{}\n", PASSPHRASE));
+        assert_eq!(stdin(&runner.history()[4]), format!("y\n0\n\ny\n{}\n", PASSPHRASE));
+        assert_isolated(&runner);
+
+        assert!(export_secret(&mut runner, &home, FINGERPRINT, "two\nlines", dir).is_err());
+        assert!(revocation_certificate(&mut runner, &home, FINGERPRINT, "two\nlines", dir).is_err());
+    }
+
+    #[test]
+    fn parses_colon_listings() {
+        let listing = format!(
+            "tru::1:1718000000:0:3:1:5\n\
+             sec:u:255:22:B8E6D9C1A0F2C4D1:1718000000:1781072000::u:::cC:::+::ed25519:::0:\n\
+             fpr:::::::::{}:\n\
+             uid:u::::1718000000::0A1B::Alice Example (backup) <alice@example.org>::::::::::0:\n\
+             ssb:u:255:18:1F2E3D4C5B6A7980:1718000000:1781072000:::::e:::+::cv25519::\n\
+             fpr:::::::::5D1C0E2B7A9F4836C1E0B2D3A4F5E6D71F2E3D4C:\n\
+             ssb:r:255:22:0A1B2C3D4E5F6071:1718000000::::::s:::+::ed25519::\n\
+             fpr:::::::::9E8D7C6B5A4F3E2D1C0B0A9F8E7D6C5B0A1B2C3D:\n",
+            FINGERPRINT
+        );
+        let keys = parse_key_listing(&listing);
+        assert_eq!(keys.len(), 1);
+        let key = &keys[0];
+        assert!(key.secret);
+        assert_eq!(key.primary.fingerprint, FINGERPRINT);
+        assert_eq!(key.primary.algorithm, "ed25519");
+        assert_eq!(key.primary.usage, "cC");
+        assert_eq!(key.primary.created, "2024-06-10");
+        assert_eq!(key.primary.expires.as_deref(), Some("2026-06-10"));
+        assert_eq!(key.user_ids, ["Alice Example (backup) <alice@example.org>"]);
+        assert_eq!(key.subkeys.len(), 2);
+        assert_eq!(key.subkeys[0].algorithm, "cv25519");
+        assert_eq!(key.subkeys[0].fingerprint, "5D1C0E2B7A9F4836C1E0B2D3A4F5E6D71F2E3D4C");
+        assert_eq!(key.subkeys[1].validity, "r");
+        assert_eq!(key.subkeys[1].expires, None);
+    }
+}
@@ -0,0 +1,230 @@
+use anyhow::{anyhow, Result};
+use chrono::Local;
+use std::path::{Path, PathBuf};
+
+use crate::core::{
+    command::{CommandRunner, CommandSpec},
+    secret::SecretString,
+};
+use crate::tools::{
+    installation::{boot_usb, plan::InstallPlan},
+    key_manager,
+};
+
+/// Mount point of an encrypted USB stick holding header backups
+const USB_MOUNT_POINT: &str = "/run/lunitool/header-usb";
+/// Filesystem label of USB sticks formatted for header backups
+const USB_LABEL: &str = "LUKS-HEADERS";
+
+/// Where LUKS header backups are kept
+#[derive(Debug, Clone)]
+pub enum HeaderStore {
+    /// Directory on a mounted medium
+    Directory(PathBuf),
+    /// USB stick encrypted with `key_manager::create_encrypted_usb`
+    EncryptedUsb {
+        device: String,
+        passphrase: SecretString,
+        /// Wipe and encrypt the stick before the first backup
+        format: bool,
+    },
+}
+
+impl std::fmt::Display for HeaderStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HeaderStore::Directory(dir) => write!(f, "{}", dir.display()),
+            HeaderStore::EncryptedUsb { device, .. } => write!(f, "encrypted USB {}", device),
+        }
+    }
+}
+
+impl HeaderStore {
+    /// Make the store available, run `action` with its root directory and release it again
+    fn with_root<T>(
+        &self,
+        runner: &mut dyn CommandRunner,
+        action: impl FnOnce(&mut dyn CommandRunner, &Path) -> Result<T>,
+    ) -> Result<T> {
+        match self {
+            HeaderStore::Directory(dir) => action(runner, dir),
+            HeaderStore::EncryptedUsb { device, passphrase, format } => {
+                if *format {
+                    key_manager::create_encrypted_usb(runner, device, passphrase.expose(), USB_LABEL)?;
+                }
+                let mount_point = Path::new(USB_MOUNT_POINT);
+                key_manager::open_encrypted_usb(runner, device, passphrase.expose(), mount_point)?;
+                let result = action(runner, mount_point);
+                // Release the stick even if the action failed
+                key_manager::close_encrypted_usb(runner, mount_point)?;
+                result
+            }
+        }
+    }
+}
+
+/// UUID of the LUKS header on `device` (a block device or a header file)
+pub fn luks_uuid(runner: &mut dyn CommandRunner, device: &str) -> Result<String> {
+    let output = runner.run_checked(&CommandSpec::new("cryptsetup").args(["luksUUID", device]))?;
+    Ok(output.stdout.trim().to_string())
+}
+
+/// Write a backup of the header of `device` into `store`
+///
+/// Returns the path of the backup, relative to the store. Names contain
+/// UUID and time, so older backups are never overwritten.
+pub fn backup_header(runner: &mut dyn CommandRunner, device: &str, store: &HeaderStore) -> Result<PathBuf> {
+    let mut uuid = luks_uuid(runner, device)?;
+    if uuid.is_empty() {
+        // Dry-run mode has no header to read
+        uuid = Path::new(device).file_name().map_or_else(|| "unknown".to_string(), |name| name.to_string_lossy().into_owned());
+    }
+    let name = PathBuf::from(format!("luks-header-{}-{}.img", uuid, Local::now().format("%Y%m%d-%H%M%S")));
+    log::info!("Backing up the LUKS header of {} to {}", device, store);
+
+    store.with_root(runner, |runner, root| {
+        let file = root.join(&name).to_string_lossy().into_owned();
+        runner.run_checked(&CommandSpec::new("install").args(["-d", "-m", "0700"]).arg(root.to_string_lossy()))?;
+        runner.run_checked(
+            &CommandSpec::new("cryptsetup").args(["luksHeaderBackup", device, "--header-backup-file", &file]),
+        )?;
+        runner.run_checked(&CommandSpec::new("chmod").args(["0400", &file]))?;
+        // A header backup must be restorable, so check it before reporting success
+        verify_header_file(runner, &file, device, None)?;
+        Ok(())
+    })?;
+    Ok(name)
+}
+
+/// Check a header file against `device` and optionally against a passphrase
+///
+/// The UUIDs must match unless the header on the device is no longer
+/// readable, which is the case a restore is needed for. The passphrase is
+/// tested with the backup header on the data of `device`.
+fn verify_header_file(runner: &mut dyn CommandRunner, header: &str, device: &str, passphrase: Option<&str>) -> Result<String> {
+    runner
+        .run_checked(&CommandSpec::new("cryptsetup").args(["isLuks", header]))
+        .map_err(|_| anyhow!("{} is not a LUKS header", header))?;
+    let header_uuid = luks_uuid(runner, header)?;
+    match luks_uuid(runner, device) {
+        Ok(device_uuid) if device_uuid != header_uuid => {
+            return Err(anyhow!(
+                "Header {} belongs to {}, not to {} ({})",
+                header,
+                header_uuid,
+                device,
+                device_uuid
+            ));
+        }
+        Ok(_) => {}
+        Err(e) => log::warn!("Header of {} is not readable, cannot compare UUIDs: {}", device, e),
+    }
+    if let Some(passphrase) = passphrase {
+        runner
+            .run_checked(
+                &CommandSpec::new("cryptsetup")
+                    .args(["open", "--test-passphrase", "--header", header, "--key-file=-", device])
+                    .stdin(passphrase),
+            )
+            .map_err(|_| anyhow!("The passphrase does not open {} with header {}", device, header))?;
+    }
+    Ok(header_uuid)
+}
+
+/// Verify the backup `header` (relative to `store`) against `device`; returns its UUID
+pub fn verify_header(
+    runner: &mut dyn CommandRunner,
+    store: &HeaderStore,
+    header: &Path,
+    device: &str,
+    passphrase: Option<&str>,
+) -> Result<String> {
+    store.with_root(runner, |runner, root| {
+        let file = root.join(header).to_string_lossy().into_owned();
+        verify_header_file(runner, &file, device, passphrase)
+    })
+}
+
+/// Write the backup `header` (relative to `store`) over the header of `device`
+///
+/// Overwrites all keyslots on the device with those of the backup. The
+/// backup is verified with `passphrase` first, so a wrong or damaged file
+/// cannot replace a working header.
+pub fn restore_header(runner: &mut dyn CommandRunner, store: &HeaderStore, header: &Path, device: &str, passphrase: &str) -> Result<()> {
+    store.with_root(runner, |runner, root| {
+        let file = root.join(header).to_string_lossy().into_owned();
+        verify_header_file(runner, &file, device, Some(passphrase))?;
+        log::warn!("Restoring the LUKS header of {} from {}", device, file);
+        runner.run_checked(
+            &CommandSpec::new("cryptsetup").args(["luksHeaderRestore", "--batch-mode", device, "--header-backup-file", &file]),
+        )?;
+        Ok(())
+    })
+}
+
+/// Back up the headers of all containers an installation created
+///
+/// Runs after the last keyslot change. Detached headers of a boot USB key
+/// are backed up from their staged copy.
+pub fn backup_installation(runner: &mut dyn CommandRunner, plan: &InstallPlan) -> Result<()> {
+    let mut containers = Vec::new();
+    if let (Some(_), Some(partition)) = (&plan.boot_encryption, plan.boot_partition()) {
+        containers.push(partition);
+    }
+    if let Some(encryption) = &plan.encryption {
+        containers.push(boot_usb::staged_header(encryption).unwrap_or_else(|| plan.root_partition()));
+    }
+    if containers.is_empty() {
+        return Ok(());
+    }
+    let Some(store) = &plan.header_backup else {
+        log::info!(
+            "No LUKS header backup configured; a damaged header means losing all data. \
+             Create one later under Key Management"
+        );
+        return Ok(());
+    };
+    let mut store = store.clone();
+    for device in containers {
+        let name = backup_header(runner, &device, &store)?;
+        log::info!("LUKS header of {} saved as {} on {}", device, name.display(), store);
+        // Formatting again would wipe the backups written so far
+        if let HeaderStore::EncryptedUsb { format, .. } = &mut store {
+            *format = false;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        app::InstallationConfig,
+        core::command::DryRunCommandRunner,
+        tools::installation::{plan::Bootloader, DistroFamily, DEFAULT_TARGET_ROOT},
+    };
+
+    #[test]
+    fn installation_formats_the_usb_stick_once() {
+        let config = InstallationConfig {
+            target_disk: Some("/dev/sda".to_string()),
+            luks_password: Some("correct horse".into()),
+            bootloader: Bootloader::GrubCryptodisk,
+            header_backup: Some(HeaderStore::EncryptedUsb {
+                device: "/dev/sdb".to_string(),
+                passphrase: "usb passphrase".into(),
+                format: true,
+            }),
+            ..Default::default()
+        };
+        let plan = InstallPlan::from_config(&config, Path::new(DEFAULT_TARGET_ROOT), true, DistroFamily::Debian).unwrap();
+        let mut runner = DryRunCommandRunner::new();
+        backup_installation(&mut runner, &plan).unwrap();
+
+        let history = runner.rendered_history();
+        let backups = history.iter().filter(|command| command.contains("luksHeaderBackup")).count();
+        let formats = history.iter().filter(|command| command.contains("luksFormat")).count();
+        assert_eq!((backups, formats), (2, 1));
+    }
+}
@@ -0,0 +1,747 @@
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    core::{
+        command::{CommandRunner, CommandSpec},
+        secret::SecretString,
+        system_info::detect_efi,
+    },
+    get_config_dir,
+    tools::{
+        backup::{self, BackupCopy},
+        installation::{
+            engine::{spawn_worker, ReportingRunner},
+            ChrootOptions, ChrootSession, InstallHandle, LuksProfile,
+        },
+    },
+};
+
+/// Space freed at the end of the device for the LUKS2 header; twice the
+/// default 16 MiB header, as cryptsetup recommends for `--reduce-device-size`
+const HEADER_SPACE_MIB: u64 = 32;
+/// Directory below the config dir keeping the progress of interrupted runs
+const STATE_DIR: &str = "encrypt-state";
+/// Mount point of the root filesystem while the tool works on it
+pub const DEFAULT_MOUNT_POINT: &str = "/mnt/lunitool-encrypt";
+
+/// What the unencrypted partition holds
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExistingRoot {
+    /// ext4 root filesystem directly on the partition
+    Ext4,
+    /// LVM physical volume carrying the root logical volume
+    LvmPv { volume_group: String, root_lv: String },
+}
+
+/// Conversion of an existing root partition to LUKS2
+#[derive(Debug, Clone)]
+pub struct InPlacePlan {
+    /// Partition to encrypt, e.g. `/dev/sda2`; must not be mounted
+    pub device: String,
+    pub content: ExistingRoot,
+    /// Device mapper name the container is opened as in the converted system
+    pub mapper_name: String,
+    pub passphrase: SecretString,
+    /// Must be LUKS2; GRUB reading the root additionally needs pbkdf2
+    pub profile: LuksProfile,
+    /// Backup of the root filesystem, verified before anything is changed
+    pub backup: BackupCopy,
+    pub mount_point: PathBuf,
+}
+
+/// Steps of the conversion, in order; the state file records the last finished one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum InPlaceStep {
+    Start,
+    /// Backup verified and the installed system inspected
+    Verified,
+    /// Filesystem or PV shrunk to make room for the header
+    Shrunk,
+    /// `cryptsetup reencrypt` finished
+    Encrypted,
+    /// Filesystem or PV grown into the opened container
+    Grown,
+    /// crypttab, fstab, initramfs and bootloader updated
+    Configured,
+}
+
+/// initramfs generator of the installed system
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum InitramfsTool {
+    /// Debian family; unlocks from /etc/crypttab
+    InitramfsTools,
+    /// Fedora/RHEL family
+    Dracut,
+    /// Arch; `systemd` when the HOOKS use the systemd hook
+    Mkinitcpio { systemd: bool },
+}
+
+/// Bootloader of the installed system
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExistingBootloader {
+    Grub,
+    SystemdBoot,
+}
+
+/// Progress of one conversion, kept across interruptions and reboots
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InPlaceState {
+    pub device: String,
+    pub step: InPlaceStep,
+    /// /boot is a filesystem of its own and stays readable for GRUB
+    #[serde(default)]
+    pub separate_boot: bool,
+    pub initramfs: Option<InitramfsTool>,
+    pub bootloader: Option<ExistingBootloader>,
+    /// UUID of the new container, known once encrypted
+    pub luks_uuid: Option<String>,
+}
+
+impl InPlaceState {
+    fn new(device: &str) -> Self {
+        Self {
+            device: device.to_string(),
+            step: InPlaceStep::Start,
+            separate_boot: false,
+            initramfs: None,
+            bootloader: None,
+            luks_uuid: None,
+        }
+    }
+}
+
+/// State file of `device`, e.g. `configs/encrypt-state/sda2.yaml`
+///
+/// Lives next to the configuration on the lunitool medium, so it survives a
+/// reboot of the live system in the middle of the conversion.
+pub fn state_path(device: &str) -> PathBuf {
+    let name = device.trim_start_matches("/dev/").replace('/', "-");
+    get_config_dir().join(STATE_DIR).join(format!("{}.yaml", name))
+}
+
+/// Progress of an interrupted conversion of `device`, if any
+pub fn load_state(device: &str) -> Result<Option<InPlaceState>> {
+    let path = state_path(device);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let state: InPlaceState =
+        serde_yaml::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))?;
+    Ok(Some(state))
+}
+
+fn save_state(runner: &dyn CommandRunner, state: &InPlaceState) -> Result<()> {
+    if runner.is_dry_run() {
+        return Ok(());
+    }
+    let path = state_path(&state.device);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    fs::write(&path, serde_yaml::to_string(state)?).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(())
+}
+
+/// Run [`encrypt_in_place`] on a worker thread
+///
+/// The conversion takes as long as reading and writing the whole partition, so
+/// the UI only polls the handle for the commands and their output. A cancelled
+/// run is picked up again by the next start.
+pub fn start(plan: InPlacePlan, dry_run: bool) -> InstallHandle {
+    log::info!("Starting in-place encryption of {} (dry run: {})", plan.device, dry_run);
+    spawn_worker(dry_run, move |runner, _, events, cancel| {
+        encrypt_in_place(&mut ReportingRunner::new(runner, events, cancel), &plan)
+    })
+}
+
+/// Encrypt the root of an existing installation in place
+///
+/// Runs the remaining steps of the conversion; after an interruption the same
+/// call picks up at the step that was cut off, including an unfinished
+/// `cryptsetup reencrypt`. Refuses to start unless the backup verifies.
+pub fn encrypt_in_place(runner: &mut dyn CommandRunner, plan: &InPlacePlan) -> Result<()> {
+    if plan.profile.luks_type != "luks2" {
+        return Err(anyhow!("In-place encryption requires a LUKS2 profile"));
+    }
+    plan.profile.validate(false)?;
+
+    let mut state = load_state(&plan.device)?.unwrap_or_else(|| InPlaceState::new(&plan.device));
+    if state.step > InPlaceStep::Start {
+        log::info!("Resuming encryption of {} after step {:?}", plan.device, state.step);
+    }
+
+    if state.step < InPlaceStep::Verified {
+        verify_and_inspect(runner, plan, &mut state)?;
+        state.step = InPlaceStep::Verified;
+        save_state(runner, &state)?;
+    }
+    if state.step < InPlaceStep::Shrunk {
+        shrink(runner, plan)?;
+        state.step = InPlaceStep::Shrunk;
+        save_state(runner, &state)?;
+    }
+    if state.step < InPlaceStep::Encrypted {
+        encrypt(runner, plan)?;
+        let uuid = runner.run_checked(&CommandSpec::new("cryptsetup").args(["luksUUID", &plan.device]))?;
+        state.luks_uuid = Some(non_empty_or(runner, uuid.stdout.trim(), "<luks-uuid>")?);
+        state.step = InPlaceStep::Encrypted;
+        save_state(runner, &state)?;
+    }
+    if state.step < InPlaceStep::Grown {
+        grow(runner, plan)?;
+        state.step = InPlaceStep::Grown;
+        save_state(runner, &state)?;
+    }
+    if state.step < InPlaceStep::Configured {
+        configure(runner, plan, &state)?;
+        state.step = InPlaceStep::Configured;
+        save_state(runner, &state)?;
+    }
+
+    close(runner, plan)?;
+    if !runner.is_dry_run() {
+        let path = state_path(&plan.device);
+        fs::remove_file(&path).with_context(|| format!("Failed to remove {}", path.display()))?;
+    }
+    log::info!("{} is encrypted; keep the backup until the system booted from it", plan.device);
+    Ok(())
+}
+
+/// Placeholder for values only a real run can read back
+fn non_empty_or(runner: &dyn CommandRunner, value: &str, placeholder: &str) -> Result<String> {
+    match (value.is_empty(), runner.is_dry_run()) {
+        (false, _) => Ok(value.to_string()),
+        (true, true) => Ok(placeholder.to_string()),
+        (true, false) => Err(anyhow!("Missing command output, expected {}", placeholder)),
+    }
+}
+
+/// Block device the root filesystem is on, before (`opened` false) or after encryption
+fn root_filesystem(plan: &InPlacePlan, opened: bool) -> String {
+    match &plan.content {
+        ExistingRoot::Ext4 if opened => format!("/dev/mapper/{}", plan.mapper_name),
+        ExistingRoot::Ext4 => plan.device.clone(),
+        ExistingRoot::LvmPv { volume_group, root_lv } => format!("/dev/{}/{}", volume_group, root_lv),
+    }
+}
+
+/// Check the device, verify the backup and record how the system boots
+fn verify_and_inspect(runner: &mut dyn CommandRunner, plan: &InPlacePlan, state: &mut InPlaceState) -> Result<()> {
+    log::info!("Checking {} before encryption", plan.device);
+    // findmnt exits non-zero when nothing is mounted
+    let mounted = runner.run(&CommandSpec::new("findmnt").args(["-rn", "-S", &plan.device]))?;
+    if !mounted.stdout.trim().is_empty() {
+        return Err(anyhow!("{} is mounted; encrypt it from a live system", plan.device));
+    }
+    if runner.run(&CommandSpec::new("cryptsetup").args(["isLuks", &plan.device]))?.success() && !runner.is_dry_run() {
+        return Err(anyhow!("{} already is a LUKS container", plan.device));
+    }
+
+    if let ExistingRoot::LvmPv { volume_group, .. } = &plan.content {
+        runner.run_checked(&CommandSpec::new("vgchange").args(["-ay", volume_group]))?;
+    }
+    let mount_point = plan.mount_point.to_string_lossy().into_owned();
+    runner.run_checked(&CommandSpec::new("mkdir").args(["-p", &mount_point]))?;
+    runner.run_checked(&CommandSpec::new("mount").args(["-o", "ro", &root_filesystem(plan, false), &mount_point]))?;
+
+    let checked = backup::verify_backup(runner, &plan.mount_point, &plan.backup)
+        .and_then(|_| inspect_system(runner, plan, state));
+    runner.run_checked(&CommandSpec::new("umount").arg(&mount_point))?;
+    if let ExistingRoot::LvmPv { volume_group, .. } = &plan.content {
+        runner.run_checked(&CommandSpec::new("vgchange").args(["-an", volume_group]))?;
+    }
+    checked.context("Refusing to encrypt without a verified backup and a supported system")
+}
+
+/// Find out how the mounted system assembles its initramfs and boots
+fn inspect_system(runner: &dyn CommandRunner, plan: &InPlacePlan, state: &mut InPlaceState) -> Result<()> {
+    let root = &plan.mount_point;
+    if runner.is_dry_run() {
+        // Nothing is mounted; assume a Debian style GRUB system
+        state.initramfs = Some(InitramfsTool::InitramfsTools);
+        state.bootloader = Some(ExistingBootloader::Grub);
+        state.separate_boot = true;
+        return Ok(());
+    }
+
+    let fstab = fs::read_to_string(root.join("etc/fstab")).context("No /etc/fstab in the root filesystem")?;
+    state.separate_boot = fstab
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .any(|line| line.split_whitespace().nth(1) == Some("/boot"));
+
+    let exists = |path: &str| root.join(path.trim_start_matches('/')).exists();
+    state.initramfs = if exists("/usr/sbin/update-initramfs") {
+        Some(InitramfsTool::InitramfsTools)
+    } else if exists("/usr/bin/dracut") || exists("/usr/sbin/dracut") {
+        Some(InitramfsTool::Dracut)
+    } else if exists("/usr/bin/mkinitcpio") {
+        let conf = fs::read_to_string(root.join("etc/mkinitcpio.conf")).unwrap_or_default();
+        let systemd = conf
+            .lines()
+            .find(|line| line.starts_with("HOOKS="))
+            .is_some_and(|hooks| hooks.split(|c: char| !c.is_alphanumeric() && c != '-').any(|hook| hook == "systemd"));
+        Some(InitramfsTool::Mkinitcpio { systemd })
+    } else {
+        return Err(anyhow!("No supported initramfs generator (initramfs-tools, dracut, mkinitcpio) found"));
+    };
+
+    state.bootloader = if exists("/etc/default/grub") {
+        Some(ExistingBootloader::Grub)
+    } else if exists("/etc/kernel/cmdline") || exists("/usr/lib/systemd/boot/efi") {
+        Some(ExistingBootloader::SystemdBoot)
+    } else {
+        return Err(anyhow!("No supported bootloader (GRUB, systemd-boot) found"));
+    };
+
+    // GRUB has to unlock the root to read the kernels from it
+    if state.bootloader == Some(ExistingBootloader::Grub) && !state.separate_boot {
+        plan.profile
+            .validate(true)
+            .context("/boot is part of the root filesystem, so GRUB must unlock it")?;
+    }
+    Ok(())
+}
+
+/// Size in KiB the device is reduced to for the header, as `resize2fs`/`pvresize` argument
+fn reduced_size_kib(runner: &mut dyn CommandRunner, device: &str) -> Result<String> {
+    let output = runner.run_checked(&CommandSpec::new("blockdev").args(["--getsize64", device]))?;
+    match output.stdout.trim().parse::<u64>() {
+        Ok(bytes) => {
+            let kib = (bytes / 1024)
+                .checked_sub(HEADER_SPACE_MIB * 1024)
+                .ok_or_else(|| anyhow!("{} is too small for a LUKS header", device))?;
+            Ok(format!("{}K", kib))
+        }
+        Err(_) if runner.is_dry_run() => Ok(format!("<size-of-{}-minus-{}M>", device, HEADER_SPACE_MIB)),
+        Err(_) => Err(anyhow!("Cannot read the size of {}", device)),
+    }
+}
+
+/// Run e2fsck, which exits with 1 after fixing errors
+fn check_filesystem(runner: &mut dyn CommandRunner, device: &str) -> Result<()> {
+    let output = runner.run(&CommandSpec::new("e2fsck").args(["-f", "-p", device]))?;
+    match output.status {
+        Some(0) | Some(1) => Ok(()),
+        _ if runner.is_dry_run() => Ok(()),
+        _ => Err(anyhow!("e2fsck found errors on {} it could not fix: {}", device, output.stderr.trim())),
+    }
+}
+
+/// Free the last 32 MiB of the device
+fn shrink(runner: &mut dyn CommandRunner, plan: &InPlacePlan) -> Result<()> {
+    let size = reduced_size_kib(runner, &plan.device)?;
+    log::info!("Shrinking {} to {} for the LUKS header", plan.device, size);
+    match &plan.content {
+        ExistingRoot::Ext4 => {
+            check_filesystem(runner, &plan.device)?;
+            runner.run_checked(&CommandSpec::new("resize2fs").args([&plan.device, &size]))?;
+        }
+        ExistingRoot::LvmPv { .. } => {
+            // Fails if extents are allocated at the end of the PV; pvmove them first
+            runner.run_checked(
+                &CommandSpec::new("pvresize").args(["--yes", "--setphysicalvolumesize", &size, &plan.device]),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Encrypt the device, or continue an interrupted `cryptsetup reencrypt`
+fn encrypt(runner: &mut dyn CommandRunner, plan: &InPlacePlan) -> Result<()> {
+    let interrupted = !runner.is_dry_run()
+        && runner.run(&CommandSpec::new("cryptsetup").args(["isLuks", &plan.device]))?.success();
+    let spec = if interrupted {
+        log::info!("Resuming the interrupted encryption of {}", plan.device);
+        CommandSpec::new("cryptsetup").args(["reencrypt", "--resume-only", "--batch-mode", "--key-file=-", &plan.device])
+    } else {
+        log::info!("Encrypting {} in place ({}), this takes a while", plan.device, plan.profile);
+        CommandSpec::new("cryptsetup")
+            .args(["reencrypt", "--encrypt"])
+            .args(plan.profile.format_args())
+            .args(["--reduce-device-size", &format!("{}M", HEADER_SPACE_MIB)])
+            .args(["--batch-mode", "--key-file=-", &plan.device])
+    };
+    // The passphrase only ever travels through stdin
+    runner.run_checked(&spec.stdin(plan.passphrase.expose()))?;
+    Ok(())
+}
+
+/// Open the container and let the filesystem or PV fill it again
+fn grow(runner: &mut dyn CommandRunner, plan: &InPlacePlan) -> Result<()> {
+    open(runner, plan)?;
+    let mapper = format!("/dev/mapper/{}", plan.mapper_name);
+    match &plan.content {
+        ExistingRoot::Ext4 => {
+            check_filesystem(runner, &mapper)?;
+            runner.run_checked(&CommandSpec::new("resize2fs").arg(&mapper))?;
+        }
+        ExistingRoot::LvmPv { .. } => {
+            runner.run_checked(&CommandSpec::new("pvresize").arg(&mapper))?;
+        }
+    }
+    Ok(())
+}
+
+/// Open the container unless a previous run left it open
+fn open(runner: &mut dyn CommandRunner, plan: &InPlacePlan) -> Result<()> {
+    let mapper = format!("/dev/mapper/{}", plan.mapper_name);
+    if !runner.is_dry_run() && Path::new(&mapper).exists() {
+        return Ok(());
+    }
+    runner.run_checked(
+        &CommandSpec::new("cryptsetup")
+            .args(["open", "--key-file=-", &plan.device, &plan.mapper_name])
+            .stdin(plan.passphrase.expose()),
+    )?;
+    if let ExistingRoot::LvmPv { volume_group, .. } = &plan.content {
+        runner.run_checked(&CommandSpec::new("vgchange").args(["-ay", volume_group]))?;
+    }
+    Ok(())
+}
+
+fn close(runner: &mut dyn CommandRunner, plan: &InPlacePlan) -> Result<()> {
+    if let ExistingRoot::LvmPv { volume_group, .. } = &plan.content {
+        runner.run_checked(&CommandSpec::new("vgchange").args(["-an", volume_group]))?;
+    }
+    runner.run_checked(&CommandSpec::new("cryptsetup").args(["close", &plan.mapper_name]))?;
+    Ok(())
+}
+
+/// Teach the installed system to unlock its root: crypttab, fstab, initramfs, bootloader
+fn configure(runner: &mut dyn CommandRunner, plan: &InPlacePlan, state: &InPlaceState) -> Result<()> {
+    open(runner, plan)?;
+    let uuid = state.luks_uuid.clone().ok_or_else(|| anyhow!("UUID of the new container unknown"))?;
+    let mount_point = plan.mount_point.to_string_lossy().into_owned();
+    runner.run_checked(&CommandSpec::new("mkdir").args(["-p", &mount_point]))?;
+    runner.run_checked(&CommandSpec::new("mount").args([&root_filesystem(plan, true), &mount_point]))?;
+
+    let options = ChrootOptions {
+        efivarfs: detect_efi(),
+        resolv_conf: false,
+    };
+    let session = ChrootSession::enter(runner, &plan.mount_point, options)?;
+    let configured = configure_in_chroot(runner, plan, state, &session, &uuid);
+    let closed = session.close(runner);
+    runner.run_checked(&CommandSpec::new("umount").args(["-R", &mount_point]))?;
+    configured?;
+    closed
+}
+
+fn configure_in_chroot(
+    runner: &mut dyn CommandRunner,
+    plan: &InPlacePlan,
+    state: &InPlaceState,
+    session: &ChrootSession,
+    uuid: &str,
+) -> Result<()> {
+    // /boot and the ESP, as the installed system mounts them
+    session.run(runner, &CommandSpec::new("mount").args(["-a", "-O", "no_netdev"]))?;
+
+    log::info!("Adding {} to /etc/crypttab", plan.mapper_name);
+    let entry = format!("{} UUID={} none luks,discard", plan.mapper_name, uuid);
+    session.run(
+        runner,
+        &CommandSpec::new("sh").args([
+            "-c",
+            "grep -q \"^$1 \" /etc/crypttab 2>/dev/null || echo \"$2\" >> /etc/crypttab; chmod 0600 /etc/crypttab",
+            "sh",
+            &plan.mapper_name,
+            &entry,
+        ]),
+    )?;
+    if plan.content == ExistingRoot::Ext4 {
+        // The filesystem UUID survives the encryption; only raw device paths need updating
+        session.run(
+            runner,
+            &CommandSpec::new("sed").args([
+                "-i",
+                &format!("s|^{}[[:space:]]|/dev/mapper/{} |", plan.device, plan.mapper_name),
+                "/etc/fstab",
+            ]),
+        )?;
+    }
+
+    let initramfs = state.initramfs.ok_or_else(|| anyhow!("initramfs generator of the system unknown"))?;
+    let params = update_initramfs(runner, plan, session, initramfs, uuid)?;
+    match state.bootloader {
+        Some(ExistingBootloader::Grub) => update_grub(runner, plan, session, state, &params),
+        Some(ExistingBootloader::SystemdBoot) => update_systemd_boot(runner, session, &params),
+        None => Err(anyhow!("Bootloader of the system unknown")),
+    }
+}
+
+/// Rebuild the initramfs with unlocking support; returns the kernel parameters it needs
+fn update_initramfs(
+    runner: &mut dyn CommandRunner,
+    plan: &InPlacePlan,
+    session: &ChrootSession,
+    tool: InitramfsTool,
+    uuid: &str,
+) -> Result<Vec<String>> {
+    log::info!("Rebuilding the initramfs with {:?}", tool);
+    match tool {
+        InitramfsTool::InitramfsTools => {
+            // The hooks come with cryptsetup-initramfs, which may not be installed
+            session.run(runner, &CommandSpec::new("dpkg").args(["-s", "cryptsetup-initramfs"])).map_err(|_| {
+                anyhow!("cryptsetup-initramfs is not installed in the system; install it and resume")
+            })?;
+            session.run(runner, &CommandSpec::new("update-initramfs").args(["-u", "-k", "all"]))?;
+            Ok(Vec::new())
+        }
+        InitramfsTool::Dracut => {
+            session.run(
+                runner,
+                &CommandSpec::new("sh").args([
+                    "-c",
+                    "mkdir -p /etc/dracut.conf.d && echo 'add_dracutmodules+=\" crypt \"' > /etc/dracut.conf.d/lunitool-encrypt.conf",
+                ]),
+            )?;
+            session.run(runner, &CommandSpec::new("dracut").args(["--regenerate-all", "--force"]))?;
+            Ok(vec![format!("rd.luks.uuid={}", uuid)])
+        }
+        InitramfsTool::Mkinitcpio { systemd } => {
+            let (hook, param) = if systemd {
+                ("sd-encrypt", format!("rd.luks.name={}={}", uuid, plan.mapper_name))
+            } else {
+                ("encrypt", format!("cryptdevice=UUID={}:{}", uuid, plan.mapper_name))
+            };
+            // Before lvm2 if present, else before filesystems
+            session.run(
+                runner,
+                &CommandSpec::new("sed").args([
+                    "-i",
+                    &format!("/^HOOKS=/{{/ {} /!s/ \\(lvm2\\|filesystems\\)/ {} \\1/}}", hook, hook),
+                    "/etc/mkinitcpio.conf",
+                ]),
+            )?;
+            session.run(runner, &CommandSpec::new("mkinitcpio").arg("-P"))?;
+            Ok(vec![param])
+        }
+    }
+}
+
+/// Append `params` to the kernel command line of GRUB and regenerate its configuration
+fn update_grub(
+    runner: &mut dyn CommandRunner,
+    plan: &InPlacePlan,
+    session: &ChrootSession,
+    state: &InPlaceState,
+    params: &[String],
+) -> Result<()> {
+    for param in params {
+        session.run(
+            runner,
+            &CommandSpec::new("sh").args([
+                "-c",
+                "grep -q -- \"$1\" /etc/default/grub || sed -i \"s|^GRUB_CMDLINE_LINUX=\\\"\\(.*\\)\\\"|GRUB_CMDLINE_LINUX=\\\"\\1 $1\\\"|\" /etc/default/grub",
+                "sh",
+                param,
+            ]),
+        )?;
+    }
+
+    let dry_run = runner.is_dry_run();
+    let exists = |path: &str| dry_run || Path::new(&session.host_path(path)).exists();
+    let fedora_layout = !dry_run && exists("/usr/sbin/grub2-mkconfig");
+    if !state.separate_boot {
+        // GRUB now has to unlock the root itself, which needs a new core image
+        log::info!("/boot is on the encrypted root; enabling GRUB cryptodisk");
+        session.run(
+            runner,
+            &CommandSpec::new("sh").args([
+                "-c",
+                "sed -i '/^#\\?GRUB_ENABLE_CRYPTODISK=/d' /etc/default/grub && echo GRUB_ENABLE_CRYPTODISK=y >> /etc/default/grub",
+            ]),
+        )?;
+        let grub_install = if fedora_layout { "grub2-install" } else { "grub-install" };
+        if detect_efi() {
+            let esp = ["/boot/efi", "/efi"].into_iter().find(|esp| exists(esp)).unwrap_or("/boot/efi");
+            session.run(
+                runner,
+                &CommandSpec::new(grub_install).args(["--target=x86_64-efi", &format!("--efi-directory={}", esp)]),
+            )?;
+        } else {
+            let parent = runner.run_checked(&CommandSpec::new("lsblk").args(["-no", "pkname", &plan.device]))?;
+            let disk = format!("/dev/{}", non_empty_or(runner, parent.stdout.trim(), "<disk>")?);
+            session.run(runner, &CommandSpec::new(grub_install).args(["--target=i386-pc", &disk]))?;
+        }
+    }
+
+    if fedora_layout {
+        // BLS entries carry their own options
+        if !params.is_empty() {
+            session.run(
+                runner,
+                &CommandSpec::new("grubby").args(["--update-kernel=ALL", &format!("--args={}", params.join(" "))]),
+            )?;
+        }
+        session.run(runner, &CommandSpec::new("grub2-mkconfig").args(["-o", "/boot/grub2/grub.cfg"]))?;
+    } else {
+        session.run(runner, &CommandSpec::new("grub-mkconfig").args(["-o", "/boot/grub/grub.cfg"]))?;
+    }
+    Ok(())
+}
+
+/// Append `params` to /etc/kernel/cmdline and the loader entries on the ESP
+fn update_systemd_boot(runner: &mut dyn CommandRunner, session: &ChrootSession, params: &[String]) -> Result<()> {
+    if params.is_empty() {
+        return Ok(());
+    }
+    let esp = session.run(runner, &CommandSpec::new("bootctl").arg("--print-esp-path"))?;
+    let esp = non_empty_or(runner, esp.stdout.trim(), "/boot")?;
+    let params = params.join(" ");
+    session.run(
+        runner,
+        &CommandSpec::new("sh").args([
+            "-c",
+            "if [ -f /etc/kernel/cmdline ] && ! grep -q -- \"$1\" /etc/kernel/cmdline; then sed -i \"s|\\$| $1|\" /etc/kernel/cmdline; fi; \
+             for entry in \"$2\"/loader/entries/*.conf; do \
+             [ -f \"$entry\" ] && ! grep -q -- \"$1\" \"$entry\" && sed -i \"/^options / s|\\$| $1|\" \"$entry\"; done; true",
+            "sh",
+            &params,
+            &esp,
+        ]),
+    )?;
+    log::info!("Kernel parameters added; unified kernel images have to be rebuilt by the system's tooling");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::command::{CommandOutput, DryRunCommandRunner},
+        tools::installation::InstallEvent,
+    };
+
+    const PASSPHRASE: &str = "correct horse battery staple";
+
+    fn plan(content: ExistingRoot) -> InPlacePlan {
+        InPlacePlan {
+            device: "/dev/vdz2".to_string(),
+            content,
+            mapper_name: "cryptroot".to_string(),
+            passphrase: SecretString::from(PASSPHRASE),
+            profile: LuksProfile::default(),
+            backup: BackupCopy::Directory(PathBuf::from("/media/backup/root")),
+            mount_point: PathBuf::from(DEFAULT_MOUNT_POINT),
+        }
+    }
+
+    fn position(history: &[String], prefix: &str) -> usize {
+        history
+            .iter()
+            .position(|command| command.starts_with(prefix))
+            .unwrap_or_else(|| panic!("no command starting with '{}' in {:#?}", prefix, history))
+    }
+
+    /// A real system on which the device already carries the header of an interrupted run
+    #[derive(Default)]
+    struct Interrupted(Vec<CommandSpec>);
+
+    impl CommandRunner for Interrupted {
+        fn run(&mut self, spec: &CommandSpec) -> Result<CommandOutput> {
+            self.0.push(spec.clone());
+            Ok(CommandOutput {
+                status: Some(0),
+                ..Default::default()
+            })
+        }
+    }
+
+    #[test]
+    fn refuses_to_touch_the_device_without_a_verified_backup() {
+        let mut runner = DryRunCommandRunner::new();
+        runner.respond_to("rsync", ">f.st...... etc/hostname\n");
+        let error = encrypt_in_place(&mut runner, &plan(ExistingRoot::Ext4)).unwrap_err();
+
+        assert!(format!("{:#}", error).contains("Refusing to encrypt without a verified backup"));
+        let history = runner.rendered_history();
+        // The root was mounted read-only for the comparison and released again
+        assert!(position(&history, "mount -o ro /dev/vdz2") < position(&history, "umount /mnt/lunitool-encrypt"));
+        assert!(!history.iter().any(|command| {
+            ["resize2fs", "pvresize", "e2fsck", "cryptsetup reencrypt"].iter().any(|p| command.starts_with(p))
+        }));
+    }
+
+    #[test]
+    fn shrinks_encrypts_and_grows_in_order() {
+        let mut runner = DryRunCommandRunner::new();
+        encrypt_in_place(&mut runner, &plan(ExistingRoot::Ext4)).unwrap();
+        let history = runner.rendered_history();
+
+        let verified = position(&history, "rsync -a -n --checksum");
+        let shrunk = position(&history, "resize2fs /dev/vdz2 <size-of-/dev/vdz2-minus-32M>");
+        let encrypted = position(&history, "cryptsetup reencrypt --encrypt");
+        let opened = position(&history, "cryptsetup open --key-file=- /dev/vdz2 cryptroot");
+        let grown = position(&history, "resize2fs /dev/mapper/cryptroot");
+        let closed = position(&history, "cryptsetup close cryptroot");
+        assert!(verified < shrunk && shrunk < encrypted && encrypted < opened && opened < grown && grown < closed);
+        assert!(position(&history, "e2fsck -f -p /dev/vdz2") < shrunk);
+        assert!(history[encrypted].contains("--reduce-device-size 32M"));
+        assert!(history[encrypted].contains("--type luks2"));
+
+        // The passphrase reaches cryptsetup through stdin only
+        assert!(!history.iter().any(|command| command.contains(PASSPHRASE)));
+        let reencrypt = &runner.history()[encrypted];
+        assert_eq!(reencrypt.stdin.as_deref().map(Vec::as_slice), Some(PASSPHRASE.as_bytes()));
+    }
+
+    #[test]
+    fn shrinks_and_grows_the_physical_volume_of_lvm_roots() {
+        let mut runner = DryRunCommandRunner::new();
+        let content = ExistingRoot::LvmPv {
+            volume_group: "vg0".to_string(),
+            root_lv: "root".to_string(),
+        };
+        encrypt_in_place(&mut runner, &plan(content)).unwrap();
+        let history = runner.rendered_history();
+
+        assert!(position(&history, "mount -o ro /dev/vg0/root") < position(&history, "pvresize --yes"));
+        assert!(
+            position(&history, "pvresize --yes --setphysicalvolumesize") < position(&history, "cryptsetup reencrypt")
+        );
+        assert!(position(&history, "cryptsetup reencrypt") < position(&history, "pvresize /dev/mapper/cryptroot"));
+        assert!(!history.iter().any(|command| command.starts_with("resize2fs")));
+    }
+
+    #[test]
+    fn worker_streams_commands_and_output() {
+        let mut handle = start(plan(ExistingRoot::Ext4), true);
+        handle.join();
+        let events = handle.drain_events();
+
+        assert!(events.iter().any(|event| matches!(event, InstallEvent::Command(c) if c.starts_with("cryptsetup reencrypt"))));
+        assert!(events.iter().any(|event| matches!(event, InstallEvent::Output(line) if line.starts_with("[dry-run] resize2fs"))));
+        assert!(matches!(events.last(), Some(InstallEvent::Finished)));
+    }
+
+    #[test]
+    fn resumes_an_interrupted_reencryption() {
+        let mut runner = Interrupted::default();
+        encrypt(&mut runner, &plan(ExistingRoot::Ext4)).unwrap();
+
+        let rendered: Vec<String> = runner.0.iter().map(CommandSpec::to_string).collect();
+        assert_eq!(
+            rendered,
+            [
+                "cryptsetup isLuks /dev/vdz2",
+                "cryptsetup reencrypt --resume-only --batch-mode --key-file=- /dev/vdz2",
+            ]
+        );
+        // Resuming must not shrink the device a second time
+        assert!(!rendered[1].contains("--reduce-device-size"));
+        assert_eq!(runner.0[1].stdin.as_deref().map(Vec::as_slice), Some(PASSPHRASE.as_bytes()));
+    }
+}
@@ -1,200 +0,0 @@
-use anyhow::Result;
-use std::process::Command;
-
-/// Start the installation process
-pub fn start_installation() -> Result<()> {
-    log::info!("Starting installation module...");
-    
-    // This is a placeholder for the actual installation functionality
-    // In a real implementation, this would include:
-    // - Detecting hardware
-    // - Partitioning disks
-    // - Installing the base system
-    // - Configuring bootloader
-    // - Setting up users and passwords
-    
-    Ok(())
-}
-
-/// Partition a disk
-pub fn partition_disk(device: &str, efi: bool) -> Result<()> {
-    log::info!("Partitioning disk {}", device);
-    
-    if cfg!(unix) {
-        // This is a simplified example using parted
-        let mut cmd = Command::new("parted");
-        cmd.args(&["-s", device, "mklabel"]);
-        
-        if efi {
-            cmd.arg("gpt");
-        } else {
-            cmd.arg("msdos");
-        }
-        
-        let status = cmd.status()?;
-        if !status.success() {
-            return Err(anyhow::anyhow!("Disk labeling failed with exit code: {}", status));
-        }
-        
-        // Create partitions
-        // For EFI:
-        if efi {
-            // EFI partition
-            let efi_part = Command::new("parted")
-                .args(&["-s", device, "mkpart", "primary", "fat32", "1MiB", "513MiB", "set", "1", "boot", "on", "set", "1", "esp", "on"])
-                .status()?;
-                
-            if !efi_part.success() {
-                return Err(anyhow::anyhow!("EFI partition creation failed with exit code: {}", efi_part));
-            }
-            
-            // Root partition
-            let root_part = Command::new("parted")
-                .args(&["-s", device, "mkpart", "primary", "513MiB", "100%"])
-                .status()?;
-                
-            if !root_part.success() {
-                return Err(anyhow::anyhow!("Root partition creation failed with exit code: {}", root_part));
-            }
-        } else {
-            // BIOS/MBR setup
-            // Boot partition
-            let boot_part = Command::new("parted")
-                .args(&["-s", device, "mkpart", "primary", "1MiB", "513MiB", "set", "1", "boot", "on"])
-                .status()?;
-                
-            if !boot_part.success() {
-                return Err(anyhow::anyhow!("Boot partition creation failed with exit code: {}", boot_part));
-            }
-            
-            // Root partition
-            let root_part = Command::new("parted")
-                .args(&["-s", device, "mkpart", "primary", "513MiB", "100%"])
-                .status()?;
-                
-            if !root_part.success() {
-                return Err(anyhow::anyhow!("Root partition creation failed with exit code: {}", root_part));
-            }
-        }
-    } else {
-        // Windows implementation would go here
-        return Err(anyhow::anyhow!("Disk partitioning not implemented for this platform"));
-    }
-    
-    Ok(())
-}
-
-/// Format partitions
-pub fn format_partitions(device: &str, efi: bool) -> Result<()> {
-    log::info!("Formatting partitions on {}", device);
-    
-    if cfg!(unix) {
-        if efi {
-            // Format EFI partition
-            let efi_part = format!("{}1", device);
-            let format_efi = Command::new("mkfs.fat")
-                .args(&["-F32", &efi_part])
-                .status()?;
-                
-            if !format_efi.success() {
-                return Err(anyhow::anyhow!("EFI partition formatting failed with exit code: {}", format_efi));
-            }
-            
-            // Format root partition
-            let root_part = format!("{}2", device);
-            let format_root = Command::new("mkfs.ext4")
-                .arg(&root_part)
-                .status()?;
-                
-            if !format_root.success() {
-                return Err(anyhow::anyhow!("Root partition formatting failed with exit code: {}", format_root));
-            }
-        } else {
-            // Format boot partition (ext4 for BIOS)
-            let boot_part = format!("{}1", device);
-            let format_boot = Command::new("mkfs.ext4")
-                .arg(&boot_part)
-                .status()?;
-                
-            if !format_boot.success() {
-                return Err(anyhow::anyhow!("Boot partition formatting failed with exit code: {}", format_boot));
-            }
-            
-            // Format root partition
-            let root_part = format!("{}2", device);
-            let format_root = Command::new("mkfs.ext4")
-                .arg(&root_part)
-                .status()?;
-                
-            if !format_root.success() {
-                return Err(anyhow::anyhow!("Root partition formatting failed with exit code: {}", format_root));
-            }
-        }
-    } else {
-        // Windows implementation would go here
-        return Err(anyhow::anyhow!("Partition formatting not implemented for this platform"));
-    }
-    
-    Ok(())
-}
-
-/// Install bootloader
-pub fn install_bootloader(device: &str, efi: bool, root_mount: &str) -> Result<()> {
-    log::info!("Installing bootloader on {}", device);
-    
-    if cfg!(unix) {
-        if efi {
-            // Mount EFI partition
-            std::fs::create_dir_all(format!("{}/boot/efi", root_mount))?;
-            let efi_part = format!("{}1", device);
-            let mount_efi = Command::new("mount")
-                .args(&[&efi_part, &format!("{}/boot/efi", root_mount)])
-                .status()?;
-                
-            if !mount_efi.success() {
-                return Err(anyhow::anyhow!("EFI partition mounting failed with exit code: {}", mount_efi));
-            }
-            
-            // Install GRUB for EFI
-            let grub_install = Command::new("chroot")
-                .args(&[root_mount, "grub-install", "--target=x86_64-efi", "--efi-directory=/boot/efi", "--bootloader-id=LUNITOOL"])
-                .status()?;
-                
-            if !grub_install.success() {
-                return Err(anyhow::anyhow!("GRUB installation failed with exit code: {}", grub_install));
-            }
-            
-            // Generate GRUB config
-            let grub_config = Command::new("chroot")
-                .args(&[root_mount, "grub-mkconfig", "-o", "/boot/grub/grub.cfg"])
-                .status()?;
-                
-            if !grub_config.success() {
-                return Err(anyhow::anyhow!("GRUB configuration failed with exit code: {}", grub_config));
-            }
-        } else {
-            // Install GRUB for BIOS
-            let grub_install = Command::new("chroot")
-                .args(&[root_mount, "grub-install", "--target=i386-pc", device])
-                .status()?;
-                
-            if !grub_install.success() {
-                return Err(anyhow::anyhow!("GRUB installation failed with exit code: {}", grub_install));
-            }
-            
-            // Generate GRUB config
-            let grub_config = Command::new("chroot")
-                .args(&[root_mount, "grub-mkconfig", "-o", "/boot/grub/grub.cfg"])
-                .status()?;
-                
-            if !grub_config.success() {
-                return Err(anyhow::anyhow!("GRUB configuration failed with exit code: {}", grub_config));
-            }
-        }
-    } else {
-        // Windows implementation would go here
-        return Err(anyhow::anyhow!("Bootloader installation not implemented for this platform"));
-    }
-    
-    Ok(())
-}
\ No newline at end of file
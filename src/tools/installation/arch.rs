@@ -0,0 +1,541 @@
+use anyhow::{anyhow, Result};
+use std::{fs, path::Path};
+
+use crate::core::command::{CommandRunner, CommandSpec};
+
+use super::{
+    backend::{filter_installed, write_file_command, DistroBackend, DistroFamily},
+    chroot::ChrootSession,
+    device_uuid,
+    fstab,
+    plan::{Bootloader, InstallPlan, UkiBuilder},
+    source::TARGET_REPO_DIR,
+    systemd_boot::{self, BootEntry},
+    tpm2,
+};
+
+/// pacman configuration used by pacstrap on the live system for offline installations
+const HOST_OFFLINE_CONF: &str = "/run/lunitool/pacman-offline.conf";
+/// pacman configuration inside the target for offline installations
+const TARGET_OFFLINE_CONF: &str = "/etc/pacman.d/lunitool-offline.conf";
+
+/// Packages of the Arch ISO that an installed system does not need
+const LIVE_ONLY_PACKAGES: &[&str] = &["mkinitcpio-archiso", "archinstall", "clonezilla", "memtest86+-efi"];
+
+/// Files archiso adds to its root filesystem
+const ARCHISO_FILES: &[&str] = &[
+    "/etc/mkinitcpio.conf.d/archiso.conf",
+    "/etc/mkinitcpio.d/linux.preset",
+    "/etc/systemd/system/getty@tty1.service.d/autologin.conf",
+    "/etc/systemd/journald.conf.d/volatile-storage.conf",
+    "/root/.automated_script.sh",
+];
+
+/// Packages pacstrap installs on every Arch system
+const BASE_PACKAGES: &[&str] = &[
+    "base",
+    "linux",
+    "linux-firmware",
+    "mkinitcpio",
+    "cryptsetup",
+    "lvm2",
+    "sudo",
+    "networkmanager",
+    "openssh",
+    "nano",
+];
+
+/// Flavour of the mkinitcpio hooks that unlock the root container
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InitramfsHooks {
+    /// busybox based `encrypt` hook, kernel parameter `cryptdevice=`
+    Encrypt,
+    /// systemd based `sd-encrypt` hook, kernel parameter `rd.luks.name=`
+    SdEncrypt,
+}
+
+/// Parameters of the Arch installation
+#[derive(Debug, Clone)]
+pub struct ArchOptions {
+    /// Packages passed to pacstrap
+    pub packages: Vec<String>,
+    /// Kernel package; determines the vmlinuz and initramfs file names
+    pub kernel: String,
+    pub hooks: InitramfsHooks,
+}
+
+impl Default for ArchOptions {
+    fn default() -> Self {
+        Self {
+            packages: BASE_PACKAGES.iter().map(|s| s.to_string()).collect(),
+            kernel: "linux".to_string(),
+            hooks: InitramfsHooks::SdEncrypt,
+        }
+    }
+}
+
+/// Installs Arch Linux with pacstrap
+#[derive(Debug, Clone, Default)]
+pub struct ArchBackend {
+    pub options: ArchOptions,
+}
+
+/// Name of the pacman repository in `dir`, derived from its `<name>.db` file
+fn local_repo_name(dir: &Path) -> String {
+    fs::read_dir(dir)
+        .ok()
+        .and_then(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter_map(|entry| entry.file_name().to_str().map(str::to_string))
+                .find_map(|name| name.strip_suffix(".db").map(str::to_string))
+        })
+        .unwrap_or_else(|| "offline".to_string())
+}
+
+/// pacman.conf using only the local repository at `server_dir`
+///
+/// The repository is a medium the operator brought along, so packages are trusted without signature.
+fn offline_pacman_conf(repo_name: &str, server_dir: &str) -> String {
+    format!(
+        "[options]\nArchitecture = auto\nSigLevel = Required DatabaseOptional\n\n[{}]\nSigLevel = Optional TrustAll\nServer = file://{}\n",
+        repo_name, server_dir
+    )
+}
+
+impl ArchBackend {
+    pub fn new(options: ArchOptions) -> Self {
+        Self { options }
+    }
+
+    /// pacman inside the target, restricted to the local repository when installing offline
+    fn pacman(plan: &InstallPlan) -> CommandSpec {
+        let spec = CommandSpec::new("pacman");
+        if plan.source.repository_dir().is_some() {
+            return spec.args(["--config", TARGET_OFFLINE_CONF]);
+        }
+        spec
+    }
+
+    /// Write the offline pacman.conf for the target, if installing from a local repository
+    fn write_offline_conf(runner: &mut dyn CommandRunner, plan: &InstallPlan) -> Result<()> {
+        if let Some(repository) = plan.source.repository_dir() {
+            let conf = offline_pacman_conf(&local_repo_name(&repository), TARGET_REPO_DIR);
+            runner.run_checked(&write_file_command(plan, TARGET_OFFLINE_CONF, "0644", &conf))?;
+        }
+        Ok(())
+    }
+
+    /// The HOOKS array of mkinitcpio.conf for the plan
+    pub fn mkinitcpio_hooks(&self, plan: &InstallPlan) -> Vec<&'static str> {
+        let mut hooks = vec!["base"];
+        match self.options.hooks {
+            InitramfsHooks::Encrypt => {
+                hooks.extend(["udev", "autodetect", "microcode", "modconf", "kms", "keyboard", "keymap", "consolefont", "block"]);
+                if plan.encryption.is_some() {
+                    hooks.push("encrypt");
+                }
+            }
+            InitramfsHooks::SdEncrypt => {
+                hooks.extend(["systemd", "autodetect", "microcode", "modconf", "kms", "keyboard", "sd-vconsole", "block"]);
+                if plan.encryption.is_some() {
+                    hooks.push("sd-encrypt");
+                }
+            }
+        }
+        if plan.lvm.is_some() {
+            hooks.push("lvm2");
+        }
+        hooks.extend(["filesystems", "fsck"]);
+        hooks
+    }
+
+    fn install_grub(
+        &self,
+        runner: &mut dyn CommandRunner,
+        session: &ChrootSession,
+        plan: &InstallPlan,
+        cmdline: &str,
+    ) -> Result<()> {
+        let mut packages = vec!["grub".to_string()];
+        if plan.boots_efi() {
+            packages.push("efibootmgr".to_string());
+        }
+        self.install_packages(runner, session, plan, &packages)?;
+
+        session.run(runner, &CommandSpec::new("sed").args([
+            "-i".to_string(),
+            format!("s|^GRUB_CMDLINE_LINUX=.*|GRUB_CMDLINE_LINUX=\"{}\"|", cmdline),
+            "/etc/default/grub".to_string(),
+        ]))?;
+        super::install_bootloader(runner, session, plan)
+    }
+
+    fn install_systemd_boot(
+        &self,
+        runner: &mut dyn CommandRunner,
+        session: &ChrootSession,
+        plan: &InstallPlan,
+        cmdline: &str,
+    ) -> Result<()> {
+        match plan.uki {
+            Some(UkiBuilder::Ukify) => self.install_packages(runner, session, plan, &["systemd-ukify".to_string()])?,
+            Some(UkiBuilder::Objcopy) => self.install_packages(runner, session, plan, &["binutils".to_string()])?,
+            None => {}
+        }
+        let kernel = &self.options.kernel;
+        let entry = BootEntry {
+            id: "arch".to_string(),
+            title: "Arch Linux".to_string(),
+            linux: format!("/vmlinuz-{}", kernel),
+            initrd: vec![format!("/initramfs-{}.img", kernel)],
+        };
+        systemd_boot::install_systemd_boot(runner, session, plan, cmdline, &[entry])
+    }
+}
+
+impl DistroBackend for ArchBackend {
+    fn name(&self) -> String {
+        "Arch Linux (pacstrap)".to_string()
+    }
+
+    fn family(&self) -> DistroFamily {
+        DistroFamily::Arch
+    }
+
+    fn validate_plan(&self, plan: &InstallPlan) -> Result<()> {
+        // Arch does not ship a Microsoft signed shim in its repositories
+        if plan.secure_boot.uses_shim() {
+            return Err(anyhow!("{} has no signed shim; use custom Secure Boot keys", self.name()));
+        }
+        // The busybox encrypt hook cannot unlock through the TPM
+        if plan.tpm2.is_some() && self.options.hooks != InitramfsHooks::SdEncrypt {
+            return Err(anyhow!("TPM2 unlocking requires the sd-encrypt mkinitcpio hook"));
+        }
+        // Only sd-encrypt reads the detached header from /etc/crypttab.initramfs
+        if plan.boot_usb.is_some() && self.options.hooks != InitramfsHooks::SdEncrypt {
+            return Err(anyhow!("A boot USB key requires the sd-encrypt mkinitcpio hook"));
+        }
+        Ok(())
+    }
+
+    fn bootstrap(&self, runner: &mut dyn CommandRunner, plan: &InstallPlan) -> Result<()> {
+        log::info!("Bootstrapping {} into {}", self.name(), plan.target());
+
+        let mut packages = self.options.packages.clone();
+        if self.options.kernel != "linux" {
+            packages.retain(|p| p != "linux");
+            packages.push(self.options.kernel.clone());
+        }
+        packages.extend(plan.extra_packages.iter().cloned());
+
+        // -K initialises an empty pacman keyring inside the target
+        let mut pacstrap = CommandSpec::new("pacstrap");
+        if let Some(repository) = plan.source.repository_dir() {
+            let conf = offline_pacman_conf(&local_repo_name(&repository), &repository.to_string_lossy());
+            runner.run_checked(
+                &CommandSpec::new("install")
+                    .args(["-D", "-m", "0644", "/dev/stdin", HOST_OFFLINE_CONF])
+                    .stdin(conf),
+            )?;
+            pacstrap = pacstrap.args(["-C", HOST_OFFLINE_CONF]);
+        }
+        runner.run_checked(&pacstrap.args(["-K", &plan.target()]).args(&packages))?;
+        Self::write_offline_conf(runner, plan)?;
+        Ok(())
+    }
+
+    fn install_packages(
+        &self,
+        runner: &mut dyn CommandRunner,
+        session: &ChrootSession,
+        plan: &InstallPlan,
+        packages: &[String],
+    ) -> Result<()> {
+        if packages.is_empty() {
+            return Ok(());
+        }
+        // An extracted Arch ISO root has no repository to resolve packages it already contains
+        let packages = if plan.source.image_root().is_some() && !runner.is_dry_run() {
+            let listing = session.run(runner, &CommandSpec::new("pacman").arg("-Qq"))?;
+            let installed = filter_installed(&listing, runner.is_dry_run(), packages);
+            packages.iter().filter(|p| !installed.contains(p)).cloned().collect()
+        } else {
+            packages.to_vec()
+        };
+        if packages.is_empty() {
+            return Ok(());
+        }
+        log::info!("Installing packages into target: {}", packages.join(" "));
+        session.run(runner, &Self::pacman(plan).args(["-S", "--noconfirm", "--needed"]).args(&packages))?;
+        Ok(())
+    }
+
+    fn configure_system(
+        &self,
+        runner: &mut dyn CommandRunner,
+        session: &ChrootSession,
+        plan: &InstallPlan,
+    ) -> Result<()> {
+        log::info!("Configuring target system {}", plan.hostname);
+
+        // Hostname
+        runner.run_checked(&write_file_command(plan, "/etc/hostname", "0644", &format!("{}\n", plan.hostname)))?;
+        runner.run_checked(&write_file_command(
+            plan,
+            "/etc/hosts",
+            "0644",
+            &format!("127.0.0.1 localhost\n::1 localhost\n127.0.1.1 {}\n", plan.hostname),
+        ))?;
+
+        // Locale
+        runner.run_checked(&write_file_command(plan, "/etc/locale.gen", "0644", &plan.locale_gen()))?;
+        session.run(runner, &CommandSpec::new("locale-gen"))?;
+        runner.run_checked(&write_file_command(plan, "/etc/locale.conf", "0644", &format!("LANG={}\n", plan.locale)))?;
+
+        // Console keymap, also used by the keymap/sd-vconsole hooks
+        runner.run_checked(&write_file_command(
+            plan,
+            "/etc/vconsole.conf",
+            "0644",
+            &format!("KEYMAP={}\n", plan.keyboard_layout),
+        ))?;
+
+        // Timezone
+        session.run(runner, &CommandSpec::new("ln").args([
+            "-sf".to_string(),
+            format!("/usr/share/zoneinfo/{}", plan.timezone),
+            "/etc/localtime".to_string(),
+        ]))?;
+        session.run(runner, &CommandSpec::new("hwclock").arg("--systohc"))?;
+
+        session.run(runner, &CommandSpec::new("systemctl").args(["enable", "NetworkManager"]))?;
+        Ok(())
+    }
+
+    fn configure_initramfs(
+        &self,
+        runner: &mut dyn CommandRunner,
+        session: &ChrootSession,
+        plan: &InstallPlan,
+    ) -> Result<()> {
+        if plan.tpm2.is_some() {
+            self.install_packages(runner, session, plan, &["tpm2-tss".to_string()])?;
+        }
+        let hooks = self.mkinitcpio_hooks(plan);
+        log::info!("mkinitcpio hooks: {}", hooks.join(" "));
+
+        let mut conf = format!("# Generated by lunitool\nHOOKS=({})\n", hooks.join(" "));
+        if let Some(encryption) = &plan.encryption {
+            // The image holds the keyfile, so it must only be readable by root
+            let files: Vec<&str> = encryption.keyfile.iter().chain(&encryption.header).map(String::as_str).collect();
+            if !files.is_empty() {
+                conf.push_str(&format!("FILES=({})\n", files.join(" ")));
+            }
+            if encryption.header.is_some() {
+                // sd-encrypt unlocks everything listed here; the header has no UUID for rd.luks.name
+                if let Some(entry) = fstab::root_crypttab_entry(runner, plan)? {
+                    let crypttab = format!("{}\n", entry.render());
+                    runner.run_checked(&write_file_command(plan, "/etc/crypttab.initramfs", "0600", &crypttab))?;
+                }
+            }
+        }
+        runner.run_checked(&write_file_command(plan, "/etc/mkinitcpio.conf.d/lunitool.conf", "0644", &conf))?;
+        session.run(runner, &CommandSpec::new("mkinitcpio").arg("-P"))?;
+        Ok(())
+    }
+
+    fn kernel_cmdline(&self, runner: &mut dyn CommandRunner, plan: &InstallPlan) -> Result<String> {
+        let mut params = Vec::new();
+        if let Some(encryption) = plan.encryption.as_ref().filter(|enc| enc.header.is_none()) {
+            let luks_uuid = device_uuid(runner, &plan.root_partition())?;
+            params.push(match self.options.hooks {
+                InitramfsHooks::Encrypt => format!("cryptdevice=UUID={}:{}", luks_uuid, encryption.mapper_name),
+                InitramfsHooks::SdEncrypt => format!("rd.luks.name={}={}", luks_uuid, encryption.mapper_name),
+            });
+            if let Some(keyfile) = &encryption.keyfile {
+                params.push(match self.options.hooks {
+                    InitramfsHooks::Encrypt => format!("cryptkey=rootfs:{}", keyfile),
+                    InitramfsHooks::SdEncrypt => format!("rd.luks.key={}={}", luks_uuid, keyfile),
+                });
+            }
+            if plan.tpm2.is_some() {
+                params.push(format!("rd.luks.options={}={}", luks_uuid, tpm2::CRYPTTAB_OPTION));
+            }
+        }
+        params.extend(plan.root_params());
+        params.push("rw".to_string());
+        Ok(params.join(" "))
+    }
+
+    fn install_bootloader(
+        &self,
+        runner: &mut dyn CommandRunner,
+        session: &ChrootSession,
+        plan: &InstallPlan,
+    ) -> Result<()> {
+        let cmdline = self.kernel_cmdline(runner, plan)?;
+        log::info!("Kernel command line: {}", cmdline);
+
+        match plan.bootloader {
+            Bootloader::Grub | Bootloader::GrubCryptodisk => self.install_grub(runner, session, plan, &cmdline),
+            Bootloader::SystemdBoot => self.install_systemd_boot(runner, session, plan, &cmdline),
+        }
+    }
+
+    fn create_user(&self, runner: &mut dyn CommandRunner, session: &ChrootSession, plan: &InstallPlan) -> Result<()> {
+        let Some(username) = &plan.username else {
+            log::info!("No user account configured, skipping user creation.");
+            return Ok(());
+        };
+        log::info!("Creating user {}", username);
+
+        session.run(runner, &CommandSpec::new("useradd").args(["-m", "-s", "/bin/bash", "-G", "wheel", username]))?;
+        if let Some(password) = &plan.user_password {
+            // The password only ever travels through stdin
+            session.run(runner, &CommandSpec::new("chpasswd").stdin(format!("{}:{}\n", username, password.expose())))?;
+        }
+        // Arch ships sudo without any group enabled
+        runner.run_checked(&write_file_command(
+            plan,
+            "/etc/sudoers.d/10-wheel",
+            "0440",
+            "%wheel ALL=(ALL:ALL) ALL\n",
+        ))?;
+        Ok(())
+    }
+
+    fn live_only_packages(&self) -> Vec<String> {
+        LIVE_ONLY_PACKAGES.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn adopt_live_root(
+        &self,
+        runner: &mut dyn CommandRunner,
+        session: &ChrootSession,
+        plan: &InstallPlan,
+        removals: &[String],
+    ) -> Result<()> {
+        // archiso keeps the kernel on the ISO instead of the root filesystem
+        if let Some(image_root) = plan.source.image_root() {
+            let kernel = format!("vmlinuz-{}", self.options.kernel);
+            runner.run_checked(
+                &CommandSpec::new("install")
+                    .args(["-D", "-m", "0644"])
+                    .arg(image_root.join("arch/boot/x86_64").join(&kernel).to_string_lossy())
+                    .arg(plan.target_path(&format!("/boot/{}", kernel))),
+            )?;
+        }
+
+        let listing = session.run(runner, &CommandSpec::new("pacman").arg("-Qq"))?;
+        let installed = filter_installed(&listing, runner.is_dry_run(), removals);
+        if !installed.is_empty() {
+            log::info!("Removing live-only packages: {}", installed.join(" "));
+            session.run(runner, &CommandSpec::new("pacman").args(["-Rdd", "--noconfirm"]).args(&installed))?;
+        }
+        session.run(runner, &CommandSpec::new("rm").arg("-f").args(ARCHISO_FILES))?;
+        // The preset removed above belongs to the kernel package; write the stock one
+        let preset = format!(
+            "ALL_kver=\"/boot/vmlinuz-{kernel}\"\nPRESETS=('default' 'fallback')\ndefault_image=\"/boot/initramfs-{kernel}.img\"\nfallback_image=\"/boot/initramfs-{kernel}-fallback.img\"\nfallback_options=\"-S autodetect\"\n",
+            kernel = self.options.kernel
+        );
+        runner.run_checked(&write_file_command(
+            plan,
+            &format!("/etc/mkinitcpio.d/{}.preset", self.options.kernel),
+            "0644",
+            &preset,
+        ))?;
+
+        session.run(runner, &CommandSpec::new("pacman-key").arg("--init"))?;
+        session.run(runner, &CommandSpec::new("pacman-key").args(["--populate", "archlinux"]))?;
+        Self::write_offline_conf(runner, plan)
+    }
+
+    fn finalize(&self, runner: &mut dyn CommandRunner, session: &ChrootSession, plan: &InstallPlan) -> Result<()> {
+        if plan.source.repository_dir().is_some() {
+            session.run(runner, &CommandSpec::new("rm").args(["-f", TARGET_OFFLINE_CONF]))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        app::InstallationConfig,
+        core::command::DryRunCommandRunner,
+        tools::installation::{backend::{command_summary, dry_run_session}, SecureBootMode, DEFAULT_TARGET_ROOT},
+    };
+
+    fn plan() -> InstallPlan {
+        let config = InstallationConfig {
+            target_disk: Some("/dev/nvme0n1".to_string()),
+            luks_password: Some("correct horse".into()),
+            username: Some("alice".to_string()),
+            ..Default::default()
+        };
+        InstallPlan::from_config(&config, Path::new(DEFAULT_TARGET_ROOT), true, DistroFamily::Arch).unwrap()
+    }
+
+    #[test]
+    fn installs_with_pacstrap_and_grub() {
+        let backend = ArchBackend::default();
+        let plan = plan();
+        let mut runner = DryRunCommandRunner::new();
+        let session = dry_run_session(&plan);
+        runner.respond_to("blkid -s UUID -o value /dev/nvme0n1p2", "7d1e-luks\n");
+        backend.validate_plan(&plan).unwrap();
+        backend.bootstrap(&mut runner, &plan).unwrap();
+        backend.configure_system(&mut runner, &session, &plan).unwrap();
+        backend.configure_initramfs(&mut runner, &session, &plan).unwrap();
+        backend.install_bootloader(&mut runner, &session, &plan).unwrap();
+        backend.create_user(&mut runner, &session, &plan).unwrap();
+
+        assert_eq!(
+            command_summary(&plan, runner.history()),
+            [
+                "pacstrap",
+                "write /etc/hostname",
+                "write /etc/hosts",
+                "write /etc/locale.gen",
+                "chroot locale-gen",
+                "write /etc/locale.conf",
+                "write /etc/vconsole.conf",
+                "chroot ln -sf",
+                "chroot hwclock --systohc",
+                "chroot systemctl enable",
+                "write /etc/mkinitcpio.conf.d/lunitool.conf",
+                "chroot mkinitcpio -P",
+                "blkid",
+                "chroot pacman -S",
+                "chroot sed -i",
+                "chroot sed -i",
+                "chroot grub-install --target=x86_64-efi",
+                "chroot grub-mkconfig -o",
+                "chroot useradd -m",
+                "write /etc/sudoers.d/10-wheel",
+            ]
+        );
+        let history = runner.rendered_history();
+        assert!(history[0].starts_with("pacstrap -K /mnt/lunitool base linux"));
+        let mkinitcpio = String::from_utf8(runner.history()[10].stdin.as_deref().cloned().unwrap()).unwrap();
+        assert!(mkinitcpio.contains(
+            "HOOKS=(base systemd autodetect microcode modconf kms keyboard sd-vconsole block sd-encrypt lvm2 filesystems fsck)"
+        ));
+        assert!(history[14].contains(
+            "GRUB_CMDLINE_LINUX=\"rd.luks.name=7d1e-luks=cryptroot root=/dev/mapper/vg-root resume=/dev/mapper/vg-swap rw\""
+        ));
+        // Grub reads /boot from the encrypted root
+        assert!(history[15].contains("$a GRUB_ENABLE_CRYPTODISK=y"));
+    }
+
+    #[test]
+    fn rejects_shim() {
+        let shim = InstallPlan {
+            secure_boot: SecureBootMode::Shim,
+            ..plan()
+        };
+        assert!(ArchBackend::default().validate_plan(&shim).is_err());
+    }
+}
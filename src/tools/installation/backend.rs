@@ -0,0 +1,186 @@
+use anyhow::{anyhow, Result};
+use std::collections::HashSet;
+
+use crate::core::command::{CommandOutput, CommandRunner, CommandSpec};
+
+use super::{arch::ArchBackend, chroot::ChrootSession, debian::DebianBackend, plan::InstallPlan, rhel::RhelBackend};
+
+/// Distribution families lunitool can install
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DistroFamily {
+    Debian,
+    Arch,
+    Rhel,
+}
+
+impl DistroFamily {
+    /// Map the package manager of the live system to the family it installs
+    pub fn from_package_manager(package_manager: &str) -> Option<Self> {
+        match package_manager {
+            "apt" => Some(DistroFamily::Debian),
+            "pacman" => Some(DistroFamily::Arch),
+            "dnf" => Some(DistroFamily::Rhel),
+            _ => None,
+        }
+    }
+
+    /// Package providing sshd
+    pub fn ssh_server_package(&self) -> &'static str {
+        match self {
+            DistroFamily::Arch => "openssh",
+            DistroFamily::Debian | DistroFamily::Rhel => "openssh-server",
+        }
+    }
+
+    /// systemd unit of sshd
+    pub fn ssh_service(&self) -> &'static str {
+        match self {
+            DistroFamily::Debian => "ssh",
+            DistroFamily::Arch | DistroFamily::Rhel => "sshd",
+        }
+    }
+}
+
+/// Distribution specific part of an installation
+///
+/// Every step receives the runner, so a backend can be exercised against the
+/// dry-run runner and its recorded command history. Steps working inside the
+/// target get the open `ChrootSession` and run their commands through it.
+pub trait DistroBackend: Send {
+    /// Human readable name for the protocol
+    fn name(&self) -> String;
+
+    fn family(&self) -> DistroFamily;
+
+    /// Reject plans the backend cannot install, before anything is written to disk
+    fn validate_plan(&self, _plan: &InstallPlan) -> Result<()> {
+        Ok(())
+    }
+
+    /// Create the base system below the target root
+    fn bootstrap(&self, runner: &mut dyn CommandRunner, plan: &InstallPlan) -> Result<()>;
+
+    /// Install additional packages into the target system
+    fn install_packages(
+        &self,
+        runner: &mut dyn CommandRunner,
+        session: &ChrootSession,
+        plan: &InstallPlan,
+        packages: &[String],
+    ) -> Result<()>;
+
+    /// Hostname, locale, keyboard layout and timezone
+    fn configure_system(
+        &self,
+        runner: &mut dyn CommandRunner,
+        session: &ChrootSession,
+        plan: &InstallPlan,
+    ) -> Result<()>;
+
+    /// Regenerate the initramfs images of all installed kernels
+    fn configure_initramfs(
+        &self,
+        runner: &mut dyn CommandRunner,
+        session: &ChrootSession,
+        plan: &InstallPlan,
+    ) -> Result<()>;
+
+    /// Kernel command line unlocking and mounting the root filesystem of the plan
+    fn kernel_cmdline(&self, runner: &mut dyn CommandRunner, plan: &InstallPlan) -> Result<String>;
+
+    /// Install and configure the bootloader
+    fn install_bootloader(
+        &self,
+        runner: &mut dyn CommandRunner,
+        session: &ChrootSession,
+        plan: &InstallPlan,
+    ) -> Result<()>;
+
+    /// Create the first user account, if the plan contains one
+    fn create_user(&self, runner: &mut dyn CommandRunner, session: &ChrootSession, plan: &InstallPlan) -> Result<()>;
+
+    /// Packages of this family's live media that do not belong on an installed system
+    fn live_only_packages(&self) -> Vec<String>;
+
+    /// Turn a root filesystem extracted from live media into the base system
+    ///
+    /// Replaces `bootstrap` for image based installations; `removals` lists the
+    /// live-only packages, some of which may not be installed.
+    fn adopt_live_root(
+        &self,
+        runner: &mut dyn CommandRunner,
+        session: &ChrootSession,
+        plan: &InstallPlan,
+        removals: &[String],
+    ) -> Result<()>;
+
+    /// Drop installation-only settings such as the offline repository configuration
+    fn finalize(&self, _runner: &mut dyn CommandRunner, _session: &ChrootSession, _plan: &InstallPlan) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Pick the backend matching the package manager of the live system
+pub fn select_backend(package_manager: &str) -> Result<Box<dyn DistroBackend>> {
+    match DistroFamily::from_package_manager(package_manager) {
+        Some(DistroFamily::Debian) => Ok(Box::new(DebianBackend::from_host())),
+        Some(DistroFamily::Arch) => Ok(Box::new(ArchBackend::default())),
+        Some(DistroFamily::Rhel) => Ok(Box::new(RhelBackend::from_host())),
+        None => Err(anyhow!("Unsupported package manager: {}", package_manager)),
+    }
+}
+
+/// Command writing `contents` to `path` inside the target system
+///
+/// The data goes through stdin, so the dry-run runner records the write
+/// instead of touching the filesystem.
+pub fn write_file_command(plan: &InstallPlan, path: &str, mode: &str, contents: &str) -> CommandSpec {
+    CommandSpec::new("install")
+        .args(["-D", "-m", mode, "/dev/stdin"])
+        .arg(plan.target_path(path))
+        .stdin(contents)
+}
+
+/// Keep those `candidates` that `listing`, one package name per line, shows as installed
+///
+/// In dry-run mode nothing is known about the target, so all candidates are kept.
+pub fn filter_installed(listing: &CommandOutput, dry_run: bool, candidates: &[String]) -> Vec<String> {
+    if dry_run {
+        return candidates.to_vec();
+    }
+    let installed: HashSet<&str> = listing.stdout.lines().map(str::trim).collect();
+    candidates
+        .iter()
+        .filter(|package| installed.contains(package.as_str()))
+        .cloned()
+        .collect()
+}
+
+/// Recorded commands of the dry-run runner in short form, for tests of backend command sequences
+///
+/// Commands inside the target show as `chroot <program> <first argument>`,
+/// file writes as `write <path inside the target>` and anything else by its program.
+#[cfg(test)]
+pub(crate) fn command_summary(plan: &InstallPlan, history: &[CommandSpec]) -> Vec<String> {
+    let target = plan.target();
+    history
+        .iter()
+        .map(|spec| match (spec.program.as_str(), spec.args.as_slice()) {
+            ("chroot", [_, program, first, ..]) => format!("chroot {} {}", program, first),
+            ("chroot", [_, program]) => format!("chroot {}", program),
+            ("install", [.., stdin, path]) if stdin == "/dev/stdin" => {
+                format!("write {}", path.strip_prefix(&target).unwrap_or(path))
+            }
+            (program, _) => program.to_string(),
+        })
+        .collect()
+}
+
+/// Session on the target of `plan` for backend tests
+///
+/// Entered with a runner of its own, so the bind mounts stay out of the history under test.
+#[cfg(test)]
+pub(crate) fn dry_run_session(plan: &InstallPlan) -> ChrootSession {
+    let mut runner = crate::core::command::DryRunCommandRunner::new();
+    ChrootSession::enter(&mut runner, &plan.target_root, Default::default()).unwrap()
+}
@@ -0,0 +1,198 @@
+use anyhow::{anyhow, Result};
+use std::fs;
+
+use crate::core::command::{CommandRunner, CommandSpec};
+
+use super::{
+    partition_path,
+    plan::{Bootloader, EncryptionPlan, InstallPlan},
+};
+
+/// Detached header of the root container, on the /boot partition of the USB key
+pub const HEADER_FILE: &str = "/boot/luks/root.header";
+/// Keyfile unlocking the root container while the USB key is plugged in
+pub const KEYFILE: &str = "/boot/luks/root.keyfile";
+/// Host directory the header lives in until /boot on the USB key is mounted
+const HEADER_STAGING_DIR: &str = "/run/lunitool";
+/// Size of the ESP and the /boot partition on the USB key
+const USB_ESP_SIZE: &str = "+512M";
+const USB_BOOT_SIZE: &str = "+1024M";
+
+/// /boot, ESP, detached root header and keyfile on a removable USB key
+///
+/// The internal disk holds nothing but encrypted data: no partition table
+/// and no LUKS header. Without the USB key (or a backup of it) the system
+/// neither boots nor reveals that the disk is encrypted.
+#[derive(Debug, Clone)]
+pub struct BootUsbPlan {
+    /// Whole USB device, e.g. `/dev/sdb`
+    pub device: String,
+    /// Second USB device receiving a copy of the key after installation
+    pub backup_device: Option<String>,
+}
+
+/// Reject plans the USB key layout cannot boot
+pub fn validate(plan: &InstallPlan) -> Result<()> {
+    let Some(usb) = &plan.boot_usb else {
+        return Ok(());
+    };
+    if plan.encryption.is_none() {
+        return Err(anyhow!("A boot USB key requires an encrypted root"));
+    }
+    if !plan.efi {
+        return Err(anyhow!("A boot USB key requires a system booted in UEFI mode"));
+    }
+    // systemd-boot keeps the kernels on the FAT ESP, which cannot protect the keyfile
+    if plan.bootloader != Bootloader::Grub {
+        return Err(anyhow!("A boot USB key is only supported with GRUB and a plain /boot on the key"));
+    }
+    // systemd-cryptenroll cannot write tokens into a detached header
+    if plan.tpm2.is_some() {
+        return Err(anyhow!("TPM2 unlocking is not available with a detached LUKS header"));
+    }
+    if usb.device == plan.target_disk {
+        return Err(anyhow!("The boot USB key must not be the installation target"));
+    }
+    if let Some(backup) = &usb.backup_device {
+        if backup == &usb.device || backup == &plan.target_disk {
+            return Err(anyhow!("The USB backup device {} is already used by the installation", backup));
+        }
+    }
+    Ok(())
+}
+
+/// ESP on the USB key
+pub fn esp_partition(usb: &BootUsbPlan) -> String {
+    partition_path(&usb.device, 1)
+}
+
+/// Plain /boot on the USB key
+pub fn boot_partition(usb: &BootUsbPlan) -> String {
+    partition_path(&usb.device, 2)
+}
+
+/// Host path the header of `encryption` is created at, if it is detached
+///
+/// cryptsetup needs the header before /boot exists, so it is staged in /run
+/// and only copied onto the USB key by `store_header`.
+pub fn staged_header(encryption: &EncryptionPlan) -> Option<String> {
+    encryption
+        .header
+        .as_ref()
+        .map(|_| format!("{}/{}.header", HEADER_STAGING_DIR, encryption.mapper_name))
+}
+
+/// `--header` arguments for cryptsetup calls on the container, empty if it has its header on disk
+pub fn header_args(encryption: &EncryptionPlan) -> Vec<String> {
+    staged_header(encryption)
+        .map(|header| vec!["--header".to_string(), header])
+        .unwrap_or_default()
+}
+
+/// Wipe the internal disk and lay out ESP and /boot on the USB key
+pub fn partition(runner: &mut dyn CommandRunner, plan: &InstallPlan, usb: &BootUsbPlan) -> Result<()> {
+    log::info!("Wiping {} and partitioning boot USB key {}", plan.target_disk, usb.device);
+    runner.run_checked(&CommandSpec::new("wipefs").args(["-a", &plan.target_disk]))?;
+    runner.run_checked(&CommandSpec::new("sgdisk").args(["--zap-all", &plan.target_disk]))?;
+
+    runner.run_checked(&CommandSpec::new("sgdisk").args(["--zap-all", &usb.device]))?;
+    runner.run_checked(&CommandSpec::new("sgdisk").args([
+        &format!("--new=1:0:{}", USB_ESP_SIZE),
+        "--typecode=1:ef00",
+        "--change-name=1:EFI",
+        &format!("--new=2:0:{}", USB_BOOT_SIZE),
+        "--typecode=2:8300",
+        "--change-name=2:boot",
+        &usb.device,
+    ]))?;
+    runner.run_checked(&CommandSpec::new("partprobe").arg(&usb.device))?;
+    runner.run_checked(&CommandSpec::new("install").args(["-d", "-m", "0700", HEADER_STAGING_DIR]))?;
+    Ok(())
+}
+
+/// Copy the staged header of the root container onto the mounted USB key
+///
+/// Runs after the last keyslot change of the installation, so the copy is final.
+pub fn store_header(runner: &mut dyn CommandRunner, plan: &InstallPlan) -> Result<()> {
+    let Some(encryption) = plan.encryption.as_ref().filter(|_| plan.boot_usb.is_some()) else {
+        return Ok(());
+    };
+    let (Some(header), Some(staged)) = (&encryption.header, staged_header(encryption)) else {
+        return Ok(());
+    };
+    log::info!("Storing the LUKS header of {} at {} on the USB key", plan.root_partition(), header);
+    runner.run_checked(&CommandSpec::new("install").args(["-D", "-m", "0400", &staged, &plan.target_path(header)]))?;
+    Ok(())
+}
+
+/// Stable name of a whole disk below /dev/disk/by-id, for devices without a UUID
+///
+/// Falls back to the kernel name, which may change between boots, if no link is found.
+pub fn stable_disk_path(runner: &dyn CommandRunner, device: &str) -> String {
+    if runner.is_dry_run() {
+        return device.to_string();
+    }
+    let Ok(target) = fs::canonicalize(device) else {
+        return device.to_string();
+    };
+    let mut links: Vec<String> = fs::read_dir("/dev/disk/by-id")
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| fs::canonicalize(entry.path()).ok().as_deref() == Some(target.as_path()))
+                .map(|entry| entry.path().to_string_lossy().into_owned())
+                .collect()
+        })
+        .unwrap_or_default();
+    // Vendor and serial based names read better than WWNs
+    links.sort_by_key(|link| (link.contains("/wwn-"), link.clone()));
+    match links.into_iter().next() {
+        Some(link) => link,
+        None => {
+            log::warn!("No /dev/disk/by-id link for {}, using the kernel name", device);
+            device.to_string()
+        }
+    }
+}
+
+/// Clone the finished USB key onto the backup device and compare both
+///
+/// Expects all filesystems of the key to be unmounted.
+pub fn create_backup(runner: &mut dyn CommandRunner, usb: &BootUsbPlan) -> Result<()> {
+    let Some(backup) = &usb.backup_device else {
+        log::info!(
+            "No backup of the boot USB key configured; without it a lost key means a lost system. \
+             Clone it later with `dd if={} of=<backup> bs=4M conv=fsync`",
+            usb.device
+        );
+        return Ok(());
+    };
+    let size = runner.run_checked(&CommandSpec::new("blockdev").args(["--getsize64", &usb.device]))?;
+    let backup_size = runner.run_checked(&CommandSpec::new("blockdev").args(["--getsize64", backup]))?;
+    let (size, backup_size) = (size.stdout.trim().parse::<u64>().ok(), backup_size.stdout.trim().parse::<u64>().ok());
+    if let (Some(size), Some(backup_size)) = (size, backup_size) {
+        if backup_size < size {
+            return Err(anyhow!("Backup device {} is smaller than the boot USB key {}", backup, usb.device));
+        }
+    }
+
+    log::info!("Cloning boot USB key {} to {}", usb.device, backup);
+    runner.run_checked(&CommandSpec::new("dd").args([
+        &format!("if={}", usb.device),
+        &format!("of={}", backup),
+        "bs=4M",
+        "conv=fsync",
+        "status=none",
+    ]))?;
+
+    // The backup may be larger; only the cloned range has to match
+    let mut compare = CommandSpec::new("cmp");
+    if let Some(size) = size {
+        compare = compare.args(["-n".to_string(), size.to_string()]);
+    }
+    runner
+        .run_checked(&compare.args([&usb.device, backup]))
+        .map_err(|e| anyhow!("Backup {} differs from the boot USB key: {}", backup, e))?;
+    log::info!("Backup of the boot USB key verified; store it apart from the original");
+    Ok(())
+}
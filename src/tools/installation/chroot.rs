@@ -0,0 +1,366 @@
+use anyhow::{anyhow, Result};
+use nix::{
+    errno::Errno,
+    mount::{umount2, MntFlags},
+};
+use std::path::{Path, PathBuf};
+
+use crate::core::command::{CommandOutput, CommandRunner, CommandSpec};
+
+/// API filesystems of the live system that are bound into the target
+const API_FILESYSTEMS: &[&str] = &["/dev", "/dev/pts", "/proc", "/sys", "/run"];
+
+/// Optional extras of a chroot session
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ChrootOptions {
+    /// Mount efivarfs, needed by grub-install/bootctl to write boot entries
+    pub efivarfs: bool,
+    /// Bind the live system's resolv.conf, needed for downloads from inside the target
+    pub resolv_conf: bool,
+}
+
+/// Unmounts a mount point directly, detaching it lazily if the flag is set
+type Unmount = fn(&str, MntFlags) -> nix::Result<()>;
+
+/// A mount that could not be removed because it is still in use
+#[derive(Debug, Clone)]
+pub struct BusyMount {
+    pub path: String,
+    /// Output of `fuser -vm` describing the processes holding the mount
+    pub holders: String,
+}
+
+/// Mounts the API filesystems into a target root and runs commands inside it
+///
+/// Every mount is recorded as soon as it succeeds. `close` removes them through
+/// the runner and reports busy mounts; if the session is dropped without being
+/// closed (early return, panic), the mounts are detached directly.
+#[derive(Debug)]
+pub struct ChrootSession {
+    root: PathBuf,
+    /// Mount points on the live system, in mount order
+    mounts: Vec<String>,
+    dry_run: bool,
+    /// Used by `Drop`, which has no runner to go through
+    unmount: Unmount,
+}
+
+impl ChrootSession {
+    /// Bind the API filesystems (and the requested extras) into `root`
+    pub fn enter(runner: &mut dyn CommandRunner, root: &Path, options: ChrootOptions) -> Result<Self> {
+        log::info!("Entering chroot session at {}", root.display());
+        let mut session = Self {
+            root: root.to_path_buf(),
+            mounts: Vec::new(),
+            dry_run: runner.is_dry_run(),
+            unmount: if runner.is_dry_run() { dry_run_unmount } else { umount2 },
+        };
+
+        // On error the partly set up session is dropped and detaches what was mounted.
+        for dir in API_FILESYSTEMS {
+            session.bind(runner, Path::new(dir), dir, false)?;
+        }
+        if options.efivarfs {
+            let efivars = session.host_path("/sys/firmware/efi/efivars");
+            runner.run_checked(&CommandSpec::new("mount").args(["-t", "efivarfs", "efivarfs", &efivars]))?;
+            session.mounts.push(efivars);
+        }
+        if options.resolv_conf {
+            session.bind_resolv_conf(runner)?;
+        }
+        Ok(session)
+    }
+
+    /// Root directory of the session
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Path of `path` (absolute inside the target) as seen from the live system
+    pub fn host_path(&self, path: &str) -> String {
+        self.root.join(path.trim_start_matches('/')).to_string_lossy().into_owned()
+    }
+
+    /// Command running `program` inside the target
+    fn command(&self, program: &str) -> CommandSpec {
+        CommandSpec::new("chroot").arg(self.root.to_string_lossy()).arg(program)
+    }
+
+    /// Run `spec` inside the target and fail on a non-zero exit code
+    pub fn run(&self, runner: &mut dyn CommandRunner, spec: &CommandSpec) -> Result<CommandOutput> {
+        let chrooted = self.command(&spec.program).args(&spec.args);
+        let chrooted = CommandSpec {
+            stdin: spec.stdin.clone(),
+            env: spec.env.clone(),
+            ..chrooted
+        };
+        runner.run_checked(&chrooted)
+    }
+
+    /// Bind mount `source` of the live system to `target` (absolute inside the chroot)
+    pub fn bind(&mut self, runner: &mut dyn CommandRunner, source: &Path, target: &str, read_only: bool) -> Result<()> {
+        let mount_point = self.host_path(target);
+        runner.run_checked(&CommandSpec::new("mkdir").args(["-p", &mount_point]))?;
+        let mut mount = CommandSpec::new("mount").arg("-B");
+        if read_only {
+            mount = mount.args(["-o", "ro"]);
+        }
+        runner.run_checked(&mount.arg(source.to_string_lossy()).arg(&mount_point))?;
+        self.mounts.push(mount_point);
+        Ok(())
+    }
+
+    /// Make the live system's name resolution available inside the target
+    ///
+    /// A symlinked resolv.conf (systemd-resolved) already resolves through the
+    /// bound /run; binding over it would follow the link out of the target.
+    fn bind_resolv_conf(&mut self, runner: &mut dyn CommandRunner) -> Result<()> {
+        let resolv_conf = self.host_path("/etc/resolv.conf");
+        if !self.dry_run && Path::new(&resolv_conf).is_symlink() {
+            log::debug!("{} is a symlink, relying on the bound /run", resolv_conf);
+            return Ok(());
+        }
+        runner.run_checked(&CommandSpec::new("touch").arg(&resolv_conf))?;
+        runner.run_checked(&CommandSpec::new("mount").args(["-B", "/etc/resolv.conf", &resolv_conf]))?;
+        self.mounts.push(resolv_conf);
+        Ok(())
+    }
+
+    /// Unmount everything the session mounted, in reverse order
+    ///
+    /// Mounts that are still in use are detached lazily and reported in the error.
+    pub fn close(mut self, runner: &mut dyn CommandRunner) -> Result<()> {
+        log::info!("Leaving chroot session at {}", self.root.display());
+        let mut busy = Vec::new();
+
+        while let Some(mount_point) = self.mounts.pop() {
+            if runner.run_checked(&CommandSpec::new("umount").arg(&mount_point)).is_ok() {
+                continue;
+            }
+            let holders = runner
+                .run(&CommandSpec::new("fuser").args(["-vm", &mount_point]))
+                .map(|output| format!("{}{}", output.stdout, output.stderr).trim().to_string())
+                .unwrap_or_default();
+            log::warn!("Mount {} is busy: {}", mount_point, holders);
+            // Detach it anyway so the target can be unmounted; it disappears once released
+            let _ = runner.run(&CommandSpec::new("umount").args(["-l", &mount_point]));
+            busy.push(BusyMount {
+                path: mount_point,
+                holders,
+            });
+        }
+
+        if busy.is_empty() {
+            return Ok(());
+        }
+        let details: Vec<String> = busy
+            .iter()
+            .map(|mount| {
+                if mount.holders.is_empty() {
+                    mount.path.clone()
+                } else {
+                    format!("{} ({})", mount.path, mount.holders.replace('\n', "; "))
+                }
+            })
+            .collect();
+        Err(anyhow!("Busy mounts were detached lazily: {}", details.join(", ")))
+    }
+}
+
+impl Drop for ChrootSession {
+    fn drop(&mut self) {
+        if self.mounts.is_empty() {
+            return;
+        }
+        log::warn!(
+            "Chroot session at {} dropped without close, detaching {} mount(s)",
+            self.root.display(),
+            self.mounts.len()
+        );
+        while let Some(mount_point) = self.mounts.pop() {
+            match (self.unmount)(&mount_point, MntFlags::empty()) {
+                Ok(()) => {}
+                Err(Errno::EBUSY) => {
+                    log::error!("Mount {} is busy, detaching it lazily", mount_point);
+                    if let Err(e) = (self.unmount)(&mount_point, MntFlags::MNT_DETACH) {
+                        log::error!("Failed to detach {}: {}", mount_point, e);
+                    }
+                }
+                Err(e) => log::error!("Failed to unmount {}: {}", mount_point, e),
+            }
+        }
+    }
+}
+
+fn dry_run_unmount(mount_point: &str, flags: MntFlags) -> nix::Result<()> {
+    log::debug!("[dry-run] umount{} {}", if flags.is_empty() { "" } else { " -l" }, mount_point);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::command::DryRunCommandRunner;
+    use std::{cell::RefCell, panic};
+
+    const ROOT: &str = "/mnt/lunitool";
+
+    /// Dry-run runner whose plain `umount` of `busy` fails like a mount still in use
+    struct BusyRunner {
+        inner: DryRunCommandRunner,
+        busy: String,
+    }
+
+    impl CommandRunner for BusyRunner {
+        fn run(&mut self, spec: &CommandSpec) -> Result<CommandOutput> {
+            let output = self.inner.run(spec)?;
+            if spec.program == "umount" && spec.args == [self.busy.as_str()] {
+                return Ok(CommandOutput {
+                    status: Some(32),
+                    stderr: format!("umount: {}: target is busy.\n", self.busy),
+                    ..output
+                });
+            }
+            if spec.program == "fuser" {
+                return Ok(CommandOutput {
+                    stdout: "root  1234 ..c.. gpg-agent\n".to_string(),
+                    ..output
+                });
+            }
+            Ok(output)
+        }
+
+        fn is_dry_run(&self) -> bool {
+            true
+        }
+    }
+
+    fn enter(runner: &mut dyn CommandRunner) -> ChrootSession {
+        let options = ChrootOptions {
+            efivarfs: true,
+            resolv_conf: true,
+        };
+        ChrootSession::enter(runner, Path::new(ROOT), options).unwrap()
+    }
+
+    #[test]
+    fn binds_api_filesystems_before_the_extras() {
+        let mut runner = DryRunCommandRunner::new();
+        let session = enter(&mut runner);
+
+        let mut expected = Vec::new();
+        for dir in API_FILESYSTEMS {
+            expected.push(format!("mkdir -p {}{}", ROOT, dir));
+            expected.push(format!("mount -B {} {}{}", dir, ROOT, dir));
+        }
+        expected.push(format!("mount -t efivarfs efivarfs {}/sys/firmware/efi/efivars", ROOT));
+        expected.push(format!("touch {}/etc/resolv.conf", ROOT));
+        expected.push(format!("mount -B /etc/resolv.conf {}/etc/resolv.conf", ROOT));
+        assert_eq!(runner.rendered_history(), expected);
+
+        session.run(&mut runner, &CommandSpec::new("chpasswd").stdin("root:secret\n")).unwrap();
+        let chpasswd = runner.history().last().unwrap();
+        assert_eq!(chpasswd.to_string(), format!("chroot {} chpasswd", ROOT));
+        assert_eq!(chpasswd.stdin.as_deref().map(Vec::as_slice), Some(&b"root:secret\n"[..]));
+        session.close(&mut runner).unwrap();
+    }
+
+    #[test]
+    fn close_unmounts_in_reverse_order() {
+        let mut runner = DryRunCommandRunner::new();
+        let session = enter(&mut runner);
+        let entered = runner.history().len();
+        session.close(&mut runner).unwrap();
+
+        assert_eq!(
+            runner.rendered_history()[entered..],
+            [
+                "umount /mnt/lunitool/etc/resolv.conf",
+                "umount /mnt/lunitool/sys/firmware/efi/efivars",
+                "umount /mnt/lunitool/run",
+                "umount /mnt/lunitool/sys",
+                "umount /mnt/lunitool/proc",
+                "umount /mnt/lunitool/dev/pts",
+                "umount /mnt/lunitool/dev",
+            ]
+        );
+    }
+
+    #[test]
+    fn close_detaches_and_reports_busy_mounts() {
+        let mut runner = BusyRunner {
+            inner: DryRunCommandRunner::new(),
+            busy: format!("{}/proc", ROOT),
+        };
+        let session = ChrootSession::enter(&mut runner, Path::new(ROOT), ChrootOptions::default()).unwrap();
+        let entered = runner.inner.history().len();
+        let error = session.close(&mut runner).unwrap_err().to_string();
+
+        assert_eq!(
+            runner.inner.rendered_history()[entered..],
+            [
+                "umount /mnt/lunitool/run",
+                "umount /mnt/lunitool/sys",
+                "umount /mnt/lunitool/proc",
+                "fuser -vm /mnt/lunitool/proc",
+                "umount -l /mnt/lunitool/proc",
+                "umount /mnt/lunitool/dev/pts",
+                "umount /mnt/lunitool/dev",
+            ]
+        );
+        assert_eq!(
+            error,
+            "Busy mounts were detached lazily: /mnt/lunitool/proc (root  1234 ..c.. gpg-agent)"
+        );
+    }
+
+    thread_local! {
+        static UNMOUNTED: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+    }
+
+    /// Records every unmount; /proc is busy until it is detached
+    fn recording_unmount(mount_point: &str, flags: MntFlags) -> nix::Result<()> {
+        let lazy = flags.contains(MntFlags::MNT_DETACH);
+        UNMOUNTED.with(|u| u.borrow_mut().push(format!("{}{}", if lazy { "-l " } else { "" }, mount_point)));
+        if mount_point.ends_with("/proc") && !lazy {
+            return Err(Errno::EBUSY);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn drop_after_a_panic_detaches_the_mounts() {
+        let mut runner = DryRunCommandRunner::new();
+        let mut session = ChrootSession::enter(&mut runner, Path::new(ROOT), ChrootOptions::default()).unwrap();
+        session.unmount = recording_unmount;
+
+        let result = panic::catch_unwind(panic::AssertUnwindSafe(move || {
+            let _session = session;
+            panic!("installation step failed");
+        }));
+
+        assert!(result.is_err());
+        assert_eq!(
+            UNMOUNTED.with(|u| u.take()),
+            [
+                "/mnt/lunitool/run",
+                "/mnt/lunitool/sys",
+                "/mnt/lunitool/proc",
+                "-l /mnt/lunitool/proc",
+                "/mnt/lunitool/dev/pts",
+                "/mnt/lunitool/dev",
+            ]
+        );
+    }
+
+    #[test]
+    fn closed_session_leaves_nothing_to_drop() {
+        let mut runner = DryRunCommandRunner::new();
+        let mut session = ChrootSession::enter(&mut runner, Path::new(ROOT), ChrootOptions::default()).unwrap();
+        session.unmount = recording_unmount;
+        let _ = UNMOUNTED.with(|u| u.take());
+        session.close(&mut runner).unwrap();
+
+        assert!(UNMOUNTED.with(|u| u.take()).is_empty());
+    }
+}
@@ -0,0 +1,594 @@
+use anyhow::Result;
+use std::path::Path;
+
+use crate::core::{
+    command::{CommandRunner, CommandSpec},
+    system_info::read_os_release,
+};
+
+use super::{
+    backend::{filter_installed, write_file_command, DistroBackend, DistroFamily},
+    chroot::ChrootSession,
+    plan::{Bootloader, InstallPlan, UkiBuilder},
+    systemd_boot::{self, BootEntry},
+    source::TARGET_REPO_DIR,
+};
+
+/// Packages every Debian based installation needs besides the kernel and bootloader
+const BASE_PACKAGES: &[&str] = &[
+    "apt-transport-https",
+    "bash-completion",
+    "ca-certificates",
+    "console-setup",
+    "cryptsetup",
+    "cryptsetup-initramfs",
+    "curl",
+    "initramfs-tools",
+    "locales",
+    "lvm2",
+    "nano",
+    "network-manager",
+    "openssh-server",
+    "sudo",
+    "util-linux",
+    "wget",
+    "zstd",
+];
+
+/// Packages Ubuntu pulls in by default that an installed system does not need
+const UBUNTU_EXCLUDED_PACKAGES: &[&str] = &["snapd", "cloud-init", "ubuntu-pro-client", "ubuntu-docs"];
+
+/// Packages of Debian and Ubuntu live media that an installed system does not need
+const LIVE_ONLY_PACKAGES: &[&str] = &[
+    "casper",
+    "live-boot",
+    "live-boot-initramfs-tools",
+    "live-config",
+    "live-config-systemd",
+    "live-tools",
+    "ubiquity",
+    "ubiquity-casper",
+    "ubuntu-desktop-bootstrap",
+    "calamares",
+    "calamares-settings-debian",
+];
+
+/// Debian derivative the backend installs
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DebianFlavor {
+    Debian,
+    Ubuntu,
+}
+
+/// Parameters of the debootstrap call
+#[derive(Debug, Clone)]
+pub struct DebootstrapOptions {
+    pub flavor: DebianFlavor,
+    /// Release codename such as `bookworm` or `noble`
+    pub release: String,
+    /// Archive components enabled for bootstrap and in sources.list
+    pub components: Vec<String>,
+    /// Packages added to the bootstrap (`--include`)
+    pub include: Vec<String>,
+    /// Packages removed from the bootstrap (`--exclude`)
+    pub exclude: Vec<String>,
+    /// Archive mirror URL
+    pub mirror: String,
+    /// Bootstrap with `--variant=minbase`
+    pub minbase: bool,
+    /// Debian architecture name
+    pub arch: String,
+}
+
+impl DebootstrapOptions {
+    /// Defaults for an Ubuntu release
+    pub fn ubuntu(release: &str) -> Self {
+        Self {
+            flavor: DebianFlavor::Ubuntu,
+            release: release.to_string(),
+            components: to_strings(&["main", "restricted", "universe", "multiverse"]),
+            include: to_strings(BASE_PACKAGES),
+            exclude: to_strings(UBUNTU_EXCLUDED_PACKAGES),
+            mirror: "http://archive.ubuntu.com/ubuntu".to_string(),
+            minbase: true,
+            arch: "amd64".to_string(),
+        }
+    }
+
+    /// Defaults for a Debian release
+    pub fn debian(release: &str) -> Self {
+        Self {
+            flavor: DebianFlavor::Debian,
+            release: release.to_string(),
+            components: to_strings(&["main", "contrib", "non-free", "non-free-firmware"]),
+            include: to_strings(BASE_PACKAGES),
+            exclude: Vec::new(),
+            mirror: "http://deb.debian.org/debian".to_string(),
+            minbase: true,
+            arch: "amd64".to_string(),
+        }
+    }
+
+    /// Derive the options from the release of the live system
+    pub fn from_host() -> Self {
+        let os_release = read_os_release();
+        let id = os_release.get("ID").map(String::as_str).unwrap_or("");
+        let codename = os_release
+            .get("UBUNTU_CODENAME")
+            .or_else(|| os_release.get("VERSION_CODENAME"))
+            .cloned();
+
+        let options = match (id, codename) {
+            ("debian", Some(codename)) => Self::debian(&codename),
+            ("debian", None) => Self::debian("bookworm"),
+            (_, Some(codename)) => Self::ubuntu(&codename),
+            (_, None) => Self::ubuntu("noble"),
+        };
+        log::info!(
+            "Debootstrap defaults from host: {:?} {} ({})",
+            options.flavor,
+            options.release,
+            options.mirror
+        );
+        options
+    }
+
+    /// The debootstrap invocation for the given target root and mirror
+    pub fn command(&self, target_root: &str, mirror: &str) -> CommandSpec {
+        let mut spec = CommandSpec::new("debootstrap");
+        if !self.include.is_empty() {
+            spec = spec.arg(format!("--include={}", self.include.join(",")));
+        }
+        if !self.exclude.is_empty() {
+            spec = spec.arg(format!("--exclude={}", self.exclude.join(",")));
+        }
+        if self.minbase {
+            spec = spec.arg("--variant=minbase");
+        }
+        spec.arg(format!("--components={}", self.components.join(",")))
+            .arg(format!("--arch={}", self.arch))
+            .args([&self.release, target_root, mirror])
+    }
+
+    /// Components the local repository actually provides for the release
+    ///
+    /// Installation media usually carry only a subset, and apt refuses
+    /// components without package indices.
+    fn local_components(&self, repository: &Path, dry_run: bool) -> Vec<String> {
+        if dry_run {
+            return self.components.clone();
+        }
+        let dists = repository.join("dists").join(&self.release);
+        self.components
+            .iter()
+            .filter(|component| dists.join(component).is_dir())
+            .cloned()
+            .collect()
+    }
+
+    /// Content of /etc/apt/sources.list while installing from the local repository
+    ///
+    /// The repository is a medium the operator brought along, so it is trusted without signature.
+    pub fn offline_sources_list(&self, components: &[String]) -> String {
+        format!(
+            "deb [trusted=yes] file://{} {} {}\n",
+            TARGET_REPO_DIR,
+            self.release,
+            components.join(" ")
+        )
+    }
+
+    /// Content of /etc/apt/sources.list for the installed system
+    pub fn sources_list(&self) -> String {
+        let components = self.components.join(" ");
+        match self.flavor {
+            DebianFlavor::Ubuntu => format!(
+                "deb {mirror} {release} {components}\n\
+                 deb {mirror} {release}-updates {components}\n\
+                 deb {mirror} {release}-security {components}\n",
+                mirror = self.mirror,
+                release = self.release,
+                components = components
+            ),
+            DebianFlavor::Debian => format!(
+                "deb {mirror} {release} {components}\n\
+                 deb {mirror} {release}-updates {components}\n\
+                 deb http://security.debian.org/debian-security {release}-security {components}\n",
+                mirror = self.mirror,
+                release = self.release,
+                components = components
+            ),
+        }
+    }
+}
+
+fn to_strings(items: &[&str]) -> Vec<String> {
+    items.iter().map(|s| s.to_string()).collect()
+}
+
+/// Installs Debian and Ubuntu with debootstrap
+#[derive(Debug, Clone)]
+pub struct DebianBackend {
+    pub options: DebootstrapOptions,
+}
+
+impl DebianBackend {
+    pub fn new(options: DebootstrapOptions) -> Self {
+        Self { options }
+    }
+
+    pub fn from_host() -> Self {
+        Self::new(DebootstrapOptions::from_host())
+    }
+
+    /// Kernel and bootloader packages for the plan
+    fn system_packages(&self, plan: &InstallPlan) -> Vec<String> {
+        let mut packages = match self.options.flavor {
+            DebianFlavor::Ubuntu => to_strings(&["linux-image-generic", "linux-headers-generic"]),
+            DebianFlavor::Debian => to_strings(&["linux-image-amd64", "linux-headers-amd64"]),
+        };
+        if plan.bootloader == Bootloader::SystemdBoot {
+            packages.extend(to_strings(&["systemd-boot", "efibootmgr"]));
+            match plan.uki {
+                Some(UkiBuilder::Ukify) => packages.push("systemd-ukify".to_string()),
+                Some(UkiBuilder::Objcopy) => packages.push("binutils".to_string()),
+                None => {}
+            }
+        } else if plan.efi {
+            packages.extend(to_strings(&["grub-efi-amd64", "grub-efi-amd64-signed", "shim-signed", "efibootmgr"]));
+            if plan.hybrid_boot {
+                // i386-pc modules without grub-pc, which would conflict with grub-efi-amd64
+                packages.push("grub-pc-bin".to_string());
+            }
+        } else {
+            packages.push("grub-pc".to_string());
+            if plan.hybrid_boot {
+                packages.push("grub-efi-amd64-bin".to_string());
+            }
+        }
+        if plan.tpm2.is_some() {
+            packages.extend(to_strings(&["dracut", "systemd-cryptsetup", "tpm2-tools"]));
+        }
+        packages
+    }
+
+    fn apt_get() -> CommandSpec {
+        CommandSpec::new("apt-get").env("DEBIAN_FRONTEND", "noninteractive")
+    }
+
+    /// Point apt at the local repository when installing offline, else at the mirror
+    fn write_sources(&self, runner: &mut dyn CommandRunner, plan: &InstallPlan) -> Result<()> {
+        let components = plan
+            .source
+            .repository_dir()
+            .map(|repository| self.options.local_components(&repository, runner.is_dry_run()))
+            .unwrap_or_default();
+        let sources = if components.is_empty() {
+            if plan.source.is_offline() {
+                log::warn!("Installation source provides no apt repository, keeping the network mirrors.");
+            }
+            self.options.sources_list()
+        } else {
+            self.options.offline_sources_list(&components)
+        };
+        runner.run_checked(&write_file_command(plan, "/etc/apt/sources.list", "0644", &sources))?;
+        Ok(())
+    }
+}
+
+impl DistroBackend for DebianBackend {
+    fn name(&self) -> String {
+        format!("{:?} {} (debootstrap)", self.options.flavor, self.options.release)
+    }
+
+    fn family(&self) -> DistroFamily {
+        DistroFamily::Debian
+    }
+
+    fn bootstrap(&self, runner: &mut dyn CommandRunner, plan: &InstallPlan) -> Result<()> {
+        log::info!("Bootstrapping {} into {}", self.name(), plan.target());
+
+        let command = match plan.source.repository_dir() {
+            Some(repository) => {
+                let mut options = self.options.clone();
+                options.components = options.local_components(&repository, runner.is_dry_run());
+                options
+                    .command(&plan.target(), &format!("file://{}", repository.display()))
+                    .arg("--no-check-gpg")
+            }
+            None => self.options.command(&plan.target(), &self.options.mirror),
+        };
+        runner.run_checked(&command)?;
+        self.write_sources(runner, plan)?;
+        // dpkg does not need to fsync every file on a freshly created system
+        runner.run_checked(&write_file_command(
+            plan,
+            "/etc/dpkg/dpkg.cfg.d/unsafe-io",
+            "0644",
+            "force-unsafe-io\n",
+        ))?;
+        Ok(())
+    }
+
+    fn install_packages(
+        &self,
+        runner: &mut dyn CommandRunner,
+        session: &ChrootSession,
+        _plan: &InstallPlan,
+        packages: &[String],
+    ) -> Result<()> {
+        if packages.is_empty() {
+            return Ok(());
+        }
+        log::info!("Installing packages into target: {}", packages.join(" "));
+
+        session.run(runner, &Self::apt_get().arg("update"))?;
+        session.run(
+            runner,
+            &Self::apt_get()
+                .args(["install", "-y", "--no-install-recommends"])
+                .args(packages),
+        )?;
+        Ok(())
+    }
+
+    fn configure_system(
+        &self,
+        runner: &mut dyn CommandRunner,
+        session: &ChrootSession,
+        plan: &InstallPlan,
+    ) -> Result<()> {
+        log::info!("Configuring target system {}", plan.hostname);
+
+        let mut packages = self.system_packages(plan);
+        packages.extend(plan.extra_packages.iter().cloned());
+        self.install_packages(runner, session, plan, &packages)?;
+
+        // Hostname
+        runner.run_checked(&write_file_command(plan, "/etc/hostname", "0644", &format!("{}\n", plan.hostname)))?;
+        runner.run_checked(&write_file_command(
+            plan,
+            "/etc/hosts",
+            "0644",
+            &format!("127.0.0.1 localhost\n127.0.1.1 {}\n", plan.hostname),
+        ))?;
+
+        // Locale; Debian's locale-gen only reads /etc/locale.gen
+        match self.options.flavor {
+            DebianFlavor::Ubuntu => {
+                session.run(runner, &CommandSpec::new("locale-gen").args([&plan.locale, "en_US.UTF-8"]))?;
+            }
+            DebianFlavor::Debian => {
+                runner.run_checked(&write_file_command(plan, "/etc/locale.gen", "0644", &plan.locale_gen()))?;
+                session.run(runner, &CommandSpec::new("locale-gen"))?;
+            }
+        }
+        session.run(runner, &CommandSpec::new("update-locale").args([
+            format!("LANG={}", plan.locale),
+            format!("LC_CTYPE={}", plan.locale),
+        ]))?;
+
+        // Keyboard layout
+        let keyboard = format!(
+            "XKBMODEL=\"pc105\"\nXKBLAYOUT=\"{}\"\nXKBVARIANT=\"\"\nXKBOPTIONS=\"\"\n",
+            plan.keyboard_layout
+        );
+        runner.run_checked(&write_file_command(plan, "/etc/default/keyboard", "0644", &keyboard))?;
+
+        // Timezone
+        session.run(runner, &CommandSpec::new("ln").args([
+            "-sf".to_string(),
+            format!("/usr/share/zoneinfo/{}", plan.timezone),
+            "/etc/localtime".to_string(),
+        ]))?;
+        runner.run_checked(&write_file_command(plan, "/etc/timezone", "0644", &format!("{}\n", plan.timezone)))?;
+
+        Ok(())
+    }
+
+    fn configure_initramfs(
+        &self,
+        runner: &mut dyn CommandRunner,
+        session: &ChrootSession,
+        plan: &InstallPlan,
+    ) -> Result<()> {
+        // initramfs-tools cannot unlock through the TPM; dracut replaces it
+        if plan.tpm2.is_some() {
+            return super::configure_dracut(runner, session, plan);
+        }
+        if let Some(keyfile) = plan.encryption.as_ref().and_then(|enc| enc.keyfile.as_ref()) {
+            let keyfile_dir = keyfile.rsplit_once('/').map(|(dir, _)| dir).unwrap_or("");
+            runner.run_checked(&write_file_command(
+                plan,
+                "/etc/cryptsetup-initramfs/conf-hook",
+                "0644",
+                &format!("KEYFILE_PATTERN=\"{}/*.keyfile\"\n", keyfile_dir),
+            ))?;
+            // The image holds the keyfile, so it must only be readable by root
+            runner.run_checked(&write_file_command(
+                plan,
+                "/etc/initramfs-tools/conf.d/lunitool-umask",
+                "0644",
+                "UMASK=0077\n",
+            ))?;
+        }
+        session.run(runner, &CommandSpec::new("update-initramfs").args(["-u", "-k", "all"]))?;
+        Ok(())
+    }
+
+    fn kernel_cmdline(&self, _runner: &mut dyn CommandRunner, plan: &InstallPlan) -> Result<String> {
+        // initramfs-tools unlocks the root from /etc/crypttab, no parameters needed for it
+        let mut params = plan.root_params();
+        params.extend(["ro".to_string(), "quiet".to_string()]);
+        Ok(params.join(" "))
+    }
+
+    fn install_bootloader(
+        &self,
+        runner: &mut dyn CommandRunner,
+        session: &ChrootSession,
+        plan: &InstallPlan,
+    ) -> Result<()> {
+        if plan.bootloader != Bootloader::SystemdBoot {
+            return super::install_bootloader(runner, session, plan);
+        }
+        let cmdline = self.kernel_cmdline(runner, plan)?;
+        log::info!("Kernel command line: {}", cmdline);
+
+        // The ESP is /boot, so the kernel packages put their files right onto it
+        let title = match self.options.flavor {
+            DebianFlavor::Ubuntu => "Ubuntu",
+            DebianFlavor::Debian => "Debian GNU/Linux",
+        };
+        let entries: Vec<BootEntry> = systemd_boot::installed_kernels(runner, plan)?
+            .into_iter()
+            .rev()
+            .map(|version| BootEntry {
+                id: format!("{}-{}", plan.bootloader_id, version),
+                title: format!("{} ({})", title, version),
+                linux: format!("/vmlinuz-{}", version),
+                initrd: vec![format!("/initrd.img-{}", version)],
+            })
+            .collect();
+        systemd_boot::install_systemd_boot(runner, session, plan, &cmdline, &entries)
+    }
+
+    fn create_user(&self, runner: &mut dyn CommandRunner, session: &ChrootSession, plan: &InstallPlan) -> Result<()> {
+        let Some(username) = &plan.username else {
+            log::info!("No user account configured, skipping user creation.");
+            return Ok(());
+        };
+        log::info!("Creating user {}", username);
+
+        session.run(runner, &CommandSpec::new("useradd").args(["-m", "-s", "/bin/bash", "-G", "sudo", username]))?;
+        if let Some(password) = &plan.user_password {
+            // The password only ever travels through stdin
+            session.run(runner, &CommandSpec::new("chpasswd").stdin(format!("{}:{}\n", username, password.expose())))?;
+        }
+        Ok(())
+    }
+
+    fn live_only_packages(&self) -> Vec<String> {
+        to_strings(LIVE_ONLY_PACKAGES)
+    }
+
+    fn adopt_live_root(
+        &self,
+        runner: &mut dyn CommandRunner,
+        session: &ChrootSession,
+        plan: &InstallPlan,
+        removals: &[String],
+    ) -> Result<()> {
+        self.write_sources(runner, plan)?;
+
+        let listing = session.run(runner, &CommandSpec::new("dpkg-query").args(["-W", "-f", "${Package}\\n"]))?;
+        let installed = filter_installed(&listing, runner.is_dry_run(), removals);
+        if installed.is_empty() {
+            log::info!("No live-only packages installed in the image root.");
+            return Ok(());
+        }
+        log::info!("Removing live-only packages: {}", installed.join(" "));
+        session.run(runner, &Self::apt_get().args(["purge", "-y", "--auto-remove"]).args(&installed))?;
+        Ok(())
+    }
+
+    fn finalize(&self, runner: &mut dyn CommandRunner, session: &ChrootSession, plan: &InstallPlan) -> Result<()> {
+        if plan.source.is_offline() {
+            // The local repository is gone after reboot
+            runner.run_checked(&write_file_command(plan, "/etc/apt/sources.list", "0644", &self.options.sources_list()))?;
+        }
+        session.run(runner, &CommandSpec::new("rm").args(["-f", "/etc/dpkg/dpkg.cfg.d/unsafe-io"]))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        app::InstallationConfig,
+        core::command::DryRunCommandRunner,
+        tools::installation::{backend::{command_summary, dry_run_session}, InstallSource, DEFAULT_TARGET_ROOT},
+    };
+
+    fn plan() -> InstallPlan {
+        let config = InstallationConfig {
+            target_disk: Some("/dev/sda".to_string()),
+            luks_password: Some("correct horse".into()),
+            username: Some("alice".to_string()),
+            user_password: Some("battery staple".into()),
+            ..Default::default()
+        };
+        InstallPlan::from_config(&config, Path::new(DEFAULT_TARGET_ROOT), true, DistroFamily::Debian).unwrap()
+    }
+
+    #[test]
+    fn installs_with_debootstrap_and_grub() {
+        let backend = DebianBackend::new(DebootstrapOptions::debian("bookworm"));
+        let plan = plan();
+        let mut runner = DryRunCommandRunner::new();
+        let session = dry_run_session(&plan);
+        backend.bootstrap(&mut runner, &plan).unwrap();
+        backend.configure_system(&mut runner, &session, &plan).unwrap();
+        backend.configure_initramfs(&mut runner, &session, &plan).unwrap();
+        backend.install_bootloader(&mut runner, &session, &plan).unwrap();
+        backend.create_user(&mut runner, &session, &plan).unwrap();
+
+        assert_eq!(
+            command_summary(&plan, runner.history()),
+            [
+                "debootstrap",
+                "write /etc/apt/sources.list",
+                "write /etc/dpkg/dpkg.cfg.d/unsafe-io",
+                "chroot apt-get update",
+                "chroot apt-get install",
+                "write /etc/hostname",
+                "write /etc/hosts",
+                "write /etc/locale.gen",
+                "chroot locale-gen",
+                "chroot update-locale LANG=en_US.UTF-8",
+                "write /etc/default/keyboard",
+                "chroot ln -sf",
+                "write /etc/timezone",
+                "chroot update-initramfs -u",
+                "chroot sed -i",
+                "chroot grub-install --target=x86_64-efi",
+                "chroot grub-mkconfig -o",
+                "chroot useradd -m",
+                "chroot chpasswd",
+            ]
+        );
+        let history = runner.rendered_history();
+        assert!(history[0].starts_with("debootstrap --include=apt-transport-https,"));
+        assert!(history[0].ends_with(
+            "--variant=minbase --components=main,contrib,non-free,non-free-firmware --arch=amd64 \
+             bookworm /mnt/lunitool http://deb.debian.org/debian"
+        ));
+        assert!(history[4].contains(" linux-image-amd64 ") && history[4].contains(" grub-efi-amd64 "));
+        // Passwords only ever go through stdin
+        assert!(history.iter().all(|command| !command.contains("battery staple")));
+        assert_eq!(runner.history()[18].stdin.as_deref().map(Vec::as_slice), Some(&b"alice:battery staple\n"[..]));
+    }
+
+    #[test]
+    fn offline_repository_replaces_the_mirror() {
+        let backend = DebianBackend::new(DebootstrapOptions::ubuntu("noble"));
+        let plan = InstallPlan {
+            source: InstallSource::LocalRepository("/media/repo".into()),
+            ..plan()
+        };
+        let mut runner = DryRunCommandRunner::new();
+        let session = dry_run_session(&plan);
+        backend.bootstrap(&mut runner, &plan).unwrap();
+        backend.finalize(&mut runner, &session, &plan).unwrap();
+
+        let history = runner.rendered_history();
+        assert!(history[0].ends_with("noble /mnt/lunitool file:///media/repo --no-check-gpg"));
+        let sources = |at: usize| String::from_utf8(runner.history()[at].stdin.as_deref().cloned().unwrap()).unwrap();
+        assert_eq!(
+            sources(1),
+            format!("deb [trusted=yes] file://{} noble main restricted universe multiverse\n", TARGET_REPO_DIR)
+        );
+        assert!(sources(3).starts_with("deb http://archive.ubuntu.com/ubuntu noble "));
+    }
+}
@@ -0,0 +1,600 @@
+use anyhow::{anyhow, Result};
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+};
+
+use crate::{
+    app::InstallationConfig,
+    core::{
+        command::{CommandOutput, CommandRunner, CommandSpec, DryRunCommandRunner, OutputSink, SystemCommandRunner},
+        system_info::{detect_efi, detect_package_manager},
+    },
+    tools::{header_backup, recovery_key::{self, RecoverySheet}, ssh_keys},
+};
+
+use super::{
+    backend::{select_backend, DistroBackend},
+    boot_usb,
+    chroot::{ChrootOptions, ChrootSession},
+    fstab::MountTables,
+    luks,
+    network,
+    plan::InstallPlan,
+    secure_boot,
+    source::{self, InstallSource},
+    tpm2,
+    DEFAULT_TARGET_ROOT,
+};
+
+/// Coarse phases of an installation run, in execution order
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InstallPhase {
+    Preparing,
+    Partitioning,
+    Formatting,
+    Mounting,
+    BaseSystem,
+    Configuring,
+    Bootloader,
+    Users,
+    Finalizing,
+}
+
+impl InstallPhase {
+    /// Localization key of the phase title
+    pub fn title_key(&self) -> &'static str {
+        match self {
+            InstallPhase::Preparing => "INSTALL_PHASE_PREPARING",
+            InstallPhase::Partitioning => "INSTALL_PHASE_PARTITIONING",
+            InstallPhase::Formatting => "INSTALL_PHASE_FORMATTING",
+            InstallPhase::Mounting => "INSTALL_PHASE_MOUNTING",
+            InstallPhase::BaseSystem => "INSTALL_PHASE_BASE_SYSTEM",
+            InstallPhase::Configuring => "INSTALL_PHASE_CONFIGURING",
+            InstallPhase::Bootloader => "INSTALL_PHASE_BOOTLOADER",
+            InstallPhase::Users => "INSTALL_PHASE_USERS",
+            InstallPhase::Finalizing => "INSTALL_PHASE_FINALIZING",
+        }
+    }
+}
+
+/// Messages sent from the installation worker to the UI
+#[derive(Debug, Clone)]
+pub enum InstallEvent {
+    /// A new phase started; `percent` is the overall progress at its start
+    Phase { phase: InstallPhase, percent: u16 },
+    /// A command is about to be executed
+    Command(String),
+    /// One line of output (child process or engine message)
+    Output(String),
+    /// A recovery key was added to the new containers; shown once at the end
+    RecoveryKey(RecoverySheet),
+    /// All phases completed successfully
+    Finished,
+    /// The run aborted with an error
+    Failed(String),
+    /// The run stopped after a cancellation request
+    Cancelled,
+}
+
+/// Handle held by the UI thread to observe and control a running installation
+#[derive(Debug)]
+pub struct InstallHandle {
+    events: Receiver<InstallEvent>,
+    cancel: Arc<AtomicBool>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl InstallHandle {
+    /// Fetch all events that arrived since the last call without blocking
+    pub fn drain_events(&self) -> Vec<InstallEvent> {
+        self.events.try_iter().collect()
+    }
+
+    /// Ask the worker to stop; running child processes are killed
+    pub fn cancel(&self) {
+        log::warn!("Installation cancellation requested.");
+        self.cancel.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancel_requested(&self) -> bool {
+        self.cancel.load(Ordering::SeqCst)
+    }
+
+    /// Whether the worker thread has terminated
+    pub fn is_finished(&self) -> bool {
+        self.worker.as_ref().is_none_or(|w| w.is_finished())
+    }
+
+    /// Wait for the worker to terminate, including the teardown after a failure or cancellation
+    pub fn join(&mut self) {
+        if let Some(worker) = self.worker.take() {
+            if worker.join().is_err() {
+                log::error!("Installation worker panicked.");
+            }
+        }
+    }
+}
+
+// Exiting while the worker runs would leave mounts and open containers behind
+impl Drop for InstallHandle {
+    fn drop(&mut self) {
+        if !self.is_finished() {
+            self.cancel();
+        }
+        self.join();
+    }
+}
+
+/// Wraps the real runner to announce every command and honour cancellation
+pub struct ReportingRunner<'a> {
+    inner: &'a mut dyn CommandRunner,
+    events: &'a Sender<InstallEvent>,
+    cancel: &'a AtomicBool,
+}
+
+impl<'a> ReportingRunner<'a> {
+    pub fn new(inner: &'a mut dyn CommandRunner, events: &'a Sender<InstallEvent>, cancel: &'a AtomicBool) -> Self {
+        Self { inner, events, cancel }
+    }
+}
+
+impl CommandRunner for ReportingRunner<'_> {
+    fn run(&mut self, spec: &CommandSpec) -> Result<CommandOutput> {
+        if self.cancel.load(Ordering::SeqCst) {
+            return Err(anyhow!("Installation cancelled"));
+        }
+        let _ = self.events.send(InstallEvent::Command(spec.to_string()));
+        self.inner.run(spec)
+    }
+
+    fn is_dry_run(&self) -> bool {
+        self.inner.is_dry_run()
+    }
+}
+
+/// State shared by all phases of one installation run
+pub struct InstallContext<'a> {
+    pub runner: &'a mut dyn CommandRunner,
+    pub plan: InstallPlan,
+    pub backend: Box<dyn DistroBackend>,
+    /// Set once the target filesystems are mounted, so failures can clean up
+    pub mounted: bool,
+    /// Set while the LUKS container / volume group of the plan is open
+    pub storage_open: bool,
+    /// Set while an installation ISO is loop mounted
+    pub image_mounted: bool,
+    /// API filesystems bound into the target while chroot steps run
+    pub chroot: Option<ChrootSession>,
+    events: &'a Sender<InstallEvent>,
+    cancel: &'a AtomicBool,
+}
+
+impl InstallContext<'_> {
+    /// Run a command and fail on a non-zero exit code
+    pub fn run(&mut self, spec: CommandSpec) -> Result<CommandOutput> {
+        self.runner.run_checked(&spec)
+    }
+
+    /// Write an engine message into the protocol
+    pub fn log(&self, message: &str) {
+        log::info!("{}", message);
+        let _ = self.events.send(InstallEvent::Output(message.to_string()));
+    }
+
+    /// Abort with an error if cancellation was requested
+    pub fn check_cancelled(&self) -> Result<()> {
+        if self.cancel.load(Ordering::SeqCst) {
+            return Err(anyhow!("Installation cancelled"));
+        }
+        Ok(())
+    }
+
+    /// Target root as string for command arguments
+    pub fn target(&self) -> String {
+        self.plan.target()
+    }
+}
+
+type PhaseFn = fn(&mut InstallContext) -> Result<()>;
+
+/// Phases with their relative weight for the progress calculation
+const PHASES: &[(InstallPhase, u16, PhaseFn)] = &[
+    (InstallPhase::Preparing, 2, phase_prepare),
+    (InstallPhase::Partitioning, 5, phase_partition),
+    (InstallPhase::Formatting, 8, phase_format),
+    (InstallPhase::Mounting, 3, phase_mount),
+    (InstallPhase::BaseSystem, 40, phase_base_system),
+    (InstallPhase::Configuring, 20, phase_configure),
+    (InstallPhase::Bootloader, 12, phase_bootloader),
+    (InstallPhase::Users, 5, phase_users),
+    (InstallPhase::Finalizing, 5, phase_finalize),
+];
+
+/// Start the installation on a worker thread
+///
+/// In `dry_run` mode commands are only recorded and echoed to the protocol.
+pub fn start_installation(config: InstallationConfig, dry_run: bool) -> InstallHandle {
+    log::info!("Starting installation worker (dry run: {})", dry_run);
+    spawn_worker(dry_run, move |runner, teardown_runner, events, cancel| {
+        run_phases(runner, teardown_runner, &config, events, cancel)
+    })
+}
+
+/// Run `work` on a worker thread that reports to the returned handle
+///
+/// `work` gets a runner honouring the cancellation flag and one without it for
+/// the teardown; both stream command output as [`InstallEvent::Output`]. The
+/// final event is sent once `work` returns.
+pub fn spawn_worker<F>(dry_run: bool, work: F) -> InstallHandle
+where
+    F: FnOnce(&mut dyn CommandRunner, &mut dyn CommandRunner, &Sender<InstallEvent>, &AtomicBool) -> Result<()>
+        + Send
+        + 'static,
+{
+    let (tx, rx) = mpsc::channel();
+    let cancel = Arc::new(AtomicBool::new(false));
+
+    let sink_tx = tx.clone();
+    let sink: OutputSink = Arc::new(move |line: &str| {
+        let _ = sink_tx.send(InstallEvent::Output(line.to_string()));
+    });
+
+    let mut runner = make_runner(dry_run, Arc::clone(&sink), Some(Arc::clone(&cancel)));
+    // Teardown must still work after a cancellation, so it gets a runner without the flag.
+    let mut teardown_runner = make_runner(dry_run, sink, None);
+
+    let worker_cancel = Arc::clone(&cancel);
+    let worker = thread::spawn(move || {
+        let result = work(runner.as_mut(), teardown_runner.as_mut(), &tx, &worker_cancel);
+        let final_event = match result {
+            Ok(()) => InstallEvent::Finished,
+            Err(_) if worker_cancel.load(Ordering::SeqCst) => InstallEvent::Cancelled,
+            Err(e) => {
+                log::error!("Worker failed: {:#}", e);
+                InstallEvent::Failed(format!("{:#}", e))
+            }
+        };
+        let _ = tx.send(final_event);
+    });
+
+    InstallHandle {
+        events: rx,
+        cancel,
+        worker: Some(worker),
+    }
+}
+
+fn make_runner(dry_run: bool, sink: OutputSink, cancel: Option<Arc<AtomicBool>>) -> Box<dyn CommandRunner> {
+    if dry_run {
+        return Box::new(DryRunCommandRunner::new().with_output_sink(sink));
+    }
+    let runner = SystemCommandRunner::new().with_output_sink(sink);
+    match cancel {
+        Some(flag) => Box::new(runner.with_cancel_flag(flag)),
+        None => Box::new(runner),
+    }
+}
+
+fn run_phases(
+    runner: &mut dyn CommandRunner,
+    teardown_runner: &mut dyn CommandRunner,
+    config: &InstallationConfig,
+    events: &Sender<InstallEvent>,
+    cancel: &AtomicBool,
+) -> Result<()> {
+    let backend = select_backend(&detect_package_manager())?;
+    let plan = InstallPlan::from_config(config, &PathBuf::from(DEFAULT_TARGET_ROOT), detect_efi(), backend.family())?;
+    run_plan(runner, teardown_runner, plan, backend, events, cancel)
+}
+
+fn run_plan(
+    runner: &mut dyn CommandRunner,
+    teardown_runner: &mut dyn CommandRunner,
+    plan: InstallPlan,
+    backend: Box<dyn DistroBackend>,
+    events: &Sender<InstallEvent>,
+    cancel: &AtomicBool,
+) -> Result<()> {
+    let mut reporting = ReportingRunner::new(runner, events, cancel);
+    let mut ctx = InstallContext {
+        runner: &mut reporting,
+        plan,
+        backend,
+        mounted: false,
+        storage_open: false,
+        image_mounted: false,
+        chroot: None,
+        events,
+        cancel,
+    };
+
+    let total_weight: u16 = PHASES.iter().map(|(_, weight, _)| weight).sum();
+    let mut done_weight = 0;
+
+    for (phase, weight, phase_fn) in PHASES {
+        let result = ctx.check_cancelled().and_then(|()| {
+            let percent = done_weight * 100 / total_weight;
+            let _ = events.send(InstallEvent::Phase { phase: *phase, percent });
+            log::info!("Installation phase {:?} started ({}%)", phase, percent);
+            phase_fn(&mut ctx).map_err(|e| e.context(format!("Phase {:?} failed", phase)))
+        });
+        if let Err(e) = result {
+            // Leave no mounts or open containers behind; the error that stopped the run is what matters.
+            release_target(&mut ctx, teardown_runner);
+            return Err(e);
+        }
+        done_weight += weight;
+    }
+
+    let _ = events.send(InstallEvent::Phase { phase: InstallPhase::Finalizing, percent: 100 });
+    Ok(())
+}
+
+/// Undo what the phases so far set up, after a failure or cancellation
+///
+/// Runs with `runner`, which ignores the cancellation flag; errors are only logged.
+fn release_target(ctx: &mut InstallContext, runner: &mut dyn CommandRunner) {
+    if let Some(session) = ctx.chroot.take() {
+        if let Err(e) = session.close(runner) {
+            log::error!("Cleanup after the aborted run failed: {:#}", e);
+        }
+    }
+    if ctx.mounted {
+        ctx.log("Unmounting target...");
+        if let Err(e) = super::unmount_target(runner, &ctx.target()) {
+            log::error!("Cleanup after the aborted run failed: {:#}", e);
+        }
+    }
+    if ctx.storage_open {
+        ctx.log("Closing encrypted storage...");
+        if let Err(e) = super::close_encrypted_storage(runner, &ctx.plan) {
+            log::error!("Cleanup after the aborted run failed: {:#}", e);
+        }
+    }
+    if ctx.image_mounted {
+        if let Err(e) = source::unmount_image(runner, &ctx.plan.source) {
+            log::error!("Cleanup after the aborted run failed: {:#}", e);
+        }
+    }
+}
+
+fn phase_prepare(ctx: &mut InstallContext) -> Result<()> {
+    ctx.log(&format!(
+        "Target disk: {} | Boot mode: {} | Target root: {}",
+        ctx.plan.target_disk,
+        if ctx.plan.efi { "UEFI" } else { "BIOS" },
+        ctx.target()
+    ));
+    ctx.log(&format!("Distribution: {}", ctx.backend.name()));
+    ctx.log(&format!(
+        "Bootloader: {:?}{}",
+        ctx.plan.bootloader,
+        if ctx.plan.hybrid_boot { " (hybrid BIOS/UEFI)" } else { "" }
+    ));
+    ctx.log(&format!("Secure Boot: {}", ctx.plan.secure_boot));
+    if let Some(usb) = &ctx.plan.boot_usb {
+        ctx.log(&format!(
+            "Boot USB key: {} (ESP, /boot, detached header){}",
+            usb.device,
+            usb.backup_device.as_ref().map(|backup| format!(", backup to {}", backup)).unwrap_or_default()
+        ));
+    }
+    if let Some(tpm2) = &ctx.plan.tpm2 {
+        ctx.log(&format!("TPM2 unlock: PCRs {} via {}", tpm2.pcr_list(), tpm2.device));
+    }
+    // Refuse unsupported plans before the disk is touched
+    ctx.backend.validate_plan(&ctx.plan)?;
+    benchmark_encryption(ctx);
+    ctx.log(&format!("Installation source: {}", ctx.plan.source));
+    ctx.log(&format!(
+        "Hostname: {} | Locale: {} | Keyboard: {} | Timezone: {}",
+        ctx.plan.hostname, ctx.plan.locale, ctx.plan.keyboard_layout, ctx.plan.timezone
+    ));
+    if ctx.runner.is_dry_run() {
+        ctx.log("Dry run: commands are recorded but not executed.");
+    }
+    if matches!(ctx.plan.source, InstallSource::Image(_)) {
+        ctx.image_mounted = true;
+        source::mount_image(ctx.runner, &ctx.plan.source)?;
+    }
+    Ok(())
+}
+
+/// Log the LUKS parameters and their measured cost on this machine
+///
+/// A failed benchmark only costs the estimate, the installation goes on.
+fn benchmark_encryption(ctx: &mut InstallContext) {
+    let containers: Vec<(&str, luks::LuksProfile, bool)> = ctx
+        .plan
+        .luks_containers()
+        .into_iter()
+        .map(|(name, profile, by_grub)| (name, profile.clone(), by_grub))
+        .collect();
+    for (name, profile, by_grub) in containers {
+        ctx.log(&format!("LUKS {}: {}", name, profile));
+        ctx.log(&format!("LUKS {} unlock: {}", name, profile.unlock_estimate(by_grub)));
+        match luks::benchmark(ctx.runner, &profile) {
+            Ok(result) => ctx.log(&format!("LUKS {} benchmark: {}", name, result.summary())),
+            Err(e) => log::warn!("cryptsetup benchmark failed: {}", e),
+        }
+    }
+}
+
+fn phase_partition(ctx: &mut InstallContext) -> Result<()> {
+    super::partition_disk(ctx.runner, &ctx.plan)
+}
+
+fn phase_format(ctx: &mut InstallContext) -> Result<()> {
+    if ctx.plan.encryption.is_some() {
+        ctx.storage_open = true;
+        super::setup_encrypted_storage(ctx.runner, &ctx.plan)?;
+    }
+    super::format_partitions(ctx.runner, &ctx.plan)
+}
+
+fn phase_mount(ctx: &mut InstallContext) -> Result<()> {
+    // Set first, so a partly mounted target is unmounted as well
+    ctx.mounted = true;
+    super::mount_partitions(ctx.runner, &ctx.plan)
+}
+
+fn phase_base_system(ctx: &mut InstallContext) -> Result<()> {
+    let from_image = matches!(ctx.plan.source, InstallSource::Image(_));
+    if from_image {
+        source::extract_image_root(ctx.runner, &ctx.plan)?;
+    } else {
+        ctx.backend.bootstrap(ctx.runner, &ctx.plan)?;
+    }
+    MountTables::from_plan(ctx.runner, &ctx.plan)?.install(ctx.runner, &ctx.plan)?;
+    // Package scripts inside the chroot expect the API filesystems
+    let options = ChrootOptions {
+        efivarfs: ctx.plan.efi,
+        resolv_conf: !ctx.plan.source.is_offline(),
+    };
+    let session = ctx.chroot.insert(ChrootSession::enter(ctx.runner, &ctx.plan.target_root, options)?);
+    source::bind_repository(ctx.runner, session, &ctx.plan)?;
+    if from_image {
+        source::finish_image_root(ctx.runner, session, &ctx.plan, ctx.backend.as_ref())?;
+    }
+    Ok(())
+}
+
+/// Chroot session on the target, entered once the base system exists
+fn entered(chroot: &Option<ChrootSession>) -> Result<&ChrootSession> {
+    chroot.as_ref().ok_or_else(|| anyhow!("The target system has not been entered"))
+}
+
+fn phase_configure(ctx: &mut InstallContext) -> Result<()> {
+    super::install_keyfile(ctx.runner, &ctx.plan)?;
+    if let Some(sheet) = recovery_key::enroll_installation(ctx.runner, &ctx.plan)? {
+        ctx.log(&format!("Recovery key added to {} (UUID {})", sheet.device, sheet.uuid));
+        let _ = ctx.events.send(InstallEvent::RecoveryKey(sheet));
+    }
+    tpm2::enroll(ctx.runner, &ctx.plan)?;
+    boot_usb::store_header(ctx.runner, &ctx.plan)?;
+    header_backup::backup_installation(ctx.runner, &ctx.plan)?;
+    let session = entered(&ctx.chroot)?;
+    ctx.backend.configure_system(ctx.runner, session, &ctx.plan)?;
+    network::configure(ctx.runner, &ctx.plan)?;
+    ctx.check_cancelled()?;
+    ctx.backend.configure_initramfs(ctx.runner, session, &ctx.plan)
+}
+
+fn phase_bootloader(ctx: &mut InstallContext) -> Result<()> {
+    ctx.backend.install_bootloader(ctx.runner, entered(&ctx.chroot)?, &ctx.plan)?;
+    ctx.check_cancelled()?;
+    secure_boot::apply(ctx.runner, &ctx.plan)
+}
+
+fn phase_users(ctx: &mut InstallContext) -> Result<()> {
+    let session = entered(&ctx.chroot)?;
+    super::set_root_password(ctx.runner, session, &ctx.plan)?;
+    ctx.backend.create_user(ctx.runner, session, &ctx.plan)?;
+    ssh_keys::provision_installation(ctx.runner, session, ctx.backend.as_ref(), &ctx.plan)
+}
+
+fn phase_finalize(ctx: &mut InstallContext) -> Result<()> {
+    ctx.backend.finalize(ctx.runner, entered(&ctx.chroot)?, &ctx.plan)?;
+    if let Some(session) = ctx.chroot.take() {
+        // Busy mounts are detached lazily; the recursive unmount below still succeeds
+        if let Err(e) = session.close(ctx.runner) {
+            ctx.log(&format!("Warning: {:#}", e));
+        }
+    }
+    ctx.run(CommandSpec::new("sync"))?;
+    let target = ctx.target();
+    super::unmount_target(ctx.runner, &target)?;
+    ctx.mounted = false;
+    if ctx.storage_open {
+        super::close_encrypted_storage(ctx.runner, &ctx.plan)?;
+        ctx.storage_open = false;
+    }
+    if ctx.image_mounted {
+        source::unmount_image(ctx.runner, &ctx.plan.source)?;
+        ctx.image_mounted = false;
+    }
+    if let Some(usb) = &ctx.plan.boot_usb {
+        boot_usb::create_backup(ctx.runner, usb)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::installation::{
+        debian::{DebianBackend, DebootstrapOptions},
+        DistroFamily,
+    };
+    use std::path::Path;
+
+    /// Dry-run runner that raises the cancellation flag after `remaining` commands
+    struct CancelAfter<'a> {
+        inner: DryRunCommandRunner,
+        remaining: Option<usize>,
+        cancel: &'a AtomicBool,
+    }
+
+    impl CommandRunner for CancelAfter<'_> {
+        fn run(&mut self, spec: &CommandSpec) -> Result<CommandOutput> {
+            let output = self.inner.run(spec);
+            if let Some(remaining) = self.remaining.as_mut() {
+                *remaining -= 1;
+                if *remaining == 0 {
+                    self.cancel.store(true, Ordering::SeqCst);
+                }
+            }
+            output
+        }
+
+        fn is_dry_run(&self) -> bool {
+            true
+        }
+    }
+
+    fn encrypted_plan() -> InstallPlan {
+        let config = InstallationConfig {
+            target_disk: Some("/dev/sda".to_string()),
+            luks_password: Some("correct horse".into()),
+            ..Default::default()
+        };
+        InstallPlan::from_config(&config, Path::new(DEFAULT_TARGET_ROOT), true, DistroFamily::Debian).unwrap()
+    }
+
+    /// Run the plan, cancelling after `cancel_after` commands; returns the result,
+    /// the events and the commands of the teardown runner
+    fn run(cancel_after: Option<usize>) -> (Result<()>, Vec<InstallEvent>, Vec<String>) {
+        let cancel = AtomicBool::new(false);
+        let mut runner = CancelAfter { inner: DryRunCommandRunner::new(), remaining: cancel_after, cancel: &cancel };
+        let mut teardown = DryRunCommandRunner::new();
+        let (tx, rx) = mpsc::channel();
+        let backend = Box::new(DebianBackend::new(DebootstrapOptions::debian("bookworm")));
+        let result = run_plan(&mut runner, &mut teardown, encrypted_plan(), backend, &tx, &cancel);
+        (result, rx.try_iter().collect(), teardown.rendered_history())
+    }
+
+    #[test]
+    fn cancel_between_phases_releases_the_target() {
+        // Commands issued up to the end of the Mounting phase in a full run
+        let (result, events, _) = run(None);
+        result.unwrap();
+        let mounted = events
+            .iter()
+            .take_while(|event| !matches!(event, InstallEvent::Phase { phase: InstallPhase::BaseSystem, .. }))
+            .filter(|event| matches!(event, InstallEvent::Command(_)))
+            .count();
+
+        let (result, events, teardown) = run(Some(mounted));
+        assert!(result.unwrap_err().to_string().contains("cancelled"));
+        assert!(!events.iter().any(|event| matches!(event, InstallEvent::Phase { phase: InstallPhase::BaseSystem, .. })));
+        let umount = teardown.iter().position(|command| command.starts_with("umount -R"));
+        let close = teardown.iter().position(|command| command.starts_with("cryptsetup close"));
+        assert!(matches!((umount, close), (Some(umount), Some(close)) if umount < close), "{:?}", teardown);
+    }
+}
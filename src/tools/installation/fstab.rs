@@ -0,0 +1,317 @@
+use anyhow::Result;
+
+use crate::core::command::CommandRunner;
+
+use super::{backend::write_file_command, boot_usb, device_uuid, plan::InstallPlan, tpm2};
+
+/// Mount options letting the system boot on while the USB key holding /boot is unplugged
+const BOOT_USB_MOUNT_OPTIONS: &str = "nofail,x-systemd.device-timeout=5s";
+
+/// One line of /etc/fstab
+#[derive(Debug, Clone, PartialEq)]
+pub struct FstabEntry {
+    /// Filesystem UUID
+    pub uuid: String,
+    /// Mount point, `none` for swap
+    pub mount_point: String,
+    pub fs_type: String,
+    pub options: String,
+    /// fsck order: 1 for the root filesystem, 2 for others, 0 to skip
+    pub pass: u8,
+}
+
+impl FstabEntry {
+    fn render(&self) -> String {
+        format!(
+            "UUID={} {} {} {} 0 {}",
+            self.uuid, self.mount_point, self.fs_type, self.options, self.pass
+        )
+    }
+}
+
+/// One line of /etc/crypttab
+#[derive(Debug, Clone, PartialEq)]
+pub struct CrypttabEntry {
+    /// Device mapper name
+    pub name: String,
+    /// Source device: `UUID=...` of the LUKS container, or a stable path for detached headers
+    pub device: String,
+    /// Keyfile path inside the target, `None` to ask for the passphrase
+    pub keyfile: Option<String>,
+    pub options: Vec<String>,
+}
+
+impl CrypttabEntry {
+    pub fn render(&self) -> String {
+        format!(
+            "{} {} {} {}",
+            self.name,
+            self.device,
+            self.keyfile.as_deref().unwrap_or("none"),
+            self.options.join(",")
+        )
+    }
+}
+
+/// crypttab entry of the root container, `None` for an unencrypted root
+///
+/// Also used by backends that put a crypttab into the initramfs.
+pub fn root_crypttab_entry(runner: &mut dyn CommandRunner, plan: &InstallPlan) -> Result<Option<CrypttabEntry>> {
+    let Some(encryption) = &plan.encryption else {
+        return Ok(None);
+    };
+    let mut options = vec!["luks".to_string(), "discard".to_string()];
+    if plan.tpm2.is_some() {
+        options.push(tpm2::CRYPTTAB_OPTION.to_string());
+    }
+    // Without a header on disk there is no UUID to find the container by
+    let device = match &encryption.header {
+        Some(header) => {
+            options.push(format!("header={}", header));
+            boot_usb::stable_disk_path(runner, &plan.root_partition())
+        }
+        None => format!("UUID={}", device_uuid(runner, &plan.root_partition())?),
+    };
+    Ok(Some(CrypttabEntry {
+        name: encryption.mapper_name.clone(),
+        device,
+        keyfile: encryption.keyfile.clone(),
+        options,
+    }))
+}
+
+/// fstab and crypttab of the target, with all UUIDs resolved
+#[derive(Debug, Clone, Default)]
+pub struct MountTables {
+    pub fstab: Vec<FstabEntry>,
+    pub crypttab: Vec<CrypttabEntry>,
+}
+
+impl MountTables {
+    /// Build the tables for the applied plan, asking blkid for the UUIDs
+    pub fn from_plan(runner: &mut dyn CommandRunner, plan: &InstallPlan) -> Result<Self> {
+        let mut tables = MountTables::default();
+
+        tables.fstab.push(FstabEntry {
+            uuid: device_uuid(runner, &plan.root_device())?,
+            mount_point: "/".to_string(),
+            fs_type: "ext4".to_string(),
+            options: "errors=remount-ro".to_string(),
+            pass: 1,
+        });
+
+        // The USB key is only needed at boot and for kernel updates
+        let boot_options = |options: &str| match plan.boot_usb {
+            Some(_) => format!("{},{}", options, BOOT_USB_MOUNT_OPTIONS),
+            None => options.to_string(),
+        };
+
+        if let Some(boot_device) = plan.boot_device() {
+            tables.fstab.push(FstabEntry {
+                uuid: device_uuid(runner, &boot_device)?,
+                mount_point: "/boot".to_string(),
+                fs_type: "ext4".to_string(),
+                options: boot_options("defaults"),
+                pass: 2,
+            });
+        }
+
+        if let Some(esp) = plan.esp_partition() {
+            tables.fstab.push(FstabEntry {
+                uuid: device_uuid(runner, &esp)?,
+                mount_point: plan.esp_mount().to_string(),
+                fs_type: "vfat".to_string(),
+                options: boot_options("umask=0077"),
+                pass: 2,
+            });
+        }
+
+        if let Some(swap) = plan.swap_device() {
+            tables.fstab.push(FstabEntry {
+                uuid: device_uuid(runner, &swap)?,
+                mount_point: "none".to_string(),
+                fs_type: "swap".to_string(),
+                options: "sw".to_string(),
+                pass: 0,
+            });
+        }
+
+        if let Some(entry) = root_crypttab_entry(runner, plan)? {
+            tables.crypttab.push(entry);
+        }
+        if let (Some(encryption), Some(boot_part)) = (&plan.boot_encryption, plan.boot_partition()) {
+            // Unlocked again after boot so /boot can be mounted for kernel updates
+            tables.crypttab.push(CrypttabEntry {
+                name: encryption.mapper_name.clone(),
+                device: format!("UUID={}", device_uuid(runner, &boot_part)?),
+                keyfile: encryption.keyfile.clone(),
+                options: vec!["luks".to_string()],
+            });
+        }
+
+        Ok(tables)
+    }
+
+    /// Content of /etc/fstab
+    pub fn render_fstab(&self) -> String {
+        let mut content = String::from("# /etc/fstab: generated by lunitool\n");
+        content.push_str("# <file system> <mount point> <type> <options> <dump> <pass>\n");
+        for entry in &self.fstab {
+            content.push_str(&entry.render());
+            content.push('\n');
+        }
+        content
+    }
+
+    /// Content of /etc/crypttab, `None` if nothing is encrypted
+    pub fn render_crypttab(&self) -> Option<String> {
+        if self.crypttab.is_empty() {
+            return None;
+        }
+        let mut content = String::from("# /etc/crypttab: generated by lunitool\n");
+        content.push_str("# <target name> <source device> <key file> <options>\n");
+        for entry in &self.crypttab {
+            content.push_str(&entry.render());
+            content.push('\n');
+        }
+        Some(content)
+    }
+
+    /// Install the tables into the target of the plan
+    ///
+    /// Goes through the runner like every other file of the target, so a
+    /// dry run records exactly the writes a real run performs.
+    pub fn install(&self, runner: &mut dyn CommandRunner, plan: &InstallPlan) -> Result<()> {
+        log::info!("Writing fstab and crypttab below {}", plan.target());
+        runner.run_checked(&write_file_command(plan, "/etc/fstab", "0644", &self.render_fstab()))?;
+        if let Some(content) = self.render_crypttab() {
+            // May name keyfiles, so only root gets to read it
+            runner.run_checked(&write_file_command(plan, "/etc/crypttab", "0600", &content))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        app::InstallationConfig,
+        core::command::DryRunCommandRunner,
+        tools::installation::{plan::Bootloader, DistroFamily, DEFAULT_TARGET_ROOT},
+    };
+    use std::path::Path;
+
+    const FSTAB: &str = "install -D -m 0644 /dev/stdin /mnt/lunitool/etc/fstab";
+    const CRYPTTAB: &str = "install -D -m 0600 /dev/stdin /mnt/lunitool/etc/crypttab";
+
+    fn plan(config: InstallationConfig) -> InstallPlan {
+        let config = InstallationConfig {
+            target_disk: Some("/dev/sda".to_string()),
+            ..config
+        };
+        InstallPlan::from_config(&config, Path::new(DEFAULT_TARGET_ROOT), true, DistroFamily::Debian).unwrap()
+    }
+
+    fn encrypted(bootloader: Bootloader) -> InstallationConfig {
+        InstallationConfig {
+            luks_password: Some("correct horse".into()),
+            bootloader,
+            ..Default::default()
+        }
+    }
+
+    /// Install the tables of `plan` through the dry-run runner; the commands with their stdin
+    fn install_tables(plan: &InstallPlan) -> Vec<(String, String)> {
+        let tables = MountTables::from_plan(&mut DryRunCommandRunner::new(), plan).unwrap();
+        let mut runner = DryRunCommandRunner::new();
+        tables.install(&mut runner, plan).unwrap();
+        runner
+            .history()
+            .iter()
+            .map(|spec| {
+                let stdin = String::from_utf8(spec.stdin.as_deref().cloned().unwrap_or_default()).unwrap();
+                (spec.to_string(), stdin)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn plain_plan() {
+        let writes = install_tables(&plan(InstallationConfig::default()));
+        assert_eq!(
+            writes,
+            [(
+                FSTAB.to_string(),
+                "# /etc/fstab: generated by lunitool\n\
+                 # <file system> <mount point> <type> <options> <dump> <pass>\n\
+                 UUID=<uuid-of-sda2> / ext4 errors=remount-ro 0 1\n\
+                 UUID=<uuid-of-sda1> /boot/efi vfat umask=0077 0 2\n"
+                    .to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn cryptodisk_plan() {
+        let config = InstallationConfig {
+            embed_keyfile: true,
+            ..encrypted(Bootloader::GrubCryptodisk)
+        };
+        let writes = install_tables(&plan(config));
+        assert_eq!(
+            writes,
+            [
+                (
+                    FSTAB.to_string(),
+                    "# /etc/fstab: generated by lunitool\n\
+                     # <file system> <mount point> <type> <options> <dump> <pass>\n\
+                     UUID=<uuid-of-mapper-vg-root> / ext4 errors=remount-ro 0 1\n\
+                     UUID=<uuid-of-mapper-cryptboot> /boot ext4 defaults 0 2\n\
+                     UUID=<uuid-of-sda3> /boot/efi vfat umask=0077 0 2\n\
+                     UUID=<uuid-of-mapper-vg-swap> none swap sw 0 0\n"
+                        .to_string()
+                ),
+                (
+                    CRYPTTAB.to_string(),
+                    "# /etc/crypttab: generated by lunitool\n\
+                     # <target name> <source device> <key file> <options>\n\
+                     cryptroot UUID=<uuid-of-sda4> /etc/luks/boot_os.keyfile luks,discard\n\
+                     cryptboot UUID=<uuid-of-sda1> /etc/luks/boot_os.keyfile luks\n"
+                        .to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn boot_usb_plan() {
+        let config = InstallationConfig {
+            boot_usb: Some("/dev/sdb".to_string()),
+            ..encrypted(Bootloader::Grub)
+        };
+        let writes = install_tables(&plan(config));
+        assert_eq!(
+            writes,
+            [
+                (
+                    FSTAB.to_string(),
+                    "# /etc/fstab: generated by lunitool\n\
+                     # <file system> <mount point> <type> <options> <dump> <pass>\n\
+                     UUID=<uuid-of-mapper-vg-root> / ext4 errors=remount-ro 0 1\n\
+                     UUID=<uuid-of-sdb2> /boot ext4 defaults,nofail,x-systemd.device-timeout=5s 0 2\n\
+                     UUID=<uuid-of-sdb1> /boot/efi vfat umask=0077,nofail,x-systemd.device-timeout=5s 0 2\n\
+                     UUID=<uuid-of-mapper-vg-swap> none swap sw 0 0\n"
+                        .to_string()
+                ),
+                (
+                    CRYPTTAB.to_string(),
+                    "# /etc/crypttab: generated by lunitool\n\
+                     # <target name> <source device> <key file> <options>\n\
+                     cryptroot /dev/sda /boot/luks/root.keyfile luks,discard,header=/boot/luks/root.header\n"
+                        .to_string()
+                ),
+            ]
+        );
+    }
+}
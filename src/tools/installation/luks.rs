@@ -0,0 +1,419 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::core::command::{CommandRunner, CommandSpec};
+
+/// Upper memory cost limit of cryptsetup's argon2 implementation (4 GiB)
+const MAX_PBKDF_MEMORY_KIB: u32 = 4 * 1024 * 1024;
+/// How much longer GRUB takes for the pbkdf2 iterations cryptsetup calibrated;
+/// its implementation lacks the optimized SHA code cryptsetup uses
+const GRUB_PBKDF2_SLOWDOWN: u32 = 10;
+
+/// Key derivation function protecting the keyslots
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Pbkdf {
+    /// Memory hard, the LUKS2 default
+    Argon2id,
+    Argon2i,
+    /// The only function GRUB can derive keys with
+    Pbkdf2,
+}
+
+impl Pbkdf {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Pbkdf::Argon2id => "argon2id",
+            Pbkdf::Argon2i => "argon2i",
+            Pbkdf::Pbkdf2 => "pbkdf2",
+        }
+    }
+
+    /// Whether the function takes a memory cost
+    pub fn is_memory_hard(&self) -> bool {
+        !matches!(self, Pbkdf::Pbkdf2)
+    }
+}
+
+/// Format parameters of a LUKS container
+///
+/// Missing fields in the configuration file fall back to the balanced profile.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LuksProfile {
+    /// `luks1` or `luks2`
+    pub luks_type: String,
+    /// dm-crypt cipher specification, e.g. `aes-xts-plain64`
+    pub cipher: String,
+    /// Key size in bits; XTS splits it into two keys, so 512 means AES-256
+    pub key_size: u32,
+    /// Encryption sector size in bytes (LUKS2 only)
+    pub sector_size: u32,
+    pub pbkdf: Pbkdf,
+    /// Time the key derivation should take on this machine, in milliseconds
+    pub iter_time_ms: u32,
+    /// Memory cost of argon2 in KiB
+    pub memory_kib: u32,
+}
+
+impl Default for LuksProfile {
+    fn default() -> Self {
+        Self::balanced()
+    }
+}
+
+impl LuksProfile {
+    /// LUKS2 with argon2id, 1 GiB memory cost and two seconds unlock time
+    pub fn balanced() -> Self {
+        Self {
+            luks_type: "luks2".to_string(),
+            cipher: "aes-xts-plain64".to_string(),
+            key_size: 512,
+            sector_size: 4096,
+            pbkdf: Pbkdf::Argon2id,
+            iter_time_ms: 2000,
+            memory_kib: 1024 * 1024,
+        }
+    }
+
+    /// Slower, memory hungrier key derivation for machines with plenty of RAM
+    pub fn hardened() -> Self {
+        Self {
+            iter_time_ms: 5000,
+            memory_kib: MAX_PBKDF_MEMORY_KIB,
+            ..Self::balanced()
+        }
+    }
+
+    /// Argon2id with 256 MiB for systems with little RAM
+    pub fn low_memory() -> Self {
+        Self {
+            memory_kib: 256 * 1024,
+            ..Self::balanced()
+        }
+    }
+
+    /// LUKS1 with PBKDF2, readable by GRUB's cryptodisk support
+    pub fn grub_compatible() -> Self {
+        Self {
+            luks_type: "luks1".to_string(),
+            sector_size: 512,
+            pbkdf: Pbkdf::Pbkdf2,
+            memory_kib: 0,
+            ..Self::balanced()
+        }
+    }
+
+    /// Named presets in the order they are offered
+    pub fn presets() -> Vec<(&'static str, LuksProfile)> {
+        vec![
+            ("balanced", Self::balanced()),
+            ("hardened", Self::hardened()),
+            ("low-memory", Self::low_memory()),
+            ("grub-compatible", Self::grub_compatible()),
+        ]
+    }
+
+    /// Check the parameters; `read_by_grub` for containers GRUB has to unlock
+    pub fn validate(&self, read_by_grub: bool) -> Result<()> {
+        let luks1 = match self.luks_type.as_str() {
+            "luks1" => true,
+            "luks2" => false,
+            other => return Err(anyhow!("Unknown LUKS type {}", other)),
+        };
+        if self.cipher.is_empty() {
+            return Err(anyhow!("No cipher set in the LUKS profile"));
+        }
+        if self.cipher.contains("xts") && !matches!(self.key_size, 256 | 512) {
+            return Err(anyhow!("XTS ciphers take a 256 or 512 bit key, not {}", self.key_size));
+        }
+        if self.key_size == 0 || !self.key_size.is_multiple_of(8) {
+            return Err(anyhow!("Invalid key size {} bits", self.key_size));
+        }
+        if !matches!(self.sector_size, 512 | 1024 | 2048 | 4096) {
+            return Err(anyhow!("Sector size must be 512, 1024, 2048 or 4096 bytes, not {}", self.sector_size));
+        }
+        if luks1 && self.sector_size != 512 {
+            return Err(anyhow!("LUKS1 only supports 512 byte sectors"));
+        }
+        if luks1 && self.pbkdf != Pbkdf::Pbkdf2 {
+            return Err(anyhow!("LUKS1 only supports pbkdf2"));
+        }
+        if self.iter_time_ms == 0 {
+            return Err(anyhow!("The PBKDF iteration time must be positive"));
+        }
+        if self.pbkdf.is_memory_hard() && !(32..=MAX_PBKDF_MEMORY_KIB).contains(&self.memory_kib) {
+            return Err(anyhow!(
+                "{} memory cost must be between 32 KiB and {} KiB, not {}",
+                self.pbkdf.as_str(),
+                MAX_PBKDF_MEMORY_KIB,
+                self.memory_kib
+            ));
+        }
+        if read_by_grub && self.pbkdf != Pbkdf::Pbkdf2 {
+            return Err(anyhow!(
+                "GRUB cannot unlock {} keyslots; use pbkdf2 (grub-compatible profile) for containers GRUB opens",
+                self.pbkdf.as_str()
+            ));
+        }
+        Ok(())
+    }
+
+    /// Keyslot arguments for `luksFormat` and `luksAddKey`
+    pub fn pbkdf_args(&self) -> Vec<String> {
+        let mut args = vec![
+            "--pbkdf".to_string(),
+            self.pbkdf.as_str().to_string(),
+            "--iter-time".to_string(),
+            self.iter_time_ms.to_string(),
+        ];
+        if self.pbkdf.is_memory_hard() {
+            args.extend(["--pbkdf-memory".to_string(), self.memory_kib.to_string()]);
+        }
+        args
+    }
+
+    /// Expected cost of unlocking with the passphrase; `read_by_grub` for containers GRUB opens
+    ///
+    /// cryptsetup calibrates each keyslot so one key derivation takes the
+    /// iteration time on the installing machine, lowering the argon2 memory if
+    /// it cannot; GRUB needs the same iterations with much slower code.
+    pub fn unlock_estimate(&self, read_by_grub: bool) -> UnlockEstimate {
+        UnlockEstimate {
+            cryptsetup_ms: self.iter_time_ms,
+            grub_ms: (read_by_grub && self.pbkdf == Pbkdf::Pbkdf2)
+                .then(|| self.iter_time_ms.saturating_mul(GRUB_PBKDF2_SLOWDOWN)),
+            memory_kib: self.pbkdf.is_memory_hard().then_some(self.memory_kib),
+        }
+    }
+
+    /// All format arguments for `cryptsetup luksFormat`
+    pub fn format_args(&self) -> Vec<String> {
+        let mut args = vec![
+            "--type".to_string(),
+            self.luks_type.clone(),
+            "--cipher".to_string(),
+            self.cipher.clone(),
+            "--key-size".to_string(),
+            self.key_size.to_string(),
+        ];
+        if self.luks_type == "luks2" {
+            args.extend(["--sector-size".to_string(), self.sector_size.to_string()]);
+        }
+        args.extend(self.pbkdf_args());
+        args
+    }
+}
+
+impl std::fmt::Display for LuksProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} {} bit, {} byte sectors, {} {} ms",
+            self.luks_type, self.cipher, self.key_size, self.sector_size, self.pbkdf.as_str(), self.iter_time_ms
+        )?;
+        if self.pbkdf.is_memory_hard() {
+            write!(f, " / {} MiB", self.memory_kib / 1024)?;
+        }
+        Ok(())
+    }
+}
+
+/// Expected time and memory to open a container with its passphrase
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UnlockEstimate {
+    /// Key derivation by cryptsetup in the initramfs, in milliseconds
+    pub cryptsetup_ms: u32,
+    /// Key derivation by GRUB before the kernel starts, for containers it opens
+    pub grub_ms: Option<u32>,
+    /// Memory argon2 needs at boot, in KiB
+    pub memory_kib: Option<u32>,
+}
+
+impl std::fmt::Display for UnlockEstimate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", seconds(self.cryptsetup_ms))?;
+        if let Some(memory) = self.memory_kib {
+            write!(f, " with {} MiB memory", memory / 1024)?;
+        }
+        if let Some(grub) = self.grub_ms {
+            write!(f, ", {} in GRUB", seconds(grub))?;
+        }
+        Ok(())
+    }
+}
+
+/// Milliseconds as rounded seconds, e.g. `2 s`; below one second `0.5 s`
+pub fn seconds(ms: u32) -> String {
+    if ms < 1000 {
+        format!("{:.1} s", f64::from(ms) / 1000.0)
+    } else {
+        format!("{} s", (ms + 500) / 1000)
+    }
+}
+
+/// Result of `cryptsetup benchmark` for a profile
+#[derive(Debug, Clone, Default)]
+pub struct LuksBenchmark {
+    /// Cipher throughput in MiB/s (encryption, decryption)
+    pub throughput: Option<(f64, f64)>,
+    /// Iterations a keyslot gets on this machine: the measured pbkdf2 rate
+    /// times the iteration time, or what cryptsetup calibrated for argon2
+    pub iterations: Option<u64>,
+    /// Memory actually used by argon2 in KiB
+    pub memory_kib: Option<u32>,
+    /// Memory cost of the profile, if cryptsetup had to lower it to stay within the iteration time
+    pub lowered_from_kib: Option<u32>,
+}
+
+impl LuksBenchmark {
+    /// One line summary for the protocol
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some((encrypt, decrypt)) = self.throughput {
+            parts.push(format!("{:.0} MiB/s encryption, {:.0} MiB/s decryption", encrypt, decrypt));
+        }
+        if let Some(iterations) = self.iterations {
+            parts.push(format!("{} PBKDF iterations per keyslot", iterations));
+        }
+        if let Some(memory) = self.memory_kib {
+            match self.lowered_from_kib {
+                Some(requested) => parts.push(format!(
+                    "{} MiB memory (lowered from {} MiB, this machine is too slow for the profile)",
+                    memory / 1024,
+                    requested / 1024
+                )),
+                None => parts.push(format!("{} MiB memory", memory / 1024)),
+            }
+        }
+        if parts.is_empty() {
+            return "no results".to_string();
+        }
+        parts.join(", ")
+    }
+}
+
+/// Measure cipher speed and key derivation of `profile` on this machine
+pub fn benchmark(runner: &mut dyn CommandRunner, profile: &LuksProfile) -> Result<LuksBenchmark> {
+    // `aes-xts-plain64` is benchmarked as `aes-xts`; the IV mode does not matter
+    let cipher_mode: String = profile.cipher.split('-').take(2).collect::<Vec<_>>().join("-");
+    let cipher = runner.run_checked(
+        &CommandSpec::new("cryptsetup")
+            .args(["benchmark", "--cipher", &cipher_mode, "--key-size"])
+            .arg(profile.key_size.to_string()),
+    )?;
+    let kdf = runner.run_checked(&CommandSpec::new("cryptsetup").arg("benchmark").args(profile.pbkdf_args()))?;
+
+    let mut result = LuksBenchmark {
+        throughput: parse_throughput(&cipher.stdout, &cipher_mode),
+        ..Default::default()
+    };
+    // `PBKDF2-sha256  1755428 iterations per second for 256-bit key`
+    // `argon2id      4 iterations, 1048576 memory, 4 parallel threads (CPUs) for 256-bit key (requested 2000 ms time)`
+    let kdf_line = kdf
+        .stdout
+        .lines()
+        .find(|line| line.trim_start().to_ascii_lowercase().starts_with(profile.pbkdf.as_str()));
+    if let Some(line) = kdf_line {
+        let iterations = number_before(line, "iteration");
+        if profile.pbkdf.is_memory_hard() {
+            result.iterations = iterations;
+            result.memory_kib = number_before(line, "memory").and_then(|kib| u32::try_from(kib).ok());
+            result.lowered_from_kib = result.memory_kib.filter(|kib| *kib < profile.memory_kib).map(|_| profile.memory_kib);
+        } else {
+            result.iterations = iterations.map(|per_second| per_second * u64::from(profile.iter_time_ms) / 1000);
+        }
+    }
+    Ok(result)
+}
+
+/// Throughput columns of the cipher line: `aes-xts   512b  2361.1 MiB/s  2374.0 MiB/s`
+fn parse_throughput(output: &str, cipher: &str) -> Option<(f64, f64)> {
+    let line = output.lines().find(|line| line.trim_start().starts_with(cipher))?;
+    let speeds: Vec<f64> = line
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .windows(2)
+        .filter(|pair| pair[1] == "MiB/s")
+        .filter_map(|pair| pair[0].parse().ok())
+        .collect();
+    match speeds.as_slice() {
+        [encrypt, decrypt, ..] => Some((*encrypt, *decrypt)),
+        _ => None,
+    }
+}
+
+/// Number directly in front of `word`: `4 iterations, 1048576 memory` -> 4 / 1048576
+fn number_before(line: &str, word: &str) -> Option<u64> {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    tokens
+        .windows(2)
+        .find(|pair| pair[1].trim_end_matches(',').starts_with(word))
+        .and_then(|pair| pair[0].parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::command::DryRunCommandRunner;
+
+    const CIPHER_OUTPUT: &str = "# Tests are approximate using memory only (no storage IO).\n\
+        #     Algorithm |       Key |      Encryption |      Decryption\n\
+        \x20       aes-xts        512b      2361.1 MiB/s      2374.0 MiB/s\n";
+
+    fn run(profile: &LuksProfile, kdf_output: &str) -> LuksBenchmark {
+        let mut runner = DryRunCommandRunner::new();
+        runner.respond_to("cryptsetup benchmark --cipher", CIPHER_OUTPUT);
+        runner.respond_to("cryptsetup benchmark --pbkdf", kdf_output);
+        benchmark(&mut runner, profile).unwrap()
+    }
+
+    #[test]
+    fn pbkdf2_iterations_follow_the_measured_rate() {
+        let result = run(
+            &LuksProfile::grub_compatible(),
+            "PBKDF2-sha256      1755428 iterations per second for 256-bit key\n",
+        );
+        assert_eq!(result.throughput, Some((2361.1, 2374.0)));
+        assert_eq!(result.iterations, Some(3_510_856));
+        assert_eq!(result.memory_kib, None);
+    }
+
+    #[test]
+    fn argon2_reports_lowered_memory() {
+        let profile = LuksProfile::balanced();
+        let result = run(
+            &profile,
+            "argon2id      4 iterations,  524288 memory, 4 parallel threads (CPUs) for 256-bit key (requested 2000 ms time)\n",
+        );
+        assert_eq!(result.iterations, Some(4));
+        assert_eq!(result.memory_kib, Some(524288));
+        assert_eq!(result.lowered_from_kib, Some(profile.memory_kib));
+        assert!(result.summary().contains("512 MiB memory (lowered from 1024 MiB"));
+
+        let result = run(
+            &profile,
+            "argon2id      7 iterations, 1048576 memory, 4 parallel threads (CPUs) for 256-bit key (requested 2000 ms time)\n",
+        );
+        assert_eq!(result.lowered_from_kib, None);
+    }
+
+    #[test]
+    fn unlock_estimate_follows_iteration_time_and_memory() {
+        let estimate = LuksProfile::hardened().unlock_estimate(false);
+        assert_eq!(estimate.cryptsetup_ms, 5000);
+        assert_eq!(estimate.memory_kib, Some(4 * 1024 * 1024));
+        assert_eq!(estimate.grub_ms, None);
+        assert_eq!(estimate.to_string(), "5 s with 4096 MiB memory");
+
+        // Only pbkdf2 keyslots can be opened by GRUB, which takes far longer for them
+        let estimate = LuksProfile::grub_compatible().unlock_estimate(true);
+        assert_eq!(estimate.memory_kib, None);
+        assert_eq!(estimate.grub_ms, Some(2000 * GRUB_PBKDF2_SLOWDOWN));
+        assert_eq!(estimate.to_string(), "2 s, 20 s in GRUB");
+        assert_eq!(LuksProfile::grub_compatible().unlock_estimate(false).grub_ms, None);
+
+        let quick = LuksProfile { iter_time_ms: 500, ..LuksProfile::low_memory() };
+        assert_eq!(quick.unlock_estimate(false).to_string(), "0.5 s with 256 MiB memory");
+    }
+}
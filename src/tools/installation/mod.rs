@@ -0,0 +1,448 @@
+pub mod arch;
+pub mod backend;
+pub mod boot_usb;
+pub mod chroot;
+pub mod debian;
+pub mod engine;
+pub mod fstab;
+pub mod luks;
+pub mod network;
+pub mod plan;
+pub mod rhel;
+pub mod secure_boot;
+pub mod source;
+pub mod systemd_boot;
+pub mod tpm2;
+
+use anyhow::Result;
+use std::path::Path;
+
+use crate::core::command::{CommandRunner, CommandSpec};
+
+pub use backend::{select_backend, DistroBackend, DistroFamily};
+pub use chroot::{ChrootOptions, ChrootSession};
+pub use engine::{start_installation, InstallEvent, InstallHandle, InstallPhase};
+pub use luks::LuksProfile;
+pub use plan::InstallPlan;
+pub use secure_boot::SecureBootMode;
+pub use tpm2::Tpm2Settings;
+
+use plan::EncryptionPlan;
+pub use source::InstallSource;
+
+/// Default mount point of the target system during installation
+pub const DEFAULT_TARGET_ROOT: &str = "/mnt/lunitool";
+
+/// Build the device path of partition `number` on `device`
+/// (`/dev/sda` + 1 -> `/dev/sda1`, `/dev/nvme0n1` + 1 -> `/dev/nvme0n1p1`)
+pub fn partition_path(device: &str, number: u32) -> String {
+    if device.ends_with(|c: char| c.is_ascii_digit()) {
+        format!("{}p{}", device, number)
+    } else {
+        format!("{}{}", device, number)
+    }
+}
+
+/// Partition a disk
+pub fn partition_disk(runner: &mut dyn CommandRunner, plan: &InstallPlan) -> Result<()> {
+    let device = plan.target_disk.as_str();
+    log::info!("Partitioning disk {}", device);
+
+    if cfg!(unix) {
+        if let Some(usb) = &plan.boot_usb {
+            return boot_usb::partition(runner, plan, usb);
+        }
+        if plan.cryptodisk() {
+            return partition_cryptodisk(runner, plan);
+        }
+
+        // This is a simplified example using parted
+        let label = if plan.efi { "gpt" } else { "msdos" };
+        runner.run_checked(&CommandSpec::new("parted").args(["-s", device, "mklabel", label]))?;
+
+        // Create partitions
+        if plan.efi {
+            // EFI partition
+            runner.run_checked(&CommandSpec::new("parted").args([
+                "-s", device, "mkpart", "primary", "fat32", "1MiB", "513MiB",
+                "set", "1", "boot", "on", "set", "1", "esp", "on",
+            ]))?;
+        } else {
+            // BIOS/MBR setup
+            // Boot partition
+            runner.run_checked(&CommandSpec::new("parted").args([
+                "-s", device, "mkpart", "primary", "1MiB", "513MiB", "set", "1", "boot", "on",
+            ]))?;
+        }
+
+        let mut root_start = "513MiB";
+        if plan.efi && plan.plain_boot {
+            // Unencrypted /boot after the ESP
+            runner.run_checked(&CommandSpec::new("parted").args(["-s", device, "mkpart", "primary", "ext4", "513MiB", "1537MiB"]))?;
+            root_start = "1537MiB";
+        }
+
+        // Root partition
+        runner.run_checked(&CommandSpec::new("parted").args(["-s", device, "mkpart", "primary", root_start, "100%"]))?;
+    } else {
+        // Windows implementation would go here
+        return Err(anyhow::anyhow!("Disk partitioning not implemented for this platform"));
+    }
+
+    Ok(())
+}
+
+/// GPT layout of the cryptodisk mode, see `InstallPlan::cryptodisk`
+fn partition_cryptodisk(runner: &mut dyn CommandRunner, plan: &InstallPlan) -> Result<()> {
+    let device = plan.target_disk.as_str();
+    runner.run_checked(&CommandSpec::new("sgdisk").args(["--zap-all", device]))?;
+
+    let mut sgdisk = CommandSpec::new("sgdisk").args(["-n", "1:0:+1024M", "-t", "1:8309", "-c", "1:boot"]);
+    if plan.bios_boot_partition().is_some() {
+        sgdisk = sgdisk.args(["-n", "2:0:+2M", "-t", "2:ef02", "-c", "2:bios"]);
+    }
+    if plan.esp_partition().is_some() {
+        sgdisk = sgdisk.args(["-n", "3:0:+256M", "-t", "3:ef00", "-c", "3:esp"]);
+    }
+    sgdisk = sgdisk.args(["-n", "4:0:0", "-t", "4:8309", "-c", "4:root"]);
+    runner.run_checked(&sgdisk.arg(device))?;
+
+    if plan.hybrid_boot {
+        // Firmware that only boots from MBR disks still finds /boot, the BIOS boot and the ESP
+        runner.run_checked(&CommandSpec::new("sgdisk").args(["--hybrid", "1:2:3", device]))?;
+    }
+    runner.run_checked(&CommandSpec::new("partprobe").arg(device))?;
+    Ok(())
+}
+
+/// Format and open one LUKS container of the plan
+fn create_container(runner: &mut dyn CommandRunner, encryption: &EncryptionPlan, partition: &str) -> Result<()> {
+    log::info!("Creating {} container on {} ({})", encryption.profile.luks_type, partition, encryption.profile);
+
+    // A detached header leaves nothing on the partition but encrypted data
+    let header_args = boot_usb::header_args(encryption);
+
+    // The passphrase only ever travels through stdin
+    runner.run_checked(
+        &CommandSpec::new("cryptsetup")
+            .arg("luksFormat")
+            .args(encryption.profile.format_args())
+            .args(&header_args)
+            .args(["--batch-mode", "--key-file=-", partition])
+            .stdin(encryption.passphrase.expose()),
+    )?;
+    runner.run_checked(
+        &CommandSpec::new("cryptsetup")
+            .arg("open")
+            .args(&header_args)
+            .args(["--key-file=-", partition, &encryption.mapper_name])
+            .stdin(encryption.passphrase.expose()),
+    )?;
+    Ok(())
+}
+
+/// Create the LUKS containers and LVM volumes of the plan, if any
+///
+/// Leaves the containers opened and the volume group active.
+pub fn setup_encrypted_storage(runner: &mut dyn CommandRunner, plan: &InstallPlan) -> Result<()> {
+    let Some(encryption) = &plan.encryption else {
+        return Ok(());
+    };
+    if let (Some(boot_encryption), Some(boot_part)) = (&plan.boot_encryption, plan.boot_partition()) {
+        create_container(runner, boot_encryption, &boot_part)?;
+    }
+    create_container(runner, encryption, &plan.root_partition())?;
+
+    if let (Some(lvm), Some(luks_device)) = (&plan.lvm, plan.luks_device()) {
+        log::info!("Creating LVM volume group {} on {}", lvm.volume_group, luks_device);
+        runner.run_checked(&CommandSpec::new("pvcreate").args(["-ff", "-y", &luks_device]))?;
+        runner.run_checked(&CommandSpec::new("vgcreate").args([&lvm.volume_group, &luks_device]))?;
+        if let Some(swap_size) = &lvm.swap_size {
+            runner.run_checked(&CommandSpec::new("lvcreate").args(["-L", swap_size, "-n", "swap", &lvm.volume_group]))?;
+        }
+        runner.run_checked(&CommandSpec::new("lvcreate").args(["-l", "100%FREE", "-n", "root", &lvm.volume_group]))?;
+    }
+
+    Ok(())
+}
+
+/// Deactivate the volume group and close the LUKS container of the plan
+pub fn close_encrypted_storage(runner: &mut dyn CommandRunner, plan: &InstallPlan) -> Result<()> {
+    if let Some(lvm) = &plan.lvm {
+        runner.run_checked(&CommandSpec::new("vgchange").args(["-an", &lvm.volume_group]))?;
+    }
+    for encryption in plan.encryption.iter().chain(&plan.boot_encryption) {
+        runner.run_checked(&CommandSpec::new("cryptsetup").args(["close", &encryption.mapper_name]))?;
+    }
+    Ok(())
+}
+
+/// Set the root password of the installed system, if the plan has one
+pub fn set_root_password(runner: &mut dyn CommandRunner, session: &ChrootSession, plan: &InstallPlan) -> Result<()> {
+    let Some(password) = &plan.root_password else {
+        return Ok(());
+    };
+    log::info!("Setting the root password");
+    session.run(runner, &CommandSpec::new("chpasswd").stdin(format!("root:{}\n", password.expose())))?;
+    Ok(())
+}
+
+/// Create the keyfile of the plan inside the target and add it to every container
+///
+/// Expects the target to be mounted; the initramfs configuration of the
+/// backend picks the keyfile up from there.
+pub fn install_keyfile(runner: &mut dyn CommandRunner, plan: &InstallPlan) -> Result<()> {
+    let Some(keyfile) = plan.encryption.as_ref().and_then(|enc| enc.keyfile.clone()) else {
+        return Ok(());
+    };
+    log::info!("Creating keyfile {} in the target", keyfile);
+    let host_keyfile = plan.target_path(&keyfile);
+    let key_dir = Path::new(&host_keyfile)
+        .parent()
+        .map(|dir| dir.to_string_lossy().into_owned())
+        .unwrap_or_else(|| plan.target());
+
+    runner.run_checked(&CommandSpec::new("install").args(["-d", "-m", "0700", &key_dir]))?;
+    runner.run_checked(&CommandSpec::new("dd").args([
+        "if=/dev/urandom", &format!("of={}", host_keyfile), "bs=512", "count=8", "status=none",
+    ]))?;
+    runner.run_checked(&CommandSpec::new("chmod").args(["0400", &host_keyfile]))?;
+
+    let containers = plan
+        .boot_encryption
+        .iter()
+        .zip(plan.boot_partition())
+        .chain(plan.encryption.iter().zip(Some(plan.root_partition())));
+    for (encryption, partition) in containers {
+        runner.run_checked(
+            &CommandSpec::new("cryptsetup")
+                .arg("luksAddKey")
+                .args(encryption.profile.pbkdf_args())
+                .args(boot_usb::header_args(encryption))
+                .args(["--batch-mode", "--key-file=-", &partition, &host_keyfile])
+                .stdin(encryption.passphrase.expose()),
+        )?;
+    }
+    Ok(())
+}
+
+/// Format partitions
+pub fn format_partitions(runner: &mut dyn CommandRunner, plan: &InstallPlan) -> Result<()> {
+    log::info!("Formatting partitions on {}", plan.target_disk);
+
+    if cfg!(unix) {
+        if let Some(esp) = plan.esp_partition() {
+            // Format EFI partition
+            runner.run_checked(&CommandSpec::new("mkfs.fat").args(["-F32", &esp]))?;
+        }
+        if let Some(boot_device) = plan.boot_device() {
+            // Format the separate /boot (plain for BIOS and dnf on UEFI, inside LUKS1 for cryptodisk)
+            runner.run_checked(&CommandSpec::new("mkfs.ext4").args(["-F", &boot_device]))?;
+        }
+
+        // Format root filesystem and swap
+        runner.run_checked(&CommandSpec::new("mkfs.ext4").args(["-F", &plan.root_device()]))?;
+        if let Some(swap) = plan.swap_device() {
+            runner.run_checked(&CommandSpec::new("mkswap").arg(&swap))?;
+        }
+    } else {
+        // Windows implementation would go here
+        return Err(anyhow::anyhow!("Partition formatting not implemented for this platform"));
+    }
+
+    Ok(())
+}
+
+/// Mount the freshly formatted filesystems below the target root
+pub fn mount_partitions(runner: &mut dyn CommandRunner, plan: &InstallPlan) -> Result<()> {
+    let root_mount = plan.target();
+    log::info!("Mounting filesystems of {} at {}", plan.target_disk, root_mount);
+
+    runner.run_checked(&CommandSpec::new("mkdir").args(["-p", &root_mount]))?;
+    runner.run_checked(&CommandSpec::new("mount").args([&plan.root_device(), &root_mount]))?;
+
+    // /boot first, the ESP is mounted inside it
+    let mounts = [
+        (plan.boot_device(), "/boot"),
+        (plan.esp_partition(), plan.esp_mount()),
+    ];
+    for (device, mount_point) in mounts {
+        let Some(device) = device else {
+            continue;
+        };
+        let mount_point = plan.target_path(mount_point);
+        runner.run_checked(&CommandSpec::new("mkdir").args(["-p", &mount_point]))?;
+        runner.run_checked(&CommandSpec::new("mount").args([&device, &mount_point]))?;
+    }
+
+    Ok(())
+}
+
+/// Recursively unmount everything below `root_mount`
+pub fn unmount_target(runner: &mut dyn CommandRunner, root_mount: &str) -> Result<()> {
+    if !runner.is_dry_run() && !Path::new(root_mount).exists() {
+        return Ok(());
+    }
+    runner.run_checked(&CommandSpec::new("umount").args(["-R", root_mount]))?;
+    Ok(())
+}
+
+/// Filesystem UUID of `device` as reported by blkid
+///
+/// In dry-run mode blkid reports nothing, so a placeholder naming the device is returned.
+pub fn device_uuid(runner: &mut dyn CommandRunner, device: &str) -> Result<String> {
+    let output = runner.run_checked(&CommandSpec::new("blkid").args(["-s", "UUID", "-o", "value", device]))?;
+    let uuid = output.stdout.trim().to_string();
+    if uuid.is_empty() {
+        if runner.is_dry_run() {
+            return Ok(format!("<uuid-of-{}>", device.trim_start_matches("/dev/").replace('/', "-")));
+        }
+        return Err(anyhow::anyhow!("No UUID found for {}", device));
+    }
+    Ok(uuid)
+}
+
+/// Set `key` in /etc/default/grub of the target, replacing an existing or commented-out line
+pub fn set_grub_default(runner: &mut dyn CommandRunner, session: &ChrootSession, key: &str, value: &str) -> Result<()> {
+    session.run(runner, &CommandSpec::new("sed").args([
+        "-i",
+        "-e",
+        &format!("/^#\\?{}=/d", key),
+        "-e",
+        &format!("$a {}={}", key, value),
+        "/etc/default/grub",
+    ]))?;
+    Ok(())
+}
+
+/// Install bootloader
+///
+/// Expects the EFI partition to be mounted at `<target>/boot/efi`; the GRUB
+/// tools run inside `session`. Hybrid plans get both GRUB targets.
+pub fn install_bootloader(runner: &mut dyn CommandRunner, session: &ChrootSession, plan: &InstallPlan) -> Result<()> {
+    log::info!("Installing bootloader on {}", plan.target_disk);
+
+    if cfg!(unix) {
+        if plan.cryptodisk() || plan.root_read_by_grub() {
+            // Makes grub-install embed the luks/cryptodisk modules and ask for the passphrase
+            // of the container holding /boot
+            set_grub_default(runner, session, "GRUB_ENABLE_CRYPTODISK", "y")?;
+        }
+        if plan.boots_efi() {
+            // Install GRUB for EFI
+            let efi_directory = format!("--efi-directory={}", plan.esp_mount());
+            let bootloader_id = format!("--bootloader-id={}", plan.bootloader_id);
+            let mut grub_install = CommandSpec::new("grub-install").args([
+                "--target=x86_64-efi", &efi_directory, &bootloader_id,
+            ]);
+            if plan.hybrid_boot || plan.boot_usb.is_some() {
+                // Firmware may come up in either mode or on another machine, so use the fallback loader
+                grub_install = grub_install.arg("--removable");
+            }
+            session.run(runner, &grub_install)?;
+        }
+        if plan.boots_bios() {
+            // Install GRUB for BIOS
+            session.run(runner, &CommandSpec::new("grub-install").args(["--target=i386-pc", &plan.target_disk]))?;
+        }
+
+        // Generate GRUB config
+        session.run(runner, &CommandSpec::new("grub-mkconfig").args(["-o", "/boot/grub/grub.cfg"]))?;
+    } else {
+        // Windows implementation would go here
+        return Err(anyhow::anyhow!("Bootloader installation not implemented for this platform"));
+    }
+
+    Ok(())
+}
+
+/// Write the dracut configuration of the plan and regenerate all images
+pub fn configure_dracut(runner: &mut dyn CommandRunner, session: &ChrootSession, plan: &InstallPlan) -> Result<()> {
+    let mut modules = Vec::new();
+    if plan.encryption.is_some() {
+        modules.push("crypt");
+    }
+    if plan.lvm.is_some() {
+        modules.push("lvm");
+    }
+    if plan.tpm2.is_some() {
+        modules.push("tpm2-tss");
+    }
+    let mut conf = String::from("# Generated by lunitool\n");
+    if !modules.is_empty() {
+        conf.push_str(&format!("add_dracutmodules+=\" {} \"\n", modules.join(" ")));
+    }
+    if let Some(keyfile) = plan.encryption.as_ref().and_then(|enc| enc.keyfile.as_ref()) {
+        // The image holds the keyfile, so it must only be readable by root
+        conf.push_str(&format!("install_items+=\" {} /etc/crypttab \"\numask=0077\n", keyfile));
+    }
+    runner.run_checked(&backend::write_file_command(plan, "/etc/dracut.conf.d/lunitool.conf", "0644", &conf))?;
+    session.run(runner, &CommandSpec::new("dracut").args(["--regenerate-all", "--force"]))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{app::InstallationConfig, core::command::DryRunCommandRunner};
+    use backend::dry_run_session;
+
+    fn encrypted_plan(bootloader: plan::Bootloader, efi: bool) -> InstallPlan {
+        let config = InstallationConfig {
+            target_disk: Some("/dev/sda".to_string()),
+            luks_password: Some("correct horse".into()),
+            bootloader,
+            ..Default::default()
+        };
+        InstallPlan::from_config(&config, Path::new(DEFAULT_TARGET_ROOT), efi, DistroFamily::Debian).unwrap()
+    }
+
+    fn enables_cryptodisk(plan: &InstallPlan) -> bool {
+        let mut runner = DryRunCommandRunner::new();
+        let session = dry_run_session(plan);
+        install_bootloader(&mut runner, &session, plan).unwrap();
+        let history = runner.rendered_history();
+        let grub_install = history.iter().position(|c| c.contains("grub-install"));
+        let cryptodisk = history.iter().position(|c| c.contains("$a GRUB_ENABLE_CRYPTODISK=y"));
+        match (cryptodisk, grub_install) {
+            (Some(set), Some(install)) => set < install,
+            _ => false,
+        }
+    }
+
+    #[test]
+    fn grub_efi_with_encrypted_root_enables_cryptodisk() {
+        let plan = encrypted_plan(plan::Bootloader::Grub, true);
+        assert!(plan.boot_partition().is_none());
+        assert!(plan.root_read_by_grub());
+        assert!(enables_cryptodisk(&plan));
+    }
+
+    #[test]
+    fn cryptodisk_mode_enables_cryptodisk() {
+        assert!(enables_cryptodisk(&encrypted_plan(plan::Bootloader::GrubCryptodisk, true)));
+    }
+
+    #[test]
+    fn plain_boot_partition_leaves_cryptodisk_off() {
+        let plan = encrypted_plan(plan::Bootloader::Grub, false);
+        assert!(plan.boot_partition().is_some());
+        assert!(!enables_cryptodisk(&plan));
+    }
+
+    #[test]
+    fn unlock_estimates_cover_every_container_grub_opens() {
+        let estimates = |plan: &InstallPlan| -> Vec<(&str, Option<u32>)> {
+            plan.luks_containers()
+                .into_iter()
+                .map(|(name, profile, by_grub)| (name, profile.unlock_estimate(by_grub).grub_ms))
+                .collect()
+        };
+        assert_eq!(estimates(&encrypted_plan(plan::Bootloader::Grub, true)), [("root", Some(20_000))]);
+        assert_eq!(estimates(&encrypted_plan(plan::Bootloader::Grub, false)), [("root", None)]);
+        assert_eq!(
+            estimates(&encrypted_plan(plan::Bootloader::GrubCryptodisk, true)),
+            [("/boot", Some(20_000)), ("root", None)]
+        );
+    }
+}
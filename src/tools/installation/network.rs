@@ -0,0 +1,267 @@
+use anyhow::{anyhow, Result};
+use std::net::IpAddr;
+
+use crate::core::command::CommandRunner;
+
+use super::{backend::write_file_command, plan::InstallPlan};
+
+/// Where the sysfs lists network interfaces
+const SYS_CLASS_NET: &str = "/sys/class/net";
+/// NetworkManager connection written for the installed system
+const CONNECTION_DIR: &str = "/etc/NetworkManager/system-connections";
+/// Smallest MTU IPv4 requires and the largest jumbo frames usually allow
+const MTU_RANGE: std::ops::RangeInclusive<u32> = 576..=9000;
+
+/// How the wired connection gets its address
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum AddressMethod {
+    #[default]
+    Dhcp,
+    Static,
+}
+
+/// Wired network of the installed system, set up with NetworkManager
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetworkSettings {
+    pub method: AddressMethod,
+    /// Interface the connection is bound to, `None` for any wired interface
+    pub interface: Option<String>,
+    /// Address with prefix length such as `192.168.1.10/24` or `fd00::10/64`, static only
+    pub address: Option<String>,
+    /// Gateway of the static address, of the same address family
+    pub gateway: Option<String>,
+    /// Name servers used in addition to, or with a static address instead of, those from DHCP
+    pub dns: Vec<String>,
+    pub mtu: Option<u32>,
+    /// Configure IPv6 automatically; switched off otherwise, unless the static address is IPv6
+    pub ipv6: bool,
+}
+
+impl Default for NetworkSettings {
+    fn default() -> Self {
+        Self {
+            method: AddressMethod::Dhcp,
+            interface: None,
+            address: None,
+            gateway: None,
+            dns: Vec::new(),
+            mtu: None,
+            ipv6: true,
+        }
+    }
+}
+
+impl NetworkSettings {
+    /// Whether NetworkManager's automatic connection does the same already
+    pub fn is_default(&self) -> bool {
+        *self == Self { interface: self.interface.clone(), ..Self::default() }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.method == AddressMethod::Static {
+            let address = self.address.as_deref().ok_or_else(|| anyhow!("A static network setup needs an address"))?;
+            check_address(address)?;
+            if let Some(gateway) = &self.gateway {
+                if is_ipv6(gateway) != is_ipv6(address) {
+                    return Err(anyhow!("Gateway {} is not of the same address family as {}", gateway, address));
+                }
+            }
+        }
+        for ip in self.gateway.iter().chain(&self.dns) {
+            check_ip(ip)?;
+        }
+        if !self.ipv6 && self.static_address().is_none_or(|address| !is_ipv6(address)) {
+            if let Some(server) = self.dns.iter().find(|server| is_ipv6(server)) {
+                return Err(anyhow!("Name server {} needs IPv6, which is switched off", server));
+            }
+        }
+        if let Some(mtu) = self.mtu {
+            check_mtu(mtu)?;
+        }
+        Ok(())
+    }
+
+    /// Name of the connection and its keyfile
+    fn connection_id(&self) -> String {
+        format!("lunitool-{}", self.interface.as_deref().unwrap_or("wired"))
+    }
+
+    /// Address of a static setup
+    fn static_address(&self) -> Option<&str> {
+        match self.method {
+            AddressMethod::Static => self.address.as_deref(),
+            AddressMethod::Dhcp => None,
+        }
+    }
+
+    /// NetworkManager keyfile of the connection
+    ///
+    /// Addresses, gateway and name servers go to the `[ipv4]` or `[ipv6]`
+    /// section of their address family; NetworkManager rejects anything else.
+    pub fn keyfile(&self) -> String {
+        let mut keyfile = format!("# Written by lunitool\n[connection]\nid={}\ntype=ethernet\n", self.connection_id());
+        if let Some(interface) = &self.interface {
+            keyfile.push_str(&format!("interface-name={}\n", interface));
+        }
+        keyfile.push_str("autoconnect=true\n\n[ethernet]\n");
+        if let Some(mtu) = self.mtu {
+            keyfile.push_str(&format!("mtu={}\n", mtu));
+        }
+
+        let address = self.static_address();
+        let (dns6, dns4): (Vec<&str>, Vec<&str>) = self.dns.iter().map(String::as_str).partition(|server| is_ipv6(server));
+        keyfile.push_str("\n[ipv4]\n");
+        keyfile.push_str(&self.ip_section(address.filter(|address| !is_ipv6(address)), &dns4, "auto"));
+        keyfile.push_str("\n[ipv6]\n");
+        let automatic = if self.ipv6 { "auto" } else { "disabled" };
+        keyfile.push_str(&self.ip_section(address.filter(|address| is_ipv6(address)), &dns6, automatic));
+        keyfile
+    }
+
+    /// Body of one address family section; `address` is set if it carries the static address
+    fn ip_section(&self, address: Option<&str>, dns: &[&str], automatic: &str) -> String {
+        let mut section = String::new();
+        match address {
+            Some(address) => {
+                section.push_str("method=manual\n");
+                match &self.gateway {
+                    Some(gateway) => section.push_str(&format!("address1={},{}\n", address, gateway)),
+                    None => section.push_str(&format!("address1={}\n", address)),
+                }
+            }
+            None => section.push_str(&format!("method={}\n", automatic)),
+        }
+        if !dns.is_empty() {
+            section.push_str(&format!("dns={};\n", dns.join(";")));
+        }
+        section
+    }
+}
+
+/// Check an address with prefix length, e.g. `192.168.1.10/24` or `fd00::10/64`
+pub fn check_address(value: &str) -> Result<()> {
+    let (ip, prefix) = value.split_once('/').ok_or_else(|| anyhow!("'{}' lacks the prefix length, e.g. /24", value))?;
+    let ip: IpAddr = ip.parse().map_err(|_| anyhow!("'{}' is no IP address", ip))?;
+    let max = if ip.is_ipv4() { 32 } else { 128 };
+    match prefix.parse::<u8>() {
+        Ok(prefix) if (1..=max).contains(&prefix) => Ok(()),
+        _ => Err(anyhow!("Invalid prefix length '{}' for {}", prefix, ip)),
+    }
+}
+
+/// Whether an address, optionally with prefix length, is an IPv6 address
+pub fn is_ipv6(value: &str) -> bool {
+    let ip = value.split('/').next().unwrap_or(value);
+    ip.parse::<IpAddr>().is_ok_and(|ip| ip.is_ipv6())
+}
+
+pub fn check_ip(value: &str) -> Result<()> {
+    value.parse::<IpAddr>().map(|_| ()).map_err(|_| anyhow!("'{}' is no IP address", value))
+}
+
+pub fn check_mtu(mtu: u32) -> Result<()> {
+    if MTU_RANGE.contains(&mtu) {
+        Ok(())
+    } else {
+        Err(anyhow!("MTU {} is outside {} to {}", mtu, MTU_RANGE.start(), MTU_RANGE.end()))
+    }
+}
+
+/// Name servers separated by spaces, commas or semicolons
+pub fn parse_dns(value: &str) -> Result<Vec<String>> {
+    let servers: Vec<String> = value.split([' ', ',', ';']).filter(|server| !server.is_empty()).map(str::to_string).collect();
+    for server in &servers {
+        check_ip(server)?;
+    }
+    Ok(servers)
+}
+
+/// Network interfaces of the live system except loopback, sorted by name
+pub fn detect_interfaces() -> Vec<String> {
+    let mut interfaces: Vec<String> = std::fs::read_dir(SYS_CLASS_NET)
+        .map(|entries| entries.filter_map(|entry| entry.ok()).map(|entry| entry.file_name().to_string_lossy().to_string()).collect())
+        .unwrap_or_default();
+    interfaces.retain(|name| name != "lo");
+    interfaces.sort();
+    interfaces
+}
+
+/// Write the NetworkManager connection of the plan into the target
+///
+/// Without settings that differ from NetworkManager's defaults nothing is
+/// written; the automatic wired connection then takes DHCP as before.
+pub fn configure(runner: &mut dyn CommandRunner, plan: &InstallPlan) -> Result<()> {
+    let Some(network) = plan.network.as_ref().filter(|network| !network.is_default()) else {
+        return Ok(());
+    };
+    log::info!("Configuring the wired network ({:?} on {})", network.method, network.interface.as_deref().unwrap_or("any interface"));
+    // NetworkManager ignores keyfiles readable by others
+    runner.run_checked(&write_file_command(
+        plan,
+        &format!("{}/{}.nmconnection", CONNECTION_DIR, network.connection_id()),
+        "0600",
+        &network.keyfile(),
+    ))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn static_settings(address: &str, gateway: &str, dns: &[&str]) -> NetworkSettings {
+        NetworkSettings {
+            method: AddressMethod::Static,
+            address: Some(address.to_string()),
+            gateway: Some(gateway.to_string()),
+            dns: dns.iter().map(|server| server.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Lines of the `[name]` section of a keyfile
+    fn section<'a>(keyfile: &'a str, name: &str) -> Vec<&'a str> {
+        let header = format!("[{}]", name);
+        keyfile
+            .lines()
+            .skip_while(|line| *line != header)
+            .skip(1)
+            .take_while(|line| !line.is_empty())
+            .collect()
+    }
+
+    #[test]
+    fn static_ipv4_goes_to_ipv4_section() {
+        let settings = static_settings("192.168.1.10/24", "192.168.1.1", &["192.168.1.1", "fd00::1"]);
+        settings.validate().unwrap();
+        let keyfile = settings.keyfile();
+        assert_eq!(
+            section(&keyfile, "ipv4"),
+            ["method=manual", "address1=192.168.1.10/24,192.168.1.1", "dns=192.168.1.1;"]
+        );
+        assert_eq!(section(&keyfile, "ipv6"), ["method=auto", "dns=fd00::1;"]);
+    }
+
+    #[test]
+    fn static_ipv6_goes_to_ipv6_section() {
+        let settings = NetworkSettings {
+            ipv6: false,
+            ..static_settings("fd00::10/64", "fd00::1", &["fd00::1"])
+        };
+        settings.validate().unwrap();
+        let keyfile = settings.keyfile();
+        assert_eq!(section(&keyfile, "ipv4"), ["method=auto"]);
+        assert_eq!(section(&keyfile, "ipv6"), ["method=manual", "address1=fd00::10/64,fd00::1", "dns=fd00::1;"]);
+    }
+
+    #[test]
+    fn rejects_mixed_families() {
+        assert!(static_settings("192.168.1.10/24", "fd00::1", &[]).validate().is_err());
+        let settings = NetworkSettings {
+            ipv6: false,
+            dns: vec!["fd00::1".to_string()],
+            ..Default::default()
+        };
+        assert!(settings.validate().is_err());
+    }
+}
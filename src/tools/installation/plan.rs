@@ -0,0 +1,452 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::app::InstallationConfig;
+use crate::core::secret::SecretString;
+use crate::tools::{header_backup::HeaderStore, recovery_key::RecoveryPlan, ssh_keys::SshAccessPlan};
+
+use super::{
+    backend::DistroFamily,
+    boot_usb::{self, BootUsbPlan},
+    luks::LuksProfile,
+    network::NetworkSettings,
+    partition_path,
+    secure_boot::{self, SecureBootMode},
+    source::InstallSource,
+    tpm2::Tpm2Plan,
+};
+
+/// Locale used when the wizard did not collect one
+pub const DEFAULT_LOCALE: &str = "en_US.UTF-8";
+/// Keyboard layout used when the wizard did not collect one
+pub const DEFAULT_KEYBOARD_LAYOUT: &str = "us";
+/// Timezone used when the wizard did not collect one
+pub const DEFAULT_TIMEZONE: &str = "UTC";
+/// Hostname used when the wizard did not collect one
+pub const DEFAULT_HOSTNAME: &str = "lunitool";
+/// Device mapper name of the opened root container
+pub const DEFAULT_LUKS_MAPPER: &str = "cryptroot";
+/// LVM volume group created inside the root container
+pub const DEFAULT_VOLUME_GROUP: &str = "vg";
+/// Size of the swap logical volume
+pub const DEFAULT_SWAP_SIZE: &str = "4G";
+/// Device mapper name of the opened /boot container in cryptodisk mode
+pub const BOOT_LUKS_MAPPER: &str = "cryptboot";
+/// EFI directory and firmware boot entry name when the distribution is unknown
+pub const DEFAULT_BOOTLOADER_ID: &str = "lunitool";
+/// Keyfile embedded in the initramfs so the passphrase is only typed once, at the GRUB prompt
+pub const BOOT_KEYFILE: &str = "/etc/luks/boot_os.keyfile";
+
+/// Bootloader installed into the target system
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Bootloader {
+    /// GRUB with an unencrypted /boot (BIOS, dnf) or the kernels on the root filesystem (UEFI)
+    #[default]
+    Grub,
+    /// GRUB unlocking a LUKS1 /boot itself (`GRUB_ENABLE_CRYPTODISK=y`); requires encryption
+    GrubCryptodisk,
+    /// Requires UEFI; the ESP is mounted at /boot so it holds the kernels
+    SystemdBoot,
+}
+
+/// Tool that builds unified kernel images for systemd-boot
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum UkiBuilder {
+    /// `ukify build` from systemd
+    Ukify,
+    /// `objcopy` adding the sections to the systemd EFI stub, for systems without ukify
+    Objcopy,
+}
+
+/// LUKS container holding the root filesystem or, in cryptodisk mode, /boot
+#[derive(Debug, Clone)]
+pub struct EncryptionPlan {
+    /// Name below /dev/mapper the container is opened as
+    pub mapper_name: String,
+    /// Format and keyslot parameters
+    pub profile: LuksProfile,
+    pub passphrase: SecretString,
+    /// Keyfile inside the target that unlocks the container at boot, if any
+    pub keyfile: Option<String>,
+    /// Detached header inside the target, if the container has none on disk
+    pub header: Option<String>,
+}
+
+/// LVM layout inside the root container
+#[derive(Debug, Clone)]
+pub struct LvmPlan {
+    pub volume_group: String,
+    /// Size of the swap volume (`lvcreate -L` syntax), `None` for no swap
+    pub swap_size: Option<String>,
+}
+
+/// Fully resolved settings of one installation run
+///
+/// The wizard collects an `InstallationConfig` with optional values; the plan
+/// fills in defaults once so distribution backends never deal with `None`.
+#[derive(Debug, Clone)]
+pub struct InstallPlan {
+    /// Mount point of the target system on the live system
+    pub target_root: PathBuf,
+    /// Disk the system is installed to
+    pub target_disk: String,
+    /// Install for UEFI instead of legacy BIOS boot
+    pub efi: bool,
+    /// Unencrypted /boot partition next to the ESP for a GRUB that cannot open the root container
+    pub plain_boot: bool,
+    pub hostname: String,
+    /// First user account, if one was configured
+    pub username: Option<String>,
+    pub user_password: Option<SecretString>,
+    /// Password of root, if one was set
+    pub root_password: Option<SecretString>,
+    /// Locale such as `de_DE.UTF-8`
+    pub locale: String,
+    /// Timezone below /usr/share/zoneinfo, such as `Europe/Berlin`
+    pub timezone: String,
+    /// Console and X11 keyboard layout, such as `de`
+    pub keyboard_layout: String,
+    /// Packages installed in addition to the backend's base set
+    pub extra_packages: Vec<String>,
+    /// Where packages or the root filesystem come from
+    pub source: InstallSource,
+    pub bootloader: Bootloader,
+    /// Directory below EFI/ on the ESP and label of the firmware boot entry
+    pub bootloader_id: String,
+    /// Boot unified kernel images instead of loader entries (systemd-boot only)
+    pub uki: Option<UkiBuilder>,
+    /// Keys the boot chain is signed with for Secure Boot
+    pub secure_boot: SecureBootMode,
+    /// One-time password confirming the MOK enrollment in MokManager (MOK mode only)
+    pub mok_password: Option<SecretString>,
+    /// Install GRUB for both UEFI and legacy BIOS, with a hybrid MBR (cryptodisk mode only)
+    pub hybrid_boot: bool,
+    /// Root is encrypted if set
+    pub encryption: Option<EncryptionPlan>,
+    /// TPM2 auto-unlock of the root container, if requested
+    pub tpm2: Option<Tpm2Plan>,
+    /// Separately encrypted /boot, set in cryptodisk mode
+    pub boot_encryption: Option<EncryptionPlan>,
+    /// Root and swap live on LVM if set
+    pub lvm: Option<LvmPlan>,
+    /// ESP, /boot and the root header on a USB key instead of the target disk
+    pub boot_usb: Option<BootUsbPlan>,
+    /// Where the headers of the new containers are backed up to
+    pub header_backup: Option<HeaderStore>,
+    /// Recovery key added to the new containers, if requested
+    pub recovery: Option<RecoveryPlan>,
+    /// SSH keys of the first user; password logins over SSH are disabled
+    pub ssh: Option<SshAccessPlan>,
+    /// Wired network of the installed system, `None` for NetworkManager's defaults
+    pub network: Option<NetworkSettings>,
+}
+
+/// Plain GRUB on UEFI keeps /boot inside the root filesystem, unless it lives on a USB key
+fn root_read_by_grub(bootloader: Bootloader, efi: bool, boot_usb: bool) -> bool {
+    bootloader == Bootloader::Grub && efi && !boot_usb
+}
+
+impl InstallPlan {
+    /// Resolve the wizard configuration into a plan for the distribution `family`
+    pub fn from_config(config: &InstallationConfig, target_root: &Path, efi: bool, family: DistroFamily) -> Result<Self> {
+        let target_disk = config
+            .target_disk
+            .clone()
+            .ok_or_else(|| anyhow!("No installation target selected"))?;
+
+        let cryptodisk = config.bootloader == Bootloader::GrubCryptodisk;
+        if cryptodisk && config.luks_password.is_none() {
+            return Err(anyhow!("GRUB cryptodisk mode requires an encryption passphrase"));
+        }
+        if config.bootloader == Bootloader::SystemdBoot && !efi {
+            return Err(anyhow!("systemd-boot requires a system booted in UEFI mode"));
+        }
+        if config.uki.is_some() && config.bootloader != Bootloader::SystemdBoot {
+            return Err(anyhow!("Unified kernel images are only available with systemd-boot"));
+        }
+        if config.hybrid_boot && !cryptodisk {
+            return Err(anyhow!("Hybrid BIOS/UEFI boot is only available in GRUB cryptodisk mode"));
+        }
+
+        if config.user_password.is_some() && config.username.is_none() {
+            return Err(anyhow!("A user password was given, but no user name"));
+        }
+        if config.ssh_authorized_keys.is_some() && config.username.is_none() {
+            return Err(anyhow!("SSH keys are installed for the new user, but no user is configured"));
+        }
+        let ssh = config
+            .ssh_authorized_keys
+            .as_ref()
+            .map(|source| SshAccessPlan::from_source(Path::new(source)))
+            .transpose()?;
+
+        if config.tpm2.is_some() && config.luks_password.is_none() {
+            return Err(anyhow!("TPM2 unlocking requires an encrypted root"));
+        }
+        let tpm2 = config
+            .tpm2
+            .as_ref()
+            .map(|settings| Tpm2Plan::from_settings(settings, config.tpm2_pin.as_ref()))
+            .transpose()?;
+
+        if let Some(network) = &config.network {
+            network.validate()?;
+        }
+
+        let boot_usb = config.boot_usb.as_ref().map(|device| BootUsbPlan {
+            device: device.clone(),
+            backup_device: config.boot_usb_backup.clone(),
+        });
+        // Without the keyfile the initramfs asks for the root passphrase a second time
+        let keyfile = match &boot_usb {
+            Some(_) => Some(boot_usb::KEYFILE.to_string()),
+            None => (cryptodisk && config.embed_keyfile).then(|| BOOT_KEYFILE.to_string()),
+        };
+        // dnf installs the prebuilt, signed grubx64.efi, which cannot open the root
+        // container, so the kernels go to a plain /boot instead
+        let plain_boot = family == DistroFamily::Rhel
+            && config.luks_password.is_some()
+            && root_read_by_grub(config.bootloader, efi, boot_usb.is_some());
+        // GRUB always opens the /boot container of cryptodisk mode
+        let root_read_by_grub = !plain_boot && root_read_by_grub(config.bootloader, efi, boot_usb.is_some());
+        let root_profile = config.luks_profile.clone().unwrap_or_else(|| {
+            if root_read_by_grub {
+                LuksProfile::grub_compatible()
+            } else {
+                LuksProfile::balanced()
+            }
+        });
+        let boot_profile = config.boot_luks_profile.clone().unwrap_or_else(LuksProfile::grub_compatible);
+        if config.luks_password.is_some() {
+            root_profile.validate(root_read_by_grub)?;
+            if cryptodisk {
+                boot_profile.validate(true)?;
+            }
+        }
+        let container = |mapper_name: &str, profile: &LuksProfile, passphrase: &SecretString| EncryptionPlan {
+            mapper_name: mapper_name.to_string(),
+            profile: profile.clone(),
+            passphrase: passphrase.clone(),
+            keyfile: keyfile.clone(),
+            header: None,
+        };
+
+        let plan = Self {
+            target_root: target_root.to_path_buf(),
+            target_disk,
+            efi,
+            plain_boot,
+            hostname: config.hostname.clone().unwrap_or_else(|| DEFAULT_HOSTNAME.to_string()),
+            username: config.username.clone(),
+            user_password: config.user_password.clone(),
+            root_password: config.root_password.clone(),
+            locale: config.locale.clone().unwrap_or_else(|| DEFAULT_LOCALE.to_string()),
+            timezone: config.timezone.clone().unwrap_or_else(|| DEFAULT_TIMEZONE.to_string()),
+            keyboard_layout: config
+                .keyboard_layout
+                .clone()
+                .unwrap_or_else(|| DEFAULT_KEYBOARD_LAYOUT.to_string()),
+            extra_packages: Vec::new(),
+            source: config.source.clone(),
+            bootloader: config.bootloader,
+            bootloader_id: config
+                .bootloader_id
+                .clone()
+                .unwrap_or_else(|| DEFAULT_BOOTLOADER_ID.to_string()),
+            uki: config.uki,
+            secure_boot: config.secure_boot,
+            mok_password: config.mok_password.clone().filter(|_| config.secure_boot == SecureBootMode::Mok),
+            hybrid_boot: config.hybrid_boot,
+            // Like UbuntuFDE.sh: an encrypted root always carries LVM with root and swap
+            encryption: config
+                .luks_password
+                .as_ref()
+                .map(|passphrase| EncryptionPlan {
+                    header: boot_usb.as_ref().map(|_| boot_usb::HEADER_FILE.to_string()),
+                    ..container(DEFAULT_LUKS_MAPPER, &root_profile, passphrase)
+                }),
+            tpm2,
+            boot_encryption: config
+                .luks_password
+                .as_ref()
+                .filter(|_| cryptodisk)
+                .map(|passphrase| container(BOOT_LUKS_MAPPER, &boot_profile, passphrase)),
+            lvm: config.luks_password.as_ref().map(|_| LvmPlan {
+                volume_group: DEFAULT_VOLUME_GROUP.to_string(),
+                swap_size: Some(DEFAULT_SWAP_SIZE.to_string()),
+            }),
+            boot_usb,
+            header_backup: config.header_backup.clone(),
+            recovery: (config.recovery_key && config.luks_password.is_some()).then(|| RecoveryPlan {
+                sheet_dir: config.recovery_sheet_dir.as_ref().map(PathBuf::from),
+            }),
+            ssh,
+            network: config.network.clone(),
+        };
+        secure_boot::validate(&plan)?;
+        boot_usb::validate(&plan)?;
+        Ok(plan)
+    }
+
+    /// Whether GRUB unlocks an encrypted /boot itself
+    ///
+    /// The cryptodisk layout follows UbuntuFDE.sh: 1 = /boot (LUKS1),
+    /// 2 = BIOS boot, 3 = ESP, 4 = root (LUKS2). Partitions that are not
+    /// needed for the boot mode are left out, the numbers stay the same.
+    pub fn cryptodisk(&self) -> bool {
+        self.bootloader == Bootloader::GrubCryptodisk
+    }
+
+    /// Whether GRUB has to unlock the root container to reach /boot
+    pub fn root_read_by_grub(&self) -> bool {
+        self.encryption.is_some() && !self.plain_boot && root_read_by_grub(self.bootloader, self.efi, self.boot_usb.is_some())
+    }
+
+    /// Containers to create, named `/boot` or `root`, and whether GRUB opens them
+    pub fn luks_containers(&self) -> Vec<(&'static str, &LuksProfile, bool)> {
+        [("/boot", &self.boot_encryption, true), ("root", &self.encryption, self.root_read_by_grub())]
+            .into_iter()
+            .filter_map(|(name, encryption, by_grub)| encryption.as_ref().map(|enc| (name, &enc.profile, by_grub)))
+            .collect()
+    }
+
+    /// Whether GRUB is installed for UEFI
+    pub fn boots_efi(&self) -> bool {
+        self.efi || self.hybrid_boot
+    }
+
+    /// Whether GRUB is installed for legacy BIOS
+    pub fn boots_bios(&self) -> bool {
+        !self.efi || self.hybrid_boot
+    }
+
+    /// EFI system partition, if the system boots via UEFI
+    pub fn esp_partition(&self) -> Option<String> {
+        if let Some(usb) = &self.boot_usb {
+            return Some(boot_usb::esp_partition(usb));
+        }
+        match (self.cryptodisk(), self.boots_efi()) {
+            (true, true) => Some(partition_path(&self.target_disk, 3)),
+            (false, true) => Some(partition_path(&self.target_disk, 1)),
+            (_, false) => None,
+        }
+    }
+
+    /// BIOS boot partition GRUB embeds its core image in (GPT with legacy boot)
+    pub fn bios_boot_partition(&self) -> Option<String> {
+        (self.cryptodisk() && self.boots_bios()).then(|| partition_path(&self.target_disk, 2))
+    }
+
+    /// Partition holding a separate /boot filesystem or its LUKS container, if any
+    pub fn boot_partition(&self) -> Option<String> {
+        if let Some(usb) = &self.boot_usb {
+            return Some(boot_usb::boot_partition(usb));
+        }
+        match (self.cryptodisk(), self.efi, self.plain_boot) {
+            (true, _, _) | (false, false, _) => Some(partition_path(&self.target_disk, 1)),
+            // After the ESP
+            (false, true, true) => Some(partition_path(&self.target_disk, 2)),
+            (false, true, false) => None,
+        }
+    }
+
+    /// Block device the separate /boot filesystem is created on, if any
+    pub fn boot_device(&self) -> Option<String> {
+        match &self.boot_encryption {
+            Some(encryption) => Some(format!("/dev/mapper/{}", encryption.mapper_name)),
+            None => self.boot_partition(),
+        }
+    }
+
+    /// Partition holding the root filesystem or its LUKS container
+    ///
+    /// With a boot USB key the whole target disk is the header-less container.
+    pub fn root_partition(&self) -> String {
+        if self.boot_usb.is_some() {
+            return self.target_disk.clone();
+        }
+        let number = match (self.cryptodisk(), self.plain_boot) {
+            (true, _) => 4,
+            (false, true) => 3,
+            (false, false) => 2,
+        };
+        partition_path(&self.target_disk, number)
+    }
+
+    /// Opened LUKS container, if the root is encrypted
+    pub fn luks_device(&self) -> Option<String> {
+        self.encryption
+            .as_ref()
+            .map(|enc| format!("/dev/mapper/{}", enc.mapper_name))
+    }
+
+    /// Block device the root filesystem is created on
+    pub fn root_device(&self) -> String {
+        if let Some(lvm) = &self.lvm {
+            return format!("/dev/mapper/{}-root", lvm.volume_group);
+        }
+        self.luks_device().unwrap_or_else(|| self.root_partition())
+    }
+
+    /// Swap volume, if the layout has one
+    pub fn swap_device(&self) -> Option<String> {
+        let lvm = self.lvm.as_ref()?;
+        lvm.swap_size.as_ref()?;
+        Some(format!("/dev/mapper/{}-swap", lvm.volume_group))
+    }
+
+    /// Kernel parameters locating the root filesystem and the resume device
+    ///
+    /// Backends add the parameters their initramfs needs to unlock the root.
+    pub fn root_params(&self) -> Vec<String> {
+        let mut params = vec![format!("root={}", self.root_device())];
+        if let Some(swap) = self.swap_device() {
+            params.push(format!("resume={}", swap));
+        }
+        params
+    }
+
+    /// Content of /etc/locale.gen: the chosen locale plus en_US as fallback
+    pub fn locale_gen(&self) -> String {
+        let charset = self.locale.split('.').nth(1).unwrap_or("UTF-8");
+        let mut content = format!("{} {}\n", self.locale, charset);
+        if self.locale != DEFAULT_LOCALE {
+            content.push_str("en_US.UTF-8 UTF-8\n");
+        }
+        content
+    }
+
+    /// Mount point of the ESP inside the target
+    pub fn esp_mount(&self) -> &'static str {
+        match self.bootloader {
+            Bootloader::SystemdBoot => "/boot",
+            _ => "/boot/efi",
+        }
+    }
+
+    /// Target root as string for command arguments
+    pub fn target(&self) -> String {
+        self.target_root.to_string_lossy().into_owned()
+    }
+
+    /// Path of `path` (absolute inside the target) as seen from the live system
+    pub fn target_path(&self, path: &str) -> String {
+        self.target_root
+            .join(path.trim_start_matches('/'))
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+/// Locale of the installed system matching a lunitool UI language
+pub fn locale_for_language(lang: &str) -> String {
+    match lang {
+        "de" => "de_DE.UTF-8".to_string(),
+        "en" => DEFAULT_LOCALE.to_string(),
+        other if other.contains('_') => format!("{}.UTF-8", other),
+        other => format!("{}_{}.UTF-8", other, other.to_uppercase()),
+    }
+}
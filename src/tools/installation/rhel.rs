@@ -0,0 +1,462 @@
+use anyhow::{anyhow, Result};
+use std::path::{Path, PathBuf};
+
+use crate::core::{
+    command::{CommandRunner, CommandSpec},
+    system_info::read_os_release,
+};
+
+use super::{
+    backend::{filter_installed, write_file_command, DistroBackend, DistroFamily},
+    chroot::ChrootSession,
+    device_uuid,
+    plan::{Bootloader, InstallPlan},
+    tpm2,
+};
+
+/// Packages installed into every RHEL-family system
+const BASE_PACKAGES: &[&str] = &[
+    "@core",
+    "kernel",
+    "dracut",
+    "cryptsetup",
+    "lvm2",
+    "NetworkManager",
+    "sudo",
+    "passwd",
+    "selinux-policy-targeted",
+];
+
+/// Packages of Fedora live media that an installed system does not need
+const LIVE_ONLY_PACKAGES: &[&str] = &[
+    "anaconda",
+    "anaconda-core",
+    "anaconda-gui",
+    "anaconda-live",
+    "anaconda-install-env-deps",
+    "anaconda-webui",
+    "initial-setup",
+    "livesys-scripts",
+    "dracut-live",
+];
+
+/// Name of the repository passed with `--repofrompath` for offline installations
+const OFFLINE_REPO_ID: &str = "lunitool-offline";
+
+/// Parameters of the dnf installation
+#[derive(Debug, Clone)]
+pub struct DnfOptions {
+    /// Distribution ID from os-release (`fedora`, `rocky`, `almalinux`, ...)
+    pub distro_id: String,
+    /// Value for `--releasever`
+    pub release: String,
+    /// Packages installed with `dnf install`
+    pub packages: Vec<String>,
+    /// Skip weak dependencies to keep the system small
+    pub no_weak_deps: bool,
+}
+
+impl DnfOptions {
+    /// Defaults for the given distribution release
+    pub fn new(distro_id: &str, release: &str) -> Self {
+        Self {
+            distro_id: distro_id.to_string(),
+            release: release.to_string(),
+            packages: BASE_PACKAGES.iter().map(|s| s.to_string()).collect(),
+            no_weak_deps: true,
+        }
+    }
+
+    /// Derive distribution and release from the live system
+    pub fn from_host() -> Self {
+        let os_release = read_os_release();
+        let id = os_release.get("ID").cloned().unwrap_or_else(|| "fedora".to_string());
+        // Enterprise releases are addressed by their major version (9.4 -> 9)
+        let release = os_release
+            .get("VERSION_ID")
+            .and_then(|v| v.split('.').next())
+            .map(str::to_string)
+            .unwrap_or_else(|| "41".to_string());
+        log::info!("dnf defaults from host: {} {}", id, release);
+        Self::new(&id, &release)
+    }
+
+    /// Directory below /boot/efi/EFI holding shim and GRUB
+    pub fn efi_vendor(&self) -> &str {
+        match self.distro_id.as_str() {
+            "rhel" => "redhat",
+            "centos" => "centos",
+            "rocky" => "rocky",
+            "almalinux" => "almalinux",
+            _ => "fedora",
+        }
+    }
+}
+
+/// Repositories of a local medium; RHEL DVDs split them into BaseOS and AppStream
+fn offline_repo_dirs(repository: &Path) -> Vec<PathBuf> {
+    let split: Vec<PathBuf> = ["BaseOS", "AppStream"]
+        .iter()
+        .map(|name| repository.join(name))
+        .filter(|dir| dir.join("repodata").is_dir())
+        .collect();
+    if split.is_empty() {
+        vec![repository.to_path_buf()]
+    } else {
+        split
+    }
+}
+
+/// Installs Fedora, RHEL and rebuilds such as Rocky or AlmaLinux with dnf
+#[derive(Debug, Clone)]
+pub struct RhelBackend {
+    pub options: DnfOptions,
+}
+
+impl RhelBackend {
+    pub fn new(options: DnfOptions) -> Self {
+        Self { options }
+    }
+
+    pub fn from_host() -> Self {
+        Self::new(DnfOptions::from_host())
+    }
+
+    fn dnf(&self, plan: &InstallPlan) -> CommandSpec {
+        let mut spec = CommandSpec::new("dnf").args([
+            format!("--installroot={}", plan.target()),
+            format!("--releasever={}", self.options.release),
+            "-y".to_string(),
+        ]);
+        if self.options.no_weak_deps {
+            spec = spec.arg("--setopt=install_weak_deps=False");
+        }
+        if let Some(repository) = plan.source.repository_dir() {
+            // Only the local medium is used; it is trusted without signature
+            spec = spec.args(["--disablerepo=*", "--nogpgcheck"]);
+            for (index, dir) in offline_repo_dirs(&repository).iter().enumerate() {
+                let id = format!("{}-{}", OFFLINE_REPO_ID, index);
+                spec = spec
+                    .arg(format!("--repofrompath={},{}", id, dir.display()))
+                    .arg(format!("--enablerepo={}", id));
+            }
+        }
+        spec
+    }
+
+    /// Bootloader packages for the plan
+    fn bootloader_packages(plan: &InstallPlan) -> Vec<String> {
+        let packages: &[&str] = if plan.efi {
+            &["grub2-efi-x64", "shim-x64", "efibootmgr", "grubby"]
+        } else {
+            &["grub2-pc", "grubby"]
+        };
+        packages.iter().map(|s| s.to_string()).collect()
+    }
+
+    /// glibc langpack providing the plan's locale (`de_DE.UTF-8` -> `glibc-langpack-de`)
+    fn langpack(plan: &InstallPlan) -> String {
+        let language = plan.locale.split(['_', '.']).next().unwrap_or("en");
+        format!("glibc-langpack-{}", language)
+    }
+}
+
+impl DistroBackend for RhelBackend {
+    fn name(&self) -> String {
+        format!("{} {} (dnf)", self.options.distro_id, self.options.release)
+    }
+
+    fn family(&self) -> DistroFamily {
+        DistroFamily::Rhel
+    }
+
+    fn validate_plan(&self, plan: &InstallPlan) -> Result<()> {
+        // The BLS setup relies on the distribution's prebuilt GRUB images reading a plain /boot
+        if plan.bootloader != Bootloader::Grub {
+            return Err(anyhow!("{} is only supported with GRUB and an unencrypted /boot", self.name()));
+        }
+        if plan.boot_usb.is_some() {
+            return Err(anyhow!("{} does not support a boot USB key with a detached LUKS header", self.name()));
+        }
+        // The signed grubx64.efi cannot open the root container; the plan puts /boot on its own partition
+        if plan.root_read_by_grub() {
+            return Err(anyhow!("{} needs an unencrypted /boot next to an encrypted root", self.name()));
+        }
+        Ok(())
+    }
+
+    fn bootstrap(&self, runner: &mut dyn CommandRunner, plan: &InstallPlan) -> Result<()> {
+        log::info!("Bootstrapping {} into {}", self.name(), plan.target());
+
+        let mut packages = self.options.packages.clone();
+        packages.extend(Self::bootloader_packages(plan));
+        packages.push(Self::langpack(plan));
+        packages.extend(plan.extra_packages.iter().cloned());
+
+        runner.run_checked(&self.dnf(plan).arg("install").args(&packages))?;
+        Ok(())
+    }
+
+    fn install_packages(
+        &self,
+        runner: &mut dyn CommandRunner,
+        _session: &ChrootSession,
+        plan: &InstallPlan,
+        packages: &[String],
+    ) -> Result<()> {
+        if packages.is_empty() {
+            return Ok(());
+        }
+        log::info!("Installing packages into target: {}", packages.join(" "));
+        runner.run_checked(&self.dnf(plan).arg("install").args(packages))?;
+        Ok(())
+    }
+
+    fn configure_system(
+        &self,
+        runner: &mut dyn CommandRunner,
+        session: &ChrootSession,
+        plan: &InstallPlan,
+    ) -> Result<()> {
+        log::info!("Configuring target system {}", plan.hostname);
+
+        runner.run_checked(&write_file_command(plan, "/etc/hostname", "0644", &format!("{}\n", plan.hostname)))?;
+        runner.run_checked(&write_file_command(plan, "/etc/locale.conf", "0644", &format!("LANG={}\n", plan.locale)))?;
+        runner.run_checked(&write_file_command(
+            plan,
+            "/etc/vconsole.conf",
+            "0644",
+            &format!("KEYMAP={}\n", plan.keyboard_layout),
+        ))?;
+        session.run(runner, &CommandSpec::new("ln").args([
+            "-sf".to_string(),
+            format!("/usr/share/zoneinfo/{}", plan.timezone),
+            "/etc/localtime".to_string(),
+        ]))?;
+        session.run(runner, &CommandSpec::new("systemctl").args(["enable", "NetworkManager"]))?;
+
+        // Files created from outside the target carry no SELinux labels yet
+        runner.run_checked(&write_file_command(plan, "/.autorelabel", "0644", ""))?;
+        Ok(())
+    }
+
+    fn configure_initramfs(
+        &self,
+        runner: &mut dyn CommandRunner,
+        session: &ChrootSession,
+        plan: &InstallPlan,
+    ) -> Result<()> {
+        if plan.tpm2.is_some() {
+            self.install_packages(runner, session, plan, &["tpm2-tss".to_string()])?;
+        }
+        super::configure_dracut(runner, session, plan)
+    }
+
+    fn kernel_cmdline(&self, runner: &mut dyn CommandRunner, plan: &InstallPlan) -> Result<String> {
+        let mut params = Vec::new();
+        if let Some(encryption) = &plan.encryption {
+            let luks_uuid = device_uuid(runner, &plan.root_partition())?;
+            params.push(format!("rd.luks.name={}={}", luks_uuid, encryption.mapper_name));
+            if plan.tpm2.is_some() {
+                params.push(format!("rd.luks.options={}={}", luks_uuid, tpm2::CRYPTTAB_OPTION));
+            }
+        }
+        if let Some(lvm) = &plan.lvm {
+            params.push(format!("rd.lvm.lv={}/root", lvm.volume_group));
+            if plan.swap_device().is_some() {
+                params.push(format!("rd.lvm.lv={}/swap", lvm.volume_group));
+            }
+        }
+        params.extend(plan.root_params());
+        params.push("rw".to_string());
+        Ok(params.join(" "))
+    }
+
+    fn install_bootloader(
+        &self,
+        runner: &mut dyn CommandRunner,
+        session: &ChrootSession,
+        plan: &InstallPlan,
+    ) -> Result<()> {
+        let cmdline = self.kernel_cmdline(runner, plan)?;
+        log::info!("Kernel command line: {}", cmdline);
+
+        // kernel-install reads /etc/kernel/cmdline for BLS entries of future kernels
+        runner.run_checked(&write_file_command(plan, "/etc/kernel/cmdline", "0644", &format!("{}\n", cmdline)))?;
+        let grub_defaults = format!(
+            "GRUB_TIMEOUT=5\n\
+             GRUB_DISTRIBUTOR=\"$(sed 's, release .*$,,g' /etc/system-release)\"\n\
+             GRUB_DEFAULT=saved\n\
+             GRUB_DISABLE_SUBMENU=true\n\
+             GRUB_TERMINAL_OUTPUT=\"console\"\n\
+             GRUB_CMDLINE_LINUX=\"{}\"\n\
+             GRUB_DISABLE_RECOVERY=\"true\"\n\
+             GRUB_ENABLE_BLSCFG=true\n",
+            cmdline
+        );
+        runner.run_checked(&write_file_command(plan, "/etc/default/grub", "0644", &grub_defaults))?;
+
+        if plan.efi {
+            // shim and GRUB come prebuilt from packages; only the firmware entry is missing
+            let loader = format!("\\EFI\\{}\\shimx64.efi", self.options.efi_vendor());
+            runner.run_checked(&CommandSpec::new("efibootmgr").args([
+                "--create",
+                "--disk",
+                &plan.target_disk,
+                "--part",
+                "1",
+                "--loader",
+                &loader,
+                "--label",
+                &self.options.distro_id,
+            ]))?;
+        } else {
+            session.run(runner, &CommandSpec::new("grub2-install").args(["--target=i386-pc", &plan.target_disk]))?;
+        }
+
+        session.run(runner, &CommandSpec::new("grub2-mkconfig").args(["-o", "/boot/grub2/grub.cfg"]))?;
+        // Entries of kernels installed during bootstrap still carry the live system's options
+        session.run(
+            runner,
+            &CommandSpec::new("grubby").args(["--update-kernel=ALL".to_string(), format!("--args={}", cmdline)]),
+        )?;
+        Ok(())
+    }
+
+    fn create_user(&self, runner: &mut dyn CommandRunner, session: &ChrootSession, plan: &InstallPlan) -> Result<()> {
+        let Some(username) = &plan.username else {
+            log::info!("No user account configured, skipping user creation.");
+            return Ok(());
+        };
+        log::info!("Creating user {}", username);
+
+        // Members of wheel may use sudo on RHEL-family systems out of the box
+        session.run(runner, &CommandSpec::new("useradd").args(["-m", "-s", "/bin/bash", "-G", "wheel", username]))?;
+        if let Some(password) = &plan.user_password {
+            // The password only ever travels through stdin
+            session.run(runner, &CommandSpec::new("chpasswd").stdin(format!("{}:{}\n", username, password.expose())))?;
+        }
+        Ok(())
+    }
+
+    fn live_only_packages(&self) -> Vec<String> {
+        LIVE_ONLY_PACKAGES.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn adopt_live_root(
+        &self,
+        runner: &mut dyn CommandRunner,
+        _session: &ChrootSession,
+        plan: &InstallPlan,
+        removals: &[String],
+    ) -> Result<()> {
+        let query = CommandSpec::new("rpm").args(["--root", &plan.target(), "-qa", "--qf", "%{NAME}\\n"]);
+        let installed = filter_installed(&runner.run_checked(&query)?, runner.is_dry_run(), removals);
+        if !installed.is_empty() {
+            log::info!("Removing live-only packages: {}", installed.join(" "));
+            runner.run_checked(&self.dnf(plan).args(["remove", "--noautoremove"]).args(&installed))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        app::InstallationConfig,
+        core::command::DryRunCommandRunner,
+        tools::installation::{backend::{command_summary, dry_run_session}, DEFAULT_TARGET_ROOT},
+    };
+
+    fn plan(efi: bool, encrypted: bool) -> InstallPlan {
+        let config = InstallationConfig {
+            target_disk: Some("/dev/vda".to_string()),
+            luks_password: encrypted.then(|| "correct horse".into()),
+            username: Some("alice".to_string()),
+            user_password: Some("battery staple".into()),
+            ..Default::default()
+        };
+        InstallPlan::from_config(&config, Path::new(DEFAULT_TARGET_ROOT), efi, DistroFamily::Rhel).unwrap()
+    }
+
+    #[test]
+    fn installs_uefi_with_encrypted_root_from_a_plain_boot() {
+        let backend = RhelBackend::new(DnfOptions::new("fedora", "41"));
+        let plan = plan(true, true);
+        backend.validate_plan(&plan).unwrap();
+        assert!(!plan.root_read_by_grub());
+        assert_eq!(plan.esp_partition().as_deref(), Some("/dev/vda1"));
+        assert_eq!(plan.boot_device().as_deref(), Some("/dev/vda2"));
+        assert_eq!(plan.root_partition(), "/dev/vda3");
+
+        let mut runner = DryRunCommandRunner::new();
+        let session = dry_run_session(&plan);
+        runner.respond_to("blkid -s UUID -o value /dev/vda3", "2f5c0e4a-luks\n");
+        super::super::partition_disk(&mut runner, &plan).unwrap();
+        super::super::format_partitions(&mut runner, &plan).unwrap();
+        super::super::mount_partitions(&mut runner, &plan).unwrap();
+        backend.install_bootloader(&mut runner, &session, &plan).unwrap();
+        let history = runner.rendered_history();
+        for command in [
+            "parted -s /dev/vda mkpart primary ext4 513MiB 1537MiB",
+            "parted -s /dev/vda mkpart primary 1537MiB 100%",
+            "mkfs.ext4 -F /dev/vda2",
+            "mount /dev/vda2 /mnt/lunitool/boot",
+            "mount /dev/vda1 /mnt/lunitool/boot/efi",
+        ] {
+            assert!(history.iter().any(|c| c == command), "{} missing in {:?}", command, history);
+        }
+        assert!(history.iter().any(|c| c.contains("rd.luks.name=2f5c0e4a-luks=cryptroot")));
+        assert!(!history.iter().any(|c| c.contains("GRUB_ENABLE_CRYPTODISK")));
+    }
+
+    #[test]
+    fn installs_with_dnf_and_bls_grub() {
+        let backend = RhelBackend::new(DnfOptions::new("fedora", "41"));
+        let plan = plan(false, true);
+        let mut runner = DryRunCommandRunner::new();
+        let session = dry_run_session(&plan);
+        runner.respond_to("blkid -s UUID -o value /dev/vda2", "2f5c0e4a-luks\n");
+        backend.validate_plan(&plan).unwrap();
+        backend.bootstrap(&mut runner, &plan).unwrap();
+        backend.configure_system(&mut runner, &session, &plan).unwrap();
+        backend.configure_initramfs(&mut runner, &session, &plan).unwrap();
+        backend.install_bootloader(&mut runner, &session, &plan).unwrap();
+        backend.create_user(&mut runner, &session, &plan).unwrap();
+
+        assert_eq!(
+            command_summary(&plan, runner.history()),
+            [
+                "dnf",
+                "write /etc/hostname",
+                "write /etc/locale.conf",
+                "write /etc/vconsole.conf",
+                "chroot ln -sf",
+                "chroot systemctl enable",
+                "write /.autorelabel",
+                "write /etc/dracut.conf.d/lunitool.conf",
+                "chroot dracut --regenerate-all",
+                "blkid",
+                "write /etc/kernel/cmdline",
+                "write /etc/default/grub",
+                "chroot grub2-install --target=i386-pc",
+                "chroot grub2-mkconfig -o",
+                "chroot grubby --update-kernel=ALL",
+                "chroot useradd -m",
+                "chroot chpasswd",
+            ]
+        );
+        let history = runner.rendered_history();
+        assert!(history[0].starts_with(
+            "dnf --installroot=/mnt/lunitool --releasever=41 -y --setopt=install_weak_deps=False install @core kernel"
+        ));
+        assert!(history[0].ends_with(" grub2-pc grubby glibc-langpack-en"));
+        assert!(history[12].ends_with("grub2-install --target=i386-pc /dev/vda"));
+        assert!(history[14].ends_with(
+            "'--args=rd.luks.name=2f5c0e4a-luks=cryptroot rd.lvm.lv=vg/root rd.lvm.lv=vg/swap \
+             root=/dev/mapper/vg-root resume=/dev/mapper/vg-swap rw'"
+        ));
+        assert_eq!(history[15], "chroot /mnt/lunitool useradd -m -s /bin/bash -G wheel alice");
+    }
+}
@@ -0,0 +1,275 @@
+use anyhow::{anyhow, Result};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    core::{
+        command::{CommandRunner, CommandSpec},
+        secret::SecretString,
+    },
+    tools::key_manager::{self, SigningKey, SECURE_BOOT_KEY_DIR},
+};
+
+use super::plan::{Bootloader, InstallPlan};
+
+/// Owner GUID of the signature lists generated for custom keys
+const KEY_OWNER_GUID: &str = "77fa9abd-0359-4d32-bd60-28f4e78f784b";
+/// Global EFI variable telling whether the firmware accepts a new PK without authentication
+const SETUP_MODE_VAR: &str = "/sys/firmware/efi/efivars/SetupMode-8be4df61-93ca-11d2-aa0d-00e098032b8c";
+/// Loaders of the Microsoft signed shim chain, which must keep their original signature
+/// (the fallback `BOOTX64.EFI` is a copy of shim)
+const SHIM_CHAIN: &[&str] = &["shim", "mm", "fb", "bootx64"];
+/// Characters of the MokManager password: lowercase letters and digits that sit
+/// on the same keys of US and German layouts and cannot be mistaken for each other
+const MOK_PASSWORD_ALPHABET: &[u8] = b"abcdefghijkmnpqrstuvwx23456789";
+/// Length of the MokManager password
+const MOK_PASSWORD_LEN: usize = 10;
+
+/// How the installed system boots with Secure Boot enabled
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum SecureBootMode {
+    /// Nothing is signed; Secure Boot has to stay disabled in the firmware
+    #[default]
+    Disabled,
+    /// Distribution signed shim, GRUB and kernel, trusted through Microsoft's keys
+    Shim,
+    /// shim plus a generated Machine Owner Key enrolled with `mokutil --import`
+    Mok,
+    /// Own PK, KEK and db keys replacing the vendor keys of the firmware
+    CustomKeys,
+}
+
+impl SecureBootMode {
+    /// All modes in the order the wizard offers them
+    pub fn all() -> [SecureBootMode; 4] {
+        [SecureBootMode::Disabled, SecureBootMode::Shim, SecureBootMode::Mok, SecureBootMode::CustomKeys]
+    }
+
+    /// Locale key of the mode's label
+    pub fn title_key(&self) -> &'static str {
+        match self {
+            SecureBootMode::Disabled => "SECURE_BOOT_MODE_DISABLED",
+            SecureBootMode::Shim => "SECURE_BOOT_MODE_SHIM",
+            SecureBootMode::Mok => "SECURE_BOOT_MODE_MOK",
+            SecureBootMode::CustomKeys => "SECURE_BOOT_MODE_CUSTOM",
+        }
+    }
+
+    /// Whether the mode boots through the distribution's shim
+    pub fn uses_shim(&self) -> bool {
+        matches!(self, SecureBootMode::Shim | SecureBootMode::Mok)
+    }
+}
+
+impl std::fmt::Display for SecureBootMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SecureBootMode::Disabled => write!(f, "disabled"),
+            SecureBootMode::Shim => write!(f, "shim (distribution keys)"),
+            SecureBootMode::Mok => write!(f, "shim with Machine Owner Key"),
+            SecureBootMode::CustomKeys => write!(f, "custom PK/KEK/db keys"),
+        }
+    }
+}
+
+/// Reject Secure Boot modes the plan cannot boot with
+pub fn validate(plan: &InstallPlan) -> Result<()> {
+    if plan.secure_boot == SecureBootMode::Disabled {
+        return Ok(());
+    }
+    if !plan.efi {
+        return Err(anyhow!("Secure Boot requires a system booted in UEFI mode"));
+    }
+    if plan.secure_boot.uses_shim() && plan.bootloader == Bootloader::SystemdBoot {
+        return Err(anyhow!("Secure Boot with shim is only set up for GRUB; use custom keys with systemd-boot"));
+    }
+    if plan.secure_boot == SecureBootMode::Mok && plan.mok_password.is_none() {
+        return Err(anyhow!("MOK enrollment needs a password to confirm it in MokManager"));
+    }
+    Ok(())
+}
+
+/// Sign the boot chain of the installed system for the plan's Secure Boot mode
+///
+/// Expects the bootloader, kernels and UKIs to be in place. Keys are generated
+/// into the target so later kernels can be signed with the same keys.
+pub fn apply(runner: &mut dyn CommandRunner, plan: &InstallPlan) -> Result<()> {
+    match plan.secure_boot {
+        SecureBootMode::Disabled => Ok(()),
+        SecureBootMode::Shim => {
+            log::info!("Secure Boot relies on the distribution signed shim, nothing to sign.");
+            Ok(())
+        }
+        SecureBootMode::Mok => setup_mok(runner, plan),
+        SecureBootMode::CustomKeys => setup_custom_keys(runner, plan),
+    }
+}
+
+/// One-time password confirming the MOK enrollment in MokManager
+///
+/// MokManager reads it before any keyboard layout is loaded, so it only uses
+/// characters that are typed the same on a US layout.
+pub fn generate_mok_password() -> Result<SecretString> {
+    // 256 is no multiple of the alphabet length; rejecting the top bytes keeps the choice uniform
+    let limit = (256 / MOK_PASSWORD_ALPHABET.len() * MOK_PASSWORD_ALPHABET.len()) as u8;
+    let mut password = String::with_capacity(MOK_PASSWORD_LEN);
+    while password.len() < MOK_PASSWORD_LEN {
+        let mut byte = [0u8; 1];
+        getrandom::getrandom(&mut byte).map_err(|e| anyhow!("No randomness for the MOK password: {}", e))?;
+        if byte[0] < limit {
+            password.push(MOK_PASSWORD_ALPHABET[byte[0] as usize % MOK_PASSWORD_ALPHABET.len()] as char);
+        }
+    }
+    Ok(SecretString::from(password))
+}
+
+/// Host directory of the target's key store
+fn key_dir(plan: &InstallPlan) -> PathBuf {
+    PathBuf::from(plan.target_path(SECURE_BOOT_KEY_DIR))
+}
+
+fn setup_mok(runner: &mut dyn CommandRunner, plan: &InstallPlan) -> Result<()> {
+    let mok = key_manager::generate_signing_key(runner, &key_dir(plan), "MOK", &format!("{} Machine Owner Key", plan.hostname))?;
+    sign_boot_chain(runner, plan, &mok)?;
+
+    // MokManager asks for this password on the next boot to confirm the enrollment
+    let password = plan
+        .mok_password
+        .as_ref()
+        .map(SecretString::expose)
+        .ok_or_else(|| anyhow!("MOK enrollment needs a password to confirm it in MokManager"))?;
+    log::info!("Queueing {} for enrollment by MokManager", mok.der.display());
+    runner.run_checked(
+        &CommandSpec::new("mokutil")
+            .arg("--import")
+            .arg(mok.der.to_string_lossy())
+            .stdin(format!("{}\n{}\n", password, password)),
+    )?;
+    Ok(())
+}
+
+fn setup_custom_keys(runner: &mut dyn CommandRunner, plan: &InstallPlan) -> Result<()> {
+    let dir = key_dir(plan);
+    let owner = &plan.hostname;
+    let pk = key_manager::generate_signing_key(runner, &dir, "PK", &format!("{} Platform Key", owner))?;
+    let kek = key_manager::generate_signing_key(runner, &dir, "KEK", &format!("{} Key Exchange Key", owner))?;
+    let db = key_manager::generate_signing_key(runner, &dir, "db", &format!("{} Signature Database Key", owner))?;
+
+    // PK signs itself, KEK is signed by PK and db by KEK
+    let mut updates = Vec::new();
+    for (key, signer) in [(&db, &kek), (&kek, &pk), (&pk, &pk)] {
+        let esl = key_manager::create_signature_list(runner, key, KEY_OWNER_GUID)?;
+        let auth = key_manager::sign_signature_list(runner, signer, &key.name, &esl)?;
+        updates.push((key.name.clone(), auth));
+    }
+    sign_boot_chain(runner, plan, &db)?;
+
+    // Copies on the ESP can be enrolled from the firmware setup or KeyTool
+    let esp_keys = plan.target_path(&format!("{}/EFI/{}/keys", plan.esp_mount(), plan.bootloader_id));
+    runner.run_checked(&CommandSpec::new("mkdir").args(["-p", &esp_keys]))?;
+    for key in [&pk, &kek, &db] {
+        runner.run_checked(&CommandSpec::new("cp").arg(key.der.to_string_lossy()).arg(&esp_keys))?;
+    }
+    for (_, auth) in &updates {
+        runner.run_checked(&CommandSpec::new("cp").arg(auth.to_string_lossy()).arg(&esp_keys))?;
+    }
+
+    if !runner.is_dry_run() && !firmware_in_setup_mode() {
+        log::warn!("Firmware is not in Setup Mode; enroll the keys from {} manually", esp_keys);
+        return Ok(());
+    }
+    // db and KEK first: once PK is written the firmware leaves Setup Mode
+    for (variable, auth) in &updates {
+        log::info!("Enrolling {} into the firmware", variable);
+        runner.run_checked(&CommandSpec::new("efi-updatevar").arg("-f").arg(auth.to_string_lossy()).arg(variable))?;
+    }
+    Ok(())
+}
+
+/// Whether the firmware accepts unauthenticated key updates
+fn firmware_in_setup_mode() -> bool {
+    // Four attribute bytes followed by the one byte value
+    fs::read(SETUP_MODE_VAR)
+        .map(|data| data.get(4) == Some(&1))
+        .unwrap_or(false)
+}
+
+/// Sign kernels, UKIs and bootloader binaries of the target with `key`
+fn sign_boot_chain(runner: &mut dyn CommandRunner, plan: &InstallPlan, key: &SigningKey) -> Result<()> {
+    let binaries = signing_targets(plan, runner.is_dry_run())?;
+    if binaries.is_empty() {
+        return Err(anyhow!("No kernels or EFI binaries found to sign in {}", plan.target()));
+    }
+    for binary in binaries {
+        key_manager::sign_efi_binary(runner, key, &binary)?;
+    }
+    Ok(())
+}
+
+/// Host paths of the boot artifacts that need a signature
+///
+/// In dry-run mode nothing is installed, so the usual locations are assumed.
+fn signing_targets(plan: &InstallPlan, dry_run: bool) -> Result<Vec<PathBuf>> {
+    let boot = PathBuf::from(plan.target_path("/boot"));
+    let efi = PathBuf::from(plan.target_path(&format!("{}/EFI", plan.esp_mount())));
+    if dry_run {
+        let loader = match plan.bootloader {
+            Bootloader::SystemdBoot => "systemd/systemd-bootx64.efi".to_string(),
+            _ => format!("{}/grubx64.efi", plan.bootloader_id),
+        };
+        return Ok(vec![boot.join("vmlinuz-<kernel>"), efi.join(loader)]);
+    }
+
+    let mut targets = Vec::new();
+    if let Ok(entries) = fs::read_dir(&boot) {
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let path = entry.path();
+            let is_kernel = entry.file_name().to_string_lossy().starts_with("vmlinuz");
+            if is_kernel && !path.is_symlink() && path.is_file() {
+                targets.push(path);
+            }
+        }
+    }
+    collect_efi_binaries(&efi, plan.secure_boot.uses_shim(), &mut targets)?;
+    targets.sort();
+    targets.dedup();
+    Ok(targets)
+}
+
+/// Recursively collect `*.efi` files below `dir`, skipping the shim chain if it stays in use
+fn collect_efi_binaries(dir: &Path, keep_shim: bool, targets: &mut Vec<PathBuf>) -> Result<()> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Ok(());
+    };
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_efi_binaries(&path, keep_shim, targets)?;
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy().to_lowercase();
+        if !name.ends_with(".efi") {
+            continue;
+        }
+        if keep_shim && SHIM_CHAIN.iter().any(|prefix| name.starts_with(prefix)) {
+            continue;
+        }
+        targets.push(path);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mok_password_uses_layout_safe_characters() {
+        let first = generate_mok_password().unwrap();
+        assert_eq!(first.expose().len(), MOK_PASSWORD_LEN);
+        assert!(first.expose().bytes().all(|c| MOK_PASSWORD_ALPHABET.contains(&c)));
+        assert_ne!(first.expose(), generate_mok_password().unwrap().expose());
+    }
+}
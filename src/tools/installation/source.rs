@@ -0,0 +1,220 @@
+use anyhow::{anyhow, Result};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::core::command::{CommandRunner, CommandSpec};
+
+use super::{
+    backend::{write_file_command, DistroBackend},
+    chroot::ChrootSession,
+    plan::InstallPlan,
+};
+
+/// Mount point of a distribution ISO on the live system
+pub const IMAGE_MOUNT: &str = "/run/lunitool/iso";
+/// Where the local repository is bind mounted inside the target during installation
+pub const TARGET_REPO_DIR: &str = "/mnt/lunitool-repo";
+
+/// Squashfs images of known live media, as layers applied in order
+const SQUASHFS_LAYOUTS: &[&[&str]] = &[
+    // Ubuntu up to 23.04
+    &["casper/filesystem.squashfs"],
+    // Ubuntu 23.10 and later ship a layered root; the `.live` layer is skipped
+    &["casper/minimal.squashfs", "casper/minimal.standard.squashfs"],
+    // Debian live
+    &["live/filesystem.squashfs"],
+    // Arch ISO
+    &["arch/x86_64/airootfs.sfs"],
+    // Fedora live
+    &["LiveOS/squashfs.img"],
+];
+
+/// Package lists some live media ship for their own installers
+const REMOVAL_MANIFESTS: &[&str] = &[
+    "casper/filesystem.manifest-remove",
+    "live/filesystem.packages-remove",
+];
+
+/// Where the packages of the target system come from
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum InstallSource {
+    /// The distribution's online mirrors
+    #[default]
+    Network,
+    /// A local apt, pacman or dnf repository directory
+    LocalRepository(PathBuf),
+    /// A distribution ISO file or a directory the ISO is mounted at
+    Image(PathBuf),
+}
+
+impl InstallSource {
+    /// Build the source from the optional config entries; an image wins over a repository
+    pub fn from_config(repository: Option<&Path>, image: Option<&Path>) -> Self {
+        match (image, repository) {
+            (Some(image), _) => InstallSource::Image(image.to_path_buf()),
+            (None, Some(repository)) => InstallSource::LocalRepository(repository.to_path_buf()),
+            (None, None) => InstallSource::Network,
+        }
+    }
+
+    /// Whether the installation works without network access
+    pub fn is_offline(&self) -> bool {
+        !matches!(self, InstallSource::Network)
+    }
+
+    /// Directory holding the ISO contents on the live system
+    pub fn image_root(&self) -> Option<PathBuf> {
+        match self {
+            InstallSource::Image(path) if path.is_dir() => Some(path.clone()),
+            InstallSource::Image(_) => Some(PathBuf::from(IMAGE_MOUNT)),
+            _ => None,
+        }
+    }
+
+    /// Local package repository on the live system
+    ///
+    /// For images the ISO root doubles as repository if it has one: Debian and
+    /// Ubuntu media carry `dists/` and `pool/`, RHEL DVDs `BaseOS/` and `AppStream/`.
+    pub fn repository_dir(&self) -> Option<PathBuf> {
+        match self {
+            InstallSource::Network => None,
+            InstallSource::LocalRepository(dir) => Some(dir.clone()),
+            InstallSource::Image(_) => self.image_root().filter(|root| image_has_repository(root)),
+        }
+    }
+}
+
+/// Whether the mounted image carries a package repository
+///
+/// An image that is not mounted (dry run) cannot be inspected and is assumed to have one.
+fn image_has_repository(image_root: &Path) -> bool {
+    if !image_root.exists() {
+        return true;
+    }
+    let has_pacman_db = fs::read_dir(image_root)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .any(|entry| entry.file_name().to_string_lossy().ends_with(".db"))
+        })
+        .unwrap_or(false);
+    has_pacman_db
+        || image_root.join("dists").is_dir()
+        || image_root.join("repodata").is_dir()
+        || image_root.join("BaseOS/repodata").is_dir()
+}
+
+impl std::fmt::Display for InstallSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InstallSource::Network => write!(f, "network mirrors"),
+            InstallSource::LocalRepository(dir) => write!(f, "local repository {}", dir.display()),
+            InstallSource::Image(path) => write!(f, "image {}", path.display()),
+        }
+    }
+}
+
+/// Loop mount an ISO file read-only; directories are used as they are
+pub fn mount_image(runner: &mut dyn CommandRunner, source: &InstallSource) -> Result<()> {
+    let InstallSource::Image(path) = source else {
+        return Ok(());
+    };
+    if path.is_dir() {
+        return Ok(());
+    }
+    if !runner.is_dry_run() && !path.exists() {
+        return Err(anyhow!("Installation image {} not found", path.display()));
+    }
+    log::info!("Mounting installation image {} at {}", path.display(), IMAGE_MOUNT);
+    runner.run_checked(&CommandSpec::new("mkdir").args(["-p", IMAGE_MOUNT]))?;
+    runner.run_checked(&CommandSpec::new("mount").args(["-o", "loop,ro"]).arg(path.to_string_lossy()).arg(IMAGE_MOUNT))?;
+    Ok(())
+}
+
+/// Unmount an ISO mounted by `mount_image`
+pub fn unmount_image(runner: &mut dyn CommandRunner, source: &InstallSource) -> Result<()> {
+    match source {
+        InstallSource::Image(path) if !path.is_dir() => {
+            runner.run_checked(&CommandSpec::new("umount").arg(IMAGE_MOUNT))?;
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Squashfs layers of the live root filesystem below `image_root`
+///
+/// In dry-run mode nothing is mounted, so the first known layout is assumed.
+pub fn find_squashfs_layers(image_root: &Path, dry_run: bool) -> Result<Vec<PathBuf>> {
+    for layout in SQUASHFS_LAYOUTS {
+        let layers: Vec<PathBuf> = layout.iter().map(|layer| image_root.join(layer)).collect();
+        if layers.iter().all(|layer| layer.exists()) {
+            return Ok(layers);
+        }
+    }
+    if dry_run {
+        return Ok(vec![image_root.join(SQUASHFS_LAYOUTS[0][0])]);
+    }
+    Err(anyhow!("No live root filesystem found in {}", image_root.display()))
+}
+
+/// Packages the image itself marks as live-only
+fn manifest_removals(image_root: &Path) -> Vec<String> {
+    REMOVAL_MANIFESTS
+        .iter()
+        .filter_map(|manifest| fs::read_to_string(image_root.join(manifest)).ok())
+        .flat_map(|content| content.split_whitespace().map(str::to_string).collect::<Vec<_>>())
+        .collect()
+}
+
+/// Extract the live root filesystem of the image into the target
+///
+/// Expects the image to be mounted. Live-only packages are removed afterwards
+/// by `finish_image_root`, once the API filesystems are bound into the target.
+pub fn extract_image_root(runner: &mut dyn CommandRunner, plan: &InstallPlan) -> Result<()> {
+    let image_root = plan
+        .source
+        .image_root()
+        .ok_or_else(|| anyhow!("Installation source is not an image"))?;
+
+    for layer in find_squashfs_layers(&image_root, runner.is_dry_run())? {
+        log::info!("Extracting {} into {}", layer.display(), plan.target());
+        runner.run_checked(
+            &CommandSpec::new("unsquashfs")
+                .args(["-f", "-d", &plan.target()])
+                .arg(layer.to_string_lossy()),
+        )?;
+    }
+    Ok(())
+}
+
+/// Remove the live system's leftovers from an extracted image root
+pub fn finish_image_root(
+    runner: &mut dyn CommandRunner,
+    session: &ChrootSession,
+    plan: &InstallPlan,
+    backend: &dyn DistroBackend,
+) -> Result<()> {
+    let mut removals = backend.live_only_packages();
+    if let Some(image_root) = plan.source.image_root() {
+        removals.extend(manifest_removals(&image_root));
+    }
+    removals.sort();
+    removals.dedup();
+    backend.adopt_live_root(runner, session, plan, &removals)?;
+
+    // Every live boot shares the image's machine id; an empty file makes systemd generate one
+    runner.run_checked(&write_file_command(plan, "/etc/machine-id", "0444", ""))?;
+    Ok(())
+}
+
+/// Make the local repository reachable from inside the target
+pub fn bind_repository(runner: &mut dyn CommandRunner, session: &mut ChrootSession, plan: &InstallPlan) -> Result<()> {
+    let Some(repository) = plan.source.repository_dir() else {
+        return Ok(());
+    };
+    log::info!("Binding local repository {} to {}", repository.display(), TARGET_REPO_DIR);
+    session.bind(runner, &repository, TARGET_REPO_DIR, true)
+}
@@ -0,0 +1,178 @@
+use anyhow::{anyhow, Result};
+use std::fs;
+
+use crate::core::command::{CommandRunner, CommandSpec};
+
+use super::{
+    backend::write_file_command,
+    chroot::ChrootSession,
+    plan::{InstallPlan, UkiBuilder},
+};
+
+/// EFI stub the objcopy builder adds the kernel sections to
+const EFI_STUB: &str = "/usr/lib/systemd/boot/efi/linuxx64.efi.stub";
+/// Kernel command line file read by kernel-install and ukify
+const KERNEL_CMDLINE: &str = "/etc/kernel/cmdline";
+
+/// One kernel booted by systemd-boot, paths relative to the ESP
+#[derive(Debug, Clone)]
+pub struct BootEntry {
+    /// File name of the entry without extension, e.g. `arch` or `debian-6.12.9-amd64`
+    pub id: String,
+    pub title: String,
+    /// Kernel image, e.g. `/vmlinuz-linux`
+    pub linux: String,
+    /// Initramfs images, microcode first
+    pub initrd: Vec<String>,
+}
+
+impl BootEntry {
+    /// Loader entry in the Boot Loader Specification format
+    fn render(&self, cmdline: &str) -> String {
+        let mut content = format!("title {}\nlinux {}\n", self.title, self.linux);
+        for initrd in &self.initrd {
+            content.push_str(&format!("initrd {}\n", initrd));
+        }
+        content.push_str(&format!("options {}\n", cmdline));
+        content
+    }
+}
+
+/// Kernel versions installed in the target, from /usr/lib/modules
+///
+/// Nothing is installed in dry-run mode, so a placeholder version is returned.
+pub fn installed_kernels(runner: &dyn CommandRunner, plan: &InstallPlan) -> Result<Vec<String>> {
+    if runner.is_dry_run() {
+        return Ok(vec!["<kernel-version>".to_string()]);
+    }
+    let modules = plan.target_path("/usr/lib/modules");
+    let mut versions: Vec<String> = fs::read_dir(&modules)
+        .map_err(|e| anyhow!("Failed to list kernels in {}: {}", modules, e))?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().join("modules.dep").exists())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .collect();
+    versions.sort();
+    if versions.is_empty() {
+        return Err(anyhow!("No kernel installed in {}", plan.target()));
+    }
+    Ok(versions)
+}
+
+/// Install systemd-boot to the ESP and make it boot `entries`
+///
+/// With a UKI builder in the plan, every entry becomes a unified kernel image
+/// in EFI/Linux, which systemd-boot picks up without a loader entry.
+pub fn install_systemd_boot(
+    runner: &mut dyn CommandRunner,
+    session: &ChrootSession,
+    plan: &InstallPlan,
+    cmdline: &str,
+    entries: &[BootEntry],
+) -> Result<()> {
+    if !plan.efi {
+        return Err(anyhow!("systemd-boot requires UEFI boot"));
+    }
+    let default = entries.first().ok_or_else(|| anyhow!("No boot entries to install"))?;
+    let esp = plan.esp_mount();
+    log::info!("Installing systemd-boot to {}", esp);
+
+    session.run(runner, &CommandSpec::new("bootctl").arg(format!("--esp-path={}", esp)).arg("install"))?;
+    runner.run_checked(&write_file_command(plan, KERNEL_CMDLINE, "0644", &format!("{}\n", cmdline)))?;
+
+    let default_file = match plan.uki {
+        Some(builder) => {
+            for entry in entries {
+                build_uki(runner, session, plan, entry, builder)?;
+            }
+            format!("{}.efi", default.id)
+        }
+        None => {
+            for entry in entries {
+                let path = format!("{}/loader/entries/{}.conf", esp, entry.id);
+                runner.run_checked(&write_file_command(plan, &path, "0644", &entry.render(cmdline)))?;
+            }
+            format!("{}.conf", default.id)
+        }
+    };
+
+    let loader = format!("default {}\ntimeout 3\neditor no\n", default_file);
+    runner.run_checked(&write_file_command(plan, &format!("{}/loader/loader.conf", esp), "0644", &loader))?;
+    Ok(())
+}
+
+/// ESP path of the unified kernel image of `entry`, absolute inside the target
+pub fn uki_path(plan: &InstallPlan, entry: &BootEntry) -> String {
+    format!("{}/EFI/Linux/{}.efi", plan.esp_mount(), entry.id)
+}
+
+/// Combine kernel, initramfs and command line of `entry` into one signable EFI binary
+fn build_uki(
+    runner: &mut dyn CommandRunner,
+    session: &ChrootSession,
+    plan: &InstallPlan,
+    entry: &BootEntry,
+    builder: UkiBuilder,
+) -> Result<()> {
+    let esp = plan.esp_mount();
+    let output = uki_path(plan, entry);
+    let linux = format!("{}{}", esp, entry.linux);
+    let initrds: Vec<String> = entry.initrd.iter().map(|initrd| format!("{}{}", esp, initrd)).collect();
+    log::info!("Building unified kernel image {} with {:?}", output, builder);
+
+    session.run(runner, &CommandSpec::new("mkdir").args(["-p", &format!("{}/EFI/Linux", esp)]))?;
+    let spec = match builder {
+        UkiBuilder::Ukify => {
+            let mut ukify = CommandSpec::new("ukify").args([
+                "build".to_string(),
+                format!("--linux={}", linux),
+                format!("--cmdline=@{}", KERNEL_CMDLINE),
+                "--os-release=@/etc/os-release".to_string(),
+                format!("--output={}", output),
+            ]);
+            for initrd in &initrds {
+                ukify = ukify.arg(format!("--initrd={}", initrd));
+            }
+            ukify
+        }
+        UkiBuilder::Objcopy => {
+            // objcopy takes a single initrd section; microcode and initramfs are concatenated
+            let initrd = match initrds.as_slice() {
+                [single] => single.clone(),
+                _ => {
+                    let combined = format!("/tmp/{}.initrd", entry.id);
+                    session.run(
+                        runner,
+                        &CommandSpec::new("sh")
+                            .args(["-c", &format!("cat \"$@\" > {}", combined), "sh"])
+                            .args(&initrds),
+                    )?;
+                    combined
+                }
+            };
+            // Section addresses of the classic systemd stub recipe, far enough apart for typical kernels
+            CommandSpec::new("objcopy").args([
+                "--add-section".to_string(),
+                ".osrel=/etc/os-release".to_string(),
+                "--change-section-vma".to_string(),
+                ".osrel=0x20000".to_string(),
+                "--add-section".to_string(),
+                format!(".cmdline={}", KERNEL_CMDLINE),
+                "--change-section-vma".to_string(),
+                ".cmdline=0x30000".to_string(),
+                "--add-section".to_string(),
+                format!(".linux={}", linux),
+                "--change-section-vma".to_string(),
+                ".linux=0x2000000".to_string(),
+                "--add-section".to_string(),
+                format!(".initrd={}", initrd),
+                "--change-section-vma".to_string(),
+                ".initrd=0x3000000".to_string(),
+                EFI_STUB.to_string(),
+                output,
+            ])
+        }
+    };
+    session.run(runner, &spec)?;
+    Ok(())
+}
@@ -0,0 +1,183 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::core::{
+    command::{CommandRunner, CommandSpec},
+    secret::SecretString,
+};
+
+use super::{plan::InstallPlan, secure_boot::SecureBootMode};
+
+/// PCR 7 measures the Secure Boot state and keys, stable across kernel updates
+pub const DEFAULT_TPM2_PCRS: &[u32] = &[7];
+/// TPM used for enrollment when none is configured
+pub const DEFAULT_TPM2_DEVICE: &str = "auto";
+
+/// TPM2 auto-unlock settings from the configuration file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Tpm2Settings {
+    /// PCRs the key is sealed against, e.g. `[0, 7]`
+    #[serde(default = "default_pcrs")]
+    pub pcrs: Vec<u32>,
+    /// Require a PIN in addition to the TPM at boot
+    #[serde(default)]
+    pub with_pin: bool,
+    /// TPM used for enrollment: `auto`, a device node, or a TCTI such as
+    /// `swtpm:path=/run/swtpm/sock` for development without a physical TPM
+    #[serde(default = "default_device")]
+    pub device: String,
+}
+
+fn default_pcrs() -> Vec<u32> {
+    DEFAULT_TPM2_PCRS.to_vec()
+}
+
+fn default_device() -> String {
+    DEFAULT_TPM2_DEVICE.to_string()
+}
+
+impl Default for Tpm2Settings {
+    fn default() -> Self {
+        Self {
+            pcrs: default_pcrs(),
+            with_pin: false,
+            device: default_device(),
+        }
+    }
+}
+
+/// Parse a PCR list as typed in the wizard: `7`, `0+7` or `0,7`
+pub fn parse_pcrs(value: &str) -> Result<Vec<u32>> {
+    let pcrs = value
+        .split(|c: char| c == '+' || c == ',' || c.is_whitespace())
+        .filter(|pcr| !pcr.is_empty())
+        .map(|pcr| pcr.parse::<u32>().ok().filter(|pcr| *pcr <= 23).ok_or_else(|| anyhow!("Invalid TPM2 PCR {}", pcr)))
+        .collect::<Result<Vec<_>>>()?;
+    if pcrs.is_empty() {
+        return Err(anyhow!("No TPM2 PCR given"));
+    }
+    Ok(pcrs)
+}
+
+/// Whether enrolling Secure Boot keys changes a PCR the TPM key is sealed against
+///
+/// MokManager and the firmware enroll the new keys on the next boot, after the
+/// TPM key was sealed, so PCR 7 no longer matches and the passphrase is asked
+/// until the TPM is enrolled again.
+pub fn breaks_on_first_boot(pcrs: &[u32], secure_boot: SecureBootMode) -> bool {
+    pcrs.contains(&7) && matches!(secure_boot, SecureBootMode::Mok | SecureBootMode::CustomKeys)
+}
+
+/// TPM2 enrollment of the root container
+#[derive(Debug, Clone)]
+pub struct Tpm2Plan {
+    pub pcrs: Vec<u32>,
+    /// PIN asked for at boot, if the policy requires one
+    pub pin: Option<SecretString>,
+    /// TPM used for enrollment; the installed system always uses `auto`
+    pub device: String,
+}
+
+impl Tpm2Plan {
+    /// Resolve the settings and the PIN collected by the wizard
+    pub fn from_settings(settings: &Tpm2Settings, pin: Option<&SecretString>) -> Result<Self> {
+        if settings.pcrs.iter().any(|pcr| *pcr > 23) {
+            return Err(anyhow!("Invalid TPM2 PCR in {:?}, PCRs range from 0 to 23", settings.pcrs));
+        }
+        let pin = match (settings.with_pin, pin) {
+            (true, Some(pin)) if !pin.is_empty() => Some(pin.clone()),
+            (true, _) => return Err(anyhow!("TPM2 unlocking with PIN requires a PIN")),
+            (false, _) => None,
+        };
+        Ok(Self {
+            pcrs: settings.pcrs.clone(),
+            pin,
+            device: settings.device.clone(),
+        })
+    }
+
+    /// PCR list in systemd syntax (`0+7`)
+    pub fn pcr_list(&self) -> String {
+        self.pcrs.iter().map(u32::to_string).collect::<Vec<_>>().join("+")
+    }
+}
+
+/// crypttab option making systemd-cryptsetup try the TPM before asking for the passphrase
+pub const CRYPTTAB_OPTION: &str = "tpm2-device=auto";
+
+/// Shell reading the PIN from the first line of stdin into the variable
+/// systemd-cryptenroll takes it from, leaving the passphrase on stdin
+const READ_PIN: &str = "IFS= read -r NEWPIN && export NEWPIN && exec systemd-cryptenroll \"$@\"";
+
+/// Seal a key for the root container to the TPM
+///
+/// The passphrase slot is left untouched as fallback for a changed PCR state
+/// or a replaced mainboard. Passphrase and PIN both travel through stdin.
+pub fn enroll(runner: &mut dyn CommandRunner, plan: &InstallPlan) -> Result<()> {
+    let (Some(tpm2), Some(encryption)) = (&plan.tpm2, &plan.encryption) else {
+        return Ok(());
+    };
+    let root_part = plan.root_partition();
+    log::info!(
+        "Enrolling {} with TPM2 device {} (PCRs {}{})",
+        root_part,
+        tpm2.device,
+        tpm2.pcr_list(),
+        if tpm2.pin.is_some() { ", with PIN" } else { "" }
+    );
+
+    let args = [
+        "--unlock-key-file=/dev/stdin".to_string(),
+        format!("--tpm2-device={}", tpm2.device),
+        format!("--tpm2-pcrs={}", tpm2.pcr_list()),
+    ];
+    let enroll = match &tpm2.pin {
+        Some(pin) => {
+            if pin.expose().contains('\n') {
+                return Err(anyhow!("The TPM2 PIN must not contain line breaks"));
+            }
+            CommandSpec::new("sh")
+                .args(["-c", READ_PIN, "systemd-cryptenroll"])
+                .args(args)
+                .args(["--tpm2-with-pin=yes", &root_part])
+                .stdin(format!("{}\n{}", pin.expose(), encryption.passphrase.expose()))
+        }
+        None => CommandSpec::new("systemd-cryptenroll").args(args).arg(&root_part).stdin(encryption.passphrase.expose()),
+    };
+    runner.run_checked(&enroll)?;
+
+    if breaks_on_first_boot(&tpm2.pcrs, plan.secure_boot) {
+        log::warn!(
+            "New Secure Boot keys change PCR 7; after the first boot re-enroll with \
+             `systemd-cryptenroll --wipe-slot=tpm2 --tpm2-device=auto --tpm2-pcrs={} {}`",
+            tpm2.pcr_list(),
+            root_part
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_pcr_lists_in_every_separator() {
+        assert_eq!(parse_pcrs("7").unwrap(), vec![7]);
+        assert_eq!(parse_pcrs("0+7").unwrap(), vec![0, 7]);
+        assert_eq!(parse_pcrs(" 0, 7 11 ").unwrap(), vec![0, 7, 11]);
+        assert!(parse_pcrs("24").is_err());
+        assert!(parse_pcrs("7+x").is_err());
+        assert!(parse_pcrs(" + ").is_err());
+    }
+
+    #[test]
+    fn pin_policy_needs_a_pin() {
+        let settings = Tpm2Settings { with_pin: true, ..Default::default() };
+        assert!(Tpm2Plan::from_settings(&settings, None).is_err());
+        assert!(Tpm2Plan::from_settings(&settings, Some(&SecretString::from(""))).is_err());
+        let plan = Tpm2Plan::from_settings(&settings, Some(&SecretString::from("2468"))).unwrap();
+        assert!(plan.pin.is_some());
+        assert_eq!(plan.pcr_list(), "7");
+    }
+}
@@ -1,86 +1,179 @@
 use anyhow::Result;
-use std::process::Command;
+use std::path::{Path, PathBuf};
 
-/// Start the key management module
-pub fn start_key_config() -> Result<()> {
-    log::info!("Starting key management module...");
-    
-    // This is a placeholder for the actual key management functionality
-    // In a real implementation, this would include:
-    // - Creating encryption keys
-    // - Managing boot keys
-    // - Setting up secure boot
-    // - Managing SSH keys
-    
-    Ok(())
+use crate::core::command::{CommandRunner, CommandSpec};
+
+/// Directory inside an installed system holding its Secure Boot signing keys
+pub const SECURE_BOOT_KEY_DIR: &str = "/etc/secureboot/keys";
+/// Lifetime of generated signing certificates (about 20 years)
+const SIGNING_CERT_DAYS: &str = "7300";
+
+/// Self-signed X.509 key pair used to sign EFI binaries or EFI signature lists
+#[derive(Debug, Clone)]
+pub struct SigningKey {
+    /// Short name such as `MOK`, `PK`, `KEK` or `db`
+    pub name: String,
+    /// PEM private key
+    pub key: PathBuf,
+    /// PEM certificate
+    pub cert: PathBuf,
+    /// DER certificate, the format mokutil and firmware menus expect
+    pub der: PathBuf,
 }
 
-/// Generate a new GPG key
-pub fn generate_gpg_key(name: &str, email: &str) -> Result<()> {
-    log::info!("Generating GPG key for {} <{}>", name, email);
-    
-    if cfg!(unix) {
-        // Create a temporary batch file for gpg
-        let batch_content = format!(
-            "Key-Type: RSA
-             Key-Length: 4096
-             Name-Real: {}
-             Name-Email: {}
-             Expire-Date: 0
-             %commit
-             %echo Done", 
-             name, email
-        );
-        
-        // Write to temp file
-        let temp_file = "/tmp/gpg-batch.txt";
-        std::fs::write(temp_file, batch_content)?;
-        
-        let status = Command::new("gpg")
-            .args(&["--batch", "--gen-key", temp_file])
-            .status()?;
-            
-        // Clean up
-        std::fs::remove_file(temp_file)?;
-            
-        if !status.success() {
-            return Err(anyhow::anyhow!("GPG key generation failed with exit code: {}", status));
+impl SigningKey {
+    /// Paths of the key pair `name` in `dir`, whether generated yet or not
+    pub fn in_dir(dir: &Path, name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            key: dir.join(format!("{}.key", name)),
+            cert: dir.join(format!("{}.crt", name)),
+            der: dir.join(format!("{}.cer", name)),
         }
-    } else {
-        // Windows implementation would go here
-        return Err(anyhow::anyhow!("GPG key generation not implemented for this platform"));
     }
-    
-    Ok(())
 }
 
-/// Create a bootable USB with LUKS encryption
-pub fn create_encrypted_usb(device: &str) -> Result<()> {
+/// Device mapper name a freshly encrypted USB stick is opened as
+const USB_MAPPER: &str = "lunitool-usb";
+
+/// Encrypt a whole USB stick with LUKS2 and create an ext4 filesystem labelled `label` in it
+///
+/// For keeping key material; boot USB keys are prepared by the installer
+/// (see `installation::boot_usb`). The passphrase only travels through stdin.
+/// The container is closed again afterwards.
+pub fn create_encrypted_usb(runner: &mut dyn CommandRunner, device: &str, passphrase: &str, label: &str) -> Result<()> {
     log::info!("Creating encrypted USB on {}", device);
-    
+
     if cfg!(unix) {
-        // Warning: This is a dangerous operation that could destroy data
-        // In a real implementation, there would be multiple checks and confirmations
-        
-        // Format with LUKS encryption
-        let cryptsetup = Command::new("cryptsetup")
-            .args(&["luksFormat", device])
-            .stdin(std::process::Stdio::piped())
-            .status()?;
-            
-        if !cryptsetup.success() {
-            return Err(anyhow::anyhow!("LUKS formatting failed with exit code: {}", cryptsetup));
+        if passphrase.is_empty() {
+            return Err(anyhow::anyhow!("An encrypted USB stick needs a passphrase"));
         }
-        
-        // More steps would follow in a real implementation:
-        // - Opening the LUKS container
-        // - Creating a filesystem
-        // - Mounting and copying files
-        // - Setting up the bootloader
+        runner.run_checked(&CommandSpec::new("wipefs").args(["-a", device]))?;
+        runner.run_checked(
+            &CommandSpec::new("cryptsetup")
+                .args(["luksFormat", "--type", "luks2", "--batch-mode", "--key-file=-", device])
+                .stdin(passphrase),
+        )?;
+        runner.run_checked(
+            &CommandSpec::new("cryptsetup")
+                .args(["open", "--key-file=-", device, USB_MAPPER])
+                .stdin(passphrase),
+        )?;
+        let mapper = format!("/dev/mapper/{}", USB_MAPPER);
+        let formatted = runner.run_checked(&CommandSpec::new("mkfs.ext4").args(["-q", "-L", label, &mapper]));
+        // Close the container even if mkfs failed
+        runner.run_checked(&CommandSpec::new("cryptsetup").args(["close", USB_MAPPER]))?;
+        formatted?;
     } else {
         // Windows implementation would go here
         return Err(anyhow::anyhow!("USB encryption not implemented for this platform"));
     }
-    
+
+    Ok(())
+}
+
+/// Open an encrypted USB stick created by `create_encrypted_usb` and mount it at `mount_point`
+pub fn open_encrypted_usb(runner: &mut dyn CommandRunner, device: &str, passphrase: &str, mount_point: &Path) -> Result<()> {
+    log::info!("Opening encrypted USB {} at {}", device, mount_point.display());
+    runner.run_checked(
+        &CommandSpec::new("cryptsetup")
+            .args(["open", "--key-file=-", device, USB_MAPPER])
+            .stdin(passphrase),
+    )?;
+    let mounted = runner
+        .run_checked(&CommandSpec::new("install").args(["-d", "-m", "0700"]).arg(mount_point.to_string_lossy()))
+        .and_then(|_| {
+            runner.run_checked(
+                &CommandSpec::new("mount")
+                    .arg(format!("/dev/mapper/{}", USB_MAPPER))
+                    .arg(mount_point.to_string_lossy()),
+            )
+        });
+    if let Err(e) = mounted {
+        runner.run_checked(&CommandSpec::new("cryptsetup").args(["close", USB_MAPPER]))?;
+        return Err(e);
+    }
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Unmount and close an encrypted USB stick opened with `open_encrypted_usb`
+pub fn close_encrypted_usb(runner: &mut dyn CommandRunner, mount_point: &Path) -> Result<()> {
+    runner.run_checked(&CommandSpec::new("umount").arg(mount_point.to_string_lossy()))?;
+    runner.run_checked(&CommandSpec::new("cryptsetup").args(["close", USB_MAPPER]))?;
+    Ok(())
+}
+
+/// Generate an RSA-2048 signing key with a self-signed certificate in `dir`
+///
+/// The private key is written unencrypted with mode 0600, in a directory only root can enter.
+pub fn generate_signing_key(runner: &mut dyn CommandRunner, dir: &Path, name: &str, common_name: &str) -> Result<SigningKey> {
+    let key = SigningKey::in_dir(dir, name);
+    log::info!("Generating {} signing key in {}", name, dir.display());
+
+    runner.run_checked(&CommandSpec::new("install").args(["-d", "-m", "0700"]).arg(dir.to_string_lossy()))?;
+    runner.run_checked(
+        &CommandSpec::new("openssl")
+            .args(["req", "-new", "-x509", "-newkey", "rsa:2048", "-sha256", "-nodes", "-days", SIGNING_CERT_DAYS])
+            .arg("-subj")
+            .arg(format!("/CN={}/", common_name))
+            .arg("-keyout")
+            .arg(key.key.to_string_lossy())
+            .arg("-out")
+            .arg(key.cert.to_string_lossy()),
+    )?;
+    runner.run_checked(&CommandSpec::new("chmod").arg("0600").arg(key.key.to_string_lossy()))?;
+    runner.run_checked(
+        &CommandSpec::new("openssl")
+            .args(["x509", "-outform", "DER", "-in"])
+            .arg(key.cert.to_string_lossy())
+            .arg("-out")
+            .arg(key.der.to_string_lossy()),
+    )?;
+    Ok(key)
+}
+
+/// Wrap the certificate of `key` into an EFI signature list owned by `owner_guid`
+pub fn create_signature_list(runner: &mut dyn CommandRunner, key: &SigningKey, owner_guid: &str) -> Result<PathBuf> {
+    let esl = key.cert.with_extension("esl");
+    runner.run_checked(
+        &CommandSpec::new("cert-to-efi-sig-list")
+            .args(["-g", owner_guid])
+            .arg(key.cert.to_string_lossy())
+            .arg(esl.to_string_lossy()),
+    )?;
+    Ok(esl)
+}
+
+/// Sign the signature list of `key` with `signer` as an authenticated update of `variable`
+///
+/// PK is signed by itself, KEK by PK and db by KEK.
+pub fn sign_signature_list(runner: &mut dyn CommandRunner, signer: &SigningKey, variable: &str, esl: &Path) -> Result<PathBuf> {
+    let auth = esl.with_extension("auth");
+    runner.run_checked(
+        &CommandSpec::new("sign-efi-sig-list")
+            .arg("-k")
+            .arg(signer.key.to_string_lossy())
+            .arg("-c")
+            .arg(signer.cert.to_string_lossy())
+            .arg(variable)
+            .arg(esl.to_string_lossy())
+            .arg(auth.to_string_lossy()),
+    )?;
+    Ok(auth)
+}
+
+/// Sign an EFI binary (kernel, UKI or bootloader) in place with sbsign
+pub fn sign_efi_binary(runner: &mut dyn CommandRunner, key: &SigningKey, binary: &Path) -> Result<()> {
+    log::info!("Signing {} with the {} key", binary.display(), key.name);
+    runner.run_checked(
+        &CommandSpec::new("sbsign")
+            .arg("--key")
+            .arg(key.key.to_string_lossy())
+            .arg("--cert")
+            .arg(key.cert.to_string_lossy())
+            .arg("--output")
+            .arg(binary.to_string_lossy())
+            .arg(binary.to_string_lossy()),
+    )?;
+    Ok(())
+}
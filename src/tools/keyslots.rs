@@ -0,0 +1,628 @@
+use anyhow::{anyhow, Result};
+use std::path::Path;
+
+use crate::core::{
+    command::{CommandRunner, CommandSpec},
+    disk_info::{PartitionContent, SystemDiskInfo},
+};
+use crate::tools::installation::LuksProfile;
+
+/// Size of generated keyfiles in bytes
+const KEYFILE_SIZE: &str = "4096";
+
+/// LUKS container found by the disk probe
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LuksContainer {
+    /// Partition holding the header, e.g. `/dev/sda2`
+    pub device: String,
+    pub uuid: Option<String>,
+    /// Device mapper name if the container is open
+    pub mapped_name: Option<String>,
+}
+
+/// Active keyslot of a container
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Keyslot {
+    pub id: u32,
+    /// `luks2` for LUKS2 slots, `luks1` for LUKS1 slots
+    pub kind: String,
+    pub pbkdf: Option<String>,
+    /// PBKDF2 iterations or argon2 time cost
+    pub iterations: Option<u64>,
+    /// argon2 memory cost in KiB
+    pub memory_kib: Option<u64>,
+    /// LUKS2 priority (`normal`, `prefer`, `ignore`)
+    pub priority: Option<String>,
+}
+
+/// LUKS2 token, e.g. a TPM2 or FIDO2 binding created by systemd-cryptenroll
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Token {
+    pub id: u32,
+    /// Token type such as `systemd-tpm2`
+    pub kind: String,
+    /// Keyslots the token unlocks
+    pub keyslots: Vec<u32>,
+}
+
+/// Keyslots and tokens of a container, parsed from `cryptsetup luksDump`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LuksDump {
+    pub version: u32,
+    pub uuid: Option<String>,
+    pub keyslots: Vec<Keyslot>,
+    pub tokens: Vec<Token>,
+}
+
+/// Section of the luksDump output the parser is in
+#[derive(PartialEq)]
+enum Section {
+    Header,
+    Keyslots,
+    Tokens,
+    Other,
+}
+
+impl LuksDump {
+    /// Parse the text output of `cryptsetup luksDump` for LUKS1 and LUKS2 headers
+    pub fn parse(output: &str) -> Result<Self> {
+        let mut dump = LuksDump::default();
+        let mut section = Section::Header;
+
+        for line in output.lines() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() {
+                continue;
+            }
+            // LUKS1 lists all eight slots: `Key Slot 0: ENABLED`
+            if let Some(rest) = trimmed.strip_prefix("Key Slot ") {
+                section = Section::Keyslots;
+                if let Some((id, state)) = rest.split_once(':') {
+                    if state.trim() == "ENABLED" {
+                        dump.keyslots.push(Keyslot {
+                            id: id.trim().parse()?,
+                            kind: "luks1".to_string(),
+                            pbkdf: Some("pbkdf2".to_string()),
+                            ..Default::default()
+                        });
+                    }
+                }
+                continue;
+            }
+            // Section titles start in the first column: `Keyslots:`, `Tokens:`, `Digests:`
+            if !line.starts_with(char::is_whitespace) && trimmed.ends_with(':') {
+                section = match trimmed {
+                    "Keyslots:" => Section::Keyslots,
+                    "Tokens:" => Section::Tokens,
+                    _ => Section::Other,
+                };
+                continue;
+            }
+            let Some((key, value)) = trimmed.split_once(':') else {
+                continue;
+            };
+            let (key, value) = (key.trim(), value.trim());
+            // Entries are indented by two spaces, their properties by a tab
+            let entry_id = if line.starts_with("  ") { key.parse::<u32>().ok() } else { None };
+
+            match section {
+                Section::Header => match key {
+                    "Version" => dump.version = value.parse()?,
+                    "UUID" => dump.uuid = Some(value.to_string()),
+                    _ => {}
+                },
+                Section::Keyslots => {
+                    if let Some(id) = entry_id {
+                        dump.keyslots.push(Keyslot {
+                            id,
+                            kind: value.to_string(),
+                            ..Default::default()
+                        });
+                    } else if let Some(slot) = dump.keyslots.last_mut() {
+                        match key {
+                            "PBKDF" => slot.pbkdf = Some(value.to_string()),
+                            "Iterations" | "Time cost" => slot.iterations = value.parse().ok(),
+                            "Memory" => slot.memory_kib = value.parse().ok(),
+                            "Priority" => slot.priority = Some(value.to_string()),
+                            _ => {}
+                        }
+                    }
+                }
+                Section::Tokens => {
+                    if let Some(id) = entry_id {
+                        dump.tokens.push(Token {
+                            id,
+                            kind: value.to_string(),
+                            keyslots: Vec::new(),
+                        });
+                    } else if let (Some(token), "Keyslot") = (dump.tokens.last_mut(), key) {
+                        token.keyslots.extend(value.split(',').filter_map(|slot| slot.trim().parse::<u32>().ok()));
+                    }
+                }
+                Section::Other => {}
+            }
+        }
+
+        if dump.version == 0 {
+            return Err(anyhow!("Not a LUKS header dump"));
+        }
+        Ok(dump)
+    }
+
+    pub fn keyslot(&self, id: u32) -> Option<&Keyslot> {
+        self.keyslots.iter().find(|slot| slot.id == id)
+    }
+
+    /// Tokens that unlock nothing but `slot` and become useless without it
+    pub fn tokens_only_for(&self, slot: u32) -> Vec<&Token> {
+        self.tokens.iter().filter(|token| token.keyslots == [slot]).collect()
+    }
+}
+
+/// LUKS containers of the disk probe, in disk order
+pub fn containers(info: &SystemDiskInfo) -> Vec<LuksContainer> {
+    info.disks
+        .iter()
+        .flat_map(|disk| disk.partitions.iter())
+        .filter_map(|partition| match &partition.content {
+            Some(PartitionContent::LuksContainer { uuid, mapped_name, .. }) => Some(LuksContainer {
+                device: partition.path.clone(),
+                uuid: Some(uuid.clone()),
+                mapped_name: mapped_name.clone(),
+            }),
+            _ if partition.fs_type.as_deref() == Some("crypto_LUKS") => Some(LuksContainer {
+                device: partition.path.clone(),
+                uuid: partition.fs_uuid.clone(),
+                mapped_name: None,
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Read keyslots and tokens of the container on `device`
+pub fn dump(runner: &mut dyn CommandRunner, device: &str) -> Result<LuksDump> {
+    let output = runner.run_checked(&CommandSpec::new("cryptsetup").args(["luksDump", device]))?;
+    LuksDump::parse(&output.stdout)
+}
+
+/// Reject passphrases cryptsetup cannot take line by line from stdin
+fn check_passphrase(passphrase: &str) -> Result<()> {
+    if passphrase.is_empty() {
+        return Err(anyhow!("The passphrase must not be empty"));
+    }
+    if passphrase.contains('\n') {
+        return Err(anyhow!("The passphrase must not contain line breaks"));
+    }
+    Ok(())
+}
+
+/// Add `new` as a passphrase in a free keyslot, unlocking with `existing`
+///
+/// Without `--key-file` and with stdin not being a terminal, cryptsetup reads
+/// one passphrase per line, so both travel through stdin.
+pub fn add_passphrase(runner: &mut dyn CommandRunner, device: &str, existing: &str, new: &str) -> Result<()> {
+    check_passphrase(existing)?;
+    check_passphrase(new)?;
+    log::info!("Adding a passphrase to {}", device);
+    runner.run_checked(
+        &CommandSpec::new("cryptsetup")
+            .args(["luksAddKey", device])
+            .stdin(format!("{}\n{}\n", existing, new)),
+    )?;
+    Ok(())
+}
+
+/// Open the container as `/dev/mapper/<name>` with `passphrase`
+pub fn open(runner: &mut dyn CommandRunner, device: &str, name: &str, passphrase: &str) -> Result<()> {
+    log::info!("Opening {} as {}", device, name);
+    runner.run_checked(
+        &CommandSpec::new("cryptsetup")
+            .args(["open", "--key-file=-", device, name])
+            .stdin(passphrase),
+    )?;
+    Ok(())
+}
+
+/// Add `keyfile` to a free keyslot, generating it from /dev/urandom if it does not exist
+pub fn add_keyfile(runner: &mut dyn CommandRunner, device: &str, existing: &str, keyfile: &Path) -> Result<()> {
+    check_passphrase(existing)?;
+    let keyfile = keyfile.to_string_lossy();
+    if !Path::new(keyfile.as_ref()).exists() {
+        log::info!("Generating keyfile {}", keyfile);
+        // Create it with restrictive permissions first, so the key is never readable by others
+        runner.run_checked(&CommandSpec::new("install").args(["-D", "-m", "0400", "/dev/null", &keyfile]))?;
+        runner.run_checked(&CommandSpec::new("dd").args([
+            "if=/dev/urandom",
+            &format!("of={}", keyfile),
+            &format!("bs={}", KEYFILE_SIZE),
+            "count=1",
+            "conv=notrunc",
+            "status=none",
+        ]))?;
+    }
+    log::info!("Adding keyfile {} to {}", keyfile, device);
+    runner.run_checked(
+        &CommandSpec::new("cryptsetup")
+            .args(["luksAddKey", "--key-file=-", device, &keyfile])
+            .stdin(existing),
+    )?;
+    Ok(())
+}
+
+/// Replace the passphrase in `slot`; cryptsetup writes the new one to a free slot and wipes the old
+pub fn change_passphrase(runner: &mut dyn CommandRunner, device: &str, slot: u32, current: &str, new: &str) -> Result<()> {
+    check_passphrase(current)?;
+    check_passphrase(new)?;
+    log::info!("Changing the passphrase of keyslot {} on {}", slot, device);
+    runner.run_checked(
+        &CommandSpec::new("cryptsetup")
+            .args(["luksChangeKey", "--key-slot", &slot.to_string(), device])
+            .stdin(format!("{}\n{}\n", current, new)),
+    )?;
+    Ok(())
+}
+
+/// Refuse to remove unused slots and the last active keyslot of a container
+pub fn check_kill_slot(dump: &LuksDump, device: &str, slot: u32) -> Result<()> {
+    if dump.keyslot(slot).is_none() {
+        return Err(anyhow!("Keyslot {} of {} is not in use", slot, device));
+    }
+    if dump.keyslots.len() <= 1 {
+        return Err(anyhow!(
+            "Keyslot {} is the last one of {}; removing it would make the data unrecoverable",
+            slot,
+            device
+        ));
+    }
+    Ok(())
+}
+
+/// Wipe `slot`, proving with `remaining` that another keyslot still opens the container
+///
+/// Refuses to remove the last active keyslot (see `check_kill_slot`). Tokens bound to nothing but the
+/// removed slot are deleted as well.
+pub fn kill_slot(runner: &mut dyn CommandRunner, device: &str, dump: &LuksDump, slot: u32, remaining: &str) -> Result<()> {
+    check_kill_slot(dump, device, slot)?;
+    check_passphrase(remaining)?;
+    log::info!("Removing keyslot {} from {}", slot, device);
+    runner.run_checked(
+        &CommandSpec::new("cryptsetup")
+            .args(["luksKillSlot", "--key-file=-", device, &slot.to_string()])
+            .stdin(remaining),
+    )?;
+    for token in dump.tokens_only_for(slot) {
+        log::info!("Removing {} token {} bound to keyslot {}", token.kind, token.id, slot);
+        runner.run_checked(
+            &CommandSpec::new("cryptsetup").args(["token", "remove", "--token-id", &token.id.to_string(), device]),
+        )?;
+    }
+    Ok(())
+}
+
+/// Re-derive the key in `slot` with the PBKDF settings of `profile` (LUKS2 only)
+pub fn convert_pbkdf(
+    runner: &mut dyn CommandRunner,
+    device: &str,
+    dump: &LuksDump,
+    slot: u32,
+    passphrase: &str,
+    profile: &LuksProfile,
+) -> Result<()> {
+    if dump.version < 2 {
+        return Err(anyhow!("{} is a LUKS1 container; LUKS1 keyslots always use pbkdf2", device));
+    }
+    if dump.keyslot(slot).is_none() {
+        return Err(anyhow!("Keyslot {} of {} is not in use", slot, device));
+    }
+    check_passphrase(passphrase)?;
+    log::info!("Converting keyslot {} on {} to {}", slot, device, profile.pbkdf.as_str());
+    runner.run_checked(
+        &CommandSpec::new("cryptsetup")
+            .args(["luksConvertKey", "--key-slot", &slot.to_string()])
+            .args(profile.pbkdf_args())
+            .args(["--key-file=-", device])
+            .stdin(passphrase),
+    )?;
+    Ok(())
+}
+
+/// luksDump output shown for every container in dry-run mode
+pub const DRY_RUN_DUMP: &str = "LUKS header information
+Version:       \t2
+Epoch:         \t4
+UUID:          \t00000000-0000-4000-8000-000000000000
+Label:         \t(no label)
+
+Keyslots:
+  0: luks2
+\tKey:        512 bits
+\tPriority:   normal
+\tPBKDF:      argon2id
+\tTime cost:  4
+\tMemory:     1048576
+\tThreads:    4
+  1: luks2
+\tKey:        512 bits
+\tPriority:   normal
+\tPBKDF:      pbkdf2
+\tIterations: 1000
+Tokens:
+  0: systemd-tpm2
+\ttpm2-hash-pcrs:   7
+\tKeyslot:    1
+Digests:
+  0: pbkdf2
+";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::command::DryRunCommandRunner;
+
+    /// `cryptsetup luksDump` of a LUKS1 container with slots 0 and 2 in use
+    const LUKS1_DUMP: &str = "LUKS header information for /dev/sdb1
+
+Version:       \t1
+Cipher name:   \taes
+Cipher mode:   \txts-plain64
+Hash spec:     \tsha256
+Payload offset:\t4096
+MK bits:       \t512
+MK digest:     \tc3 9e 1f 52 7a 0b 44 d8 91 6e 2f a0 35 c7 18 e4 0d 6b 92 af
+MK salt:       \t4f 21 d8 90 3a 6c e5 17 b2 08 9d 4e 71 c3 a6 5f
+               \te0 14 8b 2d 66 f9 03 ae 5c 72 d1 38 9b 0e 47 c6
+MK iterations: \t112604
+UUID:          \t6a2d8a07-cf52-4e52-9d8a-3b1f1b1a0c55
+
+Key Slot 0: ENABLED
+\tIterations:         \t1798902
+\tSalt:               \t8e 3b 72 c4 09 d1 5a 6f 20 e8 b7 43 9c 15 fa 62
+\t                      \ta4 0f 7d 31 e9 58 c2 86 1b 6e 94 d7 03 ba 2c 75
+\tKey material offset:\t8
+\tAF stripes:            \t4000
+Key Slot 1: DISABLED
+Key Slot 2: ENABLED
+\tIterations:         \t1765517
+\tSalt:               \t1d 96 e0 4a 7c 32 bf 58 e3 0a 61 d4 8f 27 c9 05
+\t                      \t5b a8 16 ef 73 c0 3d 92 b4 6a 08 e1 57 fc 29 8d
+\tKey material offset:\t1032
+\tAF stripes:            \t4000
+Key Slot 3: DISABLED
+Key Slot 4: DISABLED
+Key Slot 5: DISABLED
+Key Slot 6: DISABLED
+Key Slot 7: DISABLED
+";
+
+    /// LUKS2 container with a passphrase in slot 0 and a TPM2 enrollment in slot 2
+    const LUKS2_DUMP: &str = "LUKS header information
+Version:       \t2
+Epoch:         \t6
+Metadata area: \t16384 [bytes]
+Keyslots area: \t16744448 [bytes]
+UUID:          \t3f8b2e51-0c9d-4d6f-9a2e-6b7c1d2e3f40
+Label:         \t(no label)
+Subsystem:     \t(no subsystem)
+Flags:       \t(no flags)
+
+Data segments:
+  0: crypt
+\toffset: 16777216 [bytes]
+\tlength: (whole device)
+\tcipher: aes-xts-plain64
+\tsector: 4096 [bytes]
+
+Keyslots:
+  0: luks2
+\tKey:        512 bits
+\tPriority:   normal
+\tCipher:     aes-xts-plain64
+\tCipher key: 512 bits
+\tPBKDF:      argon2id
+\tTime cost:  4
+\tMemory:     1048576
+\tThreads:    4
+\tSalt:       5e 0c 9a 71 d3 28 b6 4f 80 e2 17 c5 3a 9d 62 f1
+\t            08 b4 6d 23 ea 91 57 c0 3f 7a d8 15 6b 2e 94 a3
+\tAF stripes: 4000
+\tAF hash:    sha256
+\tArea offset:32768 [bytes]
+\tArea length:258048 [bytes]
+\tDigest ID:  0
+  2: luks2
+\tKey:        512 bits
+\tPriority:   normal
+\tCipher:     aes-xts-plain64
+\tCipher key: 512 bits
+\tPBKDF:      pbkdf2
+\tHash:       sha512
+\tIterations: 1000
+\tSalt:       5e 0c 9a 71 d3 28 b6 4f 80 e2 17 c5 3a 9d 62 f1
+\t            08 b4 6d 23 ea 91 57 c0 3f 7a d8 15 6b 2e 94 a3
+\tAF stripes: 4000
+\tAF hash:    sha256
+\tArea offset:548864 [bytes]
+\tArea length:258048 [bytes]
+\tDigest ID:  0
+Tokens:
+  0: systemd-tpm2
+\ttpm2-hash-pcrs:   7
+\ttpm2-pcr-bank:    sha256
+\ttpm2-pubkey:
+\t            (null)
+\ttpm2-pubkey-pcrs:
+\ttpm2-primary-alg: ecc
+\ttpm2-blob:        00 9e 00 20 4b 71 d2 08 e6 35 af 91 c0 5d 7e 23
+\ttpm2-policy-hash:
+\t                  3a 0f 96 d2 71 c4 58 eb 2d 80 b7 16 4e f9 a3 65
+\tKeyslot:    2
+Digests:
+  0: pbkdf2
+\tHash:       sha256
+\tIterations: 129774
+\tSalt:       c1 4e 93 0a 6f d7 28 b5 e0 3c 91 7a 54 f8 0d 62
+\t            a7 19 e5 4b 80 36 cd 72 f4 0b 9e 25 d1 68 3a c9
+\tDigest:     72 de 05 a9 3b 6c f1 84 0e 57 b3 29 c6 1d 98 e0
+\t            4a f3 60 17 bc 85 2e d9 13 76 0f a4 e8 5b c2 39
+";
+
+    /// Freshly formatted LUKS2 container with a single passphrase
+    const LUKS2_SINGLE_DUMP: &str = "LUKS header information
+Version:       \t2
+Epoch:         \t6
+Metadata area: \t16384 [bytes]
+Keyslots area: \t16744448 [bytes]
+UUID:          \t3f8b2e51-0c9d-4d6f-9a2e-6b7c1d2e3f40
+Label:         \t(no label)
+Subsystem:     \t(no subsystem)
+Flags:       \t(no flags)
+
+Data segments:
+  0: crypt
+\toffset: 16777216 [bytes]
+\tlength: (whole device)
+\tcipher: aes-xts-plain64
+\tsector: 4096 [bytes]
+
+Keyslots:
+  0: luks2
+\tKey:        512 bits
+\tPriority:   normal
+\tCipher:     aes-xts-plain64
+\tCipher key: 512 bits
+\tPBKDF:      argon2id
+\tTime cost:  4
+\tMemory:     1048576
+\tThreads:    4
+\tSalt:       5e 0c 9a 71 d3 28 b6 4f 80 e2 17 c5 3a 9d 62 f1
+\t            08 b4 6d 23 ea 91 57 c0 3f 7a d8 15 6b 2e 94 a3
+\tAF stripes: 4000
+\tAF hash:    sha256
+\tArea offset:32768 [bytes]
+\tArea length:258048 [bytes]
+\tDigest ID:  0
+Tokens:
+Digests:
+  0: pbkdf2
+\tHash:       sha256
+\tIterations: 129774
+\tSalt:       c1 4e 93 0a 6f d7 28 b5 e0 3c 91 7a 54 f8 0d 62
+\t            a7 19 e5 4b 80 36 cd 72 f4 0b 9e 25 d1 68 3a c9
+\tDigest:     72 de 05 a9 3b 6c f1 84 0e 57 b3 29 c6 1d 98 e0
+\t            4a f3 60 17 bc 85 2e d9 13 76 0f a4 e8 5b c2 39
+";
+
+    #[test]
+    fn parses_luks1_slots() {
+        let dump = LuksDump::parse(LUKS1_DUMP).unwrap();
+        assert_eq!(dump.version, 1);
+        assert_eq!(dump.uuid.as_deref(), Some("6a2d8a07-cf52-4e52-9d8a-3b1f1b1a0c55"));
+        assert_eq!(
+            dump.keyslots,
+            [
+                Keyslot {
+                    id: 0,
+                    kind: "luks1".to_string(),
+                    pbkdf: Some("pbkdf2".to_string()),
+                    iterations: Some(1798902),
+                    ..Default::default()
+                },
+                Keyslot {
+                    id: 2,
+                    kind: "luks1".to_string(),
+                    pbkdf: Some("pbkdf2".to_string()),
+                    iterations: Some(1765517),
+                    ..Default::default()
+                },
+            ]
+        );
+        assert!(dump.tokens.is_empty());
+    }
+
+    #[test]
+    fn parses_luks2_slots_and_tokens() {
+        let dump = LuksDump::parse(LUKS2_DUMP).unwrap();
+        assert_eq!(dump.version, 2);
+        assert_eq!(dump.uuid.as_deref(), Some("3f8b2e51-0c9d-4d6f-9a2e-6b7c1d2e3f40"));
+        assert_eq!(
+            dump.keyslots,
+            [
+                Keyslot {
+                    id: 0,
+                    kind: "luks2".to_string(),
+                    pbkdf: Some("argon2id".to_string()),
+                    iterations: Some(4),
+                    memory_kib: Some(1048576),
+                    priority: Some("normal".to_string()),
+                },
+                Keyslot {
+                    id: 2,
+                    kind: "luks2".to_string(),
+                    pbkdf: Some("pbkdf2".to_string()),
+                    iterations: Some(1000),
+                    memory_kib: None,
+                    priority: Some("normal".to_string()),
+                },
+            ]
+        );
+        assert_eq!(
+            dump.tokens,
+            [Token {
+                id: 0,
+                kind: "systemd-tpm2".to_string(),
+                keyslots: vec![2],
+            }]
+        );
+        assert_eq!(dump.tokens_only_for(2).len(), 1);
+        assert!(dump.tokens_only_for(0).is_empty());
+    }
+
+    #[test]
+    fn rejects_output_without_a_header() {
+        assert!(LuksDump::parse("Device /dev/sdb1 is not a valid LUKS device.\n").is_err());
+    }
+
+    #[test]
+    fn kill_slot_refuses_unused_and_last_slots() {
+        let luks1 = LuksDump::parse(LUKS1_DUMP).unwrap();
+        check_kill_slot(&luks1, "/dev/sdb1", 0).unwrap();
+        assert_eq!(
+            check_kill_slot(&luks1, "/dev/sdb1", 1).unwrap_err().to_string(),
+            "Keyslot 1 of /dev/sdb1 is not in use"
+        );
+
+        let luks2 = LuksDump::parse(LUKS2_DUMP).unwrap();
+        check_kill_slot(&luks2, "/dev/sda2", 2).unwrap();
+        assert!(check_kill_slot(&luks2, "/dev/sda2", 1).is_err());
+        assert!(check_kill_slot(&luks2, "/dev/sda2", 31).is_err());
+
+        let single = LuksDump::parse(LUKS2_SINGLE_DUMP).unwrap();
+        assert_eq!(single.keyslots.len(), 1);
+        assert_eq!(
+            check_kill_slot(&single, "/dev/sda2", 0).unwrap_err().to_string(),
+            "Keyslot 0 is the last one of /dev/sda2; removing it would make the data unrecoverable"
+        );
+        assert!(check_kill_slot(&single, "/dev/sda2", 1).unwrap_err().to_string().contains("not in use"));
+    }
+
+    #[test]
+    fn kill_slot_removes_tokens_bound_to_the_slot() {
+        let dump = LuksDump::parse(LUKS2_DUMP).unwrap();
+        let mut runner = DryRunCommandRunner::new();
+        kill_slot(&mut runner, "/dev/sda2", &dump, 2, "correct horse").unwrap();
+        assert_eq!(
+            runner.rendered_history(),
+            [
+                "cryptsetup luksKillSlot --key-file=- /dev/sda2 2",
+                "cryptsetup token remove --token-id 0 /dev/sda2",
+            ]
+        );
+        assert_eq!(runner.history()[0].stdin.as_deref().map(Vec::as_slice), Some(&b"correct horse"[..]));
+
+        let single = LuksDump::parse(LUKS2_SINGLE_DUMP).unwrap();
+        let mut runner = DryRunCommandRunner::new();
+        assert!(kill_slot(&mut runner, "/dev/sda2", &single, 0, "correct horse").is_err());
+        assert!(runner.history().is_empty());
+    }
+}
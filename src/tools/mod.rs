@@ -1,3 +1,13 @@
 pub mod backup;
+pub mod diceware;
+pub mod gpg;
+pub mod header_backup;
+pub mod in_place_encryption;
 pub mod key_manager;
+pub mod keyslots;
+pub mod password_strength;
+pub mod recovery_key;
+pub mod shamir;
+pub mod ssh_keys;
+pub mod vault;
 pub mod installation;
\ No newline at end of file
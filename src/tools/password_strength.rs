@@ -0,0 +1,392 @@
+use std::collections::HashMap;
+
+use crate::tools::diceware;
+
+/// Most common passwords of public leak statistics, most common first
+const COMMON_PASSWORDS: &[&str] = &[
+    "123456", "password", "12345678", "qwerty", "123456789", "12345", "1234", "111111", "1234567", "dragon",
+    "123123", "baseball", "abc123", "football", "monkey", "letmein", "696969", "shadow", "master", "666666",
+    "qwertyuiop", "123321", "mustang", "1234567890", "michael", "654321", "superman", "1qaz2wsx", "7777777",
+    "121212", "000000", "qazwsx", "123qwe", "killer", "trustno1", "jordan", "jennifer", "zxcvbnm", "asdfgh",
+    "hunter", "buster", "soccer", "harley", "batman", "andrew", "tigger", "sunshine", "iloveyou", "2000",
+    "charlie", "robert", "thomas", "hockey", "ranger", "daniel", "starwars", "klaster", "112233", "george",
+    "computer", "michelle", "jessica", "pepper", "1111", "zxcvbn", "555555", "11111111", "131313", "freedom",
+    "777777", "pass", "maggie", "159753", "aaaaaa", "ginger", "princess", "joshua", "cheese", "amanda",
+    "summer", "love", "ashley", "nicole", "chelsea", "matthew", "access", "yankees", "987654321",
+    "dallas", "austin", "thunder", "taylor", "matrix", "admin", "welcome", "passwort", "hallo", "hallo123",
+    "schatz", "schalke04", "qwertz", "lunitool", "linux", "root", "toor", "changeme", "secret",
+];
+
+/// Keyboard rows and their shifted variants that make up typical keyboard walks
+const KEYBOARD_ROWS: &[&str] = &["1234567890", "qwertyuiop", "qwertzuiop", "asdfghjkl", "yxcvbnm", "zxcvbnm", "1qaz2wsx3edc"];
+
+/// Guesses per character of input that no pattern explains
+const BRUTEFORCE_PER_CHAR: f64 = 10.0;
+
+/// Kind of pattern found in a password
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pattern {
+    CommonPassword,
+    DictionaryWord,
+    Sequence,
+    Repeat,
+    KeyboardWalk,
+    Year,
+}
+
+impl Pattern {
+    /// Locale key of the feedback shown for the pattern
+    pub fn warning_key(&self) -> &'static str {
+        match self {
+            Pattern::CommonPassword => "STRENGTH_WARNING_COMMON",
+            Pattern::DictionaryWord => "STRENGTH_WARNING_WORD",
+            Pattern::Sequence => "STRENGTH_WARNING_SEQUENCE",
+            Pattern::Repeat => "STRENGTH_WARNING_REPEAT",
+            Pattern::KeyboardWalk => "STRENGTH_WARNING_KEYBOARD",
+            Pattern::Year => "STRENGTH_WARNING_YEAR",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Match {
+    start: usize,
+    end: usize,
+    guesses: f64,
+    pattern: Pattern,
+}
+
+/// Estimated strength of a password
+#[derive(Debug, Clone, PartialEq)]
+pub struct Strength {
+    /// 0 (guessed instantly) to 4 (out of reach of offline attacks), like zxcvbn
+    pub score: u8,
+    pub guesses_log10: f64,
+    /// Patterns the estimate is based on, without duplicates
+    pub patterns: Vec<Pattern>,
+}
+
+impl Strength {
+    /// Locale key of the score's name
+    pub fn label_key(&self) -> &'static str {
+        match self.score {
+            0 => "STRENGTH_VERY_WEAK",
+            1 => "STRENGTH_WEAK",
+            2 => "STRENGTH_FAIR",
+            3 => "STRENGTH_STRONG",
+            _ => "STRENGTH_VERY_STRONG",
+        }
+    }
+}
+
+/// Estimate how many guesses an attacker needs for `password`, in the spirit of zxcvbn
+///
+/// The password is covered by the cheapest combination of known patterns
+/// (common passwords, dictionary words incl. simple l33t substitutions,
+/// sequences, repeats, keyboard walks, years); characters no pattern
+/// explains count as brute force.
+pub fn estimate(password: &str) -> Strength {
+    let chars: Vec<char> = password.chars().collect();
+    let matches = find_matches(&chars);
+
+    // Cheapest cover of the first i characters and the match ending there
+    let mut best: Vec<(f64, Option<Match>)> = vec![(1.0, None); chars.len() + 1];
+    for end in 1..=chars.len() {
+        best[end] = (best[end - 1].0 * BRUTEFORCE_PER_CHAR, None);
+        for candidate in matches.iter().filter(|candidate| candidate.end == end) {
+            let guesses = best[candidate.start].0 * candidate.guesses;
+            if guesses < best[end].0 {
+                best[end] = (guesses, Some(*candidate));
+            }
+        }
+    }
+
+    let mut patterns = Vec::new();
+    let mut at = chars.len();
+    while at > 0 {
+        match best[at].1 {
+            Some(found) => {
+                if !patterns.contains(&found.pattern) {
+                    patterns.push(found.pattern);
+                }
+                at = found.start;
+            }
+            None => at -= 1,
+        }
+    }
+
+    let guesses = best[chars.len()].0.max(1.0);
+    let score = match guesses {
+        g if g < 1e3 => 0,
+        g if g < 1e6 => 1,
+        g if g < 1e8 => 2,
+        g if g < 1e10 => 3,
+        _ => 4,
+    };
+    Strength {
+        score,
+        guesses_log10: guesses.log10(),
+        patterns,
+    }
+}
+
+/// Undo the usual l33t substitutions; the number of substituted characters is returned as well
+fn unleet(chars: &[char]) -> (String, usize) {
+    let mut substituted = 0;
+    let plain = chars
+        .iter()
+        .map(|c| {
+            let plain = match c {
+                '0' => 'o',
+                '1' | '!' => 'i',
+                '3' => 'e',
+                '4' | '@' => 'a',
+                '5' | '$' => 's',
+                '7' => 't',
+                other => other.to_ascii_lowercase(),
+            };
+            if plain != c.to_ascii_lowercase() {
+                substituted += 1;
+            }
+            plain
+        })
+        .collect();
+    (plain, substituted)
+}
+
+/// Extra guesses for capitalisation: the first or all letters upper case are tried early
+fn case_variations(chars: &[char]) -> f64 {
+    let upper = chars.iter().filter(|c| c.is_uppercase()).count();
+    let first_only = upper == 1 && chars[0].is_uppercase();
+    match upper {
+        0 => 1.0,
+        _ if first_only || upper == chars.len() => 2.0,
+        _ => 2f64.powi(upper.min(8) as i32),
+    }
+}
+
+lazy_static::lazy_static! {
+    /// Guesses for every known word: its rank for common passwords, the list size for wordlist entries
+    static ref RANKED: HashMap<String, f64> = {
+        let mut ranked = HashMap::new();
+        for language in ["en", "de"] {
+            let words = diceware::wordlist(language);
+            for word in &words {
+                ranked.insert(word.to_string(), words.len() as f64);
+            }
+        }
+        for (rank, password) in COMMON_PASSWORDS.iter().enumerate() {
+            ranked.insert(password.to_string(), rank as f64 + 1.0);
+        }
+        ranked
+    };
+}
+
+fn find_matches(chars: &[char]) -> Vec<Match> {
+    let mut matches = Vec::new();
+    for start in 0..chars.len() {
+        for end in start + 3..=chars.len() {
+            let part = &chars[start..end];
+            let lower: String = part.iter().map(|c| c.to_ascii_lowercase()).collect();
+            let (plain, substituted) = unleet(part);
+            for (candidate, leet) in [(&lower, 0), (&plain, substituted)] {
+                if let Some(rank) = RANKED.get(candidate) {
+                    let pattern = if *rank > COMMON_PASSWORDS.len() as f64 { Pattern::DictionaryWord } else { Pattern::CommonPassword };
+                    let guesses = rank * case_variations(part) * 2f64.powi(leet.min(8) as i32);
+                    matches.push(Match { start, end, guesses, pattern });
+                }
+            }
+
+            if part.iter().all(|c| *c == part[0]) {
+                matches.push(Match { start, end, guesses: charset_size(part[0]) * part.len() as f64, pattern: Pattern::Repeat });
+            }
+            let deltas: Vec<i64> = part.windows(2).map(|pair| pair[1] as i64 - pair[0] as i64).collect();
+            if deltas.iter().all(|delta| *delta == deltas[0] && delta.abs() == 1) {
+                // Starting at a or 1 is what everybody does first
+                let base = if matches!(part[0], 'a' | 'A' | '1' | '0') { 4.0 } else { charset_size(part[0]) };
+                matches.push(Match { start, end, guesses: base * part.len() as f64, pattern: Pattern::Sequence });
+            }
+            if part.len() >= 4 && is_keyboard_walk(&lower) {
+                matches.push(Match { start, end, guesses: 50.0 * part.len() as f64, pattern: Pattern::KeyboardWalk });
+            }
+            if part.len() == 4 && lower.parse::<u32>().is_ok_and(|year| (1900..=2039).contains(&year)) {
+                matches.push(Match { start, end, guesses: 140.0, pattern: Pattern::Year });
+            }
+        }
+    }
+    matches
+}
+
+fn is_keyboard_walk(lower: &str) -> bool {
+    let reversed: String = lower.chars().rev().collect();
+    KEYBOARD_ROWS.iter().any(|row| row.contains(lower) || row.contains(&reversed))
+}
+
+fn charset_size(c: char) -> f64 {
+    if c.is_ascii_digit() {
+        10.0
+    } else if c.is_ascii_alphabetic() {
+        26.0
+    } else {
+        33.0
+    }
+}
+
+/// Characters that may not come out as typed at a boot prompt
+#[derive(Debug, Clone, PartialEq)]
+pub enum LayoutPitfall {
+    /// Not on a US keyboard at all, e.g. umlauts; GRUB cannot read them
+    NotOnUsKeyboard(Vec<char>),
+    /// On other keys with the US layout than with the configured one
+    DiffersFromUs(Vec<char>),
+}
+
+impl LayoutPitfall {
+    pub fn warning_key(&self) -> &'static str {
+        match self {
+            LayoutPitfall::NotOnUsKeyboard(_) => "LAYOUT_WARNING_NOT_US",
+            LayoutPitfall::DiffersFromUs(_) => "LAYOUT_WARNING_DIFFERS",
+        }
+    }
+
+    pub fn chars(&self) -> &[char] {
+        match self {
+            LayoutPitfall::NotOnUsKeyboard(chars) | LayoutPitfall::DiffersFromUs(chars) => chars,
+        }
+    }
+}
+
+/// Characters of `password` that change with the keyboard layout
+///
+/// GRUB reads LUKS passphrases with the US layout, whatever the system uses.
+/// Only the German layout family is known in detail; for other layouts only
+/// characters missing from US keyboards are reported.
+pub fn layout_pitfalls(password: &str, layout: &str) -> Vec<LayoutPitfall> {
+    let mut missing = Vec::new();
+    let mut moved = Vec::new();
+    for c in password.chars() {
+        let list = if !c.is_ascii() || c.is_ascii_control() {
+            &mut missing
+        } else if matches!(layout, "de" | "at" | "ch") && !same_key_on_us_and_de(c) {
+            &mut moved
+        } else {
+            continue;
+        };
+        if !list.contains(&c) {
+            list.push(c);
+        }
+    }
+    let mut pitfalls = Vec::new();
+    if !missing.is_empty() {
+        pitfalls.push(LayoutPitfall::NotOnUsKeyboard(missing));
+    }
+    if !moved.is_empty() {
+        pitfalls.push(LayoutPitfall::DiffersFromUs(moved));
+    }
+    pitfalls
+}
+
+/// Whether `c` is typed with the same keys on US and German (QWERTZ) keyboards
+fn same_key_on_us_and_de(c: char) -> bool {
+    match c {
+        'y' | 'z' | 'Y' | 'Z' => false,
+        c if c.is_ascii_alphanumeric() => true,
+        ' ' | ',' | '.' | '!' | '$' | '%' => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patterns(password: &str) -> Vec<Pattern> {
+        estimate(password).patterns
+    }
+
+    #[test]
+    fn common_passwords_are_guessed_instantly() {
+        for password in ["password", "Password", "p4ssw0rd", "qwertz", "hallo123", "123456"] {
+            let strength = estimate(password);
+            assert_eq!(strength.score, 0, "{}", password);
+            assert_eq!(strength.patterns, [Pattern::CommonPassword], "{}", password);
+            assert_eq!(strength.label_key(), "STRENGTH_VERY_WEAK");
+        }
+    }
+
+    #[test]
+    fn recognises_simple_patterns() {
+        assert_eq!(patterns("abcdefgh"), [Pattern::Sequence]);
+        assert_eq!(patterns("98765432"), [Pattern::Sequence]);
+        assert_eq!(patterns("xxxxxxxxxx"), [Pattern::Repeat]);
+        assert_eq!(patterns("asdfghjk"), [Pattern::KeyboardWalk]);
+        assert_eq!(patterns("yxcvbnm,"), [Pattern::KeyboardWalk]);
+        assert!(patterns("Sommer1987").contains(&Pattern::Year));
+        assert!(patterns("acornable").contains(&Pattern::DictionaryWord));
+        for password in ["abcdefgh", "xxxxxxxxxx", "asdfghjk"] {
+            assert!(estimate(password).score <= 1, "{}", password);
+        }
+    }
+
+    #[test]
+    fn random_characters_and_long_passphrases_score_high() {
+        assert_eq!(estimate("").score, 0);
+        let random = estimate("Xk9#vQ2!mT7w");
+        assert!(random.patterns.is_empty());
+        assert_eq!(random.score, 4);
+        assert!((random.guesses_log10 - 12.0).abs() < 1e-9);
+
+        let passphrase = diceware::generate("en", &diceware::DicewareSettings::default()).unwrap();
+        let strength = estimate(&passphrase.value);
+        assert_eq!(strength.score, 4);
+        assert_eq!(strength.label_key(), "STRENGTH_VERY_STRONG");
+    }
+
+    #[test]
+    fn more_words_never_score_lower() {
+        let words = ["acorn", "blade", "cider", "dwarf", "ember"];
+        let mut previous = 0.0;
+        for count in 1..=words.len() {
+            let guesses = estimate(&words[..count].join(" ")).guesses_log10;
+            assert!(guesses > previous, "{} words", count);
+            previous = guesses;
+        }
+    }
+
+    #[test]
+    fn us_layout_only_warns_about_characters_missing_there() {
+        assert!(layout_pitfalls("zebra yacht", "us").is_empty());
+        assert!(layout_pitfalls("a-b_c=d;e", "us").is_empty());
+        assert_eq!(layout_pitfalls("Grüße", "us"), [LayoutPitfall::NotOnUsKeyboard(vec!['ü', 'ß'])]);
+        assert_eq!(layout_pitfalls("Grüße zu", "fr"), [LayoutPitfall::NotOnUsKeyboard(vec!['ü', 'ß'])]);
+    }
+
+    #[test]
+    fn german_layouts_warn_about_y_z_and_moved_symbols() {
+        for layout in ["de", "at", "ch"] {
+            assert_eq!(layout_pitfalls("Yoga zoo", layout), [LayoutPitfall::DiffersFromUs(vec!['Y', 'z'])]);
+        }
+        assert!(layout_pitfalls("correct horse, bitter staple!", "de").is_empty());
+
+        let pitfalls = layout_pitfalls("zähler-1", "de");
+        assert_eq!(
+            pitfalls,
+            [
+                LayoutPitfall::NotOnUsKeyboard(vec!['ä']),
+                LayoutPitfall::DiffersFromUs(vec!['z', '-']),
+            ]
+        );
+        assert_eq!(pitfalls[0].warning_key(), "LAYOUT_WARNING_NOT_US");
+        assert_eq!(pitfalls[1].warning_key(), "LAYOUT_WARNING_DIFFERS");
+        assert_eq!(pitfalls[1].chars(), ['z', '-']);
+    }
+
+    #[test]
+    fn diceware_words_never_trigger_layout_warnings() {
+        for language in ["en", "de"] {
+            for word in diceware::wordlist(language) {
+                assert!(layout_pitfalls(word, "de").is_empty(), "{}", word);
+            }
+        }
+    }
+}
@@ -0,0 +1,350 @@
+use anyhow::{anyhow, Result};
+use chrono::Local;
+use qrcode::{render::svg, render::unicode, QrCode};
+use std::path::{Path, PathBuf};
+
+use crate::core::{
+    command::{CommandRunner, CommandSpec},
+    secret::{SecretString, REDACTED},
+};
+use crate::tools::{
+    header_backup::luks_uuid,
+    installation::{boot_usb, plan::InstallPlan},
+    shamir::{self, Share},
+};
+
+/// Random bytes in a recovery key (256 bit)
+const KEY_BYTES: usize = 32;
+/// Characters per dash separated group
+const GROUP_LEN: usize = 8;
+/// ModHex alphabet as used by systemd-cryptenroll: the same keys on QWERTY,
+/// QWERTZ and AZERTY layouts, so the key can be typed at any unlock prompt
+const MODHEX: &[u8; 16] = b"cbdefghijklnrtuv";
+
+/// High entropy LUKS passphrase meant to be printed and locked away
+///
+/// The dashed form is the passphrase itself, like systemd recovery keys.
+#[derive(Clone, PartialEq)]
+pub struct RecoveryKey(SecretString);
+
+impl std::fmt::Debug for RecoveryKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "RecoveryKey({})", REDACTED)
+    }
+}
+
+impl RecoveryKey {
+    /// Generate a new key from the operating system's random source
+    pub fn generate() -> Result<Self> {
+        let mut bytes = [0u8; KEY_BYTES];
+        getrandom::getrandom(&mut bytes).map_err(|e| anyhow!("No randomness for the recovery key: {}", e))?;
+        Ok(Self::from_bytes(&bytes))
+    }
+
+    /// Encode `bytes` in ModHex, grouped by dashes
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        let chars: Vec<char> = bytes
+            .iter()
+            .flat_map(|byte| [MODHEX[(byte >> 4) as usize], MODHEX[(byte & 0x0f) as usize]])
+            .map(char::from)
+            .collect();
+        let groups: Vec<String> = chars.chunks(GROUP_LEN).map(|group| group.iter().collect()).collect();
+        Self(SecretString::from(groups.join("-")))
+    }
+
+    /// Parse a typed key; case and whitespace are ignored, dashes are optional
+    pub fn parse(input: &str) -> Result<Self> {
+        Ok(Self::from_bytes(&decode(input)?))
+    }
+
+    /// The random bytes the key encodes
+    fn bytes(&self) -> Vec<u8> {
+        decode(self.0.expose()).unwrap_or_default()
+    }
+
+    /// Split the key into `count` shares, any `threshold` of which restore it
+    pub fn split(&self, threshold: u8, count: u8) -> Result<Vec<Share>> {
+        shamir::split(&self.bytes(), threshold, count)
+    }
+
+    /// Restore a key from shares created by [`RecoveryKey::split`]
+    pub fn combine(shares: &[Share]) -> Result<Self> {
+        let bytes = shamir::combine(shares)?;
+        if bytes.len() != KEY_BYTES {
+            return Err(anyhow!("The shares do not hold a recovery key"));
+        }
+        Ok(Self::from_bytes(&bytes))
+    }
+
+    /// The passphrase as handed to cryptsetup
+    pub fn as_str(&self) -> &str {
+        self.0.expose()
+    }
+
+    /// The key in lines of four groups, for display and print
+    pub fn display_lines(&self) -> Vec<String> {
+        let groups: Vec<&str> = self.0.expose().split('-').collect();
+        groups.chunks(4).map(|line| line.join(" - ")).collect()
+    }
+
+    /// QR code of the key drawn with Unicode half blocks, two modules per character
+    ///
+    /// Light modules are drawn as blocks, so it has to be shown light on dark.
+    pub fn qr_lines(&self) -> Result<Vec<String>> {
+        qr_lines(self.0.expose())
+    }
+
+    /// QR code of the key as SVG for the HTML sheet
+    fn qr_svg(&self) -> Result<String> {
+        let code = QrCode::new(self.0.expose().as_bytes())?;
+        Ok(code.render::<svg::Color>().min_dimensions(240, 240).build())
+    }
+}
+
+/// Decode ModHex `input` into bytes; case, whitespace and dashes are ignored
+fn decode(input: &str) -> Result<Vec<u8>> {
+    let chars: Vec<u8> = input
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .map(|c| c.to_ascii_lowercase() as u8)
+        .collect();
+    if chars.len() != KEY_BYTES * 2 || !chars.iter().all(|c| MODHEX.contains(c)) {
+        return Err(anyhow!("Not a recovery key: expected {} groups of {} characters", KEY_BYTES * 2 / GROUP_LEN, GROUP_LEN));
+    }
+    let nibble = |c: u8| MODHEX.iter().position(|m| *m == c).unwrap_or_default() as u8;
+    Ok(chars.chunks(2).map(|pair| (nibble(pair[0]) << 4) | nibble(pair[1])).collect())
+}
+
+/// QR code of `data` drawn with Unicode half blocks, light on dark
+fn qr_lines(data: &str) -> Result<Vec<String>> {
+    let code = QrCode::new(data.as_bytes())?;
+    let rendered = code
+        .render::<unicode::Dense1x2>()
+        .dark_color(unicode::Dense1x2::Light)
+        .light_color(unicode::Dense1x2::Dark)
+        .build();
+    Ok(rendered.lines().map(str::to_string).collect())
+}
+
+/// Install `contents` as `path`, readable by root only
+fn write_private(runner: &mut dyn CommandRunner, path: &Path, contents: &str) -> Result<()> {
+    runner.run_checked(
+        &CommandSpec::new("install")
+            .args(["-D", "-m", "0400", "/dev/stdin"])
+            .arg(path.to_string_lossy())
+            .stdin(contents),
+    )?;
+    Ok(())
+}
+
+/// Add `key` to a free keyslot of `device`, unlocking with `existing`
+///
+/// `extra_args` carries PBKDF and `--header` arguments; both passphrases go
+/// line by line through stdin.
+pub fn enroll(runner: &mut dyn CommandRunner, device: &str, existing: &str, key: &RecoveryKey, extra_args: &[String]) -> Result<()> {
+    if existing.contains('\n') {
+        return Err(anyhow!("The passphrase must not contain line breaks"));
+    }
+    log::info!("Adding a recovery key to {}", device);
+    runner.run_checked(
+        &CommandSpec::new("cryptsetup")
+            .arg("luksAddKey")
+            .args(extra_args)
+            .arg(device)
+            .stdin(format!("{}\n{}\n", existing, key.as_str())),
+    )?;
+    Ok(())
+}
+
+/// Printable recovery information for one machine
+#[derive(Debug, Clone)]
+pub struct RecoverySheet {
+    pub hostname: String,
+    /// Device of the container, e.g. `/dev/nvme0n1p2`
+    pub device: String,
+    pub uuid: String,
+    pub created: String,
+    pub key: RecoveryKey,
+}
+
+impl RecoverySheet {
+    pub fn new(hostname: &str, device: &str, uuid: &str, key: RecoveryKey) -> Self {
+        Self {
+            hostname: hostname.to_string(),
+            device: device.to_string(),
+            uuid: uuid.to_string(),
+            created: Local::now().format("%Y-%m-%d %H:%M").to_string(),
+            key,
+        }
+    }
+
+    /// Base file name without extension
+    fn file_stem(&self) -> String {
+        let id = if self.uuid.is_empty() { self.device.rsplit('/').next().unwrap_or("luks") } else { &self.uuid };
+        format!("recovery-{}-{}", self.hostname, id)
+    }
+
+    /// Host, device, UUID and date block shared by key and share sheets
+    fn header(&self, title: &str) -> String {
+        format!(
+            "{}\n{}\n\n\
+             Host:    {}\n\
+             Device:  {}\n\
+             UUID:    {}\n\
+             Created: {}\n\n",
+            title,
+            "=".repeat(title.chars().count()),
+            self.hostname,
+            self.device,
+            self.uuid,
+            self.created
+        )
+    }
+
+    pub fn render_text(&self) -> String {
+        let mut text = self.header("LUKS RECOVERY KEY");
+        text.push_str("Recovery key (type it at the passphrase prompt, including the dashes):\n\n");
+        for line in self.key.display_lines() {
+            text.push_str(&format!("    {}\n", line));
+        }
+        text.push_str(&format!("\nAs one line: {}\n", self.key.as_str()));
+        if let Ok(qr) = self.key.qr_lines() {
+            text.push('\n');
+            for line in qr {
+                text.push_str(&line);
+                text.push('\n');
+            }
+        }
+        text.push_str("\nAnyone holding this key can decrypt the device. Keep it locked away.\n");
+        text
+    }
+
+    /// Self-contained HTML page, printable from any browser
+    pub fn render_html(&self) -> Result<String> {
+        let escape = |value: &str| value.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;");
+        let key_lines: Vec<String> = self.key.display_lines().iter().map(|line| escape(line)).collect();
+        Ok(format!(
+            "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>Recovery key {host}</title>\n\
+             <style>body{{font-family:sans-serif;margin:2em}}th{{text-align:left;padding-right:1em}}\
+             .key{{font-family:monospace;font-size:1.4em;line-height:1.6}}</style></head>\n<body>\n\
+             <h1>LUKS recovery key</h1>\n<table>\n\
+             <tr><th>Host</th><td>{host}</td></tr>\n<tr><th>Device</th><td>{device}</td></tr>\n\
+             <tr><th>UUID</th><td>{uuid}</td></tr>\n<tr><th>Created</th><td>{created}</td></tr>\n</table>\n\
+             <p>Type the key at the passphrase prompt, including the dashes.</p>\n\
+             <p class=\"key\">{key}</p>\n{qr}\n\
+             <p><strong>Anyone holding this key can decrypt the device. Keep it locked away.</strong></p>\n\
+             </body></html>\n",
+            host = escape(&self.hostname),
+            device = escape(&self.device),
+            uuid = escape(&self.uuid),
+            created = escape(&self.created),
+            key = key_lines.join("<br>\n"),
+            qr = self.key.qr_svg()?,
+        ))
+    }
+
+    /// Write the sheet as text and HTML into `dir`, readable by root only
+    pub fn write(&self, runner: &mut dyn CommandRunner, dir: &Path) -> Result<Vec<PathBuf>> {
+        let stem = self.file_stem();
+        let files = [(dir.join(format!("{}.txt", stem)), self.render_text()), (dir.join(format!("{}.html", stem)), self.render_html()?)];
+        for (path, contents) in &files {
+            write_private(runner, path, contents)?;
+        }
+        log::info!("Recovery sheet for {} written to {}", self.device, dir.display());
+        Ok(files.into_iter().map(|(path, _)| path).collect())
+    }
+
+    /// Printable page for one share of the key; never contains the key itself
+    pub fn render_share(&self, share: &Share, count: usize) -> String {
+        let mut text = self.header(&format!("RECOVERY KEY SHARE {} OF {}", share.index, count));
+        text.push_str(&format!(
+            "Any {} of the {} shares restore the recovery key (Key Management, combine shares).\n\n",
+            share.threshold, count
+        ));
+        for line in share.display_lines() {
+            text.push_str(&format!("    {}\n", line));
+        }
+        if let Ok(qr) = qr_lines(&share.encode()) {
+            text.push('\n');
+            for line in qr {
+                text.push_str(&line);
+                text.push('\n');
+            }
+        }
+        text.push_str("\nKeep this share apart from the other shares.\n");
+        text
+    }
+
+    /// Write every share into its own file in `dir`, readable by root only
+    ///
+    /// Each file is meant to go to a different person or medium.
+    pub fn write_shares(&self, runner: &mut dyn CommandRunner, dir: &Path, shares: &[Share]) -> Result<Vec<PathBuf>> {
+        let stem = self.file_stem();
+        let mut paths = Vec::new();
+        for share in shares {
+            let path = dir.join(format!("{}-share-{}-of-{}.txt", stem, share.index, shares.len()));
+            write_private(runner, &path, &self.render_share(share, shares.len()))?;
+            paths.push(path);
+        }
+        log::info!("{} recovery key shares for {} written to {}", shares.len(), self.device, dir.display());
+        Ok(paths)
+    }
+}
+
+/// Generate a recovery key, add it to every container of the installation and write the sheet
+///
+/// One key unlocks both the /boot container of cryptodisk mode and the root
+/// container. Runs before the detached header is copied to the USB key.
+pub fn enroll_installation(runner: &mut dyn CommandRunner, plan: &InstallPlan) -> Result<Option<RecoverySheet>> {
+    let (Some(recovery), Some(root)) = (&plan.recovery, &plan.encryption) else {
+        return Ok(None);
+    };
+    let key = RecoveryKey::generate()?;
+    let boot = plan.boot_encryption.as_ref().zip(plan.boot_partition());
+    for (encryption, device) in boot.into_iter().chain(Some((root, plan.root_partition()))) {
+        let mut args = encryption.profile.pbkdf_args();
+        args.extend(boot_usb::header_args(encryption));
+        enroll(runner, &device, encryption.passphrase.expose(), &key, &args)?;
+    }
+
+    let header = boot_usb::staged_header(root).unwrap_or_else(|| plan.root_partition());
+    let uuid = luks_uuid(runner, &header)?;
+    let sheet = RecoverySheet::new(&plan.hostname, &plan.root_partition(), &uuid, key);
+    match &recovery.sheet_dir {
+        Some(dir) => {
+            sheet.write(runner, dir)?;
+        }
+        None => log::warn!("No medium configured for the recovery sheet; write the key down from the summary screen"),
+    }
+    Ok(Some(sheet))
+}
+
+/// Recovery key settings of an installation
+#[derive(Debug, Clone)]
+pub struct RecoveryPlan {
+    /// Mounted removable medium receiving the recovery sheet
+    pub sheet_dir: Option<PathBuf>,
+}
+
+/// Shares typed in or read from share sheets, separated by whitespace
+///
+/// Entries starting with `/` are share sheet files written by
+/// [`RecoverySheet::write_shares`], e.g. on a USB stick.
+pub fn read_shares(input: &str) -> Result<Vec<Share>> {
+    input
+        .split_whitespace()
+        .map(|entry| {
+            if !entry.starts_with('/') {
+                return Share::parse(entry);
+            }
+            let sheet = std::fs::read_to_string(entry).map_err(|e| anyhow!("Cannot read share sheet {}: {}", entry, e))?;
+            // The share is the only block of lines made of hex groups and dashes
+            let encoded: String = sheet
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && line.chars().all(|c| c.is_ascii_hexdigit() || c == '-' || c == ' '))
+                .collect();
+            Share::parse(&encoded).map_err(|e| anyhow!("{}: {}", entry, e))
+        })
+        .collect()
+}
@@ -0,0 +1,286 @@
+use anyhow::{anyhow, Result};
+
+/// Characters per dash separated group of an encoded share
+const GROUP_LEN: usize = 8;
+
+/// Multiply in GF(2^8) with the AES polynomial x^8 + x^4 + x^3 + x + 1
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0;
+    while b != 0 {
+        if b & 1 != 0 {
+            product ^= a;
+        }
+        let carry = a & 0x80 != 0;
+        a <<= 1;
+        if carry {
+            a ^= 0x1b;
+        }
+        b >>= 1;
+    }
+    product
+}
+
+/// Multiplicative inverse in GF(2^8), a^254; zero has none and maps to zero
+fn gf_inv(a: u8) -> u8 {
+    let mut result = 1;
+    let mut base = a;
+    let mut exponent = 254u8;
+    while exponent != 0 {
+        if exponent & 1 != 0 {
+            result = gf_mul(result, base);
+        }
+        base = gf_mul(base, base);
+        exponent >>= 1;
+    }
+    result
+}
+
+/// CRC-16/CCITT-FALSE, enough to catch typos in a transcribed share
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc = 0xffffu16;
+    for byte in data {
+        crc ^= (*byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x1021 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+/// One share of a secret split with Shamir's scheme
+#[derive(Clone, PartialEq)]
+pub struct Share {
+    /// Shares needed to reconstruct the secret
+    pub threshold: u8,
+    /// x coordinate, 1 based and unique within a split
+    pub index: u8,
+    /// y coordinates, one per byte of the secret
+    data: Vec<u8>,
+}
+
+impl std::fmt::Debug for Share {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Share").field("threshold", &self.threshold).field("index", &self.index).finish_non_exhaustive()
+    }
+}
+
+impl Share {
+    /// Hex encoding of threshold, index, data and checksum, grouped by dashes
+    ///
+    /// Each share carries everything needed to combine it, so it can be
+    /// printed or exported on its own.
+    pub fn encode(&self) -> String {
+        let mut bytes = vec![self.threshold, self.index];
+        bytes.extend(&self.data);
+        bytes.extend(crc16(&bytes).to_be_bytes());
+        let hex: Vec<char> = bytes.iter().flat_map(|byte| format!("{:02x}", byte).chars().collect::<Vec<_>>()).collect();
+        let groups: Vec<String> = hex.chunks(GROUP_LEN).map(|group| group.iter().collect()).collect();
+        groups.join("-")
+    }
+
+    /// Parse an encoded share; case, whitespace and dashes are ignored
+    pub fn parse(input: &str) -> Result<Self> {
+        let hex: String = input.chars().filter(|c| !c.is_whitespace() && *c != '-').collect();
+        if !hex.is_ascii() || !hex.len().is_multiple_of(2) || hex.len() < 12 {
+            return Err(anyhow!("Share is too short or has an odd number of characters"));
+        }
+        let bytes = (0..hex.len())
+            .step_by(2)
+            .map(|at| u8::from_str_radix(&hex[at..at + 2], 16))
+            .collect::<Result<Vec<u8>, _>>()
+            .map_err(|_| anyhow!("Share contains characters other than 0-9 and a-f"))?;
+        let (payload, checksum) = bytes.split_at(bytes.len() - 2);
+        if crc16(payload).to_be_bytes() != checksum {
+            return Err(anyhow!("Checksum of the share does not match, check for typos"));
+        }
+        let share = Self {
+            threshold: payload[0],
+            index: payload[1],
+            data: payload[2..].to_vec(),
+        };
+        if share.threshold < 2 || share.index == 0 {
+            return Err(anyhow!("Share has an invalid threshold or index"));
+        }
+        Ok(share)
+    }
+
+    /// The encoded share in lines of four groups, for display and print
+    pub fn display_lines(&self) -> Vec<String> {
+        let encoded = self.encode();
+        let groups: Vec<&str> = encoded.split('-').collect();
+        groups.chunks(4).map(|line| line.join(" - ")).collect()
+    }
+}
+
+/// Split `secret` into `count` shares, any `threshold` of which reconstruct it
+///
+/// Every byte is the constant term of its own random polynomial of degree
+/// `threshold - 1`; share `i` holds the values of all polynomials at `x = i`.
+pub fn split(secret: &[u8], threshold: u8, count: u8) -> Result<Vec<Share>> {
+    if threshold < 2 || threshold > count {
+        return Err(anyhow!("Threshold must be between 2 and the number of shares ({})", count));
+    }
+    if secret.is_empty() {
+        return Err(anyhow!("Nothing to split"));
+    }
+    let mut coefficients = vec![0u8; secret.len() * (threshold as usize - 1)];
+    getrandom::getrandom(&mut coefficients).map_err(|e| anyhow!("No randomness for the shares: {}", e))?;
+
+    Ok((1..=count)
+        .map(|x| {
+            let data = secret
+                .iter()
+                .zip(coefficients.chunks(threshold as usize - 1))
+                .map(|(constant, higher)| {
+                    // Horner's scheme from the highest coefficient down
+                    let value = higher.iter().rev().fold(0u8, |acc, coefficient| gf_mul(acc, x) ^ coefficient);
+                    gf_mul(value, x) ^ constant
+                })
+                .collect();
+            Share { threshold, index: x, data }
+        })
+        .collect())
+}
+
+/// Reconstruct the secret from at least `threshold` shares of one split
+///
+/// Lagrange interpolation at `x = 0`. Shares of different splits cannot be
+/// told apart beyond their threshold and length, so the result has to be
+/// checked against the container it belongs to.
+pub fn combine(shares: &[Share]) -> Result<Vec<u8>> {
+    let Some(first) = shares.first() else {
+        return Err(anyhow!("No shares given"));
+    };
+    if shares.iter().any(|share| share.threshold != first.threshold || share.data.len() != first.data.len()) {
+        return Err(anyhow!("The shares do not belong to the same split"));
+    }
+    let mut used: Vec<&Share> = Vec::new();
+    for share in shares {
+        if !used.iter().any(|other| other.index == share.index) {
+            used.push(share);
+        }
+    }
+    if used.len() < first.threshold as usize {
+        return Err(anyhow!("{} different shares needed, {} given", first.threshold, used.len()));
+    }
+    used.truncate(first.threshold as usize);
+
+    let mut secret = vec![0u8; first.data.len()];
+    for share in &used {
+        // Lagrange basis polynomial of this share evaluated at zero
+        let basis = used
+            .iter()
+            .filter(|other| other.index != share.index)
+            .fold(1u8, |acc, other| gf_mul(acc, gf_mul(other.index, gf_inv(other.index ^ share.index))));
+        for (byte, y) in secret.iter_mut().zip(&share.data) {
+            *byte ^= gf_mul(*y, basis);
+        }
+    }
+    Ok(secret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &[u8] = b"\x00\x01\x7f\x80\xfe\xffrecovery key bytes";
+
+    /// Every subset of `shares` with exactly `size` members
+    fn subsets(shares: &[Share], size: usize) -> Vec<Vec<Share>> {
+        (0u32..1 << shares.len())
+            .filter(|mask| mask.count_ones() as usize == size)
+            .map(|mask| {
+                shares
+                    .iter()
+                    .enumerate()
+                    .filter(|(at, _)| mask & (1 << at) != 0)
+                    .map(|(_, share)| share.clone())
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn gf_inverse() {
+        for a in 1..=255u8 {
+            assert_eq!(gf_mul(a, gf_inv(a)), 1, "inverse of {}", a);
+        }
+    }
+
+    #[test]
+    fn every_threshold_subset_combines() {
+        for (threshold, count) in [(2, 2), (2, 3), (3, 5), (4, 6), (5, 5)] {
+            let shares = split(SECRET, threshold, count).unwrap();
+            assert_eq!(shares.len(), count as usize);
+            for subset in subsets(&shares, threshold as usize) {
+                assert_eq!(combine(&subset).unwrap(), SECRET, "{} of {}", threshold, count);
+            }
+            assert_eq!(combine(&shares).unwrap(), SECRET);
+        }
+    }
+
+    #[test]
+    fn fewer_shares_than_threshold_fail() {
+        for (threshold, count) in [(2, 3), (3, 5), (4, 6)] {
+            let shares = split(SECRET, threshold, count).unwrap();
+            for subset in subsets(&shares, threshold as usize - 1) {
+                assert!(combine(&subset).is_err());
+            }
+            // A repeated share does not count twice
+            let mut repeated = subsets(&shares, threshold as usize - 1).remove(0);
+            repeated.push(repeated[0].clone());
+            assert!(combine(&repeated).is_err());
+        }
+    }
+
+    #[test]
+    fn shares_of_different_splits_are_rejected() {
+        let short = split(b"short", 2, 3).unwrap();
+        let long = split(SECRET, 2, 3).unwrap();
+        assert!(combine(&[short[0].clone(), long[1].clone()]).is_err());
+        let other_threshold = split(SECRET, 3, 3).unwrap();
+        assert!(combine(&[long[0].clone(), other_threshold[1].clone()]).is_err());
+    }
+
+    #[test]
+    fn encode_parse_round_trip() {
+        for share in split(SECRET, 3, 5).unwrap() {
+            let encoded = share.encode();
+            assert!(encoded.split('-').all(|group| group.len() <= GROUP_LEN));
+            assert_eq!(Share::parse(&encoded).unwrap(), share);
+            // Case, spaces and line breaks of a transcription do not matter
+            let transcribed = share.display_lines().join("\n").to_uppercase();
+            assert_eq!(Share::parse(&transcribed).unwrap(), share);
+        }
+    }
+
+    #[test]
+    fn flipped_digit_fails_checksum() {
+        let encoded = split(SECRET, 2, 3).unwrap()[0].encode();
+        for (at, c) in encoded.char_indices().filter(|(_, c)| *c != '-') {
+            let flipped = if c == '0' { '1' } else { '0' };
+            let mut typo = encoded.clone();
+            typo.replace_range(at..at + 1, &flipped.to_string());
+            assert!(Share::parse(&typo).is_err(), "typo at {} accepted", at);
+        }
+    }
+
+    #[test]
+    fn malformed_shares_are_rejected() {
+        assert!(Share::parse("").is_err());
+        assert!(Share::parse("0201abc").is_err());
+        assert!(Share::parse("0201zz00-0000").is_err());
+        // Valid checksum, but a threshold below two
+        let invalid = Share { threshold: 1, index: 1, data: vec![42] };
+        assert!(Share::parse(&invalid.encode()).is_err());
+    }
+
+    #[test]
+    fn threshold_is_validated() {
+        assert!(split(SECRET, 0, 3).is_err());
+        assert!(split(SECRET, 1, 3).is_err());
+        assert!(split(SECRET, 4, 3).is_err());
+        assert!(split(&[], 2, 3).is_err());
+        assert!(split(SECRET, 3, 3).is_ok());
+    }
+}
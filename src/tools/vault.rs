@@ -0,0 +1,401 @@
+use anyhow::{anyhow, Context, Result};
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    XChaCha20Poly1305, XNonce,
+};
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use zeroize::Zeroizing;
+
+use crate::core::{
+    command::{CommandRunner, CommandSpec},
+    secret::SecretString,
+};
+use crate::get_config_dir;
+
+/// File name of the vault in the config directory
+const VAULT_FILE: &str = "vault.lkv";
+/// First bytes of a vault file, the last one is the format version
+const MAGIC: &[u8; 8] = b"LUNIVLT1";
+/// Argon2id cost of new vaults: 64 MiB, three passes, one lane
+///
+/// Also the upper bound when opening; a header asking for more is refused
+/// instead of letting a crafted file exhaust memory or time.
+const MEMORY_KIB: u32 = 64 * 1024;
+const ITERATIONS: u32 = 3;
+const PARALLELISM: u32 = 1;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+/// Magic, three cost parameters, salt and nonce
+const HEADER_LEN: usize = MAGIC.len() + 12 + SALT_LEN + NONCE_LEN;
+
+/// Vault in the lunitool config directory
+pub fn default_path() -> PathBuf {
+    get_config_dir().join(VAULT_FILE)
+}
+
+/// What kind of key a vault entry holds
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EntryKind {
+    RecoveryKey,
+    Passphrase,
+    Keyfile,
+    SecureBootKey,
+    SshKey,
+    GpgKey,
+    Other,
+}
+
+impl EntryKind {
+    pub fn all() -> &'static [EntryKind] {
+        &[
+            EntryKind::RecoveryKey,
+            EntryKind::Passphrase,
+            EntryKind::Keyfile,
+            EntryKind::SecureBootKey,
+            EntryKind::SshKey,
+            EntryKind::GpgKey,
+            EntryKind::Other,
+        ]
+    }
+
+    /// Short name as shown in lists and choice fields
+    pub fn name(&self) -> &'static str {
+        match self {
+            EntryKind::RecoveryKey => "recovery key",
+            EntryKind::Passphrase => "passphrase",
+            EntryKind::Keyfile => "keyfile",
+            EntryKind::SecureBootKey => "secure boot key",
+            EntryKind::SshKey => "ssh key",
+            EntryKind::GpgKey => "gpg key",
+            EntryKind::Other => "other",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::all().iter().copied().find(|kind| kind.name() == name)
+    }
+}
+
+impl std::fmt::Display for EntryKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// One key in the vault
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct VaultEntry {
+    pub kind: EntryKind,
+    pub label: String,
+    /// Device or host the key belongs to
+    #[serde(default)]
+    pub device: String,
+    /// Local time the entry was created, `YYYY-MM-DD HH:MM`
+    pub created: String,
+    #[serde(default)]
+    pub notes: String,
+    /// The key itself; hex encoded if `binary` is set
+    pub secret: SecretString,
+    #[serde(default)]
+    pub binary: bool,
+}
+
+impl std::fmt::Debug for VaultEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VaultEntry")
+            .field("kind", &self.kind)
+            .field("label", &self.label)
+            .field("device", &self.device)
+            .field("created", &self.created)
+            .finish_non_exhaustive()
+    }
+}
+
+impl VaultEntry {
+    /// New text entry created now
+    pub fn new(kind: EntryKind, label: &str, device: &str, secret: &str) -> Self {
+        Self {
+            kind,
+            label: label.to_string(),
+            device: device.to_string(),
+            created: Local::now().format("%Y-%m-%d %H:%M").to_string(),
+            notes: String::new(),
+            secret: SecretString::from(secret),
+            binary: false,
+        }
+    }
+
+    /// New entry with the contents of `file`; anything but UTF-8 text is stored hex encoded
+    pub fn from_file(kind: EntryKind, label: &str, device: &str, file: &Path) -> Result<Self> {
+        let data = std::fs::read(file).with_context(|| format!("Cannot read {}", file.display()))?;
+        Ok(match String::from_utf8(data) {
+            Ok(text) => Self::new(kind, label, device, &text),
+            Err(e) => {
+                let hex: String = e.as_bytes().iter().map(|byte| format!("{:02x}", byte)).collect();
+                Self { binary: true, ..Self::new(kind, label, device, &hex) }
+            }
+        })
+    }
+
+    pub fn with_notes(self, notes: &str) -> Self {
+        Self { notes: notes.to_string(), ..self }
+    }
+
+    /// Copy without the secret, for listings
+    pub fn without_secret(&self) -> Self {
+        Self { secret: SecretString::default(), ..self.clone() }
+    }
+
+    /// The secret in lines for display; binary secrets in groups of 64 hex digits
+    pub fn secret_lines(&self) -> Vec<String> {
+        if !self.binary {
+            return self.secret.expose().lines().map(str::to_string).collect();
+        }
+        let chars: Vec<char> = self.secret.expose().chars().collect();
+        chars.chunks(64).map(|line| line.iter().collect()).collect()
+    }
+}
+
+/// Encrypted file holding keys created or imported by lunitool
+///
+/// The entries are serialized as YAML and sealed with XChaCha20-Poly1305
+/// under a key derived from the master passphrase with Argon2id. Salt and
+/// nonce are fresh on every save; the header is authenticated as well.
+#[derive(Debug, Clone)]
+pub struct Vault {
+    pub path: PathBuf,
+    pub entries: Vec<VaultEntry>,
+}
+
+impl Vault {
+    /// Empty vault, not written until `save`
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            entries: Vec::new(),
+        }
+    }
+
+    /// Read and decrypt the vault at `path`
+    pub fn open(path: &Path, passphrase: &str) -> Result<Self> {
+        let data = std::fs::read(path).with_context(|| format!("Cannot read vault {}", path.display()))?;
+        if data.len() < HEADER_LEN || !data.starts_with(MAGIC) {
+            return Err(anyhow!("{} is not a lunitool vault", path.display()));
+        }
+        let (header, ciphertext) = data.split_at(HEADER_LEN);
+        let number = |at: usize| u32::from_le_bytes([header[at], header[at + 1], header[at + 2], header[at + 3]]);
+        let params = (number(8), number(12), number(16));
+        if params.0 > MEMORY_KIB || params.1 > ITERATIONS || params.2 > PARALLELISM {
+            return Err(anyhow!(
+                "{} asks for a higher key derivation cost than lunitool uses ({} KiB, {} passes, {} lanes)",
+                path.display(),
+                params.0,
+                params.1,
+                params.2
+            ));
+        }
+        let salt = &header[20..20 + SALT_LEN];
+        let nonce: [u8; NONCE_LEN] = header[20 + SALT_LEN..].try_into()?;
+
+        let cipher = cipher(passphrase, salt, params)?;
+        let plaintext = Zeroizing::new(
+            cipher
+                .decrypt(&XNonce::from(nonce), Payload { msg: ciphertext, aad: header })
+                .map_err(|_| anyhow!("Wrong master passphrase or damaged vault"))?,
+        );
+        let entries = serde_yaml::from_slice(&plaintext).context("Vault contents cannot be read")?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            entries,
+        })
+    }
+
+    /// Open the vault at `path`, or start a new one if there is no file yet
+    pub fn open_or_new(path: &Path, passphrase: &str) -> Result<Self> {
+        if path.exists() {
+            Self::open(path, passphrase)
+        } else {
+            Ok(Self::new(path))
+        }
+    }
+
+    /// Encrypt and write the vault with mode 0600, creating missing directories
+    ///
+    /// The file is replaced as a whole; it only holds ciphertext, so passing
+    /// it through stdin does not expose anything.
+    pub fn save(&self, runner: &mut dyn CommandRunner, passphrase: &str) -> Result<()> {
+        if passphrase.is_empty() {
+            return Err(anyhow!("The vault needs a master passphrase"));
+        }
+        let mut salt = [0u8; SALT_LEN];
+        let mut nonce = [0u8; NONCE_LEN];
+        for random in [&mut salt[..], &mut nonce[..]] {
+            getrandom::getrandom(random).map_err(|e| anyhow!("No randomness for the vault: {}", e))?;
+        }
+
+        let mut header = MAGIC.to_vec();
+        for number in [MEMORY_KIB, ITERATIONS, PARALLELISM] {
+            header.extend(number.to_le_bytes());
+        }
+        header.extend(salt);
+        header.extend(nonce);
+
+        let plaintext = Zeroizing::new(serde_yaml::to_string(&self.entries)?);
+        let cipher = cipher(passphrase, &salt, (MEMORY_KIB, ITERATIONS, PARALLELISM))?;
+        let ciphertext = cipher
+            .encrypt(&XNonce::from(nonce), Payload { msg: plaintext.as_bytes(), aad: &header })
+            .map_err(|_| anyhow!("Encrypting the vault failed"))?;
+        header.extend(ciphertext);
+
+        runner.run_checked(
+            &CommandSpec::new("install")
+                .args(["-D", "-m", "0600", "/dev/stdin"])
+                .arg(self.path.to_string_lossy())
+                .stdin(header),
+        )?;
+        log::info!("Vault {} saved with {} entries", self.path.display(), self.entries.len());
+        Ok(())
+    }
+
+    /// Entry by its 1 based number as shown in listings
+    pub fn entry(&self, number: usize) -> Result<&VaultEntry> {
+        number
+            .checked_sub(1)
+            .and_then(|index| self.entries.get(index))
+            .ok_or_else(|| anyhow!("The vault has no entry {}", number))
+    }
+
+    /// Remove the entry with the 1 based `number`
+    pub fn remove(&mut self, number: usize) -> Result<VaultEntry> {
+        self.entry(number)?;
+        Ok(self.entries.remove(number - 1))
+    }
+}
+
+/// Add `entry` to the existing vault at `path`
+///
+/// Used by key producing features. Unlike `Vault::open_or_new` this never
+/// creates a vault, so a mistyped master passphrase cannot start a second one.
+pub fn store(runner: &mut dyn CommandRunner, path: &Path, passphrase: &str, entry: VaultEntry) -> Result<()> {
+    if !path.exists() {
+        return Err(anyhow!("No vault at {}; create it in Key Management first", path.display()));
+    }
+    let mut vault = Vault::open(path, passphrase)?;
+    log::info!("Storing {} '{}' in the vault", entry.kind, entry.label);
+    vault.entries.push(entry);
+    vault.save(runner, passphrase)
+}
+
+/// AEAD cipher keyed with Argon2id of `passphrase`
+fn cipher(passphrase: &str, salt: &[u8], (memory_kib, iterations, parallelism): (u32, u32, u32)) -> Result<XChaCha20Poly1305> {
+    let params = Params::new(memory_kib, iterations, parallelism, Some(32)).map_err(|e| anyhow!("Invalid vault parameters: {}", e))?;
+    let mut key = [0u8; 32];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| anyhow!("Deriving the vault key failed: {}", e))?;
+    let cipher = XChaCha20Poly1305::new((&key).into());
+    key.fill(0);
+    Ok(cipher)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::command::DryRunCommandRunner;
+
+    fn vault_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("lunitool-vault-{}-{}.lkv", std::process::id(), name))
+    }
+
+    fn sample(path: &Path) -> Vault {
+        let mut vault = Vault::new(path);
+        vault.entries.push(VaultEntry::new(EntryKind::RecoveryKey, "root", "/dev/sda2", "1234-5678").with_notes("office safe"));
+        vault.entries.push(VaultEntry::new(EntryKind::Passphrase, "backup", "", "correct horse"));
+        vault
+    }
+
+    /// Save `vault` through the dry-run runner and write the bytes it would have installed
+    fn save(vault: &Vault, passphrase: &str) -> Vec<u8> {
+        let mut runner = DryRunCommandRunner::new();
+        vault.save(&mut runner, passphrase).unwrap();
+        let install = &runner.history()[0];
+        assert_eq!(install.to_string(), format!("install -D -m 0600 /dev/stdin {}", vault.path.display()));
+        let data = install.stdin.as_deref().cloned().unwrap();
+        std::fs::write(&vault.path, &data).unwrap();
+        data
+    }
+
+    #[test]
+    fn round_trip_and_wrong_passphrase() {
+        let path = vault_path("round-trip");
+        let vault = sample(&path);
+        let data = save(&vault, "master");
+        assert!(data.starts_with(MAGIC));
+        assert!(!data.windows(b"correct horse".len()).any(|w| w == b"correct horse"));
+
+        let opened = Vault::open(&path, "master").unwrap();
+        assert_eq!(opened.entries, vault.entries);
+        assert_eq!(opened.entry(1).unwrap().secret.expose(), "1234-5678");
+
+        let error = Vault::open(&path, "Master").unwrap_err();
+        assert_eq!(error.to_string(), "Wrong master passphrase or damaged vault");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn tampered_header_fails_authentication() {
+        let path = vault_path("tampered");
+        let mut data = save(&sample(&path), "master");
+        // Every header byte is authenticated; flip the last one of the nonce
+        data[HEADER_LEN - 1] ^= 1;
+        std::fs::write(&path, &data).unwrap();
+
+        let error = Vault::open(&path, "master").unwrap_err();
+        assert_eq!(error.to_string(), "Wrong master passphrase or damaged vault");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn refuses_costlier_parameters_than_new_vaults() {
+        let path = vault_path("costly");
+        for (offset, value) in [(8, MEMORY_KIB + 1), (12, ITERATIONS + 1), (16, PARALLELISM + 1), (8, u32::MAX)] {
+            let mut header = MAGIC.to_vec();
+            for number in [MEMORY_KIB, ITERATIONS, PARALLELISM] {
+                header.extend(number.to_le_bytes());
+            }
+            header[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+            header.resize(HEADER_LEN + 32, 0);
+            std::fs::write(&path, &header).unwrap();
+
+            let error = Vault::open(&path, "master").unwrap_err().to_string();
+            assert!(error.contains("higher key derivation cost"), "{}", error);
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn entries_are_numbered_from_one() {
+        let mut vault = sample(Path::new("/nonexistent/vault.lkv"));
+        assert!(vault.entry(0).is_err());
+        assert_eq!(vault.entry(2).unwrap().label, "backup");
+        assert_eq!(vault.entry(3).unwrap_err().to_string(), "The vault has no entry 3");
+
+        assert!(vault.remove(0).is_err());
+        assert!(vault.remove(3).is_err());
+        assert_eq!(vault.entries.len(), 2);
+        assert_eq!(vault.remove(1).unwrap().label, "root");
+        assert_eq!(vault.entry(1).unwrap().label, "backup");
+        assert!(vault.remove(2).is_err());
+    }
+
+    #[test]
+    fn save_needs_a_passphrase() {
+        let mut runner = DryRunCommandRunner::new();
+        assert!(sample(Path::new("/nonexistent/vault.lkv")).save(&mut runner, "").is_err());
+        assert!(runner.history().is_empty());
+    }
+}
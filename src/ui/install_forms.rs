@@ -0,0 +1,130 @@
+use crate::{
+    tools::installation::{network, tpm2, Tpm2Settings},
+    ui::widgets::form::{Form, Input},
+};
+
+/// Option of the interface dropdown that binds the connection to no interface
+pub const ANY_INTERFACE: &str = "any";
+pub const METHOD_DHCP: &str = "dhcp";
+pub const METHOD_STATIC: &str = "static";
+pub const HEADER_BACKUP_NONE: &str = "none";
+pub const HEADER_BACKUP_DIRECTORY: &str = "directory";
+pub const HEADER_BACKUP_USB: &str = "encrypted USB";
+
+/// Host, account and password fields of the User Setup step; empty fields keep the defaults of the plan
+pub fn user_setup_form() -> Form {
+    Form::new(vec![
+        Input::text("hostname", "PROMPT_HOSTNAME").validate(check_hostname),
+        Input::text("username", "PROMPT_USERNAME").validate(check_username),
+        Input::masked("user_password", "PROMPT_USER_PASSWORD"),
+        Input::masked("user_password_confirm", "PROMPT_CONFIRM_PASSWORD"),
+        Input::masked("root_password", "PROMPT_ROOT_PASSWORD"),
+        Input::masked("root_password_confirm", "PROMPT_CONFIRM_PASSWORD"),
+        Input::masked("luks_password", "PROMPT_LUKS_PASSPHRASE"),
+        Input::masked("luks_password_confirm", "PROMPT_CONFIRM_PASSPHRASE"),
+    ])
+}
+
+/// Wired network of the Network step; the address fields only show for a static setup
+pub fn network_form(interfaces: &[String]) -> Form {
+    let interfaces = std::iter::once(ANY_INTERFACE.to_string()).chain(interfaces.iter().cloned()).collect();
+    Form::new(vec![
+        Input::radio("method", "PROMPT_NETWORK_METHOD", vec![METHOD_DHCP.to_string(), METHOD_STATIC.to_string()]),
+        Input::dropdown("interface", "PROMPT_NETWORK_INTERFACE", interfaces),
+        Input::text("address", "PROMPT_NETWORK_ADDRESS")
+            .required()
+            .validate(|value| network::check_address(value.trim()).map_err(|_| "FORM_ERROR_ADDRESS"))
+            .shown_when("method", METHOD_STATIC),
+        Input::text("gateway", "PROMPT_NETWORK_GATEWAY")
+            .validate(|value| network::check_ip(value.trim()).map_err(|_| "FORM_ERROR_IP"))
+            .shown_when("method", METHOD_STATIC),
+        Input::text("dns", "PROMPT_NETWORK_DNS").validate(|value| network::parse_dns(value).map(|_| ()).map_err(|_| "FORM_ERROR_DNS")),
+        Input::number("mtu", "PROMPT_NETWORK_MTU", "bytes")
+            .validate(|value| value.parse().ok().and_then(|mtu| network::check_mtu(mtu).ok()).ok_or("FORM_ERROR_MTU")),
+        Input::checkbox("ipv6", "PROMPT_NETWORK_IPV6").with_value("yes"),
+    ])
+}
+
+/// LUKS header backup of the Summary step, preset to the directory from config.yaml
+pub fn header_backup_form(directory: Option<&str>) -> Form {
+    let mut store = Input::radio(
+        "header_backup",
+        "PROMPT_HEADER_BACKUP",
+        vec![HEADER_BACKUP_NONE.to_string(), HEADER_BACKUP_DIRECTORY.to_string(), HEADER_BACKUP_USB.to_string()],
+    );
+    if directory.is_some() {
+        store = store.with_value(HEADER_BACKUP_DIRECTORY);
+    }
+    Form::new(vec![
+        store,
+        Input::text("header_dir", "PROMPT_HEADER_BACKUP_DIR")
+            .with_value(directory.unwrap_or_default())
+            .required()
+            .shown_when("header_backup", HEADER_BACKUP_DIRECTORY),
+        Input::text("header_usb", "PROMPT_HEADER_BACKUP_USB")
+            .required()
+            .validate(check_device)
+            .shown_when("header_backup", HEADER_BACKUP_USB),
+        Input::masked("header_usb_passphrase", "PROMPT_HEADER_BACKUP_USB_PASSPHRASE")
+            .required()
+            .shown_when("header_backup", HEADER_BACKUP_USB),
+        Input::masked("header_usb_passphrase_confirm", "PROMPT_CONFIRM_PASSPHRASE").shown_when("header_backup", HEADER_BACKUP_USB),
+        Input::checkbox("header_usb_format", "PROMPT_HEADER_BACKUP_USB_FORMAT").shown_when("header_backup", HEADER_BACKUP_USB),
+    ])
+}
+
+/// TPM2 auto-unlock of the TPM2 step, preset from config.yaml; PCRs and PIN only show while it is enabled
+pub fn tpm2_form(settings: Option<&Tpm2Settings>) -> Form {
+    let checked = |on: bool| if on { "yes" } else { "" };
+    let pcrs = settings.map_or_else(|| tpm2::DEFAULT_TPM2_PCRS.to_vec(), |settings| settings.pcrs.clone());
+    let pcrs = pcrs.iter().map(u32::to_string).collect::<Vec<_>>().join("+");
+    Form::new(vec![
+        Input::checkbox("tpm2", "PROMPT_TPM2").with_value(checked(settings.is_some())),
+        Input::text("tpm2_pcrs", "PROMPT_TPM2_PCRS")
+            .with_value(&pcrs)
+            .required()
+            .validate(|value| tpm2::parse_pcrs(value).map(|_| ()).map_err(|_| "FORM_ERROR_PCRS"))
+            .shown_when("tpm2", "yes"),
+        Input::checkbox("tpm2_with_pin", "PROMPT_TPM2_WITH_PIN")
+            .with_value(checked(settings.is_some_and(|settings| settings.with_pin)))
+            .shown_when("tpm2", "yes"),
+        Input::masked("tpm2_pin", "PROMPT_TPM2_PIN").required().shown_when("tpm2_with_pin", "yes"),
+        Input::masked("tpm2_pin_confirm", "PROMPT_CONFIRM_PIN").shown_when("tpm2_with_pin", "yes"),
+    ])
+}
+
+/// Block device node such as `/dev/sdb`
+fn check_device(value: &str) -> Result<(), &'static str> {
+    match value.trim().strip_prefix("/dev/") {
+        Some(name) if !name.is_empty() => Ok(()),
+        _ => Err("FORM_ERROR_DEVICE"),
+    }
+}
+
+/// Single label of letters, digits and inner hyphens, as `hostnamectl` accepts it statically
+fn check_hostname(value: &str) -> Result<(), &'static str> {
+    let value = value.trim();
+    let valid = value.len() <= 63
+        && value.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        && !value.starts_with('-')
+        && !value.ends_with('-');
+    if valid {
+        Ok(())
+    } else {
+        Err("FORM_ERROR_HOSTNAME")
+    }
+}
+
+/// Lower case name as `useradd` accepts it on every supported distribution
+fn check_username(value: &str) -> Result<(), &'static str> {
+    let value = value.trim();
+    let mut chars = value.chars();
+    let valid = value.len() <= 32
+        && chars.next().is_some_and(|c| c.is_ascii_lowercase() || c == '_')
+        && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '_' | '-'));
+    if valid {
+        Ok(())
+    } else {
+        Err("FORM_ERROR_USERNAME")
+    }
+}
@@ -0,0 +1,795 @@
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Margin, Rect},
+    style::{Color, Modifier, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, BorderType, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
+    Frame,
+};
+
+use crate::{
+    lang::get_text,
+    tools::{
+        gpg::GpgKey,
+        installation::LuksProfile,
+        keyslots::{check_kill_slot, LuksContainer, LuksDump},
+        recovery_key::RecoverySheet,
+        password_strength::{self, LayoutPitfall},
+        shamir::Share,
+        vault::{EntryKind, VaultEntry},
+    },
+    ui::{
+        theme::Theme,
+        widgets::form::{Form, Input},
+    },
+};
+
+/// Operations offered for the selected LUKS container
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeyAction {
+    AddPassphrase,
+    AddKeyfile,
+    ChangePassphrase,
+    KillSlot,
+    ConvertPbkdf,
+    AddRecoveryKey,
+    SplitRecoveryKey,
+    CombineShares,
+    BackupHeader,
+    VerifyHeader,
+    RestoreHeader,
+    EncryptInPlace,
+    GenerateSshKey,
+    ImportSshKeys,
+    GenerateGpgKey,
+    ListGpgKeys,
+    ExportGpgKey,
+    RevokeGpgKey,
+    ImportGpgKeys,
+    CreateVault,
+    BrowseVault,
+    ShowVaultEntry,
+    AddVaultEntry,
+    RemoveVaultEntry,
+}
+
+impl KeyAction {
+    pub fn all() -> &'static [KeyAction] {
+        &[
+            KeyAction::AddPassphrase,
+            KeyAction::AddKeyfile,
+            KeyAction::ChangePassphrase,
+            KeyAction::KillSlot,
+            KeyAction::ConvertPbkdf,
+            KeyAction::AddRecoveryKey,
+            KeyAction::SplitRecoveryKey,
+            KeyAction::CombineShares,
+            KeyAction::BackupHeader,
+            KeyAction::VerifyHeader,
+            KeyAction::RestoreHeader,
+            KeyAction::EncryptInPlace,
+            KeyAction::GenerateSshKey,
+            KeyAction::ImportSshKeys,
+            KeyAction::GenerateGpgKey,
+            KeyAction::ListGpgKeys,
+            KeyAction::ExportGpgKey,
+            KeyAction::RevokeGpgKey,
+            KeyAction::ImportGpgKeys,
+            KeyAction::CreateVault,
+            KeyAction::BrowseVault,
+            KeyAction::ShowVaultEntry,
+            KeyAction::AddVaultEntry,
+            KeyAction::RemoveVaultEntry,
+        ]
+    }
+
+    /// Locale key of the menu entry
+    pub fn title_key(&self) -> &'static str {
+        match self {
+            KeyAction::AddPassphrase => "KEYS_ACTION_ADD_PASSPHRASE",
+            KeyAction::AddKeyfile => "KEYS_ACTION_ADD_KEYFILE",
+            KeyAction::ChangePassphrase => "KEYS_ACTION_CHANGE_PASSPHRASE",
+            KeyAction::KillSlot => "KEYS_ACTION_KILL_SLOT",
+            KeyAction::ConvertPbkdf => "KEYS_ACTION_CONVERT_PBKDF",
+            KeyAction::AddRecoveryKey => "KEYS_ACTION_ADD_RECOVERY_KEY",
+            KeyAction::SplitRecoveryKey => "KEYS_ACTION_SPLIT_RECOVERY_KEY",
+            KeyAction::CombineShares => "KEYS_ACTION_COMBINE_SHARES",
+            KeyAction::BackupHeader => "KEYS_ACTION_BACKUP_HEADER",
+            KeyAction::VerifyHeader => "KEYS_ACTION_VERIFY_HEADER",
+            KeyAction::RestoreHeader => "KEYS_ACTION_RESTORE_HEADER",
+            KeyAction::EncryptInPlace => "KEYS_ACTION_ENCRYPT_IN_PLACE",
+            KeyAction::GenerateSshKey => "KEYS_ACTION_GENERATE_SSH_KEY",
+            KeyAction::ImportSshKeys => "KEYS_ACTION_IMPORT_SSH_KEYS",
+            KeyAction::GenerateGpgKey => "KEYS_ACTION_GENERATE_GPG_KEY",
+            KeyAction::ListGpgKeys => "KEYS_ACTION_LIST_GPG_KEYS",
+            KeyAction::ExportGpgKey => "KEYS_ACTION_EXPORT_GPG_KEY",
+            KeyAction::RevokeGpgKey => "KEYS_ACTION_REVOKE_GPG_KEY",
+            KeyAction::ImportGpgKeys => "KEYS_ACTION_IMPORT_GPG_KEYS",
+            KeyAction::CreateVault => "KEYS_ACTION_CREATE_VAULT",
+            KeyAction::BrowseVault => "KEYS_ACTION_BROWSE_VAULT",
+            KeyAction::ShowVaultEntry => "KEYS_ACTION_SHOW_VAULT_ENTRY",
+            KeyAction::AddVaultEntry => "KEYS_ACTION_ADD_VAULT_ENTRY",
+            KeyAction::RemoveVaultEntry => "KEYS_ACTION_REMOVE_VAULT_ENTRY",
+        }
+    }
+
+    /// Whether the action works on the selected LUKS container
+    pub fn needs_container(&self) -> bool {
+        !matches!(
+            self,
+            KeyAction::EncryptInPlace
+                | KeyAction::GenerateSshKey
+                | KeyAction::ImportSshKeys
+                | KeyAction::GenerateGpgKey
+                | KeyAction::ListGpgKeys
+                | KeyAction::ExportGpgKey
+                | KeyAction::RevokeGpgKey
+                | KeyAction::ImportGpgKeys
+                | KeyAction::CreateVault
+                | KeyAction::BrowseVault
+                | KeyAction::ShowVaultEntry
+                | KeyAction::AddVaultEntry
+                | KeyAction::RemoveVaultEntry
+        )
+    }
+
+    /// Whether the "new" field becomes a LUKS passphrase, which GRUB reads with the US layout
+    pub fn sets_luks_passphrase(&self) -> bool {
+        matches!(
+            self,
+            KeyAction::AddPassphrase | KeyAction::ChangePassphrase | KeyAction::CombineShares | KeyAction::EncryptInPlace
+        )
+    }
+
+    /// Whether the action works on keyslots and needs a readable header
+    pub fn needs_header(&self) -> bool {
+        self.needs_container()
+            && !matches!(
+                self,
+                KeyAction::CombineShares | KeyAction::BackupHeader | KeyAction::VerifyHeader | KeyAction::RestoreHeader
+            )
+    }
+
+    /// Inputs the action asks for, in order
+    fn inputs(&self) -> Vec<Input> {
+        let existing = Input::masked("existing", "KEYS_FIELD_EXISTING_PASSPHRASE").required();
+        let slot = Input::number("slot", "KEYS_FIELD_SLOT", "").required();
+        let new = Input::masked("new", "KEYS_FIELD_NEW_PASSPHRASE");
+        let confirm = Input::masked("confirm", "KEYS_FIELD_CONFIRM_PASSPHRASE");
+        // Master passphrase of the vault the new key is stored in, if any
+        let store = Input::masked("vault", "KEYS_FIELD_STORE_IN_VAULT");
+        let master = Input::masked("master", "KEYS_FIELD_VAULT_PASSPHRASE").required();
+        let vault_path = Input::text("vault_path", "KEYS_FIELD_VAULT_PATH");
+        let entry = Input::number("entry", "KEYS_FIELD_VAULT_ENTRY", "").required();
+        match self {
+            KeyAction::AddPassphrase => vec![existing, new.required(), confirm.required(), store],
+            KeyAction::AddKeyfile => vec![existing, Input::text("keyfile", "KEYS_FIELD_KEYFILE").required(), store],
+            KeyAction::ChangePassphrase => vec![
+                slot,
+                Input::masked("existing", "KEYS_FIELD_CURRENT_PASSPHRASE").required(),
+                new.required(),
+                confirm.required(),
+            ],
+            KeyAction::KillSlot => vec![slot, Input::masked("existing", "KEYS_FIELD_REMAINING_PASSPHRASE").required()],
+            KeyAction::ConvertPbkdf => vec![
+                slot,
+                Input::masked("existing", "KEYS_FIELD_SLOT_PASSPHRASE").required(),
+                Input::dropdown("profile", "KEYS_FIELD_PROFILE", presets()),
+            ],
+            KeyAction::AddRecoveryKey => vec![existing, Input::text("sheet_dir", "KEYS_FIELD_SHEET_DIR"), store],
+            KeyAction::SplitRecoveryKey => vec![
+                existing,
+                Input::number("threshold", "KEYS_FIELD_THRESHOLD", "").required(),
+                Input::number("count", "KEYS_FIELD_SHARE_COUNT", "").required(),
+                Input::text("sheet_dir", "KEYS_FIELD_SHARE_DIR"),
+            ],
+            KeyAction::CombineShares => vec![
+                Input::text("shares", "KEYS_FIELD_SHARES").required(),
+                Input::radio("mode", "KEYS_FIELD_COMBINE_MODE", options(&["unlock", "add passphrase"])),
+                new.required().shown_when("mode", "add passphrase"),
+                confirm.required().shown_when("mode", "add passphrase"),
+            ],
+            KeyAction::BackupHeader => vec![
+                Input::text("target", "KEYS_FIELD_HEADER_TARGET").required(),
+                Input::masked("usb_passphrase", "KEYS_FIELD_USB_PASSPHRASE"),
+                Input::checkbox("format", "KEYS_FIELD_FORMAT_USB"),
+            ],
+            KeyAction::VerifyHeader => vec![
+                Input::text("header", "KEYS_FIELD_HEADER_FILE").required(),
+                Input::masked("existing", "KEYS_FIELD_TEST_PASSPHRASE"),
+                Input::text("usb", "KEYS_FIELD_HEADER_USB"),
+                Input::masked("usb_passphrase", "KEYS_FIELD_USB_PASSPHRASE"),
+            ],
+            KeyAction::RestoreHeader => vec![
+                Input::text("header", "KEYS_FIELD_HEADER_FILE").required(),
+                Input::masked("existing", "KEYS_FIELD_BACKUP_PASSPHRASE").required(),
+                Input::text("usb", "KEYS_FIELD_HEADER_USB"),
+                Input::masked("usb_passphrase", "KEYS_FIELD_USB_PASSPHRASE"),
+                Input::text("confirm_device", "KEYS_FIELD_CONFIRM_DEVICE").required(),
+            ],
+            KeyAction::EncryptInPlace => vec![
+                Input::text("partition", "KEYS_FIELD_INPLACE_PARTITION").required(),
+                Input::radio("content", "KEYS_FIELD_INPLACE_CONTENT", options(&["ext4", "lvm"])),
+                Input::text("volume_group", "KEYS_FIELD_INPLACE_VOLUME_GROUP").required().shown_when("content", "lvm"),
+                Input::text("root_lv", "KEYS_FIELD_INPLACE_ROOT_LV").required().shown_when("content", "lvm"),
+                Input::text("backup", "KEYS_FIELD_INPLACE_BACKUP").required(),
+                Input::radio("backup_kind", "KEYS_FIELD_INPLACE_BACKUP_KIND", options(&["directory", "image"])),
+                Input::dropdown("profile", "KEYS_FIELD_PROFILE", presets()),
+                new.required(),
+                confirm.required(),
+                Input::text("confirm_device", "KEYS_FIELD_INPLACE_CONFIRM_PARTITION").required(),
+            ],
+            KeyAction::GenerateSshKey => vec![
+                Input::text("path", "KEYS_FIELD_SSH_KEY_PATH").required(),
+                Input::text("comment", "KEYS_FIELD_SSH_COMMENT"),
+                Input::masked("new", "KEYS_FIELD_SSH_PASSPHRASE"),
+                confirm,
+                store,
+            ],
+            KeyAction::ImportSshKeys => vec![
+                Input::text("source", "KEYS_FIELD_SSH_SOURCE").required(),
+                Input::text("user", "KEYS_FIELD_SSH_USER").required(),
+            ],
+            KeyAction::GenerateGpgKey => vec![
+                Input::text("name", "KEYS_FIELD_GPG_NAME").required(),
+                Input::text("email", "KEYS_FIELD_GPG_EMAIL").required(),
+                Input::text("comment", "KEYS_FIELD_GPG_COMMENT"),
+                Input::text("expire", "KEYS_FIELD_GPG_EXPIRE"),
+                Input::masked("new", "KEYS_FIELD_GPG_PASSPHRASE"),
+                confirm,
+            ],
+            KeyAction::ListGpgKeys => vec![Input::text("filter", "KEYS_FIELD_GPG_FILTER")],
+            KeyAction::ExportGpgKey => vec![
+                Input::text("key", "KEYS_FIELD_GPG_KEY").required(),
+                Input::text("target", "KEYS_FIELD_GPG_TARGET").required(),
+                Input::radio("what", "KEYS_FIELD_GPG_EXPORT", options(&["public", "public + secret backup"])),
+                Input::masked("existing", "KEYS_FIELD_GPG_KEY_PASSPHRASE"),
+            ],
+            KeyAction::RevokeGpgKey => vec![
+                Input::text("key", "KEYS_FIELD_GPG_KEY").required(),
+                Input::text("target", "KEYS_FIELD_GPG_TARGET").required(),
+                Input::masked("existing", "KEYS_FIELD_GPG_KEY_PASSPHRASE"),
+            ],
+            KeyAction::ImportGpgKeys => vec![
+                Input::text("source", "KEYS_FIELD_GPG_SOURCE").required(),
+                Input::masked("existing", "KEYS_FIELD_GPG_KEY_PASSPHRASE"),
+            ],
+            KeyAction::CreateVault => vec![
+                Input::masked("new", "KEYS_FIELD_VAULT_PASSPHRASE").required(),
+                confirm.required(),
+                vault_path,
+            ],
+            KeyAction::BrowseVault => vec![master, vault_path],
+            KeyAction::ShowVaultEntry | KeyAction::RemoveVaultEntry => vec![entry, master, vault_path],
+            KeyAction::AddVaultEntry => vec![
+                Input::dropdown("kind", "KEYS_FIELD_VAULT_KIND", EntryKind::all().iter().map(|kind| kind.name().to_string()).collect()),
+                Input::text("label", "KEYS_FIELD_VAULT_LABEL").required(),
+                Input::text("device", "KEYS_FIELD_VAULT_DEVICE"),
+                Input::text("notes", "KEYS_FIELD_VAULT_NOTES"),
+                Input::masked("secret", "KEYS_FIELD_VAULT_SECRET"),
+                Input::text("file", "KEYS_FIELD_VAULT_FILE"),
+                master,
+                vault_path,
+            ],
+        }
+    }
+}
+
+fn options(names: &[&str]) -> Vec<String> {
+    names.iter().map(|name| name.to_string()).collect()
+}
+
+/// Names of the LUKS profile presets, for choice inputs
+fn presets() -> Vec<String> {
+    LuksProfile::presets().into_iter().map(|(name, _)| name.to_string()).collect()
+}
+
+/// Input form of a running key action
+#[derive(Debug, Clone)]
+pub struct KeyForm {
+    pub action: KeyAction,
+    pub form: Form,
+}
+
+impl KeyForm {
+    pub fn new(action: KeyAction) -> Self {
+        Self {
+            action,
+            form: Form::new(action.inputs()),
+        }
+    }
+
+    /// Value of the input `id`, empty if the action has no such input
+    pub fn value(&self, id: &str) -> &str {
+        self.form.value(id)
+    }
+
+    pub fn checked(&self, id: &str) -> bool {
+        self.form.checked(id)
+    }
+
+    /// Keyslot number entered in the form, if the action asks for one
+    pub fn slot(&self) -> Option<u32> {
+        self.value("slot").parse().ok()
+    }
+
+    /// Check the entries before anything is handed to cryptsetup
+    ///
+    /// `dump` is `None` if the header of the container could not be read.
+    /// Failing inputs are marked in the form as well.
+    pub fn validate(&mut self, device: &str, dump: Option<&LuksDump>) -> Result<(), String> {
+        if !self.form.validate() {
+            let input = self.form.focused_input();
+            return Err(format!("{}: {}", get_text(input.label_key), input.error.clone().unwrap_or_default()));
+        }
+        let unreadable = LuksDump::default();
+        let dump = match dump {
+            Some(dump) => dump,
+            None if self.action.needs_header() => return Err(get_text("KEYS_ERROR_NO_HEADER")),
+            None => &unreadable,
+        };
+        let uses_usb = self.value("target").starts_with("/dev/") || !self.value("usb").is_empty();
+        if uses_usb && self.value("usb_passphrase").is_empty() {
+            return self.reject("usb_passphrase", "KEYS_ERROR_USB_PASSPHRASE");
+        }
+        if self.action == KeyAction::RestoreHeader && self.value("confirm_device") != device {
+            return self.reject("confirm_device", "KEYS_ERROR_CONFIRM_DEVICE");
+        }
+        if self.action == KeyAction::EncryptInPlace && self.value("confirm_device") != self.value("partition") {
+            return self.reject("confirm_device", "KEYS_ERROR_CONFIRM_PARTITION");
+        }
+        if self.form.inputs.iter().any(|input| input.id == "slot") {
+            let Some(slot) = self.slot().filter(|slot| dump.keyslot(*slot).is_some()) else {
+                return self.reject("slot", "KEYS_ERROR_SLOT");
+            };
+            if self.action == KeyAction::KillSlot {
+                check_kill_slot(dump, device, slot).map_err(|e| e.to_string())?;
+            }
+        }
+        if self.action == KeyAction::SplitRecoveryKey {
+            let threshold: u8 = self.value("threshold").parse().unwrap_or(0);
+            let count: u8 = self.value("count").parse().unwrap_or(0);
+            if threshold < 2 || threshold > count {
+                return self.reject("threshold", "KEYS_ERROR_SHARES");
+            }
+        }
+        // Combining shares to unlock leaves the hidden passphrase inputs unused
+        if self.value("mode") != "unlock" && self.value("new") != self.value("confirm") {
+            return self.reject("confirm", "KEYS_ERROR_MISMATCH");
+        }
+        if self.action == KeyAction::AddVaultEntry && self.value("secret").is_empty() == self.value("file").is_empty() {
+            return self.reject("secret", "KEYS_ERROR_VAULT_SECRET");
+        }
+        Ok(())
+    }
+
+    /// Mark the input `id` with the message `key` and return it as the error
+    fn reject(&mut self, id: &str, key: &str) -> Result<(), String> {
+        self.form.set_error(id, get_text(key));
+        Err(get_text(key))
+    }
+
+    /// Title and message keys of the confirmation dialog the action needs, if any
+    pub fn confirmation(&self) -> Option<(&'static str, &'static str)> {
+        match self.action {
+            KeyAction::KillSlot => Some(("KEYS_KILL_CONFIRM_TITLE", "KEYS_KILL_CONFIRM_MESSAGE")),
+            KeyAction::RestoreHeader => Some(("KEYS_RESTORE_CONFIRM_TITLE", "KEYS_RESTORE_CONFIRM_MESSAGE")),
+            KeyAction::EncryptInPlace => Some(("KEYS_INPLACE_CONFIRM_TITLE", "KEYS_INPLACE_CONFIRM_MESSAGE")),
+            KeyAction::RemoveVaultEntry => Some(("KEYS_VAULT_REMOVE_CONFIRM_TITLE", "KEYS_VAULT_REMOVE_CONFIRM_MESSAGE")),
+            KeyAction::BackupHeader if self.checked("format") => Some(("KEYS_FORMAT_USB_CONFIRM_TITLE", "KEYS_FORMAT_USB_CONFIRM_MESSAGE")),
+            _ => None,
+        }
+    }
+
+    /// Strength and layout feedback for the focused input while a new passphrase is typed
+    pub fn feedback(&self, layout: &str, theme: &Theme) -> Vec<Line<'static>> {
+        let input = self.form.focused_input();
+        if !matches!(input.id, "new" | "confirm") || input.value.is_empty() {
+            return Vec::new();
+        }
+        let passphrase = self.value("new");
+        let layout = self.action.sets_luks_passphrase().then_some(layout);
+        passphrase_feedback(passphrase, layout, theme)
+    }
+}
+
+/// Lines rating `passphrase`: a strength bar, what makes it guessable and,
+/// for passphrases typed at boot, characters the `layout` puts elsewhere than GRUB
+pub fn passphrase_feedback(passphrase: &str, layout: Option<&str>, theme: &Theme) -> Vec<Line<'static>> {
+    let strength = password_strength::estimate(passphrase);
+    let color = match strength.score {
+        0 | 1 => theme.error,
+        2 => theme.warning,
+        _ => theme.success,
+    };
+    let filled = strength.score as usize + 1;
+    let mut lines = vec![Line::from(vec![
+        Span::styled(format!("{}: ", get_text("STRENGTH_TITLE")), Style::default().fg(theme.text_secondary)),
+        Span::styled(format!("{}{} ", "■".repeat(filled), "□".repeat(5 - filled)), Style::default().fg(color)),
+        Span::styled(get_text(strength.label_key()), Style::default().fg(color).add_modifier(Modifier::BOLD)),
+    ])];
+    if strength.score < 3 {
+        for pattern in &strength.patterns {
+            lines.push(Line::from(Span::styled(format!("  {}", get_text(pattern.warning_key())), Style::default().fg(theme.warning))));
+        }
+    }
+    for pitfall in layout.map(|layout| password_strength::layout_pitfalls(passphrase, layout)).unwrap_or_default() {
+        let chars: String = pitfall.chars().iter().collect();
+        let color = if matches!(pitfall, LayoutPitfall::NotOnUsKeyboard(_)) { theme.error } else { theme.warning };
+        lines.push(Line::from(Span::styled(format!("  {} {}", get_text(pitfall.warning_key()), chars), Style::default().fg(color))));
+    }
+    lines
+}
+
+/// Key material shown once after an action, until the next key press
+#[derive(Debug, Clone)]
+pub enum Revealed {
+    RecoveryKey(RecoverySheet),
+    /// Shares of a freshly split recovery key
+    Shares(RecoverySheet, Vec<Share>),
+    /// Keys of the lunitool GPG keyring
+    GpgKeys(Vec<GpgKey>),
+    /// Vault entries without their secrets
+    VaultEntries(Vec<VaultEntry>),
+    /// One vault entry with its secret and 1 based number
+    VaultSecret(usize, VaultEntry),
+}
+
+/// Which list of the key management screen has the focus
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeyFocus {
+    Containers,
+    Actions,
+}
+
+/// State of the key management screen
+#[derive(Debug, Clone)]
+pub struct KeyManagementState {
+    pub containers: Vec<LuksContainer>,
+    pub selected_container: usize,
+    /// Keyslots of the selected container, or the error reading them
+    pub dump: Option<Result<LuksDump, String>>,
+    pub focus: KeyFocus,
+    pub selected_action: usize,
+    pub form: Option<KeyForm>,
+    /// Outcome of the last action: Ok(message) or Err(error)
+    pub status: Option<Result<String, String>>,
+    /// Freshly created key material or a key listing, shown until the next key press
+    pub revealed: Option<Revealed>,
+    /// Layout of the running system, compared with the US layout of GRUB
+    pub keyboard_layout: String,
+}
+
+impl Default for KeyManagementState {
+    fn default() -> Self {
+        Self {
+            containers: Vec::new(),
+            selected_container: 0,
+            dump: None,
+            focus: KeyFocus::Containers,
+            selected_action: 0,
+            form: None,
+            status: None,
+            revealed: None,
+            keyboard_layout: String::new(),
+        }
+    }
+}
+
+impl KeyManagementState {
+    pub fn selected(&self) -> Option<&LuksContainer> {
+        self.containers.get(self.selected_container)
+    }
+
+    /// Dump of the selected container, if it could be read
+    pub fn selected_dump(&self) -> Option<&LuksDump> {
+        self.dump.as_ref().and_then(|dump| dump.as_ref().ok())
+    }
+
+    pub fn selected_action(&self) -> KeyAction {
+        KeyAction::all()[self.selected_action]
+    }
+}
+
+/// Draw the key management screen: containers, keyslots and tokens, actions
+pub fn draw_key_management(f: &mut Frame, state: &KeyManagementState, area: Rect, theme: &Theme) {
+    let area = area.inner(Margin { horizontal: 1, vertical: 0 });
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(35), Constraint::Percentage(65)])
+        .split(area);
+    let right = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(6),
+            Constraint::Max(KeyAction::all().len() as u16 + 2),
+            Constraint::Length(3),
+        ])
+        .split(columns[1]);
+
+    draw_container_list(f, state, columns[0], theme);
+    draw_keyslots(f, state, right[0], theme);
+    draw_actions(f, state, right[1], theme);
+    draw_status(f, state, right[2], theme);
+
+    if let Some(form) = &state.form {
+        draw_form(f, form, &state.keyboard_layout, columns[1], theme);
+    }
+    if let Some(revealed) = &state.revealed {
+        draw_revealed(f, revealed, area, theme);
+    }
+}
+
+/// Recovery key of `sheet` in groups, followed by its QR code
+///
+/// The QR code is drawn white on black so phones can scan it from the screen.
+pub fn recovery_key_lines(sheet: &RecoverySheet, theme: &Theme) -> Vec<Line<'static>> {
+    let mut lines = vec![
+        Line::from(Span::styled(get_text("RECOVERY_KEY_TITLE"), Style::default().fg(theme.warning).add_modifier(Modifier::BOLD))),
+        Line::from(Span::styled(format!("{} {} ({})", get_text("RECOVERY_KEY_DEVICE"), sheet.device, sheet.uuid), Style::default().fg(theme.text_secondary))),
+        Line::from(""),
+    ];
+    for line in sheet.key.display_lines() {
+        lines.push(Line::from(Span::styled(line, Style::default().fg(theme.text_primary).add_modifier(Modifier::BOLD))));
+    }
+    lines.push(Line::from(""));
+    if let Ok(qr) = sheet.key.qr_lines() {
+        for line in qr {
+            lines.push(Line::from(Span::styled(line, Style::default().fg(Color::White).bg(Color::Black))));
+        }
+        lines.push(Line::from(""));
+    }
+    lines.push(Line::from(Span::styled(get_text("RECOVERY_KEY_WARNING"), Style::default().fg(theme.warning))));
+    lines
+}
+
+/// Every share with its number, grouped like on the printed share sheets
+fn share_lines(sheet: &RecoverySheet, shares: &[Share], theme: &Theme) -> Vec<Line<'static>> {
+    let label_style = Style::default().fg(theme.accent_secondary).add_modifier(Modifier::BOLD);
+    let mut lines = vec![
+        Line::from(Span::styled(format!("{} {} ({})", get_text("RECOVERY_KEY_DEVICE"), sheet.device, sheet.uuid), Style::default().fg(theme.text_secondary))),
+        Line::from(""),
+    ];
+    for share in shares {
+        lines.push(Line::from(Span::styled(
+            format!("{} {}/{} ({} {})", get_text("KEYS_SHARE"), share.index, shares.len(), get_text("KEYS_SHARE_THRESHOLD"), share.threshold),
+            label_style,
+        )));
+        for line in share.display_lines() {
+            lines.push(Line::from(Span::styled(format!("  {}", line), Style::default().fg(theme.text_primary))));
+        }
+        lines.push(Line::from(""));
+    }
+    lines.push(Line::from(Span::styled(get_text("KEYS_SHARES_WARNING"), Style::default().fg(theme.warning))));
+    lines
+}
+
+/// Keys with fingerprint, dates, user IDs and subkeys, like `gpg --list-keys`
+fn gpg_key_lines(keys: &[GpgKey], theme: &Theme) -> Vec<Line<'static>> {
+    if keys.is_empty() {
+        return vec![Line::from(Span::styled(get_text("KEYS_GPG_EMPTY"), Style::default().fg(theme.text_secondary)))];
+    }
+    let label_style = Style::default().fg(theme.accent_secondary).add_modifier(Modifier::BOLD);
+    let text_style = Style::default().fg(theme.text_primary);
+    let detail_style = Style::default().fg(theme.text_secondary);
+    let dates = |created: &str, expires: &Option<String>| match expires {
+        Some(expires) => format!("{} → {}", created, expires),
+        None => created.to_string(),
+    };
+    let mut lines = Vec::new();
+    for key in keys {
+        let kind = if key.secret { "sec" } else { "pub" };
+        lines.push(Line::from(vec![
+            Span::styled(format!("{} {} ", kind, key.primary.algorithm), label_style),
+            Span::styled(key.primary.fingerprint.clone(), text_style),
+            Span::styled(format!("  [{}] {}", key.primary.usage, dates(&key.primary.created, &key.primary.expires)), detail_style),
+        ]));
+        for user_id in &key.user_ids {
+            lines.push(Line::from(Span::styled(format!("    {}", user_id), text_style)));
+        }
+        for subkey in &key.subkeys {
+            lines.push(Line::from(Span::styled(
+                format!("    sub {} [{}] {}", subkey.algorithm, subkey.usage, dates(&subkey.created, &subkey.expires)),
+                detail_style,
+            )));
+        }
+        lines.push(Line::from(""));
+    }
+    lines
+}
+
+/// Vault entry numbered like in the listing, with device, date and notes
+fn vault_entry_lines(number: usize, entry: &VaultEntry, theme: &Theme) -> Vec<Line<'static>> {
+    let detail_style = Style::default().fg(theme.text_secondary);
+    let mut details = vec![entry.created.clone()];
+    if !entry.device.is_empty() {
+        details.insert(0, entry.device.clone());
+    }
+    let mut lines = vec![Line::from(vec![
+        Span::styled(format!("{:>3}  {} ", number, entry.kind), Style::default().fg(theme.accent_secondary).add_modifier(Modifier::BOLD)),
+        Span::styled(entry.label.clone(), Style::default().fg(theme.text_primary)),
+        Span::styled(format!("  {}", details.join(", ")), detail_style),
+    ])];
+    if !entry.notes.is_empty() {
+        lines.push(Line::from(Span::styled(format!("     {}", entry.notes), detail_style)));
+    }
+    lines
+}
+
+fn vault_lines(entries: &[VaultEntry], theme: &Theme) -> Vec<Line<'static>> {
+    if entries.is_empty() {
+        return vec![Line::from(Span::styled(get_text("KEYS_VAULT_EMPTY"), Style::default().fg(theme.text_secondary)))];
+    }
+    entries.iter().enumerate().flat_map(|(index, entry)| vault_entry_lines(index + 1, entry, theme)).collect()
+}
+
+fn vault_secret_lines(number: usize, entry: &VaultEntry, theme: &Theme) -> Vec<Line<'static>> {
+    let mut lines = vault_entry_lines(number, entry, theme);
+    lines.push(Line::from(""));
+    for line in entry.secret_lines() {
+        lines.push(Line::from(Span::styled(line, Style::default().fg(theme.text_primary).add_modifier(Modifier::BOLD))));
+    }
+    lines
+}
+
+fn draw_revealed(f: &mut Frame, revealed: &Revealed, area: Rect, theme: &Theme) {
+    let (title_key, lines) = match revealed {
+        Revealed::RecoveryKey(sheet) => ("KEYS_ACTION_ADD_RECOVERY_KEY", recovery_key_lines(sheet, theme)),
+        Revealed::Shares(sheet, shares) => ("KEYS_ACTION_SPLIT_RECOVERY_KEY", share_lines(sheet, shares, theme)),
+        Revealed::GpgKeys(keys) => ("KEYS_ACTION_LIST_GPG_KEYS", gpg_key_lines(keys, theme)),
+        Revealed::VaultEntries(entries) => ("KEYS_ACTION_BROWSE_VAULT", vault_lines(entries, theme)),
+        Revealed::VaultSecret(number, entry) => ("KEYS_ACTION_SHOW_VAULT_ENTRY", vault_secret_lines(*number, entry, theme)),
+    };
+    f.render_widget(Clear, area);
+    let block = Block::default()
+        .title(Span::styled(get_text(title_key), Style::default().fg(theme.dialog_title).add_modifier(Modifier::BOLD)))
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .border_style(Style::default().fg(theme.dialog_border))
+        .bg(theme.dialog_bg);
+    f.render_widget(Paragraph::new(lines).block(block), area);
+}
+
+fn panel<'a>(title_key: &str, focused: bool, theme: &Theme) -> Block<'a> {
+    let border = if focused { theme.border_highlight } else { theme.border_primary };
+    Block::default()
+        .title(Span::styled(get_text(title_key), Style::default().fg(theme.accent_primary).add_modifier(Modifier::BOLD)))
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .border_style(Style::default().fg(border))
+        .bg(theme.background_secondary)
+}
+
+fn list_style(selected: bool, theme: &Theme) -> Style {
+    if selected {
+        Style::default().fg(theme.list_item_selected_fg).bg(theme.list_item_selected_bg).add_modifier(Modifier::BOLD)
+    } else {
+        Style::default().fg(theme.text_primary)
+    }
+}
+
+fn draw_container_list(f: &mut Frame, state: &KeyManagementState, area: Rect, theme: &Theme) {
+    let block = panel("KEYS_CONTAINERS_TITLE", state.focus == KeyFocus::Containers && state.form.is_none(), theme);
+    if state.containers.is_empty() {
+        let empty = Paragraph::new(get_text("KEYS_NO_CONTAINERS"))
+            .style(Style::default().fg(theme.text_secondary))
+            .wrap(Wrap { trim: true })
+            .block(block);
+        f.render_widget(empty, area);
+        return;
+    }
+    let items: Vec<ListItem> = state
+        .containers
+        .iter()
+        .enumerate()
+        .map(|(index, container)| {
+            let state_text = match &container.mapped_name {
+                Some(name) => format!("{} {}", get_text("KEYS_OPEN_AS"), name),
+                None => get_text("KEYS_CLOSED"),
+            };
+            ListItem::new(vec![
+                Line::from(format!("🔒 {}", container.device)),
+                Line::from(Span::styled(format!("   {}", state_text), Style::default().fg(theme.text_secondary))),
+            ])
+            .style(list_style(index == state.selected_container, theme))
+        })
+        .collect();
+    f.render_widget(List::new(items).block(block), area);
+}
+
+fn draw_keyslots(f: &mut Frame, state: &KeyManagementState, area: Rect, theme: &Theme) {
+    let block = panel("KEYS_KEYSLOTS_TITLE", false, theme);
+    let text_style = Style::default().fg(theme.text_primary);
+    let label_style = Style::default().fg(theme.accent_secondary).add_modifier(Modifier::BOLD);
+
+    let lines = match &state.dump {
+        None => vec![Line::from(Span::styled(get_text("KEYS_SELECT_CONTAINER"), text_style))],
+        Some(Err(error)) => vec![Line::from(Span::styled(error.clone(), Style::default().fg(theme.error)))],
+        Some(Ok(dump)) => {
+            let mut lines = vec![Line::from(vec![
+                Span::styled(format!("LUKS{} ", dump.version), label_style),
+                Span::styled(dump.uuid.clone().unwrap_or_default(), Style::default().fg(theme.text_secondary)),
+            ])];
+            for slot in &dump.keyslots {
+                let mut details = vec![slot.pbkdf.clone().unwrap_or_else(|| slot.kind.clone())];
+                if let Some(memory) = slot.memory_kib {
+                    details.push(format!("{} MiB", memory / 1024));
+                }
+                if let Some(priority) = slot.priority.as_deref().filter(|priority| *priority != "normal") {
+                    details.push(priority.to_string());
+                }
+                let tokens: Vec<String> = dump
+                    .tokens
+                    .iter()
+                    .filter(|token| token.keyslots.contains(&slot.id))
+                    .map(|token| token.kind.clone())
+                    .collect();
+                if !tokens.is_empty() {
+                    details.push(format!("{} {}", get_text("KEYS_BOUND_TO"), tokens.join(", ")));
+                }
+                lines.push(Line::from(vec![
+                    Span::styled(format!("  {} {:>2}  ", get_text("KEYS_SLOT"), slot.id), label_style),
+                    Span::styled(details.join(", "), text_style),
+                ]));
+            }
+            for token in &dump.tokens {
+                let slots: Vec<String> = token.keyslots.iter().map(|slot| slot.to_string()).collect();
+                lines.push(Line::from(vec![
+                    Span::styled(format!("  {} {:>2}  ", get_text("KEYS_TOKEN"), token.id), label_style),
+                    Span::styled(format!("{} → {}", token.kind, slots.join(", ")), text_style),
+                ]));
+            }
+            lines
+        }
+    };
+    f.render_widget(Paragraph::new(lines).wrap(Wrap { trim: false }).block(block), area);
+}
+
+fn draw_actions(f: &mut Frame, state: &KeyManagementState, area: Rect, theme: &Theme) {
+    let focused = state.focus == KeyFocus::Actions && state.form.is_none();
+    let items: Vec<ListItem> = KeyAction::all()
+        .iter()
+        .enumerate()
+        .map(|(index, action)| {
+            let marker = if focused && index == state.selected_action { "> " } else { "  " };
+            ListItem::new(format!("{}{}", marker, get_text(action.title_key())))
+                .style(list_style(focused && index == state.selected_action, theme))
+        })
+        .collect();
+    // Stateful, so the list scrolls to the selection on small terminals
+    let mut list_state = ListState::default().with_selected(Some(state.selected_action));
+    f.render_stateful_widget(List::new(items).block(panel("KEYS_ACTIONS_TITLE", focused, theme)), area, &mut list_state);
+}
+
+fn draw_status(f: &mut Frame, state: &KeyManagementState, area: Rect, theme: &Theme) {
+    let line = match &state.status {
+        Some(Ok(message)) => Span::styled(message.clone(), Style::default().fg(theme.success)),
+        Some(Err(error)) => Span::styled(error.clone(), Style::default().fg(theme.error)),
+        None => Span::styled(get_text("KEYS_HINT"), Style::default().fg(theme.text_secondary)),
+    };
+    f.render_widget(Paragraph::new(Line::from(line)).wrap(Wrap { trim: true }), area.inner(Margin { horizontal: 1, vertical: 1 }));
+}
+
+fn draw_form(f: &mut Frame, form: &KeyForm, layout: &str, area: Rect, theme: &Theme) {
+    let mut lines = form.form.lines(theme);
+    lines.push(Line::from(""));
+    lines.extend(form.feedback(layout, theme));
+    lines.push(Line::from(Span::styled(get_text("KEYS_FORM_HINT"), Style::default().fg(theme.text_secondary))));
+    let form_area = area.inner(Margin { horizontal: 2, vertical: 0 });
+    // Borders and margin take four rows; one more for the hint, which may wrap
+    let height = lines.len() as u16 + 5;
+    let form_area = Rect {
+        y: area.y + area.height.saturating_sub(height) / 2,
+        height: height.min(area.height),
+        ..form_area
+    };
+    f.render_widget(Clear, form_area);
+    let block = Block::default()
+        .title(Span::styled(get_text(form.action.title_key()), Style::default().fg(theme.dialog_title).add_modifier(Modifier::BOLD)))
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .border_style(Style::default().fg(theme.dialog_border))
+        .bg(theme.dialog_bg);
+    let inner = block.inner(form_area).inner(Margin { horizontal: 1, vertical: 1 });
+    f.render_widget(block, form_area);
+    f.render_widget(Paragraph::new(lines).wrap(Wrap { trim: false }), inner);
+}
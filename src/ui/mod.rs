@@ -1,4 +1,6 @@
 pub mod tui;
+pub mod key_management;
+pub mod install_forms;
 pub mod widgets;
 pub mod theme;
 
@@ -160,8 +160,8 @@ impl Theme {
             accent_secondary: accent_color,
             border_primary: border_color,
             border_highlight: Color::Cyan,
-            app_bg: app_bg,
-            content_bg: content_bg,
+            app_bg,
+            content_bg,
             block_bg: content_bg,
             text: text_color,
             title: title_color,
@@ -170,11 +170,11 @@ impl Theme {
             list_item_fg: text_color,
             list_item_selected_fg: list_item_selected_text,
             list_item_bg: content_bg,
-            list_item_selected_bg: list_item_selected_bg,
-            log_panel_bg: log_panel_bg,
+            list_item_selected_bg,
+            log_panel_bg,
             log_panel_text: text_color,
-            scrollbar_bg: scrollbar_bg,
-            scrollbar_thumb: scrollbar_thumb,
+            scrollbar_bg,
+            scrollbar_thumb,
             status_active: Some(accent_color),
             status_completed: Some(Color::Green),
             status_pending: Some(text_secondary_color),
@@ -183,8 +183,8 @@ impl Theme {
             dialog_fg: dialog_text_color,
             dialog_border: dialog_border_color,
             dialog_title: dialog_title_color,
-            dialog_selected_option_bg: dialog_selected_option_bg,
-            dialog_selected_option_text: dialog_selected_option_text,
+            dialog_selected_option_bg,
+            dialog_selected_option_text,
             input_bg: content_bg,
             input_text: text_color,
             input_border: border_color,
@@ -317,8 +317,8 @@ impl Theme {
             title: title_color,
             border: border_color,
             accent: accent_color,
-            list_item_fg: list_item_fg,
-            list_item_selected_fg: list_item_selected_fg,
+            list_item_fg,
+            list_item_selected_fg,
             list_item_bg: content_bg_color,
             list_item_selected_bg: list_item_selected_bg_color,
             list_item_inactive_selection_bg: list_item_selected_bg_color,
@@ -377,13 +377,13 @@ impl Theme {
             background_primary: base_bg,
             background_secondary: content_bg,
             text_primary: text_normal,
-            text_secondary: text_secondary,
+            text_secondary,
             accent_primary: accent_orange,
             accent_secondary: accent_green,
             border_primary: border_color,
             border_highlight: accent_yellow,
             app_bg: base_bg,
-            content_bg: content_bg,
+            content_bg,
             block_bg: content_bg,
             text: text_normal,
             title: accent_orange,
@@ -447,13 +447,13 @@ impl Theme {
             background_primary: base_bg,
             background_secondary: content_bg,
             text_primary: text_normal,
-            text_secondary: text_secondary,
+            text_secondary,
             accent_primary: accent_ubuntu_orange,
             accent_secondary: accent_ubuntu_aubergine,
             border_primary: border_color,
             border_highlight: accent_ubuntu_orange,
             app_bg: base_bg,
-            content_bg: content_bg,
+            content_bg,
             block_bg: content_bg,
             text: text_normal,
             title: accent_ubuntu_orange,
@@ -535,8 +535,8 @@ impl Theme {
             title: title_color,
             border: border_color,
             accent: accent_color,
-            list_item_fg: list_item_fg,
-            list_item_selected_fg: list_item_selected_fg,
+            list_item_fg,
+            list_item_selected_fg,
             list_item_bg: content_bg_color,
             list_item_selected_bg: list_item_selected_bg_color,
             list_item_inactive_selection_bg: Color::Rgb(50, 60, 80),
@@ -611,8 +611,8 @@ impl Theme {
             title: title_color,
             border: border_color,
             accent: accent_color,
-            list_item_fg: list_item_fg,
-            list_item_selected_fg: list_item_selected_fg,
+            list_item_fg,
+            list_item_selected_fg,
             list_item_bg: content_bg_color,
             list_item_selected_bg: list_item_selected_bg_color,
             list_item_inactive_selection_bg: Color::Rgb(70, 60, 75),
@@ -688,8 +688,8 @@ impl Theme {
             title: title_color,
             border: border_color,
             accent: accent_color,
-            list_item_fg: list_item_fg,
-            list_item_selected_fg: list_item_selected_fg,
+            list_item_fg,
+            list_item_selected_fg,
             list_item_bg: content_bg_color,
             list_item_selected_bg: list_item_selected_bg_color,
             list_item_inactive_selection_bg: Color::Rgb(70, 50, 40),
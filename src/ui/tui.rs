@@ -4,7 +4,7 @@ use crossterm::{
     execute,
     terminal::{self, EnterAlternateScreen},
 };
-use std::io;
+use std::{borrow::Cow, io};
 use ratatui::{
     backend::{CrosstermBackend},
     layout::{Constraint, Direction, Layout, Rect, Alignment, Margin},
@@ -18,10 +18,32 @@ use crate::{
     lang::get_text,
     ui::widgets::{Screen, DialogType, DisplayListItem, DisplayItemType, MenuItem},
     app::{InstallationStep, InstallationConfig, InstallationTaskItem, InstallationTaskStatus},
-    core::disk_info::{SystemDiskInfo, PartitionContent, MappedContent},
+    core::{disk_info::{SystemDiskInfo, PartitionContent, MappedContent}, secret::{redact, SecretString}},
+    tools::installation::{luks::{self, UnlockEstimate}, tpm2, InstallPhase, SecureBootMode},
 };
+use crate::ui::install_forms::{header_backup_form, network_form, tpm2_form, user_setup_form};
+use crate::ui::key_management::{draw_key_management, passphrase_feedback, recovery_key_lines, KeyManagementState};
+use crate::ui::widgets::form::Form;
 use crate::ui::theme::Theme;
 use crate::ui::theme::ThemeName;
+use crate::tools::{diceware::Passphrase, recovery_key::RecoverySheet};
+
+/// Maximum number of lines kept in the protocol buffer
+const MAX_LOG_LINES: usize = 2000;
+
+/// Live progress of the installation worker, fed by its events
+#[derive(Debug, Clone, Default)]
+pub struct InstallProgress {
+    pub phase: Option<InstallPhase>,
+    pub percent: u16,
+    pub current_command: Option<String>,
+    pub running: bool,
+    pub finished: bool,
+    pub cancelled: bool,
+    pub error: Option<String>,
+    /// Recovery key added during installation, shown on the summary
+    pub recovery: Option<RecoverySheet>,
+}
 
 /// UI state for the application
 #[derive(Debug)]
@@ -39,7 +61,16 @@ pub struct UiState {
     pub installation_tasks: Vec<InstallationTaskItem>,
     pub current_installation_task_index: usize,
     pub show_log_panel: bool,
-    pub input_buffer: String,
+    /// Host, account and password fields of the User Setup step
+    pub user_setup: Form,
+    /// Wired network of the Network step
+    pub network: Form,
+    /// Where the Summary step backs up the LUKS headers of an encrypted installation
+    pub header_backup: Form,
+    /// TPM2 auto-unlock of the root container
+    pub tpm2: Form,
+    /// Expected passphrase unlock cost per planned container, computed when the summary is entered
+    pub unlock_estimates: Vec<(&'static str, UnlockEstimate)>,
     pub installation_task_list_state: ListState,
 
     pub system_disk_info: Option<SystemDiskInfo>,
@@ -47,7 +78,13 @@ pub struct UiState {
     pub disk_setup_list_state: ListState,
     pub current_disk_display_items: Vec<DisplayListItem>,
     pub is_loading_disks: bool, 
+    /// Index into `SecureBootMode::all()` highlighted in the Secure Boot step
+    pub secure_boot_selected: usize,
     pub log_buffer: Vec<String>,
+    pub install_progress: InstallProgress,
+    pub key_management: KeyManagementState,
+    /// Passphrase generated for a password field, shown once until the next key press
+    pub generated_passphrase: Option<Passphrase>,
 
     // Scroll state for task description
     pub task_description_scroll_offset: usize,
@@ -90,14 +127,22 @@ impl UiState {
             installation_tasks: Vec::new(),
             current_installation_task_index: 0,
             show_log_panel: false,
-            input_buffer: String::new(),
+            user_setup: user_setup_form(),
+            network: network_form(&[]),
+            header_backup: header_backup_form(None),
+            tpm2: tpm2_form(None),
+            unlock_estimates: Vec::new(),
             installation_task_list_state: initial_task_list_state,
             system_disk_info: None,
             disk_setup_selected_item_path: None,
             disk_setup_list_state,
             current_disk_display_items: Vec::new(), 
             is_loading_disks: false, 
+            secure_boot_selected: 0,
             log_buffer: Vec::new(),
+            install_progress: InstallProgress::default(),
+            key_management: KeyManagementState::default(),
+            generated_passphrase: None,
             task_description_scroll_offset: 0,
             task_description_total_lines: 0,
             task_description_scrollbar_state: ScrollbarState::default(),
@@ -124,8 +169,8 @@ impl UiState {
             Screen::Message => {
                 // No reset for Message screen.
             }
-            Screen::SystemInstallation => {
-                // selected_index for SystemInstallation is managed by its own logic.
+            Screen::SystemInstallation | Screen::KeyManagement => {
+                // selected_index for these screens is managed by their own logic.
             }
         }
     }
@@ -205,12 +250,45 @@ impl UiState {
         self.current_screen = self.previous_screen;
     }
 
+    /// Append a line to the protocol, dropping the oldest lines beyond MAX_LOG_LINES
+    ///
+    /// Child output ends up here as well, so secrets are cut like in the log file.
+    pub fn push_log_line(&mut self, line: String) {
+        let line = match redact(&line) {
+            Cow::Borrowed(_) => line,
+            Cow::Owned(redacted) => redacted,
+        };
+        self.log_buffer.push(line);
+        if self.log_buffer.len() > MAX_LOG_LINES {
+            let overflow = self.log_buffer.len() - MAX_LOG_LINES;
+            self.log_buffer.drain(..overflow);
+        }
+    }
+
     /// Helper function to determine if current installation step needs text input
     pub fn installation_step_requires_text_input(&self) -> bool {
-        if let Some(step) = self.installation_step {
-            matches!(step, InstallationStep::UserSetup)
-        } else {
-            false
+        self.step_form().is_some()
+    }
+
+    /// Form of the current installation step, if it is built from one
+    pub fn step_form(&self) -> Option<&Form> {
+        match self.installation_step? {
+            InstallationStep::UserSetup => Some(&self.user_setup),
+            InstallationStep::NetworkConfig => Some(&self.network),
+            // TPM2 unlocking and header backups only exist for an encrypted installation
+            InstallationStep::Tpm2Setup if self.installation_config.luks_password.is_some() => Some(&self.tpm2),
+            InstallationStep::Summary if self.installation_config.luks_password.is_some() => Some(&self.header_backup),
+            _ => None,
+        }
+    }
+
+    pub fn step_form_mut(&mut self) -> Option<&mut Form> {
+        match self.installation_step? {
+            InstallationStep::UserSetup => Some(&mut self.user_setup),
+            InstallationStep::NetworkConfig => Some(&mut self.network),
+            InstallationStep::Tpm2Setup if self.installation_config.luks_password.is_some() => Some(&mut self.tpm2),
+            InstallationStep::Summary if self.installation_config.luks_password.is_some() => Some(&mut self.header_backup),
+            _ => None,
         }
     }
 
@@ -282,15 +360,43 @@ pub fn draw_ui(f: &mut Frame, state: &mut UiState) {
                 let theme_clone_for_installation = state.themes[active_theme_index].clone();
                 draw_installation_screen(f, state, content_area, &theme_clone_for_installation);
             }
+            Screen::KeyManagement => draw_key_management(f, &state.key_management, content_area, current_theme_ref),
             Screen::Message => draw_message(f, state, content_area, current_theme_ref),
             Screen::ConfirmExit => draw_confirm_exit(f, state, content_area, current_theme_ref),
         }
         // The theme reference is re-fetched here to avoid potential conflicts with the borrow checker,
         // if `state` was borrowed mutably in the match block above.
         draw_footer(f, state, footer_area, &state.themes[active_theme_index]); 
+        if let Some(passphrase) = &state.generated_passphrase {
+            draw_generated_passphrase(f, passphrase, &state.themes[active_theme_index]);
+        }
     }
 }
 
+/// Generated passphrase with its strength, shown once to be noted down
+fn draw_generated_passphrase(f: &mut Frame, passphrase: &Passphrase, theme: &Theme) {
+    let popup_area = centered_rect_exact(70, 10, f.area());
+    f.render_widget(Clear, popup_area);
+    let block = Block::default()
+        .title(Span::styled(get_text("PASSPHRASE_GENERATED_TITLE"), Style::default().fg(theme.dialog_title).add_modifier(Modifier::BOLD)))
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .border_style(Style::default().fg(theme.dialog_border))
+        .bg(theme.dialog_bg);
+    let lines = vec![
+        Line::from(""),
+        Line::from(Span::styled(passphrase.value.clone(), Style::default().fg(theme.text_primary).add_modifier(Modifier::BOLD))),
+        Line::from(""),
+        Line::from(Span::styled(
+            format!("{} {}, ≈ {:.0} {}", passphrase.words, get_text("PASSPHRASE_WORDS"), passphrase.entropy_bits, get_text("PASSPHRASE_BITS")),
+            Style::default().fg(theme.text_secondary),
+        )),
+        Line::from(""),
+        Line::from(Span::styled(get_text("PASSPHRASE_GENERATED_HINT"), Style::default().fg(theme.warning))),
+    ];
+    f.render_widget(Paragraph::new(lines).alignment(Alignment::Center).wrap(Wrap { trim: true }).block(block), popup_area);
+}
+
 fn draw_header(f: &mut Frame, state: &UiState, area: Rect, theme: &Theme) { 
     let bg_color = theme.background_secondary;
     
@@ -329,6 +435,7 @@ fn draw_header(f: &mut Frame, state: &UiState, area: Rect, theme: &Theme) {
         Screen::KeyboardSelect => "LANG_KEYBOARD_SELECT",
         Screen::MainMenu => "LANG_MAIN_MENU",
         Screen::SystemInstallation => "INSTALL_HEADER_LINE2",
+        Screen::KeyManagement => "LANG_KEYS",
         _ => "LANG_SUBTITLE", 
     };
     let subtitle_str = get_text(subtitle_key);
@@ -377,7 +484,18 @@ fn draw_footer(f: &mut Frame, state: &UiState, area: Rect, theme: &Theme) {
             hints.push(Span::styled(format!(": {} ", get_text("LANG_NAVIGATE_SHORT")), Style::default().fg(key_description_color)));
             hints.push(Span::styled(format!("| Backspace: {}", get_text("LANG_BACK_SHORT")), Style::default().fg(key_description_color)));
         }
-        Screen::LanguageSelect | Screen::KeyboardSelect | Screen::MainMenu => {
+        Screen::KeyManagement if state.key_management.revealed.is_some() => {
+            hints.push(Span::styled(get_text("KEYS_RECOVERY_DISMISS"), Style::default().fg(key_description_color)));
+        }
+        Screen::KeyManagement if state.key_management.form.is_some() => {
+            hints.push(Span::styled("Tab/↑/↓", Style::default().fg(key_label_color)));
+            hints.push(Span::styled(format!(": {} | ", get_text("LANG_NAVIGATE_SHORT")), Style::default().fg(key_description_color)));
+            hints.push(Span::styled("Enter", Style::default().fg(key_label_color)));
+            hints.push(Span::styled(format!(": {} | ", get_text("LANG_CONFIRM_SHORT")), Style::default().fg(key_description_color)));
+            hints.push(Span::styled("Esc", Style::default().fg(key_label_color)));
+            hints.push(Span::styled(format!(": {}", get_text("LANG_CANCEL_SHORT")), Style::default().fg(key_description_color)));
+        }
+        Screen::LanguageSelect | Screen::KeyboardSelect | Screen::MainMenu | Screen::KeyManagement => {
             hints.push(Span::styled("↑/↓", Style::default().fg(key_label_color)));
             hints.push(Span::styled(format!(": {} | ", get_text("LANG_NAVIGATE_SHORT")), Style::default().fg(key_description_color)));
             hints.push(Span::styled("Enter", Style::default().fg(key_label_color)));
@@ -813,7 +931,7 @@ pub fn build_disk_display_list(disk_info: &SystemDiskInfo) -> Vec<DisplayListIte
 
             let part_display_text = format!(
                 "{}└─ {} {} ({}, {})",
-                indent_char.repeat(1),
+                indent_char,
                 part_prefix,
                 partition.path,
                 format_size(partition.size_bytes),
@@ -834,7 +952,7 @@ pub fn build_disk_display_list(disk_info: &SystemDiskInfo) -> Vec<DisplayListIte
                         let luks_id = format!("{}/luks/{}", part_id_base, uuid);
                         let luks_display_text = format!(
                             "{}  └─ {} LUKS Container ({}){}",
-                            indent_char.repeat(1),
+                            indent_char,
                             luks_prefix,
                             uuid,
                             mapped_name.as_ref().map_or_else(|| " - Not active".to_string(), |mn| format!("  {}", mn))
@@ -854,7 +972,7 @@ pub fn build_disk_display_list(disk_info: &SystemDiskInfo) -> Vec<DisplayListIte
                                     let pv_id = format!("{}/lvm_pv/{}", luks_id, pv_data.pv_uuid);
                                     let pv_text = format!(
                                         "{}    └─ {} LVM PV on {} (for VG: {})",
-                                        indent_char.repeat(1),
+                                        indent_char,
                                         lvm_prefix,
                                         mn, 
                                         pv_data.vg_name.as_deref().unwrap_or("Unknown")
@@ -875,7 +993,7 @@ pub fn build_disk_display_list(disk_info: &SystemDiskInfo) -> Vec<DisplayListIte
                                     if let Some(mp) = mount_point { fs_details_mc.push(format!("at '{}'", mp)); }
                                     let fs_text_mc = format!(
                                         "{}    └─ {} {} on {} ({})",
-                                        indent_char.repeat(1),
+                                        indent_char,
                                         fs_prefix,
                                         fs_type.as_deref().unwrap_or("Filesystem"),
                                         mn,
@@ -898,7 +1016,7 @@ pub fn build_disk_display_list(disk_info: &SystemDiskInfo) -> Vec<DisplayListIte
                         let lvm_pv_id = format!("{}/direct_lvm_pv/{}", part_id_base, pv_uuid);
                         let lvm_pv_text = format!(
                             "{}  └─ {} LVM PV (for VG: {})",
-                            indent_char.repeat(1),
+                            indent_char,
                             lvm_prefix,
                             vg_name.as_deref().unwrap_or("Unknown")
                         );
@@ -916,7 +1034,7 @@ pub fn build_disk_display_list(disk_info: &SystemDiskInfo) -> Vec<DisplayListIte
                     PartitionContent::Unknown => {
                         items.push(DisplayListItem {
                             id_path: format!("{}/unallocated", part_id_base),
-                            display_text: format!("{}  └─ Unallocated or Unknown Space", indent_char.repeat(1)),
+                            display_text: format!("{}  └─ Unallocated or Unknown Space", indent_char),
                             indent_level: 2,
                             item_type: DisplayItemType::UnallocatedSpace,
                             selectable: true,
@@ -964,7 +1082,7 @@ pub fn build_disk_display_list(disk_info: &SystemDiskInfo) -> Vec<DisplayListIte
                 
                 let lv_text = format!(
                     "{}└─ {} {} ({}){}",
-                    indent_char.repeat(1),
+                    indent_char,
                     fs_prefix,
                     lv.name, 
                     lv_details.join(", "),
@@ -998,10 +1116,12 @@ pub fn draw_installation_screen(
     let total_tasks = state.installation_tasks.len();
     let current_task_idx = state.current_installation_task_index;
 
-    let calculated_progress_percent = if total_tasks == 0 {
+    let calculated_progress_percent = if matches!(state.installation_step, Some(InstallationStep::Installing) | Some(InstallationStep::Completed)) {
+        state.install_progress.percent
+    } else if total_tasks == 0
+        || (state.installation_step == Some(InstallationStep::Welcome) && current_task_idx == 0)
+    {
         0
-    } else if state.installation_step == Some(InstallationStep::Welcome) && current_task_idx == 0 {
-        0 
     } else {
         let percentage = (current_task_idx as f32 / total_tasks as f32) * 100.0;
         percentage.round() as u16
@@ -1108,7 +1228,10 @@ pub fn draw_installation_screen(
         let inner_area_for_bg = log_border_block.inner(main_content_area_for_drawing);
         f.render_widget(Block::default().bg(log_panel_bg), inner_area_for_bg);
 
-        let log_text_content = state.log_buffer.iter().map(|line| Line::from(line.as_str())).collect::<Vec<Line>>();
+        // Only the tail fits; keep the newest output visible while the installation runs.
+        let visible_lines = inner_area_for_bg.height as usize;
+        let first_visible = state.log_buffer.len().saturating_sub(visible_lines);
+        let log_text_content = state.log_buffer[first_visible..].iter().map(|line| Line::from(line.as_str())).collect::<Vec<Line>>();
         let log_text_paragraph = Paragraph::new(log_text_content)
             .style(Style::default().fg(log_panel_text))
             .wrap(Wrap { trim: true })
@@ -1135,7 +1258,7 @@ pub fn draw_installation_screen(
     let command_status_label_text = get_text("INSTALL_LABEL_COMMAND_STATUS");
     
     let current_task_name = state.current_installation_task_name();
-    let command_text = "<idle>"; 
+    let command_text = state.install_progress.current_command.as_deref().unwrap_or("<idle>");
 
     let status_label_color = theme.accent_primary; 
     let status_value_color = text_color;  
@@ -1189,7 +1312,7 @@ fn draw_main_installation_content(f: &mut Frame, state: &mut UiState, area: Rect
             let mut lines = vec![];
             if state.is_loading_disks {
                 lines.push(Line::from(Span::styled(get_text("INFO_LOADING_DISKS"), Style::default().fg(text_color))));
-            } else if state.system_disk_info.is_none() || state.system_disk_info.as_ref().map_or(true, |sdi| sdi.disks.is_empty()) {
+            } else if state.system_disk_info.is_none() || state.system_disk_info.as_ref().is_none_or(|sdi| sdi.disks.is_empty()) {
                 lines.push(Line::from(Span::styled(get_text("INFO_NO_DISKS_FOUND"), Style::default().fg(text_color))));
             } else {
                 lines.push(Line::from(Span::styled(get_text("PROMPT_SELECT_DISK"), Style::default().fg(text_color))));
@@ -1197,20 +1320,204 @@ fn draw_main_installation_content(f: &mut Frame, state: &mut UiState, area: Rect
             lines
         }
         InstallationStep::UserSetup => {
-            let prompt = format!("{}: ", get_text("PROMPT_HOSTNAME"));
-            let input_text = state.input_buffer.as_str();
-            vec![Line::from(Span::styled(format!("{}{}", prompt, input_text), Style::default().fg(text_color)))] 
+            let form = &state.user_setup;
+            let mut lines = form.lines(theme);
+            lines.push(Line::from(""));
+            // Rate the password the focused field belongs to, also while it is confirmed
+            let focused = form.focused_input();
+            let id = focused.id.trim_end_matches("_confirm");
+            let password = form.value(id);
+            if focused.is_masked() && !password.is_empty() {
+                // The disk passphrase is typed at the GRUB prompt as well, with the US layout
+                let layout = (id == "luks_password").then(|| state.installation_config.keyboard_layout.as_deref().unwrap_or("us"));
+                lines.extend(passphrase_feedback(password, layout, theme));
+                lines.push(Line::from(""));
+            }
+            lines.push(Line::from(Span::styled(get_text("INSTALL_USER_SETUP_HINT"), Style::default().fg(theme.text_secondary))));
+            lines
+        }
+        InstallationStep::NetworkConfig => {
+            let mut lines = state.network.lines(theme);
+            lines.push(Line::from(""));
+            lines.push(Line::from(Span::styled(get_text("INSTALL_NETWORK_HINT"), Style::default().fg(theme.text_secondary))));
+            lines
+        }
+        InstallationStep::SecureBootChoice => {
+            let mut lines = vec![
+                Line::from(Span::styled(get_text("PROMPT_SECURE_BOOT_MODE"), Style::default().fg(text_color))),
+                Line::from(""),
+            ];
+            for (index, mode) in SecureBootMode::all().iter().enumerate() {
+                let label = get_text(mode.title_key());
+                if index == state.secure_boot_selected {
+                    lines.push(Line::from(Span::styled(
+                        format!("> {}", label),
+                        Style::default().fg(theme.list_item_selected_fg).bg(theme.list_item_selected_bg).add_modifier(Modifier::BOLD),
+                    )));
+                } else {
+                    lines.push(Line::from(Span::styled(format!("  {}", label), Style::default().fg(text_color))));
+                }
+            }
+            lines
+        }
+        InstallationStep::Tpm2Setup => match state.step_form() {
+            Some(form) => {
+                let mut lines = form.lines(theme);
+                lines.push(Line::from(""));
+                lines.push(Line::from(Span::styled(get_text("INSTALL_TPM2_HINT"), Style::default().fg(theme.text_secondary))));
+                lines
+            }
+            None => vec![Line::from(Span::styled(get_text("INSTALL_TPM2_NO_ENCRYPTION"), Style::default().fg(text_color)))],
+        },
+        InstallationStep::Summary => {
+            let config = &state.installation_config;
+            let label_style = Style::default().fg(theme.accent_primary).add_modifier(Modifier::BOLD);
+            let mut lines = vec![
+                Line::from(Span::styled(get_text("INSTALL_SUMMARY_MESSAGE"), Style::default().fg(text_color))),
+                Line::from(""),
+                Line::from(vec![
+                    Span::styled(format!("{} ", get_text("SUMMARY_LABEL_DISK")), label_style),
+                    Span::styled(config.target_disk.clone().unwrap_or_default(), Style::default().fg(text_color)),
+                ]),
+                Line::from(vec![
+                    Span::styled(format!("{} ", get_text("SUMMARY_LABEL_SECURE_BOOT")), label_style),
+                    Span::styled(get_text(config.secure_boot.title_key()), Style::default().fg(text_color)),
+                ]),
+                Line::from(vec![
+                    Span::styled(format!("{} ", get_text("SUMMARY_LABEL_TPM2")), label_style),
+                    Span::styled(tpm2_summary(config), Style::default().fg(text_color)),
+                ]),
+            ];
+            if !state.unlock_estimates.is_empty() {
+                lines.push(Line::from(vec![
+                    Span::styled(format!("{} ", get_text("SUMMARY_LABEL_UNLOCK")), label_style),
+                    Span::styled(unlock_summary(&state.unlock_estimates), Style::default().fg(text_color)),
+                ]));
+            }
+            if state.unlock_estimates.iter().any(|(_, estimate)| estimate.grub_ms.is_some()) {
+                lines.push(Line::from(Span::styled(get_text("SUMMARY_UNLOCK_GRUB_HINT"), Style::default().fg(theme.text_secondary))));
+            }
+            if let Some(password) = &config.mok_password {
+                lines.push(Line::from(""));
+                lines.extend(mok_password_lines(password, theme));
+            }
+            lines.extend(tpm2_warning_lines(config, theme));
+            if let Some(form) = state.step_form() {
+                lines.push(Line::from(""));
+                lines.push(Line::from(Span::styled(get_text("INSTALL_HEADER_BACKUP_HINT"), Style::default().fg(theme.text_secondary))));
+                lines.extend(form.lines(theme));
+            }
+            lines
+        }
+        InstallationStep::Installing => {
+            let progress = &state.install_progress;
+            let phase_text = progress.phase.map_or_else(|| get_text("INSTALL_PHASE_PREPARING"), |p| get_text(p.title_key()));
+            let mut lines = vec![
+                Line::from(vec![
+                    Span::styled(format!("{} ", get_text("INSTALL_LABEL_PHASE")), Style::default().fg(theme.accent_primary).add_modifier(Modifier::BOLD)),
+                    Span::styled(format!("{} ({}%)", phase_text, progress.percent), Style::default().fg(text_color)),
+                ]),
+                Line::from(""),
+            ];
+            if let Some(error) = &progress.error {
+                lines.push(Line::from(Span::styled(get_text("INSTALL_FAILED_MESSAGE"), Style::default().fg(theme.error).add_modifier(Modifier::BOLD))));
+                lines.push(Line::from(Span::styled(error.clone(), Style::default().fg(theme.error))));
+            } else if progress.cancelled {
+                lines.push(Line::from(Span::styled(get_text("INSTALL_CANCELLED_MESSAGE"), Style::default().fg(theme.warning).add_modifier(Modifier::BOLD))));
+            } else {
+                lines.push(Line::from(Span::styled(get_text("INSTALL_RUNNING_MESSAGE"), Style::default().fg(text_color))));
+            }
+            lines.push(Line::from(""));
+            lines.push(Line::from(Span::styled(get_text("INSTALL_LOG_HINT"), Style::default().fg(theme.text_secondary))));
+            lines
+        }
+        InstallationStep::Completed => {
+            let mut lines = vec![
+                Line::from(Span::styled(get_text("INSTALL_COMPLETED_MESSAGE"), Style::default().fg(theme.success).add_modifier(Modifier::BOLD))),
+            ];
+            if let Some(sheet) = &state.install_progress.recovery {
+                lines.push(Line::from(""));
+                lines.extend(recovery_key_lines(sheet, theme));
+            }
+            if let Some(password) = &state.installation_config.mok_password {
+                lines.push(Line::from(""));
+                lines.extend(mok_password_lines(password, theme));
+            }
+            lines.extend(tpm2_warning_lines(&state.installation_config, theme));
+            lines
         }
         _ => vec![Line::from(Span::styled(get_text("INFO_PENDING_IMPLEMENTATION"), Style::default().fg(text_color)))],
     };
     
+    // Forms are aligned with leading spaces, which trimming would remove
     let content_paragraph = Paragraph::new(current_step_details)
-        .wrap(Wrap { trim: true })
+        .wrap(Wrap { trim: state.step_form().is_none() })
         .style(Style::default().fg(text_color));
     
     f.render_widget(content_paragraph, inner_area.inner(Margin { vertical: 1, horizontal: 1 }));
 }
 
+/// The MokManager password with the steps confirming the key enrollment
+fn mok_password_lines(password: &SecretString, theme: &Theme) -> Vec<Line<'static>> {
+    vec![
+        Line::from(Span::styled(get_text("MOK_PASSWORD_TITLE"), Style::default().fg(theme.warning).add_modifier(Modifier::BOLD))),
+        Line::from(Span::styled(password.expose().to_string(), Style::default().fg(theme.text_primary).add_modifier(Modifier::BOLD))),
+        Line::from(Span::styled(get_text("MOK_PASSWORD_HINT"), Style::default().fg(theme.text_secondary))),
+    ]
+}
+
+/// PCRs the TPM key is sealed against and whether a PIN is asked, or that TPM2 is off
+fn tpm2_summary(config: &InstallationConfig) -> String {
+    match &config.tpm2 {
+        Some(tpm2) => {
+            let pcrs = tpm2.pcrs.iter().map(u32::to_string).collect::<Vec<_>>().join("+");
+            let pin = if tpm2.with_pin { get_text("SUMMARY_TPM2_PIN") } else { String::new() };
+            format!("PCR {}{}", pcrs, pin)
+        }
+        None => get_text("SUMMARY_TPM2_OFF"),
+    }
+}
+
+/// Unlock time per container, e.g. `root 2 s, 1024 MiB memory; /boot 2 s, 20 s in GRUB`
+fn unlock_summary(estimates: &[(&str, UnlockEstimate)]) -> String {
+    estimates
+        .iter()
+        .map(|(name, estimate)| {
+            let mut text = format!("{} {}", name, luks::seconds(estimate.cryptsetup_ms));
+            if let Some(memory) = estimate.memory_kib {
+                text.push_str(&format!(", {} MiB {}", memory / 1024, get_text("SUMMARY_UNLOCK_MEMORY")));
+            }
+            if let Some(grub) = estimate.grub_ms {
+                text.push_str(&format!(", {} {}", luks::seconds(grub), get_text("SUMMARY_UNLOCK_GRUB")));
+            }
+            text
+        })
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Warning that the TPM cannot unlock the root container until it is enrolled again
+///
+/// Empty unless the TPM key is sealed against PCR 7 and the installation
+/// enrolls new Secure Boot keys, which change PCR 7 on the next boot.
+fn tpm2_warning_lines(config: &InstallationConfig, theme: &Theme) -> Vec<Line<'static>> {
+    let Some(tpm2) = config.tpm2.as_ref().filter(|_| config.luks_password.is_some()) else {
+        return Vec::new();
+    };
+    if !tpm2::breaks_on_first_boot(&tpm2.pcrs, config.secure_boot) {
+        return Vec::new();
+    }
+    let pcrs = tpm2.pcrs.iter().map(u32::to_string).collect::<Vec<_>>().join("+");
+    vec![
+        Line::from(""),
+        Line::from(Span::styled(get_text("TPM2_PCR7_WARNING"), Style::default().fg(theme.warning).add_modifier(Modifier::BOLD))),
+        Line::from(Span::styled(
+            format!("systemd-cryptenroll --wipe-slot=tpm2 --tpm2-device=auto --tpm2-pcrs={} {}", pcrs, get_text("TPM2_ROOT_PARTITION")),
+            Style::default().fg(theme.text_primary),
+        )),
+    ]
+}
+
 fn draw_task_description(f: &mut Frame, state: &mut UiState, area: Rect, theme: &Theme) {
     let text_color = theme.text_secondary;
     let border_color = theme.border_primary;
@@ -1233,6 +1540,12 @@ fn draw_task_description(f: &mut Frame, state: &mut UiState, area: Rect, theme:
         InstallationStep::Welcome => "INSTALL_WELCOME_DESC",
         InstallationStep::DiskSetup => "INSTALL_DISK_SETUP_DESC",
         InstallationStep::UserSetup => "INSTALL_USER_SETUP_DESC",
+        InstallationStep::NetworkConfig => "INSTALL_NETWORK_DESC",
+        InstallationStep::SecureBootChoice => "INSTALL_SECURE_BOOT_DESC",
+        InstallationStep::Tpm2Setup => "INSTALL_TPM2_DESC",
+        InstallationStep::Summary => "INSTALL_SUMMARY_DESC",
+        InstallationStep::Installing => "INSTALL_INSTALLING_DESC",
+        InstallationStep::Completed => "INSTALL_COMPLETED_DESC",
         _ => "INFO_PENDING_IMPLEMENTATION",
     };
     let description_text_content = get_text(description_text_key);
@@ -1263,7 +1576,7 @@ fn draw_task_description(f: &mut Frame, state: &mut UiState, area: Rect, theme:
 
         f.render_widget(text_widget, text_render_area);
 
-        let current_content_length = state.task_description_total_lines.saturating_sub(visible_height).max(0);
+        let current_content_length = state.task_description_total_lines.saturating_sub(visible_height);
         state.task_description_scrollbar_state = state.task_description_scrollbar_state
             .content_length(current_content_length)
             .position(state.task_description_scroll_offset);
@@ -0,0 +1,550 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    style::{Modifier, Style},
+    text::{Line, Span},
+};
+use zeroize::Zeroize;
+
+use crate::{core::secret::REDACTED, lang::get_text, ui::theme::Theme};
+
+/// Width of the input area of text fields, in characters
+const INPUT_WIDTH: usize = 28;
+/// Value of a ticked checkbox
+const CHECKED: &str = "yes";
+
+/// Check of a single value; the error is a locale key
+pub type Validator = fn(&str) -> Result<(), &'static str>;
+
+/// How an input takes and shows its value
+#[derive(Debug, Clone, PartialEq)]
+pub enum InputKind {
+    Text,
+    /// Shown as bullets until revealed with F3
+    Masked,
+    /// Digits only, followed by the unit
+    Number { unit: &'static str },
+    /// Toggled with Space; the value is "yes" or empty
+    Checkbox,
+    /// All options in a row, selected with Left/Right or Space
+    Radio(Vec<String>),
+    /// Current option only; Enter or Space opens the list
+    Dropdown(Vec<String>),
+}
+
+/// One field of a `Form`
+#[derive(Clone)]
+pub struct Input {
+    pub id: &'static str,
+    pub label_key: &'static str,
+    pub kind: InputKind,
+    pub value: String,
+    /// Cursor position in characters
+    cursor: usize,
+    /// Masked value shown in plain text
+    pub revealed: bool,
+    pub required: bool,
+    validator: Option<Validator>,
+    /// Only shown and checked while the input `.0` is shown and has the value `.1`
+    shown_when: Option<(&'static str, &'static str)>,
+    /// Message of the last failed check
+    pub error: Option<String>,
+    /// Highlighted option while a dropdown is open
+    open: Option<usize>,
+}
+
+impl Input {
+    fn new(id: &'static str, label_key: &'static str, kind: InputKind) -> Self {
+        let value = match &kind {
+            InputKind::Radio(options) | InputKind::Dropdown(options) => options.first().cloned().unwrap_or_default(),
+            _ => String::new(),
+        };
+        Self {
+            id,
+            label_key,
+            kind,
+            value,
+            cursor: 0,
+            revealed: false,
+            required: false,
+            validator: None,
+            shown_when: None,
+            error: None,
+            open: None,
+        }
+    }
+
+    pub fn text(id: &'static str, label_key: &'static str) -> Self {
+        Self::new(id, label_key, InputKind::Text)
+    }
+
+    pub fn masked(id: &'static str, label_key: &'static str) -> Self {
+        Self::new(id, label_key, InputKind::Masked)
+    }
+
+    pub fn number(id: &'static str, label_key: &'static str, unit: &'static str) -> Self {
+        Self::new(id, label_key, InputKind::Number { unit })
+    }
+
+    pub fn checkbox(id: &'static str, label_key: &'static str) -> Self {
+        Self::new(id, label_key, InputKind::Checkbox)
+    }
+
+    /// Radio group preset to its first option
+    pub fn radio(id: &'static str, label_key: &'static str, options: Vec<String>) -> Self {
+        Self::new(id, label_key, InputKind::Radio(options))
+    }
+
+    /// Dropdown preset to its first option
+    pub fn dropdown(id: &'static str, label_key: &'static str, options: Vec<String>) -> Self {
+        Self::new(id, label_key, InputKind::Dropdown(options))
+    }
+
+    pub fn required(mut self) -> Self {
+        self.required = true;
+        self
+    }
+
+    pub fn validate(mut self, validator: Validator) -> Self {
+        self.validator = Some(validator);
+        self
+    }
+
+    /// Show the input only while the input `id` is shown and has `value`
+    pub fn shown_when(mut self, id: &'static str, value: &'static str) -> Self {
+        self.shown_when = Some((id, value));
+        self
+    }
+
+    /// Preset the value, with the cursor at its end
+    pub fn with_value(mut self, value: &str) -> Self {
+        self.set_value(value);
+        self
+    }
+
+    pub fn set_value(&mut self, value: &str) {
+        self.value.zeroize();
+        self.value = value.to_string();
+        self.cursor = value.chars().count();
+    }
+
+    pub fn is_masked(&self) -> bool {
+        self.kind == InputKind::Masked
+    }
+
+    /// Show or hide a masked value; other inputs are always shown
+    pub fn toggle_reveal(&mut self) {
+        self.revealed = self.is_masked() && !self.revealed;
+    }
+
+    fn is_typed(&self) -> bool {
+        matches!(self.kind, InputKind::Text | InputKind::Masked | InputKind::Number { .. })
+    }
+
+    fn options(&self) -> &[String] {
+        match &self.kind {
+            InputKind::Radio(options) | InputKind::Dropdown(options) => options,
+            _ => &[],
+        }
+    }
+
+    fn selected(&self) -> usize {
+        self.options().iter().position(|option| *option == self.value).unwrap_or(0)
+    }
+
+    fn select(&mut self, index: usize) {
+        if let Some(option) = self.options().get(index) {
+            self.value = option.clone();
+        }
+    }
+
+    fn cycle(&mut self, forward: bool) {
+        let count = self.options().len();
+        if count > 0 {
+            let current = self.selected();
+            self.select(if forward { (current + 1) % count } else { (current + count - 1) % count });
+        }
+    }
+
+    /// Byte offset of the character position `at`
+    fn byte_at(&self, at: usize) -> usize {
+        self.value.char_indices().nth(at).map_or(self.value.len(), |(offset, _)| offset)
+    }
+
+    fn len(&self) -> usize {
+        self.value.chars().count()
+    }
+
+    /// Remove the characters between the positions `from` and `to`
+    fn remove_range(&mut self, from: usize, to: usize) {
+        let (start, end) = (self.byte_at(from), self.byte_at(to));
+        self.value.replace_range(start..end, "");
+        self.cursor = from;
+    }
+
+    /// Start of the word before the cursor, skipping the spaces in front of it
+    fn word_start(&self) -> usize {
+        let chars: Vec<char> = self.value.chars().take(self.cursor).collect();
+        let mut at = chars.len();
+        while at > 0 && chars[at - 1].is_whitespace() {
+            at -= 1;
+        }
+        while at > 0 && !chars[at - 1].is_whitespace() {
+            at -= 1;
+        }
+        at
+    }
+
+    /// End of the word after the cursor, skipping the spaces in front of it
+    fn word_end(&self) -> usize {
+        let chars: Vec<char> = self.value.chars().collect();
+        let mut at = self.cursor;
+        while at < chars.len() && chars[at].is_whitespace() {
+            at += 1;
+        }
+        while at < chars.len() && !chars[at].is_whitespace() {
+            at += 1;
+        }
+        at
+    }
+
+    /// Edit a typed value; false if the key means nothing here
+    fn edit(&mut self, key: KeyEvent) -> bool {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = key.modifiers.contains(KeyModifiers::ALT);
+        match key.code {
+            KeyCode::Char('w') if ctrl => self.remove_range(self.word_start(), self.cursor),
+            KeyCode::Backspace if ctrl || alt => self.remove_range(self.word_start(), self.cursor),
+            KeyCode::Delete if ctrl || alt => {
+                let (cursor, end) = (self.cursor, self.word_end());
+                self.remove_range(cursor, end);
+            }
+            KeyCode::Char('a') if ctrl => self.cursor = 0,
+            KeyCode::Char('e') if ctrl => self.cursor = self.len(),
+            KeyCode::Char(_) if ctrl || alt => return false,
+            KeyCode::Char(c) => {
+                if matches!(self.kind, InputKind::Number { .. }) && !c.is_ascii_digit() {
+                    return true;
+                }
+                let at = self.byte_at(self.cursor);
+                self.value.insert(at, c);
+                self.cursor += 1;
+            }
+            // From an empty field Backspace is left to the wizard, which goes back a step
+            KeyCode::Backspace if self.value.is_empty() => return false,
+            KeyCode::Backspace => {
+                if self.cursor > 0 {
+                    self.remove_range(self.cursor - 1, self.cursor);
+                }
+            }
+            KeyCode::Delete => {
+                if self.cursor < self.len() {
+                    self.remove_range(self.cursor, self.cursor + 1);
+                }
+            }
+            KeyCode::Left => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Right => self.cursor = (self.cursor + 1).min(self.len()),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.len(),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Handle a key for this input; false if it is left to the form or the caller
+    fn handle_key(&mut self, key: KeyEvent) -> bool {
+        if let Some(highlighted) = self.open {
+            let count = self.options().len();
+            match key.code {
+                KeyCode::Up => self.open = Some((highlighted + count - 1) % count),
+                KeyCode::Down => self.open = Some((highlighted + 1) % count),
+                KeyCode::Enter | KeyCode::Char(' ') => {
+                    self.select(highlighted);
+                    self.open = None;
+                }
+                KeyCode::Esc => self.open = None,
+                _ => {}
+            }
+            return true;
+        }
+        match (&self.kind, key.code) {
+            (InputKind::Checkbox, KeyCode::Char(' ')) => {
+                self.value = if self.value == CHECKED { String::new() } else { CHECKED.to_string() };
+            }
+            (InputKind::Radio(_), KeyCode::Char(' ')) => self.cycle(true),
+            (InputKind::Radio(_) | InputKind::Dropdown(_), KeyCode::Left) => self.cycle(false),
+            (InputKind::Radio(_) | InputKind::Dropdown(_), KeyCode::Right) => self.cycle(true),
+            (InputKind::Dropdown(options), KeyCode::Enter | KeyCode::Char(' ')) if !options.is_empty() => {
+                self.open = Some(self.selected());
+            }
+            _ if self.is_typed() => return self.edit(key),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Check the value; `Err` holds the message
+    fn check(&self) -> Result<(), String> {
+        if self.value.trim().is_empty() {
+            return if self.required { Err(get_text("FORM_ERROR_REQUIRED")) } else { Ok(()) };
+        }
+        match self.validator {
+            Some(validator) => validator(&self.value).map_err(get_text),
+            None => Ok(()),
+        }
+    }
+
+    /// The value as drawn, with the cursor cell of a focused typed input highlighted
+    fn value_spans(&self, focused: bool, theme: &Theme) -> Vec<Span<'static>> {
+        let input = Style::default().fg(theme.input_text).bg(theme.input_bg);
+        let frame = Style::default().fg(if focused { theme.input_border_focus } else { theme.input_border });
+        let mark = |on: bool| if on { "x" } else { " " };
+        match &self.kind {
+            InputKind::Checkbox => vec![Span::styled("[", frame), Span::styled(mark(self.value == CHECKED), input), Span::styled("]", frame)],
+            InputKind::Radio(options) => {
+                let mut spans = Vec::new();
+                for option in options {
+                    let chosen = *option == self.value;
+                    let style = if chosen && focused { input.add_modifier(Modifier::BOLD) } else { Style::default().fg(theme.input_text) };
+                    spans.push(Span::styled(format!("({}) {}", if chosen { "•" } else { " " }, option), style));
+                    spans.push(Span::raw("  "));
+                }
+                spans
+            }
+            InputKind::Dropdown(_) => {
+                let arrow = if self.open.is_some() { "▴" } else { "▾" };
+                vec![
+                    Span::styled("[", frame),
+                    Span::styled(format!("{:<width$} {}", self.value, arrow, width = INPUT_WIDTH - 2), input),
+                    Span::styled("]", frame),
+                ]
+            }
+            _ => {
+                let shown: Vec<char> = if self.is_masked() && !self.revealed {
+                    self.value.chars().map(|_| '•').collect()
+                } else {
+                    self.value.chars().collect()
+                };
+                let width = INPUT_WIDTH.max(shown.len() + 1);
+                let mut padded: Vec<char> = shown;
+                padded.resize(width, ' ');
+                let mut spans = vec![Span::styled("[", frame)];
+                if focused {
+                    let cursor = self.cursor.min(padded.len() - 1);
+                    spans.push(Span::styled(padded[..cursor].iter().collect::<String>(), input));
+                    spans.push(Span::styled(padded[cursor].to_string(), input.add_modifier(Modifier::REVERSED)));
+                    spans.push(Span::styled(padded[cursor + 1..].iter().collect::<String>(), input));
+                } else {
+                    spans.push(Span::styled(padded.iter().collect::<String>(), input));
+                }
+                spans.push(Span::styled("]", frame));
+                if let InputKind::Number { unit } = self.kind {
+                    spans.push(Span::styled(format!(" {}", unit), Style::default().fg(theme.text_secondary)));
+                }
+                spans
+            }
+        }
+    }
+}
+
+// Values may be passwords: never print them, and wipe them when the form goes
+impl std::fmt::Debug for Input {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let value = if self.is_masked() { REDACTED } else { self.value.as_str() };
+        f.debug_struct("Input").field("id", &self.id).field("value", &value).finish()
+    }
+}
+
+impl Drop for Input {
+    fn drop(&mut self) {
+        self.value.zeroize();
+    }
+}
+
+/// Whether `confirm` is the confirmation field of `id`: "confirm" for "new", `<id>_confirm` otherwise
+pub fn confirms(confirm: &str, id: &str) -> bool {
+    (id == "new" && confirm == "confirm") || confirm.strip_suffix("_confirm") == Some(id)
+}
+
+/// Inputs with a focus order, drawn as lines of a paragraph
+///
+/// The form takes editing and focus keys; Enter, Esc and Backspace in an
+/// empty field stay with the caller, so a wizard can move between steps.
+#[derive(Debug, Clone, Default)]
+pub struct Form {
+    pub inputs: Vec<Input>,
+    focused: usize,
+}
+
+impl Form {
+    pub fn new(inputs: Vec<Input>) -> Self {
+        Self { inputs, focused: 0 }
+    }
+
+    fn index(&self, id: &str) -> Option<usize> {
+        self.inputs.iter().position(|input| input.id == id)
+    }
+
+    /// Whether the input at `index` is shown with the current values
+    fn is_shown(&self, index: usize) -> bool {
+        self.inputs[index]
+            .shown_when
+            .is_none_or(|(id, value)| self.index(id).is_some_and(|other| self.inputs[other].value == value && self.is_shown(other)))
+    }
+
+    /// Value of the input `id`, empty if there is none
+    pub fn value(&self, id: &str) -> &str {
+        self.index(id).map_or("", |index| self.inputs[index].value.as_str())
+    }
+
+    /// Trimmed value of the input `id` if it is shown and not empty; masked values are kept as typed
+    pub fn filled(&self, id: &str) -> Option<String> {
+        let index = self.index(id).filter(|index| self.is_shown(*index))?;
+        let input = &self.inputs[index];
+        let value = if input.is_masked() { input.value.as_str() } else { input.value.trim() };
+        (!value.trim().is_empty()).then(|| value.to_string())
+    }
+
+    pub fn checked(&self, id: &str) -> bool {
+        self.value(id) == CHECKED
+    }
+
+    pub fn focused_input(&self) -> &Input {
+        &self.inputs[self.focused]
+    }
+
+    pub fn focused_input_mut(&mut self) -> &mut Input {
+        &mut self.inputs[self.focused]
+    }
+
+    /// Whether no shown input follows the focused one
+    pub fn is_last_focused(&self) -> bool {
+        (self.focused + 1..self.inputs.len()).all(|index| !self.is_shown(index))
+    }
+
+    /// Move the focus to the next shown input, checking the one that is left
+    pub fn focus_next(&mut self) {
+        self.move_focus(true);
+    }
+
+    pub fn focus_previous(&mut self) {
+        self.move_focus(false);
+    }
+
+    fn move_focus(&mut self, forward: bool) {
+        let count = self.inputs.len();
+        let left = self.focused;
+        if !self.inputs[left].value.is_empty() {
+            self.inputs[left].error = self.inputs[left].check().err();
+        }
+        for step in 1..=count {
+            let index = if forward { (left + step) % count } else { (left + count * step - step) % count };
+            if self.is_shown(index) {
+                self.focused = index;
+                return;
+            }
+        }
+    }
+
+    /// Check every shown input and focus the first one that fails
+    pub fn validate(&mut self) -> bool {
+        let mut first_error = None;
+        for index in 0..self.inputs.len() {
+            let result = if self.is_shown(index) { self.inputs[index].check() } else { Ok(()) };
+            if result.is_err() && first_error.is_none() {
+                first_error = Some(index);
+            }
+            self.inputs[index].error = result.err();
+        }
+        if let Some(index) = first_error {
+            self.focused = index;
+        }
+        first_error.is_none()
+    }
+
+    /// Show `message` below the input `id` and focus it
+    pub fn set_error(&mut self, id: &str, message: String) {
+        if let Some(index) = self.index(id) {
+            self.inputs[index].error = Some(message);
+            self.focused = index;
+        }
+    }
+
+    /// Put a generated secret into the focused masked input and its confirmation
+    ///
+    /// Returns false if the focused input is not masked.
+    pub fn fill_secret(&mut self, value: &str) -> bool {
+        if !self.focused_input().is_masked() {
+            return false;
+        }
+        let id = self.focused_input().id;
+        for input in self.inputs.iter_mut().filter(|input| input.id == id || confirms(input.id, id) || confirms(id, input.id)) {
+            input.set_value(value);
+            input.error = None;
+        }
+        true
+    }
+
+    /// Handle a key; false if it is left to the caller
+    pub fn handle_key(&mut self, key: KeyEvent) -> bool {
+        if self.inputs[self.focused].open.is_none() {
+            match key.code {
+                KeyCode::Tab | KeyCode::Down => {
+                    self.focus_next();
+                    return true;
+                }
+                KeyCode::BackTab | KeyCode::Up => {
+                    self.focus_previous();
+                    return true;
+                }
+                _ => {}
+            }
+        }
+        let input = &mut self.inputs[self.focused];
+        // Every edit changes the length and every choice the selected option, so
+        // the value itself, which may be a password, is never copied
+        let before = (input.value.len(), input.selected());
+        let handled = input.handle_key(key);
+        if (input.value.len(), input.selected()) != before {
+            input.error = None;
+        }
+        handled
+    }
+
+    /// Lines of the shown inputs: label, value and the validation message below
+    pub fn lines(&self, theme: &Theme) -> Vec<Line<'static>> {
+        let shown: Vec<usize> = (0..self.inputs.len()).filter(|index| self.is_shown(*index)).collect();
+        let label_width = shown.iter().map(|index| get_text(self.inputs[*index].label_key).chars().count()).max().unwrap_or(0);
+        let mut lines = Vec::new();
+        for index in shown {
+            let input = &self.inputs[index];
+            let focused = index == self.focused;
+            let label_style = if focused {
+                Style::default().fg(theme.input_border_focus).add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(theme.accent_secondary)
+            };
+            let mut spans = vec![
+                Span::styled(if focused { "› " } else { "  " }, label_style),
+                Span::styled(format!("{:<width$}  ", get_text(input.label_key), width = label_width), label_style),
+            ];
+            spans.extend(input.value_spans(focused, theme));
+            lines.push(Line::from(spans));
+
+            let indent = " ".repeat(label_width + 4);
+            if let (Some(highlighted), InputKind::Dropdown(options)) = (input.open, &input.kind) {
+                for (option_index, option) in options.iter().enumerate() {
+                    let style = if option_index == highlighted {
+                        Style::default().fg(theme.list_item_selected_fg).bg(theme.list_item_selected_bg)
+                    } else {
+                        Style::default().fg(theme.input_text).bg(theme.input_bg)
+                    };
+                    lines.push(Line::from(vec![Span::raw(indent.clone()), Span::styled(format!(" {:<width$}", option, width = INPUT_WIDTH - 1), style)]));
+                }
+            }
+            if let Some(error) = &input.error {
+                lines.push(Line::from(vec![Span::raw(indent), Span::styled(error.clone(), Style::default().fg(theme.error))]));
+            }
+        }
+        lines
+    }
+}
@@ -1,3 +1,5 @@
+pub mod form;
+
 /// Type of menu item
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum MenuType {
@@ -21,6 +23,7 @@ pub enum Screen {
     LanguageSelect,
     KeyboardSelect,
     SystemInstallation,
+    KeyManagement,
     Message,
     ConfirmExit,
 }